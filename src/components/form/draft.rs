@@ -0,0 +1,158 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use ahash::AHashMap;
+use chrono::Utc;
+use chrono_humanize::{Accuracy, HumanTime, Tense};
+use gloo_storage::{LocalStorage, Storage};
+use leptos::*;
+use leptos_router::use_location;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::icon::IconExclamationTriangle,
+    core::{
+        form::{FormData, FormValue},
+        schema::{Schema, Type},
+    },
+    DRAFT_STORAGE_KEY,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Draft {
+    values: AHashMap<String, FormValue>,
+    saved_at: i64,
+}
+
+pub fn draft_key(schema_id: &str, record_id: &str) -> String {
+    format!(
+        "{DRAFT_STORAGE_KEY}:{}:{schema_id}:{record_id}",
+        use_location().pathname.get_untracked()
+    )
+}
+
+pub fn clear_draft(key: &str) {
+    LocalStorage::delete(key);
+}
+
+// Renders a banner offering to restore a previously stored draft and keeps
+// the draft in sync with the form. Must be created after the form data has
+// been loaded from the server, which is used as the baseline for changes.
+#[component]
+pub fn DraftRecovery(
+    #[prop(into)] key: String,
+    data: RwSignal<FormData>,
+    #[prop(optional)] exclude: &'static [&'static str],
+) -> impl IntoView {
+    let baseline = draft_values(&data.get_untracked(), exclude);
+    let stored = LocalStorage::get::<Draft>(&key)
+        .ok()
+        .filter(|draft| draft.values != baseline);
+    let show_banner = create_rw_signal(stored.is_some());
+    let age = stored
+        .as_ref()
+        .map(|draft| {
+            HumanTime::from(chrono::Duration::seconds(
+                draft.saved_at - Utc::now().timestamp(),
+            ))
+            .to_text_en(Accuracy::Rough, Tense::Past)
+        })
+        .unwrap_or_default();
+    let stored = store_value(stored);
+    let key = store_value(key);
+
+    create_effect(move |_| {
+        let values = data.with(|data| draft_values(data, exclude));
+        if values != baseline {
+            let draft = Draft {
+                values,
+                saved_at: Utc::now().timestamp(),
+            };
+            key.with_value(|key| {
+                if let Err(err) = LocalStorage::set(key, &draft) {
+                    log::error!("Failed to save draft to local storage: {}", err);
+                }
+            });
+        } else if !show_banner.get_untracked() {
+            key.with_value(|key| clear_draft(key));
+        }
+    });
+
+    view! {
+        <Show when=move || show_banner.get()>
+            <div
+                class="mb-5 bg-yellow-50 border border-yellow-200 text-sm text-yellow-800 rounded-lg p-4 dark:bg-yellow-800/10 dark:border-yellow-900 dark:text-yellow-500"
+                role="alert"
+            >
+                <div class="flex">
+                    <div class="flex-shrink-0">
+                        <IconExclamationTriangle
+                            attr:class="flex-shrink-0 size-4 mt-0.5"
+                            attr:stroke="#854d0e"
+                        />
+                    </div>
+                    <div class="ms-4 flex-1">
+                        <h3 class="text-sm font-semibold">Restore unsaved draft?</h3>
+                        <div class="mt-1 text-sm">
+                            {format!("You have unsaved changes to this form from {age}.")}
+                        </div>
+                        <div class="mt-3 flex gap-x-3">
+                            <button
+                                type="button"
+                                class="text-sm font-semibold underline"
+                                on:click=move |_| {
+                                    if let Some(draft) = stored.get_value() {
+                                        data.update(|data| {
+                                            let schema = data.schema.clone();
+                                            data.errors.clear();
+                                            data.values
+                                                .retain(|id, _| {
+                                                    !is_draft_field(&schema, id, exclude)
+                                                });
+                                            data.values.extend(draft.values);
+                                        });
+                                    }
+                                    show_banner.set(false);
+                                }
+                            >
+
+                                Restore
+                            </button>
+                            <button
+                                type="button"
+                                class="text-sm font-semibold underline"
+                                on:click=move |_| {
+                                    key.with_value(|key| clear_draft(key));
+                                    show_banner.set(false);
+                                }
+                            >
+
+                                Discard
+                            </button>
+                        </div>
+                    </div>
+                </div>
+            </div>
+        </Show>
+    }
+}
+
+fn draft_values(data: &FormData, exclude: &[&str]) -> AHashMap<String, FormValue> {
+    data.values
+        .iter()
+        .filter(|(id, _)| is_draft_field(&data.schema, id, exclude))
+        .map(|(id, value)| (id.clone(), value.clone()))
+        .collect()
+}
+
+// Secrets are never written to local storage
+fn is_draft_field(schema: &Schema, id: &str, exclude: &[&str]) -> bool {
+    !exclude.contains(&id)
+        && schema
+            .fields
+            .get(id)
+            .is_none_or(|field| !matches!(field.typ_, Type::Secret))
+}
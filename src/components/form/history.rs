@@ -0,0 +1,299 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::sync::Arc;
+
+use ahash::AHashMap;
+use leptos::*;
+use wasm_bindgen::JsCast;
+
+use crate::{
+    components::{
+        form::button::Button,
+        icon::{IconArrowUTurnLeft, IconArrowUTurnRight},
+        Color,
+    },
+    core::{
+        form::{FormData, FormValue},
+        schema::Type,
+    },
+};
+
+const MAX_HISTORY: usize = 100;
+
+type Snapshot = AHashMap<String, FormValue>;
+
+// Undo/redo stack kept next to (not inside) the form data. Secret fields are
+// never snapshotted and consecutive edits to the same field share one step.
+#[derive(Default)]
+pub struct FormHistory {
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+    current: Snapshot,
+    last_edit: Option<String>,
+    loaded: Option<Option<Arc<Snapshot>>>,
+    exclude: &'static [&'static str],
+}
+
+impl FormHistory {
+    pub fn new(exclude: &'static [&'static str]) -> Self {
+        FormHistory {
+            exclude,
+            ..Default::default()
+        }
+    }
+
+    fn is_excluded(&self, data: &FormData, id: &str) -> bool {
+        self.exclude.contains(&id)
+            || data
+                .schema
+                .fields
+                .get(id)
+                .is_some_and(|field| matches!(field.typ_, Type::Secret))
+    }
+
+    fn snapshot(&self, data: &FormData) -> Snapshot {
+        data.values
+            .iter()
+            .filter(|(id, _)| !self.is_excluded(data, id))
+            .map(|(id, value)| (id.clone(), value.clone()))
+            .collect()
+    }
+
+    // Records the changes made to the form since the last call. Loading a
+    // record (see `FormData::mark_saved`) starts a new history instead.
+    pub fn observe(&mut self, data: &FormData) {
+        let is_reload = match (&self.loaded, &data.saved) {
+            (Some(Some(loaded)), Some(saved)) => !Arc::ptr_eq(loaded, saved),
+            (Some(None), None) => false,
+            _ => true,
+        };
+        let snapshot = self.snapshot(data);
+
+        if is_reload {
+            self.undo.clear();
+            self.redo.clear();
+            self.last_edit = None;
+            self.loaded = Some(data.saved.clone());
+        } else if snapshot != self.current {
+            let mut changed = snapshot
+                .keys()
+                .filter(|id| snapshot.get(*id) != self.current.get(*id))
+                .chain(self.current.keys().filter(|id| !snapshot.contains_key(*id)));
+            let edit = changed.next().filter(|_| changed.next().is_none()).cloned();
+
+            if edit.is_none() || edit != self.last_edit {
+                if self.undo.len() == MAX_HISTORY {
+                    self.undo.remove(0);
+                }
+                self.undo.push(std::mem::take(&mut self.current));
+                self.redo.clear();
+            }
+            self.last_edit = edit;
+        } else {
+            return;
+        }
+
+        self.current = snapshot;
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn undo(&mut self, data: &mut FormData) -> bool {
+        if let Some(values) = self.undo.pop() {
+            self.redo.push(std::mem::replace(&mut self.current, values));
+            self.restore(data);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn redo(&mut self, data: &mut FormData) -> bool {
+        if let Some(values) = self.redo.pop() {
+            self.undo.push(std::mem::replace(&mut self.current, values));
+            self.restore(data);
+            true
+        } else {
+            false
+        }
+    }
+
+    // Replaces the form values with the current snapshot, secret fields keep
+    // whatever the user typed last.
+    fn restore(&mut self, data: &mut FormData) {
+        let secrets = data
+            .values
+            .iter()
+            .filter(|(id, _)| self.is_excluded(data, id))
+            .map(|(id, value)| (id.clone(), value.clone()))
+            .collect::<Vec<_>>();
+        data.values = self.current.clone();
+        data.values.extend(secrets);
+        data.errors.clear();
+        self.last_edit = None;
+    }
+}
+
+// Undo/redo buttons for a form, also bound to Ctrl+Z, Ctrl+Shift+Z and Ctrl+Y
+// (Cmd on macOS) while the focus is not inside an editable element.
+#[component]
+pub fn UndoRedo(
+    data: RwSignal<FormData>,
+    #[prop(optional)] exclude: &'static [&'static str],
+) -> impl IntoView {
+    let history = store_value(FormHistory::new(exclude));
+    let available = create_rw_signal((false, false));
+    let refresh = move || {
+        let state = history.with_value(|history| (history.can_undo(), history.can_redo()));
+        if available.get_untracked() != state {
+            available.set(state);
+        }
+    };
+
+    create_effect(move |_| {
+        data.with(|data| history.update_value(|history| history.observe(data)));
+        refresh();
+    });
+
+    let undo = move |redo: bool| {
+        data.update(|data| {
+            history.update_value(|history| {
+                if redo {
+                    history.redo(data);
+                } else {
+                    history.undo(data);
+                }
+            });
+        });
+        refresh();
+    };
+
+    let undo_with_keyboard = window_event_listener(ev::keydown, move |ev| {
+        if (ev.ctrl_key() || ev.meta_key()) && !ev.alt_key() && !is_editing() {
+            let key = ev.key();
+            let redo = if key.eq_ignore_ascii_case("z") {
+                ev.shift_key()
+            } else if key.eq_ignore_ascii_case("y") {
+                true
+            } else {
+                return;
+            };
+            ev.prevent_default();
+            undo(redo);
+        }
+    });
+    on_cleanup(move || undo_with_keyboard.remove());
+
+    view! {
+        <div class="me-auto flex gap-x-2">
+            <Button
+                text="Undo"
+                color=Color::Gray
+                on_click=move |_| undo(false)
+                disabled=Signal::derive(move || !available.get().0)
+            >
+                <IconArrowUTurnLeft/>
+            </Button>
+            <Button
+                text="Redo"
+                color=Color::Gray
+                on_click=move |_| undo(true)
+                disabled=Signal::derive(move || !available.get().1)
+            >
+                <IconArrowUTurnRight/>
+            </Button>
+        </div>
+    }
+}
+
+// Text inputs handle their own undo, so shortcuts are left alone while one has the focus.
+fn is_editing() -> bool {
+    document().active_element().is_some_and(|element| {
+        matches!(
+            element.tag_name().to_ascii_lowercase().as_str(),
+            "input" | "textarea" | "select"
+        ) || element
+            .dyn_ref::<web_sys::HtmlElement>()
+            .is_some_and(|element| element.is_content_editable())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(data: &mut FormData, history: &mut FormHistory, id: &str, value: &str) {
+        data.values
+            .insert(id.to_string(), FormValue::Value(value.to_string()));
+        history.observe(data);
+    }
+
+    fn value(data: &FormData, id: &str) -> Option<String> {
+        match data.values.get(id) {
+            Some(FormValue::Value(value)) => Some(value.clone()),
+            _ => None,
+        }
+    }
+
+    fn loaded(exclude: &'static [&'static str]) -> (FormData, FormHistory) {
+        let mut data = FormData::default();
+        data.mark_saved();
+        let mut history = FormHistory::new(exclude);
+        history.observe(&data);
+        (data, history)
+    }
+
+    #[test]
+    fn coalesces_edits_to_the_same_field() {
+        let (mut data, mut history) = loaded(&[]);
+        edit(&mut data, &mut history, "name", "j");
+        edit(&mut data, &mut history, "name", "jo");
+        edit(&mut data, &mut history, "name", "john");
+        edit(&mut data, &mut history, "email", "john@example.org");
+
+        assert!(history.undo(&mut data));
+        assert_eq!(value(&data, "name").as_deref(), Some("john"));
+        assert_eq!(value(&data, "email"), None);
+        assert!(history.undo(&mut data));
+        assert_eq!(value(&data, "name"), None);
+        assert!(!history.undo(&mut data));
+
+        assert!(history.redo(&mut data));
+        assert_eq!(value(&data, "name").as_deref(), Some("john"));
+    }
+
+    #[test]
+    fn never_snapshots_secrets() {
+        let (mut data, mut history) = loaded(&["password"]);
+        edit(&mut data, &mut history, "password", "secret");
+        assert!(!history.can_undo());
+
+        edit(&mut data, &mut history, "name", "john");
+        assert!(history.undo.iter().all(|s| !s.contains_key("password")));
+        assert!(history.undo(&mut data));
+        assert_eq!(value(&data, "password").as_deref(), Some("secret"));
+    }
+
+    #[test]
+    fn loading_resets_history() {
+        let (mut data, mut history) = loaded(&[]);
+        edit(&mut data, &mut history, "name", "john");
+        assert!(history.can_undo());
+
+        data.values
+            .insert("name".to_string(), FormValue::Value("jane".to_string()));
+        data.mark_saved();
+        history.observe(&data);
+        assert!(!history.can_undo());
+    }
+}
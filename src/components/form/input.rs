@@ -8,7 +8,13 @@ use std::str::FromStr;
 
 use leptos::*;
 
-use crate::components::icon::{IconClock, IconExclamationCircle, IconInfo};
+use crate::{
+    components::icon::{IconClock, IconExclamationCircle, IconInfo},
+    core::{
+        oauth::use_authorization,
+        password::{load_password_policy, password_policy, PasswordStrength},
+    },
+};
 
 use super::FormElement;
 
@@ -82,6 +88,7 @@ pub fn InputPassword(
     element: FormElement,
     #[prop(optional, into)] placeholder: Option<MaybeSignal<String>>,
     #[prop(optional, into)] disabled: MaybeSignal<bool>,
+    #[prop(optional)] strength_meter: bool,
     #[prop(attrs)] attrs: Vec<(&'static str, Attribute)>,
 ) -> impl IntoView {
     let value = create_memo(move |_| {
@@ -123,6 +130,16 @@ pub fn InputPassword(
                             data.update(element.id, event_target_value(&ev));
                         });
                 }
+
+                on:input=move |ev| {
+                    if strength_meter {
+                        element
+                            .data
+                            .update(|data| {
+                                data.update(element.id, event_target_value(&ev));
+                            });
+                    }
+                }
             />
 
             <button
@@ -194,6 +211,64 @@ pub fn InputPassword(
                     view! { <p class="text-xs text-red-600 mt-2">{error}</p> }
                 })
         }}
+
+        {strength_meter.then(|| view! { <PasswordStrengthMeter element=element/> })}
+    }
+}
+
+#[component]
+fn PasswordStrengthMeter(element: FormElement) -> impl IntoView {
+    let auth = use_authorization();
+    let policy = create_resource(
+        || (),
+        move |_| {
+            let auth = auth.get_untracked();
+
+            async move { load_password_policy(&auth).await.ok() }
+        },
+    );
+
+    move || {
+        let policy = policy.get().flatten().or_else(|| password_policy().ok())?;
+        element.data.with(|data| {
+            let password = data.value_as_str(element.id).unwrap_or_default();
+            if !data.is_policy_checked(element.id) {
+                return None;
+            }
+            let personal = data.personal_values(element.id);
+            let strength = policy.strength(password, &personal);
+            let violations = policy.violations(password, &personal);
+            let color = match strength {
+                PasswordStrength::Weak => "bg-red-500",
+                PasswordStrength::Fair => "bg-yellow-500",
+                PasswordStrength::Good => "bg-teal-500",
+                PasswordStrength::Strong => "bg-green-500",
+            };
+
+            Some(view! {
+                <div class="mt-2 flex items-center gap-x-2">
+                    <div class="grid grid-cols-4 gap-x-1 w-full">
+                        {(1..=4)
+                            .map(|level| {
+                                let class = if level <= strength.score() {
+                                    color
+                                } else {
+                                    "bg-gray-200 dark:bg-neutral-700"
+                                };
+                                view! { <div class=format!("h-1.5 rounded-full {class}")></div> }
+                            })
+                            .collect_view()}
+                    </div>
+                    <span class="text-xs text-gray-600 dark:text-neutral-400">{strength.label()}</span>
+                </div>
+                <ul class="mt-1 text-xs text-gray-500 dark:text-neutral-500">
+                    {violations
+                        .into_iter()
+                        .map(|violation| view! { <li>{violation}</li> })
+                        .collect_view()}
+                </ul>
+            })
+        })
     }
 }
 
@@ -5,12 +5,15 @@
  */
 
 pub mod button;
+pub mod draft;
 pub mod expression;
+pub mod history;
 pub mod input;
 pub mod select;
 pub mod stacked_badge;
 pub mod stacked_duration;
 pub mod stacked_input;
+pub mod steps;
 pub mod tab;
 
 use leptos::*;
@@ -99,6 +102,7 @@ pub fn FormItem(
     #[prop(optional, into)] hide: MaybeSignal<bool>,
     #[prop(optional, into)] is_optional: MaybeSignal<bool>,
     #[prop(optional)] stacked: bool,
+    #[prop(optional)] reset: Option<FormElement>,
     children: Children,
 ) -> impl IntoView {
     let tooltip = tooltip
@@ -148,6 +152,23 @@ pub fn FormItem(
         }
     };
 
+    // Offers to revert the field to the value it had when the form was loaded
+    let reset = reset.map(|FormElement { id, data }| {
+        view! {
+            <button
+                type="button"
+                class="block text-xs text-blue-600 decoration-2 hover:underline dark:text-blue-500"
+                class:hidden=move || !data.with(|data| data.is_modified(id))
+                on:click=move |_| {
+                    data.update(|data| data.reset_field(id));
+                }
+            >
+
+                Revert changes
+            </button>
+        }
+    });
+
     if !stacked {
         view! {
             <div class="sm:col-span-3" class:hidden=move || hide.get()>
@@ -156,6 +177,7 @@ pub fn FormItem(
                 </label>
                 {tooltip}
                 {is_optional}
+                {reset}
 
             </div>
             <div class="sm:col-span-9" class:hidden=move || hide.get()>
@@ -168,6 +190,7 @@ pub fn FormItem(
             <div class="mb-4 sm:mb-8" class:hidden=move || hide.get()>
                 <label class="block mb-2 text-sm font-medium dark:text-white">{label}</label>
                 <div class="relative">{children()}</div>
+                {reset}
             </div>
         }
         .into_view()
@@ -7,14 +7,21 @@
 use std::{
     fmt::{Display, Formatter},
     str::FromStr,
+    time::Duration,
 };
 
 use ahash::AHashSet;
+use chrono::{DateTime, Datelike, Local, NaiveDateTime, TimeZone, Timelike, Utc};
+use chrono_humanize::{Accuracy, HumanTime, Tense};
+use gloo_storage::{LocalStorage, Storage};
 use leptos::*;
 
-use crate::core::{
-    form::FormData,
-    schema::{Field, Schema, Source, Type, Value},
+use crate::{
+    core::{
+        form::FormData,
+        schema::{Field, Schema, Source, Type, Value},
+    },
+    CRON_THRESHOLD_STORAGE_KEY,
 };
 
 use super::FormElement;
@@ -182,10 +189,18 @@ pub fn CheckboxGroup(
     }
 }
 
+const CRON_PREVIEW_RUNS: usize = 5;
+const CRON_THRESHOLDS: [(u64, &str); 3] = [
+    (0, "Never warn"),
+    (24 * 60 * 60, "Warn if more often than daily"),
+    (7 * 24 * 60 * 60, "Warn if more often than weekly"),
+];
+
 #[component]
 pub fn SelectCron(
     element: FormElement,
     #[prop(optional, into)] disabled: MaybeSignal<bool>,
+    #[prop(optional_no_strip)] min_interval: Option<Duration>,
 ) -> impl IntoView {
     let value = create_memo(move |_| {
         element
@@ -194,6 +209,7 @@ pub fn SelectCron(
             .value::<SimpleCron>(element.id)
             .unwrap_or_default()
     });
+    let schedule = create_memo(move |_| value.get().schedule());
     let error = create_memo(move |_| {
         element
             .data
@@ -202,6 +218,22 @@ pub fn SelectCron(
             .map(|s| s.to_string())
     });
 
+    // The schema's interval is only the default, admins can raise, lower or
+    // silence the warning per field from the browser.
+    let threshold_key = store_value(format!("{CRON_THRESHOLD_STORAGE_KEY}:{}", element.id));
+    let threshold = create_rw_signal(
+        LocalStorage::get::<u64>(threshold_key.get_value())
+            .ok()
+            .map(|secs| (secs > 0).then(|| Duration::from_secs(secs)))
+            .unwrap_or(min_interval),
+    );
+    let set_threshold = move |secs: u64| {
+        threshold.set((secs > 0).then(|| Duration::from_secs(secs)));
+        if let Err(err) = LocalStorage::set(threshold_key.get_value(), secs) {
+            log::warn!("Failed to save cron warning threshold: {err}");
+        }
+    };
+
     view! {
         <div class="space-y-3">
             <div class="relative">
@@ -365,6 +397,79 @@ pub fn SelectCron(
                     view! { <p class="text-xs text-red-600 mt-2">{error}</p> }
                 })
         }}
+
+        {move || {
+            schedule
+                .get()
+                .map(|schedule| {
+                    let too_frequent = schedule.frequency_warning(threshold.get());
+                    let next_runs = schedule
+                        .next_runs(Local::now(), CRON_PREVIEW_RUNS)
+                        .into_iter()
+                        .map(|run| {
+                            view! {
+                                <li>
+                                    {run.format("%a, %d %b %Y %H:%M").to_string()}
+                                    <span class="text-gray-400 dark:text-gray-600">
+                                        {format!(
+                                            " ({} UTC)",
+                                            run.with_timezone(&Utc).format("%a %H:%M"),
+                                        )}
+
+                                    </span>
+                                </li>
+                            }
+                        })
+                        .collect_view();
+
+                    view! {
+                        <div class="mt-3 text-sm text-gray-600 dark:text-gray-400">
+                            <p class="font-medium text-gray-800 dark:text-gray-200">
+                                {format!("Runs {}", schedule.describe())}
+                            </p>
+                            <p class="mt-2 text-xs uppercase text-gray-400 dark:text-gray-600">
+                                "Next runs (local time)"
+                            </p>
+                            <ul class="mt-1 space-y-0.5 font-mono text-xs">{next_runs}</ul>
+                            {too_frequent
+                                .map(|warning| {
+                                    view! {
+                                        <p class="text-xs text-yellow-600 mt-2">{warning}</p>
+                                    }
+                                })}
+
+                            <select
+                                class="mt-2 py-1 px-2 pe-8 block border-gray-200 rounded-lg text-xs focus:border-blue-500 focus:ring-blue-500 dark:bg-slate-900 dark:border-gray-700 dark:text-gray-400 dark:focus:ring-gray-600"
+                                on:change=move |ev| {
+                                    set_threshold(
+                                        event_target_value(&ev).parse::<u64>().unwrap_or_default(),
+                                    );
+                                }
+                            >
+
+                                {CRON_THRESHOLDS
+                                    .iter()
+                                    .map(|(secs, label)| {
+                                        let secs = *secs;
+                                        view! {
+                                            <option
+                                                value=secs
+                                                selected=move || {
+                                                    threshold.get().map_or(0, |t| t.as_secs()) == secs
+                                                }
+                                            >
+
+                                                {*label}
+                                            </option>
+                                        }
+                                    })
+                                    .collect_view()}
+
+                            </select>
+                        </div>
+                    }
+                })
+        }}
     }
 }
 
@@ -463,7 +568,32 @@ impl FromStr for SimpleCron {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CronSchedule {
+    Hourly { minute: u32 },
+    Daily { hour: u32, minute: u32 },
+    Weekly { day: u32, hour: u32, minute: u32 },
+}
+
 impl SimpleCron {
+    fn schedule(&self) -> Option<CronSchedule> {
+        let minute = self.minute.parse::<u32>().ok().filter(|m| *m < 60)?;
+        if self.hour == "*" {
+            return Some(CronSchedule::Hourly { minute });
+        }
+        let hour = self.hour.parse::<u32>().ok().filter(|h| *h < 24)?;
+        if self.day == "*" {
+            Some(CronSchedule::Daily { hour, minute })
+        } else {
+            let day = self
+                .day
+                .parse::<u32>()
+                .ok()
+                .filter(|d| (1..=7).contains(d))?;
+            Some(CronSchedule::Weekly { day, hour, minute })
+        }
+    }
+
     fn hour(mut self, hour: String) -> Self {
         self.hour = hour;
         self
@@ -495,3 +625,159 @@ impl Display for SimpleCron {
         write!(f, "{} {} {}", self.minute, self.hour, self.day)
     }
 }
+
+impl CronSchedule {
+    fn interval(&self) -> Duration {
+        match self {
+            CronSchedule::Hourly { .. } => Duration::from_secs(60 * 60),
+            CronSchedule::Daily { .. } => Duration::from_secs(24 * 60 * 60),
+            CronSchedule::Weekly { .. } => Duration::from_secs(7 * 24 * 60 * 60),
+        }
+    }
+
+    fn frequency(&self) -> &'static str {
+        match self {
+            CronSchedule::Hourly { .. } => "every hour",
+            CronSchedule::Daily { .. } => "every day",
+            CronSchedule::Weekly { .. } => "every week",
+        }
+    }
+
+    fn frequency_warning(&self, min_interval: Option<Duration>) -> Option<String> {
+        min_interval
+            .filter(|min_interval| self.interval() < *min_interval)
+            .map(|min_interval| {
+                format!(
+                    "This schedule runs {}, which is more often than the recommended {}.",
+                    self.frequency(),
+                    format_interval(min_interval),
+                )
+            })
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            CronSchedule::Hourly { minute: 0 } => "every hour, on the hour".to_string(),
+            CronSchedule::Hourly { minute } => {
+                format!("every hour at {minute} minutes past the hour")
+            }
+            CronSchedule::Daily { hour, minute } => {
+                format!("every day at {hour:02}:{minute:02}")
+            }
+            CronSchedule::Weekly { day, hour, minute } => {
+                format!("every {} at {hour:02}:{minute:02}", weekday_name(*day))
+            }
+        }
+    }
+
+    fn next_runs<Tz: TimeZone>(&self, now: DateTime<Tz>, count: usize) -> Vec<DateTime<Tz>> {
+        let local_now = now.naive_local();
+        let candidates: Box<dyn Iterator<Item = NaiveDateTime>> = match *self {
+            CronSchedule::Hourly { minute } => {
+                let start = local_now
+                    .date()
+                    .and_hms_opt(local_now.hour(), minute, 0)
+                    .unwrap();
+                Box::new((0..).map(move |hours| start + chrono::Duration::hours(hours)))
+            }
+            CronSchedule::Daily { hour, minute } => {
+                let start = local_now.date().and_hms_opt(hour, minute, 0).unwrap();
+                Box::new((0..).map(move |days| start + chrono::Duration::days(days)))
+            }
+            CronSchedule::Weekly { day, hour, minute } => {
+                let start = local_now.date().and_hms_opt(hour, minute, 0).unwrap();
+                Box::new(
+                    (0..)
+                        .map(move |days| start + chrono::Duration::days(days))
+                        .filter(move |dt| dt.weekday().number_from_monday() == day),
+                )
+            }
+        };
+        let timezone = now.timezone();
+
+        candidates
+            .filter_map(|naive| timezone.from_local_datetime(&naive).earliest())
+            .filter(|run| *run > now)
+            .take(count)
+            .collect()
+    }
+}
+
+fn weekday_name(day: u32) -> &'static str {
+    match day {
+        1 => "Monday",
+        2 => "Tuesday",
+        3 => "Wednesday",
+        4 => "Thursday",
+        5 => "Friday",
+        6 => "Saturday",
+        _ => "Sunday",
+    }
+}
+
+fn format_interval(interval: Duration) -> String {
+    chrono::Duration::from_std(interval)
+        .map(|interval| HumanTime::from(interval).to_text_en(Accuracy::Precise, Tense::Present))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cron(expr: &str) -> Option<CronSchedule> {
+        expr.parse::<SimpleCron>().unwrap().schedule()
+    }
+
+    #[test]
+    fn parses_schedules() {
+        assert_eq!(cron("15 * *"), Some(CronSchedule::Hourly { minute: 15 }));
+        assert_eq!(
+            cron("0 3 *"),
+            Some(CronSchedule::Daily { hour: 3, minute: 0 })
+        );
+        assert_eq!(
+            cron("30 4 7"),
+            Some(CronSchedule::Weekly {
+                day: 7,
+                hour: 4,
+                minute: 30
+            })
+        );
+        assert_eq!(cron("60 3 *"), None);
+        assert_eq!(cron("0 24 *"), None);
+        assert_eq!(cron("0 3 8"), None);
+    }
+
+    #[test]
+    fn warns_only_below_threshold() {
+        let day = Some(Duration::from_secs(24 * 60 * 60));
+        let hourly = cron("0 * *").unwrap();
+        let daily = cron("0 3 *").unwrap();
+
+        assert!(hourly.frequency_warning(day).is_some());
+        assert!(hourly.frequency_warning(None).is_none());
+        assert!(daily.frequency_warning(day).is_none());
+    }
+
+    #[test]
+    fn lists_next_runs() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 1, 10, 30, 0).unwrap();
+
+        let runs = cron("0 3 *").unwrap().next_runs(now, 2);
+        assert_eq!(
+            runs,
+            vec![
+                Utc.with_ymd_and_hms(2024, 1, 2, 3, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2024, 1, 3, 3, 0, 0).unwrap(),
+            ]
+        );
+
+        // 2024-01-01 is a Monday, so the next Sunday is the 7th.
+        let runs = cron("0 3 7").unwrap().next_runs(now, 1);
+        assert_eq!(
+            runs,
+            vec![Utc.with_ymd_and_hms(2024, 1, 7, 3, 0, 0).unwrap()]
+        );
+    }
+}
@@ -0,0 +1,42 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use leptos::*;
+
+#[component]
+pub fn StepProgress(
+    steps: &'static [&'static str],
+    #[prop(into)] current: Signal<usize>,
+) -> impl IntoView {
+    view! {
+        <ul class="mb-8 flex flex-row gap-x-2">
+            {steps
+                .iter()
+                .enumerate()
+                .map(|(idx, title)| {
+                    view! {
+                        <li class="flex-1 group">
+                            <div class=move || {
+                                if current.get() >= idx {
+                                    "h-1 rounded-full bg-blue-600"
+                                } else {
+                                    "h-1 rounded-full bg-gray-200 dark:bg-gray-700"
+                                }
+                            }></div>
+                            <span class=move || {
+                                if current.get() == idx {
+                                    "mt-2 block text-xs font-medium text-blue-600"
+                                } else {
+                                    "mt-2 block text-xs font-medium text-gray-500"
+                                }
+                            }>{format!("{}. {}", idx + 1, title)}</span>
+                        </li>
+                    }
+                })
+                .collect_view()}
+        </ul>
+    }
+}
@@ -626,6 +626,22 @@ pub fn IconArrowUTurnLeft(
     }
 }
 
+#[component]
+pub fn IconArrowUTurnRight(
+    #[prop(optional)] size: Option<usize>,
+    #[prop(attrs)] attrs: Vec<(&'static str, Attribute)>,
+) -> impl IntoView {
+    view! {
+        <SvgWrapper size attrs>
+            <path
+                stroke-linecap="round"
+                stroke-linejoin="round"
+                d="m15 15 6-6m0 0-6-6m6 6H9a6 6 0 0 0 0 12h3"
+            ></path>
+        </SvgWrapper>
+    }
+}
+
 #[component]
 pub fn IconThreeDots(
     #[prop(optional)] size: Option<usize>,
@@ -9,12 +9,16 @@ use leptos::*;
 use leptos_router::use_navigate;
 
 use crate::{
-    components::icon::{
-        IconAdjustmentsHorizontal, IconHeart, IconPower, IconServer, IconUserCircle,
+    components::{
+        icon::{IconAdjustmentsHorizontal, IconHeart, IconPower, IconServer, IconUserCircle},
+        layout::tenant::{use_tenant_scope, TenantSelector},
+    },
+    core::{
+        oauth::use_authorization, password::reset_password_policy, url::UrlBuilder, AccessToken,
+        Permission, Permissions,
     },
-    core::{oauth::use_authorization, url::UrlBuilder, AccessToken, Permission, Permissions},
     pages::config::edit::DEFAULT_SETTINGS_URL,
-    STATE_STORAGE_KEY, VERSION_NAME,
+    IMPERSONATION_STORAGE_KEY, STATE_STORAGE_KEY, VERSION_NAME,
 };
 use web_sys::wasm_bindgen::JsCast;
 
@@ -109,6 +113,8 @@ pub fn Header(permissions: Memo<Option<Permissions>>) -> impl IntoView {
 
                     <div class="flex flex-row items-center justify-end gap-2 ms-auto">
 
+                        <TenantSelector/>
+
                         <div class="flex flex-row items-center justify-end gap-1">
                             <div class="hs-dropdown relative inline-flex">
 
@@ -294,6 +300,9 @@ pub fn Header(permissions: Memo<Option<Permissions>>) -> impl IntoView {
                                             class="flex items-center gap-x-3.5 py-2 px-3 rounded-lg text-sm text-gray-800 hover:bg-gray-100 focus:outline-none focus:bg-gray-100 dark:text-neutral-400 dark:hover:bg-neutral-700 dark:hover:text-neutral-300 dark:focus:bg-neutral-700 dark:focus:text-neutral-300"
                                             on:click=move |_| {
                                                 SessionStorage::delete(STATE_STORAGE_KEY);
+                                                SessionStorage::delete(IMPERSONATION_STORAGE_KEY);
+                                                reset_password_policy();
+                                                use_tenant_scope().set(None);
                                                 use_authorization().set(AccessToken::default());
                                                 use_navigate()("/login", Default::default());
                                             }
@@ -0,0 +1,80 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use gloo_storage::{SessionStorage, Storage};
+use leptos::*;
+use leptos_router::use_navigate;
+
+use crate::{
+    core::{oauth::use_authorization, password::reset_password_policy, AccessToken},
+    IMPERSONATION_STORAGE_KEY, STATE_STORAGE_KEY,
+};
+
+#[component]
+pub fn ImpersonationBanner() -> impl IntoView {
+    let auth = use_authorization();
+
+    view! {
+        <Show when=move || auth.get().is_impersonated()>
+            <div class="w-full bg-yellow-100 border-b border-yellow-300 text-sm text-yellow-900 lg:ps-64 dark:bg-yellow-800/30 dark:border-yellow-900 dark:text-yellow-400">
+                <div class="flex flex-wrap items-center justify-between gap-2 px-4 py-2 sm:px-6 md:px-8">
+                    <p>
+                        {move || {
+                            let auth = auth.get();
+                            format!(
+                                "Viewing as {} (impersonated by {}). Changes are recorded under the login {}. Pages asking for the current password expect your own password.",
+                                auth.username.split('%').next().unwrap_or_default(),
+                                auth.impersonator.as_deref().map(String::as_str).unwrap_or_default(),
+                                auth.username,
+                            )
+                        }}
+
+                    </p>
+                    <button
+                        type="button"
+                        class="py-1 px-3 inline-flex items-center gap-x-2 text-sm font-semibold rounded-lg border border-yellow-400 text-yellow-900 hover:bg-yellow-200 dark:border-yellow-700 dark:text-yellow-400 dark:hover:bg-yellow-800/40"
+                        on:click=move |_| end_impersonation()
+                    >
+                        "Return to admin session"
+                    </button>
+                </div>
+            </div>
+        </Show>
+    }
+}
+
+pub fn end_impersonation() {
+    let auth = use_authorization();
+    let account = auth
+        .get_untracked()
+        .username
+        .split('%')
+        .next()
+        .unwrap_or_default()
+        .to_string();
+
+    SessionStorage::delete(IMPERSONATION_STORAGE_KEY);
+    // The policy was loaded with the permissions of the impersonated account
+    reset_password_policy();
+    let admin = SessionStorage::get::<AccessToken>(STATE_STORAGE_KEY)
+        .map(|mut admin| {
+            // Refresh the admin token as it may have expired in the meantime
+            admin.is_valid = false;
+            admin
+        })
+        .unwrap_or_default();
+
+    if admin.is_logged_in() {
+        auth.set(admin);
+        use_navigate()(
+            &format!("/manage/directory/accounts/{account}/edit"),
+            Default::default(),
+        );
+    } else {
+        auth.set(AccessToken::default());
+        use_navigate()("/login", Default::default());
+    }
+}
@@ -5,7 +5,9 @@
  */
 
 pub mod header;
+pub mod impersonation;
 pub mod sidebar;
+pub mod tenant;
 pub mod toggle;
 
 use std::hash::{DefaultHasher, Hash, Hasher};
@@ -16,10 +18,15 @@ use leptos_router::Outlet;
 
 use crate::{
     components::{
-        layout::{header::Header, sidebar::SideBar, toggle::ToggleNavigation},
+        layout::{
+            header::Header, impersonation::ImpersonationBanner, sidebar::SideBar,
+            toggle::ToggleNavigation,
+        },
         messages::modal::Modal,
     },
     core::{
+        oauth::use_authorization,
+        password::load_password_policy,
         schema::{Schema, SchemaType},
         Permissions,
     },
@@ -52,10 +59,22 @@ pub fn Layout(
 ) -> impl IntoView {
     let menu_items_toggle = menu_items.clone();
     let show_sidebar = create_rw_signal(false);
+    let auth = use_authorization();
+
+    // Secrets are hashed synchronously, so the policy needs to be at hand
+    // before any form is submitted.
+    let _password_policy = create_resource(
+        move || auth.get(),
+        |auth| async move {
+            // Failures are logged and reported when a secret is hashed
+            let _ = load_password_policy(&auth).await;
+        },
+    );
 
     view! {
         <Body class="bg-gray-50 dark:bg-slate-900"/>
         <Modal/>
+        <ImpersonationBanner/>
         <Header permissions/>
         <ToggleNavigation menu_items show_sidebar/>
         <SideBar menu_items=menu_items_toggle show_sidebar/>
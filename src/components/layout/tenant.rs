@@ -0,0 +1,151 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use gloo_storage::{SessionStorage, Storage};
+use leptos::*;
+
+use crate::{
+    core::{http::HttpRequest, oauth::use_authorization, Permission},
+    pages::{directory::Principal, List},
+    TENANT_SCOPE_STORAGE_KEY,
+};
+
+// The scope is kept per browser tab so that staff can work on different
+// tenants side by side without one tab leaking into the other.
+#[derive(Clone, Copy)]
+pub struct TenantScope(RwSignal<Option<String>>);
+
+pub fn init_tenant_scope() {
+    provide_context(TenantScope(create_rw_signal(
+        SessionStorage::get::<String>(TENANT_SCOPE_STORAGE_KEY)
+            .ok()
+            .filter(|tenant| !tenant.is_empty()),
+    )));
+}
+
+pub fn use_tenant_scope() -> TenantScope {
+    expect_context::<TenantScope>()
+}
+
+impl TenantScope {
+    pub fn get(&self) -> Option<String> {
+        self.0.get()
+    }
+
+    pub fn get_untracked(&self) -> Option<String> {
+        self.0.get_untracked()
+    }
+
+    pub fn set(&self, tenant: Option<String>) {
+        let tenant = tenant.filter(|tenant| !tenant.is_empty());
+        let result = if let Some(tenant) = &tenant {
+            SessionStorage::set(TENANT_SCOPE_STORAGE_KEY, tenant)
+        } else {
+            SessionStorage::delete(TENANT_SCOPE_STORAGE_KEY);
+            Ok(())
+        };
+        if let Err(err) = result {
+            log::error!("Failed to save tenant scope to session storage: {}", err);
+        }
+        self.0.set(tenant);
+    }
+}
+
+#[component]
+pub fn TenantSelector() -> impl IntoView {
+    let auth = use_authorization();
+    let scope = use_tenant_scope();
+    let can_select = create_memo(move |_| {
+        let auth = auth.get();
+        auth.is_logged_in()
+            && auth.is_enterprise()
+            && auth.permissions().has_access(Permission::TenantList)
+    });
+
+    let tenants = create_resource(
+        move || can_select.get(),
+        move |can_select| {
+            let auth = auth.get_untracked();
+
+            async move {
+                if !can_select {
+                    return Vec::new();
+                }
+
+                match HttpRequest::get("/api/principal")
+                    .with_authorization(&auth)
+                    .with_parameter("types", "tenant")
+                    .with_parameter("fields", "name,description")
+                    .send::<List<Principal>>()
+                    .await
+                {
+                    Ok(list) => list
+                        .items
+                        .into_iter()
+                        .filter_map(|tenant| {
+                            let name = tenant.name()?.to_string();
+                            let description = tenant
+                                .description()
+                                .map(|d| format!("{d} ({name})"))
+                                .unwrap_or_else(|| name.clone());
+                            Some((name, description))
+                        })
+                        .collect::<Vec<_>>(),
+                    Err(err) => {
+                        log::warn!("Failed to fetch tenants: {:?}", err);
+                        Vec::new()
+                    }
+                }
+            }
+        },
+    );
+
+    view! {
+        <Show when=move || can_select.get()>
+            <select
+                class=move || {
+                    if scope.get().is_some() {
+                        "py-2 px-3 pe-9 block text-sm font-medium rounded-lg border-blue-600 bg-blue-50 text-blue-800 focus:border-blue-500 focus:ring-blue-500 dark:bg-blue-800/20 dark:border-blue-700 dark:text-blue-400"
+                    } else {
+                        "py-2 px-3 pe-9 block text-sm rounded-lg border-gray-200 focus:border-blue-500 focus:ring-blue-500 dark:bg-slate-900 dark:border-gray-700 dark:text-gray-400"
+                    }
+                }
+
+                title="Tenant scope"
+                on:change=move |ev| {
+                    scope.set(Some(event_target_value(&ev)));
+                }
+            >
+
+                <option value="" selected=move || scope.get().is_none()>
+                    "All tenants"
+                </option>
+                {move || {
+                    tenants
+                        .get()
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|(name, description)| {
+                            let is_selected = name.clone();
+                            view! {
+                                <option
+                                    value=name
+                                    selected=move || {
+                                        scope.get().is_some_and(|scope| scope == is_selected)
+                                    }
+                                >
+
+                                    {description}
+                                </option>
+                            }
+                        })
+                        .collect_view()
+                }}
+
+            </select>
+        </Show>
+    }
+}
@@ -220,4 +220,34 @@ impl ItemSelection {
     pub fn is_all(&self) -> bool {
         matches!(self, ItemSelection::All)
     }
+
+    // Bulk actions on a whole listing cannot be limited to a tenant, so while a
+    // tenant scope is active selecting all only covers the listed items.
+    pub fn scoped(self, is_scoped: bool, listed: &[String]) -> Self {
+        match self {
+            ItemSelection::All if is_scoped => {
+                ItemSelection::Some(listed.iter().cloned().collect())
+            }
+            selection => selection,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scopes_select_all_to_listed_items() {
+        let listed = vec!["1".to_string(), "2".to_string()];
+
+        assert!(ItemSelection::All.scoped(false, &listed).is_all());
+        let scoped = ItemSelection::All.scoped(true, &listed);
+        assert_eq!(scoped.total_selected(Some(100)), 2);
+        assert!(scoped.is_selected("2") && !scoped.is_selected("3"));
+
+        let some = ItemSelection::Some(AHashSet::from_iter(["3".to_string()]));
+        let some = some.scoped(true, &listed);
+        assert!(some.is_selected("3") && !some.is_selected("1"));
+    }
 }
@@ -13,12 +13,14 @@ use std::time::Duration;
 
 use ahash::AHashMap;
 use leptos::RwSignal;
+use serde::{Deserialize, Serialize};
 
 use crate::pages::config::{Settings, SettingsValues};
 
 use super::expr::parser::ExpressionParser;
 use super::expr::tokenizer::Tokenizer;
 use super::expr::{Constant, ParseValue, Token};
+use super::password::{hash_secret, password_policy};
 use super::schema::{NumberType, SchemaType, SelectType, Type};
 
 use super::schema::{InputCheck, Schema, Transformer, Validator};
@@ -32,22 +34,23 @@ pub struct FormData {
     pub external_sources: Arc<ExternalSources>,
     pub schema: Arc<Schema>,
     pub is_update: bool,
+    pub saved: Option<Arc<AHashMap<String, FormValue>>>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum FormValue {
     Value(String),
     Array(Vec<String>),
     Expression(Expression),
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub struct Expression {
     pub if_thens: Vec<ExpressionIfThen>,
     pub else_: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ExpressionIfThen {
     pub if_: String,
     pub then_: String,
@@ -107,6 +110,52 @@ impl FormData {
         );
     }
 
+    fn strong_password(&self, id: &str) -> Option<&'static [&'static str]> {
+        self.schema
+            .fields
+            .get(id)
+            .and_then(|field| field.input_check(self))
+            .and_then(|check| {
+                check
+                    .validators
+                    .iter()
+                    .find_map(|validator| match validator {
+                        Validator::IsStrongPassword { personal } => Some(*personal),
+                        _ => None,
+                    })
+            })
+    }
+
+    /// Values of the fields a password must not contain, as declared by its
+    /// strong password validator.
+    pub fn personal_values(&self, id: &str) -> Vec<&str> {
+        self.strong_password(id)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|id| self.value_as_str(id))
+            .map(|value| value.split('%').next().unwrap_or_default())
+            .collect()
+    }
+
+    /// Whether the password policy applies to the current value of a field.
+    /// Values loaded from the server are left alone, anything else is checked
+    /// even if it looks like a hash.
+    pub fn is_policy_checked(&self, id: &str) -> bool {
+        self.strong_password(id).is_some()
+            && self.value_as_str(id).is_some_and(|value| !value.is_empty())
+            && !self
+                .saved
+                .as_ref()
+                .is_some_and(|saved| saved.get(id) == self.values.get(id))
+    }
+
+    fn check_password(&self, id: &str, value: String) -> Result<String, Cow<'static, str>> {
+        if self.is_policy_checked(id) {
+            password_policy()?.check(&value, &self.personal_values(id))?;
+        }
+        Ok(value)
+    }
+
     pub fn value<T: FromStr>(&self, id: &str) -> Option<T> {
         self.values.get(id).and_then(|v| match v {
             FormValue::Value(v) => T::from_str(v.as_str()).ok(),
@@ -337,6 +386,32 @@ impl FormData {
         self.errors.remove(id);
     }
 
+    // Marks the current values as the ones loaded from the server, this also
+    // tells the undo history that a new record was loaded.
+    pub fn mark_saved(&mut self) {
+        self.saved = Some(Arc::new(self.values.clone()));
+    }
+
+    pub fn is_modified(&self, id: &str) -> bool {
+        self.saved
+            .as_ref()
+            .is_some_and(|saved| saved.get(id) != self.values.get(id))
+    }
+
+    pub fn reset_field(&mut self, id: &str) {
+        if let Some(saved) = self.saved.clone() {
+            match saved.get(id) {
+                Some(value) => {
+                    self.values.insert(id.to_string(), value.clone());
+                }
+                None => {
+                    self.values.remove(id);
+                }
+            }
+            self.errors.remove(id);
+        }
+    }
+
     fn cascading_reset(&mut self, id: &str) {
         let schema = self.schema.clone();
         let mut ids = vec![id.to_string()];
@@ -484,7 +559,9 @@ impl FormData {
                         typ: SelectType::Single,
                         ..
                     } => {
-                        match check.check_value(self.value::<String>(field.id).unwrap_or_default())
+                        match check
+                            .check_value(self.value::<String>(field.id).unwrap_or_default())
+                            .and_then(|value| self.check_password(field.id, value))
                         {
                             Ok(value) => {
                                 if !value.is_empty() {
@@ -785,6 +862,7 @@ impl FormData {
         } else {
             data.apply_defaults(false);
         }
+        data.mark_saved();
         data
     }
 
@@ -807,7 +885,7 @@ impl InputCheck {
                 Transformer::Uppercase => value.to_uppercase(),
                 Transformer::HashSecret => {
                     if !is_hashed_secret(&value) {
-                        pwhash::sha512_crypt::hash(value).unwrap()
+                        hash_secret(value)?
                     } else {
                         value
                     }
@@ -851,6 +929,12 @@ impl InputCheck {
                             return Err("This field must be a valid URL".into());
                         }
                     }
+                    Validator::IsRedirectUri => {
+                        check_redirect_uri(&value)?;
+                    }
+                    // Checked by the form, which knows the personal details
+                    // and whether the value came from the server
+                    Validator::IsStrongPassword { .. } => (),
                     Validator::IsDomain => {
                         if !value.contains('.') || value.starts_with('.') || value.ends_with('.') {
                             return Err("This field must be a valid domain name".into());
@@ -931,7 +1015,7 @@ impl InputCheck {
     }
 }
 
-fn is_hashed_secret(value: &str) -> bool {
+pub fn is_hashed_secret(value: &str) -> bool {
     if let Some(value) = value.strip_prefix('$') {
         value.starts_with("argon2")
             || value.starts_with("pbkdf2")
@@ -953,6 +1037,50 @@ fn is_hashed_secret(value: &str) -> bool {
     }
 }
 
+// Redirect URIs are matched verbatim by the authorization server, so anything
+// that would make the match ambiguous or leak the code in transit is rejected.
+fn check_redirect_uri(value: &str) -> Result<(), Cow<'static, str>> {
+    let Some((scheme, rest)) = value.split_once("://") else {
+        return Err("This field must be a valid URL".into());
+    };
+    if value.contains('#') {
+        return Err("Redirect URIs must not contain a fragment".into());
+    }
+    if value.contains('*') {
+        return Err("Redirect URIs must not contain wildcards".into());
+    }
+
+    let authority = rest
+        .split(['/', '?'])
+        .next()
+        .unwrap_or_default()
+        .rsplit('@')
+        .next()
+        .unwrap_or_default();
+    let host = if let Some(host) = authority.strip_prefix('[') {
+        host.split(']').next().unwrap_or_default()
+    } else {
+        authority.split(':').next().unwrap_or_default()
+    };
+    if host.is_empty() {
+        return Err("Redirect URIs must include a host".into());
+    }
+
+    match scheme.to_ascii_lowercase().as_str() {
+        "https" => Ok(()),
+        "http"
+            if host.eq_ignore_ascii_case("localhost")
+                || host == "::1"
+                || host
+                    .parse::<std::net::Ipv4Addr>()
+                    .is_ok_and(|ip| ip.is_loopback()) =>
+        {
+            Ok(())
+        }
+        _ => Err("Redirect URIs must use https unless they point to a loopback address".into()),
+    }
+}
+
 impl From<String> for FormValue {
     fn from(value: String) -> Self {
         FormValue::Value(value)
@@ -1025,3 +1153,46 @@ impl Default for FormValue {
         FormValue::Value("".to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_redirect_uris() {
+        for uri in [
+            "https://app.example.org/callback",
+            "https://user@app.example.org:8443/cb?x=1",
+            "http://localhost:8080/callback",
+            "http://127.0.0.1/callback",
+            "http://[::1]:3000/callback",
+        ] {
+            assert_eq!(check_redirect_uri(uri), Ok(()), "{uri}");
+        }
+        for uri in [
+            "app.example.org/callback",
+            "http://app.example.org/callback",
+            "http://localhost.example.org/callback",
+            "https://app.example.org/callback#section",
+            "https://*.example.org/callback",
+            "https:///callback",
+        ] {
+            assert!(check_redirect_uri(uri).is_err(), "{uri}");
+        }
+    }
+
+    #[test]
+    fn checks_passwords_not_loaded_from_the_server() {
+        let mut data = crate::build_schemas()
+            .build_form("change-pass")
+            .with_value("new-password", "$6$looks-hashed");
+        assert!(data.is_policy_checked("new-password"));
+        assert!(!data.is_policy_checked("old-password"));
+
+        data.mark_saved();
+        assert!(!data.is_policy_checked("new-password"));
+
+        data.set("new-password", "$6$typed-again");
+        assert!(data.is_policy_checked("new-password"));
+    }
+}
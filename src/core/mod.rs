@@ -9,10 +9,13 @@ use std::{fmt::Display, sync::Arc};
 use ahash::AHashSet;
 use serde::{Deserialize, Serialize};
 
+use crate::{IMPERSONATION_STORAGE_KEY, STATE_STORAGE_KEY};
+
 pub mod expr;
 pub mod form;
 pub mod http;
 pub mod oauth;
+pub mod password;
 pub mod schema;
 pub mod url;
 
@@ -27,6 +30,8 @@ pub struct AccessToken {
     pub is_valid: bool,
     pub is_enterprise: bool,
     pub permissions: Permissions,
+    #[serde(default)]
+    pub impersonator: Option<Arc<String>>,
 }
 
 #[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
@@ -141,6 +146,19 @@ impl AccessToken {
         &self.permissions
     }
 
+    pub fn is_impersonated(&self) -> bool {
+        self.impersonator.is_some()
+    }
+
+    // Impersonated sessions are kept apart so the admin session can be restored
+    pub fn storage_key(&self) -> &'static str {
+        if self.is_impersonated() {
+            IMPERSONATION_STORAGE_KEY
+        } else {
+            STATE_STORAGE_KEY
+        }
+    }
+
     // SPDX-SnippetBegin
     // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
     // SPDX-License-Identifier: LicenseRef-SEL
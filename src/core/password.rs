@@ -0,0 +1,691 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{borrow::Cow, cell::RefCell, sync::Arc};
+
+use ahash::{AHashMap, AHashSet};
+use pwhash::{bcrypt, sha512_crypt, HashSetup};
+use rand::{seq::SliceRandom, thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    http::{self, HttpRequest},
+    AccessToken, Permission,
+};
+
+// Bundled with the web admin, one password per line.
+const DICTIONARY_URL: &str = "/common-passwords.txt";
+const POLICY_PREFIX: &str = "authentication.password";
+const GENERATED_MIN_LENGTH: usize = 16;
+// Used by sha-crypt when the hash does not carry a "rounds=" parameter
+const SHA_CRYPT_DEFAULT_ROUNDS: u32 = 5000;
+const BCRYPT_DEFAULT_COST: u32 = 12;
+// Hashing runs on the main thread, higher costs would freeze the page
+const SHA_CRYPT_MAX_ROUNDS: u32 = 5_000_000;
+const BCRYPT_MAX_COST: u32 = 15;
+
+const LOWERCASE: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
+const UPPERCASE: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &[u8] = b"0123456789";
+// '$' and '%' are left out as they carry meaning in login names and TOTP suffixes
+const SYMBOLS: &[u8] = b"!#&*+-=?@^_";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CharClass {
+    Lowercase,
+    Uppercase,
+    Digit,
+    Symbol,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub required: Vec<CharClass>,
+    pub deny_personal: bool,
+    pub dictionary: Arc<AHashSet<String>>,
+    pub hash: HashScheme,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HashAlgorithm {
+    Sha512Crypt,
+    Bcrypt,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HashScheme {
+    pub algorithm: HashAlgorithm,
+    pub cost: u32,
+}
+
+/// Algorithm and cost parameters read back from an existing hash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HashInfo {
+    pub algorithm: &'static str,
+    pub cost: Option<String>,
+    family: HashFamily,
+    work: Option<u32>,
+}
+
+// Rough ordering of hash families by resistance to offline attacks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum HashFamily {
+    Legacy,
+    ShaCrypt,
+    Bcrypt,
+    Modern,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PasswordStrength {
+    Weak,
+    Fair,
+    Good,
+    Strong,
+}
+
+thread_local! {
+    static POLICY: RefCell<Option<Arc<PasswordPolicy>>> = const { RefCell::new(None) };
+}
+
+/// Returns the policy loaded for this session. Falling back to the defaults
+/// would silently ignore the configured rules and hashing scheme, so an error
+/// is returned until [`load_password_policy`] has succeeded.
+pub fn password_policy() -> Result<Arc<PasswordPolicy>, Cow<'static, str>> {
+    POLICY
+        .with(|policy| policy.borrow().clone())
+        .ok_or(Cow::Borrowed(
+            "The password policy could not be loaded yet, please try again in a moment",
+        ))
+}
+
+pub fn set_password_policy(policy: Arc<PasswordPolicy>) {
+    POLICY.with(|cached| *cached.borrow_mut() = Some(policy));
+}
+
+/// Drops the cached policy so the next call to [`load_password_policy`]
+/// fetches it again, e.g. after the settings were changed or on logout.
+pub fn reset_password_policy() {
+    POLICY.with(|policy| *policy.borrow_mut() = None);
+}
+
+/// Hashes an admin-set secret with the scheme configured in the policy.
+pub fn hash_secret(secret: impl AsRef<[u8]>) -> Result<String, Cow<'static, str>> {
+    password_policy().map(|policy| policy.hash.hash(secret))
+}
+
+/// Loads the password policy from the server settings and the bundled
+/// dictionary. Accounts that cannot read the settings get the defaults.
+pub async fn load_password_policy(auth: &AccessToken) -> http::Result<Arc<PasswordPolicy>> {
+    if let Some(policy) = POLICY.with(|policy| policy.borrow().clone()) {
+        return Ok(policy);
+    }
+
+    let mut policy = PasswordPolicy::default();
+    let mut use_dictionary = true;
+    if auth.permissions().has_access(Permission::SettingsList) {
+        match HttpRequest::get("/api/settings/keys")
+            .with_authorization(auth)
+            .with_parameter("prefixes", POLICY_PREFIX)
+            .send::<AHashMap<String, Option<String>>>()
+            .await
+        {
+            Ok(settings) => {
+                let setting = |key: &str| {
+                    settings
+                        .get(&format!("{POLICY_PREFIX}.{key}"))
+                        .and_then(|value| value.as_deref())
+                };
+                let is_enabled = |key: &str| setting(key).map(|value| value == "true");
+
+                if let Some(min_length) = setting("min-length").and_then(|value| value.parse().ok())
+                {
+                    policy.min_length = min_length;
+                }
+                policy.required = [
+                    ("require-lowercase", CharClass::Lowercase),
+                    ("require-uppercase", CharClass::Uppercase),
+                    ("require-digit", CharClass::Digit),
+                    ("require-symbol", CharClass::Symbol),
+                ]
+                .into_iter()
+                .filter(|(key, _)| is_enabled(key).unwrap_or_default())
+                .map(|(_, class)| class)
+                .collect();
+                policy.deny_personal = is_enabled("deny-personal").unwrap_or(policy.deny_personal);
+                use_dictionary = is_enabled("dictionary").unwrap_or(use_dictionary);
+                if let Some(algorithm) = setting("hash.algorithm").and_then(HashAlgorithm::from_id)
+                {
+                    policy.hash = HashScheme {
+                        algorithm,
+                        cost: setting(algorithm.cost_key())
+                            .and_then(|value| value.parse().ok())
+                            .unwrap_or_else(|| algorithm.default_cost())
+                            .min(algorithm.max_cost()),
+                    };
+                }
+            }
+            Err(err) => {
+                // Not cached so the policy is fetched again on the next call
+                log::warn!("Failed to load password policy: {err:?}");
+                return Err(err);
+            }
+        }
+    }
+
+    if use_dictionary {
+        match HttpRequest::get(DICTIONARY_URL).send_raw().await {
+            Ok(contents) => {
+                policy.dictionary = Arc::new(
+                    String::from_utf8_lossy(&contents)
+                        .lines()
+                        .map(|line| line.trim().to_lowercase())
+                        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                        .collect(),
+                );
+            }
+            Err(err) => {
+                log::warn!("Failed to load password dictionary: {err:?}");
+            }
+        }
+    }
+
+    let policy = Arc::new(policy);
+    set_password_policy(policy.clone());
+    Ok(policy)
+}
+
+impl PasswordPolicy {
+    pub fn check(&self, password: &str, personal: &[&str]) -> Result<(), String> {
+        match self.violations(password, personal).into_iter().next() {
+            Some(violation) => Err(violation),
+            None => Ok(()),
+        }
+    }
+
+    pub fn violations(&self, password: &str, personal: &[&str]) -> Vec<String> {
+        let mut violations = vec![];
+
+        if password.chars().count() < self.min_length {
+            violations.push(format!(
+                "Passwords must be at least {} characters long",
+                self.min_length
+            ));
+        }
+        for class in &self.required {
+            if !password.chars().any(|ch| class.matches(ch)) {
+                violations.push(format!("Passwords must contain {}", class.description()));
+            }
+        }
+
+        let password = password.to_lowercase();
+        if self.deny_personal
+            && personal
+                .iter()
+                .flat_map(|value| {
+                    let value = value.trim();
+                    [Some(value), value.split_once('@').map(|(local, _)| local)]
+                })
+                .flatten()
+                .any(|value| value.len() >= 3 && password.contains(&value.to_lowercase()))
+        {
+            violations.push("Passwords must not contain the login name or e-mail address".into());
+        }
+        if self.dictionary.contains(&password) {
+            violations.push("This password is too common or has appeared in a data breach".into());
+        }
+
+        violations
+    }
+
+    pub fn strength(&self, password: &str, personal: &[&str]) -> PasswordStrength {
+        if !self.violations(password, personal).is_empty() {
+            return PasswordStrength::Weak;
+        }
+
+        let length = password.chars().count();
+        let classes = CharClass::ALL
+            .iter()
+            .filter(|class| password.chars().any(|ch| class.matches(ch)))
+            .count();
+        match (length >= 12) as u8
+            + (length >= 16) as u8
+            + (classes >= 3) as u8
+            + (classes == 4) as u8
+        {
+            0 | 1 => PasswordStrength::Fair,
+            2 | 3 => PasswordStrength::Good,
+            _ => PasswordStrength::Strong,
+        }
+    }
+
+    pub fn generate(&self) -> String {
+        let mut rng = thread_rng();
+        let length = self.min_length.max(GENERATED_MIN_LENGTH);
+
+        // One character from every class so any combination of rules is met
+        let mut password = CharClass::ALL
+            .iter()
+            .map(|class| *class.alphabet().choose(&mut rng).unwrap() as char)
+            .collect::<Vec<_>>();
+        while password.len() < length {
+            let class = CharClass::ALL[rng.gen_range(0..CharClass::ALL.len())];
+            password.push(*class.alphabet().choose(&mut rng).unwrap() as char);
+        }
+        password.shuffle(&mut rng);
+
+        password.into_iter().collect()
+    }
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: 8,
+            required: vec![],
+            deny_personal: false,
+            dictionary: Default::default(),
+            hash: HashScheme::default(),
+        }
+    }
+}
+
+impl HashAlgorithm {
+    pub fn id(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha512Crypt => "sha512-crypt",
+            HashAlgorithm::Bcrypt => "bcrypt",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "sha512-crypt" => Some(HashAlgorithm::Sha512Crypt),
+            "bcrypt" => Some(HashAlgorithm::Bcrypt),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha512Crypt => "SHA-512-crypt",
+            HashAlgorithm::Bcrypt => "bcrypt",
+        }
+    }
+
+    fn cost_key(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha512Crypt => "hash.rounds",
+            HashAlgorithm::Bcrypt => "hash.cost",
+        }
+    }
+
+    fn default_cost(&self) -> u32 {
+        match self {
+            HashAlgorithm::Sha512Crypt => SHA_CRYPT_DEFAULT_ROUNDS,
+            HashAlgorithm::Bcrypt => BCRYPT_DEFAULT_COST,
+        }
+    }
+
+    fn max_cost(&self) -> u32 {
+        match self {
+            HashAlgorithm::Sha512Crypt => SHA_CRYPT_MAX_ROUNDS,
+            HashAlgorithm::Bcrypt => BCRYPT_MAX_COST,
+        }
+    }
+
+    fn describe_cost(&self, cost: u32) -> Option<String> {
+        Some(match self {
+            HashAlgorithm::Sha512Crypt => format!("{cost} rounds"),
+            HashAlgorithm::Bcrypt => format!("cost {cost}"),
+        })
+    }
+
+    fn family(&self) -> HashFamily {
+        match self {
+            HashAlgorithm::Sha512Crypt => HashFamily::ShaCrypt,
+            HashAlgorithm::Bcrypt => HashFamily::Bcrypt,
+        }
+    }
+}
+
+impl HashScheme {
+    pub fn hash(&self, secret: impl AsRef<[u8]>) -> String {
+        let cost = self.cost.min(self.algorithm.max_cost());
+        let setup = HashSetup {
+            salt: None,
+            rounds: Some(cost),
+        };
+        match self.algorithm {
+            HashAlgorithm::Sha512Crypt => sha512_crypt::hash_with(setup, secret),
+            HashAlgorithm::Bcrypt => bcrypt::hash_with(
+                bcrypt::BcryptSetup {
+                    cost: Some(cost.max(bcrypt::MIN_COST)),
+                    ..Default::default()
+                },
+                secret,
+            ),
+        }
+        .unwrap()
+    }
+
+    pub fn description(&self) -> String {
+        HashInfo {
+            algorithm: self.algorithm.label(),
+            cost: self.algorithm.describe_cost(self.cost),
+            family: self.algorithm.family(),
+            work: Some(self.cost),
+        }
+        .to_string()
+    }
+}
+
+impl Default for HashScheme {
+    fn default() -> Self {
+        HashScheme {
+            algorithm: HashAlgorithm::Sha512Crypt,
+            cost: SHA_CRYPT_DEFAULT_ROUNDS,
+        }
+    }
+}
+
+impl HashInfo {
+    /// Parses the algorithm and cost out of a stored hash, or returns `None`
+    /// if the value is not a recognised hash.
+    pub fn parse(hash: &str) -> Option<Self> {
+        if let Some(hash) = hash.strip_prefix('{') {
+            let (scheme, _) = hash.split_once('}')?;
+            let (algorithm, family) = match scheme.to_uppercase().as_str() {
+                "ARGON2" | "ARGON2I" | "ARGON2ID" => ("Argon2", HashFamily::Modern),
+                "PBKDF2" | "PBKDF2-SHA256" | "PBKDF2-SHA512" => ("PBKDF2", HashFamily::Modern),
+                "SSHA" => ("Salted SHA-1", HashFamily::Legacy),
+                "SHA" => ("SHA-1", HashFamily::Legacy),
+                "SSHA256" | "SHA256" => ("SHA-256", HashFamily::Legacy),
+                "SSHA512" | "SHA512" => ("SHA-512", HashFamily::Legacy),
+                "MD5" | "SMD5" => ("MD5", HashFamily::Legacy),
+                "CRYPT" => ("crypt", HashFamily::Legacy),
+                _ => return None,
+            };
+            return Some(HashInfo {
+                algorithm,
+                cost: None,
+                family,
+                work: None,
+            });
+        }
+
+        let mut parts = hash.strip_prefix('$')?.split('$');
+        let id = parts.next()?;
+        let params = parts.next().unwrap_or_default();
+        let (algorithm, family, work, cost) = match id {
+            "6" | "5" => {
+                let rounds = params
+                    .strip_prefix("rounds=")
+                    .and_then(|rounds| rounds.parse().ok())
+                    .unwrap_or(SHA_CRYPT_DEFAULT_ROUNDS);
+                (
+                    if id == "6" {
+                        "SHA-512-crypt"
+                    } else {
+                        "SHA-256-crypt"
+                    },
+                    HashFamily::ShaCrypt,
+                    Some(rounds),
+                    HashAlgorithm::Sha512Crypt.describe_cost(rounds),
+                )
+            }
+            "2" | "2a" | "2b" | "2x" | "2y" => {
+                let cost = params.parse().ok()?;
+                (
+                    HashAlgorithm::Bcrypt.label(),
+                    HashFamily::Bcrypt,
+                    Some(cost),
+                    HashAlgorithm::Bcrypt.describe_cost(cost),
+                )
+            }
+            "1" => ("MD5-crypt", HashFamily::Legacy, None, None),
+            "sha1" => (
+                "SHA-1-crypt",
+                HashFamily::Legacy,
+                None,
+                Some(format!("{params} rounds")),
+            ),
+            "argon2i" | "argon2d" | "argon2id" => (
+                "Argon2",
+                HashFamily::Modern,
+                None,
+                // Skip the version so the memory and time costs are shown
+                parts.next().map(|params| params.replace(',', ", ")),
+            ),
+            "scrypt" => (
+                "scrypt",
+                HashFamily::Modern,
+                None,
+                Some(params.replace(',', ", ")),
+            ),
+            id if id.starts_with("pbkdf2") => (
+                "PBKDF2",
+                HashFamily::Modern,
+                None,
+                Some(format!(
+                    "{} iterations",
+                    params.strip_prefix("i=").unwrap_or(params)
+                )),
+            ),
+            _ => return None,
+        };
+
+        Some(HashInfo {
+            algorithm,
+            cost,
+            family,
+            work,
+        })
+    }
+
+    /// Whether this hash is weaker than what the scheme would produce today.
+    /// Only hashes from the same algorithm family can be compared by cost.
+    pub fn is_weaker_than(&self, scheme: &HashScheme) -> bool {
+        let family = scheme.algorithm.family();
+        self.family < family
+            || (self.family == family && self.work.is_some_and(|work| work < scheme.cost))
+    }
+}
+
+impl std::fmt::Display for HashInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.cost {
+            Some(cost) => write!(f, "{} ({cost})", self.algorithm),
+            None => f.write_str(self.algorithm),
+        }
+    }
+}
+
+impl CharClass {
+    pub const ALL: &'static [CharClass] = &[
+        CharClass::Lowercase,
+        CharClass::Uppercase,
+        CharClass::Digit,
+        CharClass::Symbol,
+    ];
+
+    fn matches(&self, ch: char) -> bool {
+        match self {
+            CharClass::Lowercase => ch.is_lowercase(),
+            CharClass::Uppercase => ch.is_uppercase(),
+            CharClass::Digit => ch.is_numeric(),
+            CharClass::Symbol => !ch.is_alphanumeric() && !ch.is_whitespace(),
+        }
+    }
+
+    fn alphabet(&self) -> &'static [u8] {
+        match self {
+            CharClass::Lowercase => LOWERCASE,
+            CharClass::Uppercase => UPPERCASE,
+            CharClass::Digit => DIGITS,
+            CharClass::Symbol => SYMBOLS,
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            CharClass::Lowercase => "a lowercase letter",
+            CharClass::Uppercase => "an uppercase letter",
+            CharClass::Digit => "a digit",
+            CharClass::Symbol => "a symbol",
+        }
+    }
+}
+
+impl PasswordStrength {
+    pub fn label(&self) -> &'static str {
+        match self {
+            PasswordStrength::Weak => "Weak",
+            PasswordStrength::Fair => "Fair",
+            PasswordStrength::Good => "Good",
+            PasswordStrength::Strong => "Strong",
+        }
+    }
+
+    pub fn score(&self) -> usize {
+        match self {
+            PasswordStrength::Weak => 1,
+            PasswordStrength::Fair => 2,
+            PasswordStrength::Good => 3,
+            PasswordStrength::Strong => 4,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> PasswordPolicy {
+        PasswordPolicy {
+            min_length: 10,
+            required: vec![CharClass::Uppercase, CharClass::Digit],
+            dictionary: Arc::new(["password123a".to_string()].into_iter().collect()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn reports_every_violation() {
+        let policy = policy();
+
+        assert_eq!(policy.violations("short", &[]).len(), 3);
+        assert!(policy.check("Correct-Horse-9", &[]).is_ok());
+        assert_eq!(
+            policy.check("Password123A", &[]),
+            Err("This password is too common or has appeared in a data breach".to_string())
+        );
+    }
+
+    #[test]
+    fn personal_details_are_opt_in() {
+        let mut policy = policy();
+        let personal = ["jdoe", "john.doe@example.org"];
+
+        assert!(!PasswordPolicy::default().deny_personal);
+        assert!(policy.check("Jdoe-Secret-42", &personal).is_ok());
+
+        policy.deny_personal = true;
+        assert!(policy.check("Jdoe-Secret-42", &personal).is_err());
+        assert!(policy.check("xJohn.Doe-Secret-42", &personal).is_err());
+        assert!(policy.check("Correct-Horse-9", &personal).is_ok());
+    }
+
+    #[test]
+    fn rates_strength() {
+        let policy = PasswordPolicy::default();
+
+        assert_eq!(policy.strength("abc", &[]), PasswordStrength::Weak);
+        assert_eq!(policy.strength("abcdefgh", &[]), PasswordStrength::Fair);
+        assert_eq!(
+            policy.strength("abcdefgh12345678", &[]),
+            PasswordStrength::Good
+        );
+        assert_eq!(
+            policy.strength("Abcdefgh-1234567", &[]),
+            PasswordStrength::Strong
+        );
+    }
+
+    #[test]
+    fn refuses_to_hash_without_a_policy() {
+        POLICY.with(|policy| *policy.borrow_mut() = None);
+        assert!(hash_secret("secret").is_err());
+
+        set_password_policy(Arc::new(PasswordPolicy {
+            hash: HashScheme {
+                algorithm: HashAlgorithm::Sha512Crypt,
+                cost: 6000,
+            },
+            ..Default::default()
+        }));
+        let hash = hash_secret("secret").unwrap();
+        assert!(hash.starts_with("$6$rounds=6000$"), "{hash}");
+    }
+
+    #[test]
+    fn parses_hashes() {
+        let info = HashInfo::parse("$6$rounds=10000$salt$hash").unwrap();
+        assert_eq!(info.to_string(), "SHA-512-crypt (10000 rounds)");
+        assert_eq!(
+            HashInfo::parse("$6$salt$hash").unwrap().to_string(),
+            "SHA-512-crypt (5000 rounds)"
+        );
+        assert_eq!(
+            HashInfo::parse("$2b$10$abcdefghijklmnopqrstuv")
+                .unwrap()
+                .to_string(),
+            "bcrypt (cost 10)"
+        );
+        assert_eq!(
+            HashInfo::parse("$argon2id$v=19$m=19456,t=2,p=1$salt$hash")
+                .unwrap()
+                .to_string(),
+            "Argon2 (m=19456, t=2, p=1)"
+        );
+        assert_eq!(
+            HashInfo::parse("{SSHA}abc").unwrap().algorithm,
+            "Salted SHA-1"
+        );
+        assert_eq!(HashInfo::parse("plaintext"), None);
+        assert_eq!(HashInfo::parse("{UNKNOWN}abc"), None);
+    }
+
+    #[test]
+    fn compares_hash_strength() {
+        let bcrypt = HashScheme {
+            algorithm: HashAlgorithm::Bcrypt,
+            cost: 12,
+        };
+        let parse = |hash: &str| HashInfo::parse(hash).unwrap();
+
+        assert!(parse("$6$rounds=900000$salt$hash").is_weaker_than(&bcrypt));
+        assert!(parse("$2b$10$abc").is_weaker_than(&bcrypt));
+        assert!(!parse("$2b$12$abc").is_weaker_than(&bcrypt));
+        assert!(!parse("$argon2id$v=19$m=19456,t=2,p=1$salt$hash").is_weaker_than(&bcrypt));
+        assert!(parse("{SHA}abc").is_weaker_than(&HashScheme::default()));
+        assert!(!parse("$6$rounds=5000$salt$hash").is_weaker_than(&HashScheme::default()));
+    }
+
+    #[test]
+    fn generates_compliant_passwords() {
+        let mut policy = policy();
+        policy.required = CharClass::ALL.to_vec();
+        policy.min_length = 20;
+
+        for _ in 0..20 {
+            let password = policy.generate();
+            assert_eq!(password.len(), 20);
+            assert!(policy.check(&password, &[]).is_ok(), "{password}");
+        }
+    }
+}
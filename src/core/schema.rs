@@ -4,7 +4,7 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::{hash::Hasher, sync::Arc};
+use std::{hash::Hasher, sync::Arc, time::Duration};
 
 use ahash::AHashMap;
 
@@ -67,6 +67,7 @@ pub struct Field {
     pub display: Vec<Eval>,
     pub readonly: bool,
     pub enterprise: bool,
+    pub min_interval: Option<Duration>,
 }
 
 #[derive(Clone, Default, Debug)]
@@ -208,6 +209,8 @@ pub enum Validator {
     IsPort,
     IsIpOrMask,
     IsUrl,
+    IsRedirectUri,
+    IsStrongPassword { personal: &'static [&'static str] },
     IsRegex,
     IsSocketAddr,
     MinLength(usize),
@@ -253,6 +256,7 @@ impl From<Arc<Schema>> for FormData {
             external_sources: Default::default(),
             schema,
             is_update: false,
+            saved: None,
         }
     }
 }
@@ -633,6 +637,11 @@ impl Builder<(Schemas, Schema), Field> {
         self
     }
 
+    pub fn min_interval(mut self, interval: Duration) -> Self {
+        self.item.min_interval = Some(interval);
+        self
+    }
+
     pub fn typ(mut self, typ_: Type<&'static str, &'static str>) -> Self {
         self.item.typ_ = match typ_ {
             Type::Select {
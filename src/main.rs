@@ -27,7 +27,13 @@ use pages::{
         mfa::ManageMfa,
     },
     config::edit::DEFAULT_SETTINGS_URL,
-    directory::{dns::DnsDisplay, edit::PrincipalEdit, list::PrincipalList},
+    directory::{
+        addresses::DomainAddresses, api_key::ApiKeyManager, bulk::PrincipalBulkEdit,
+        dkim::DkimKeys, dns::DnsDisplay, edit::PrincipalEdit, export::PrincipalExport,
+        graph::MembershipGraphView, impersonate::Impersonate, import::PrincipalImport,
+        list::PrincipalList, matrix::RoleMatrix, oauth_client::OAuthClientEdit,
+        onboard::DomainOnboarding, quota::QuotaReport, subscribers::ListSubscribers,
+    },
     manage::{
         spam::{SpamTest, SpamTrain},
         troubleshoot::{TroubleshootDelivery, TroubleshootDmarc},
@@ -49,14 +55,16 @@ pub static VERSION_NAME: &str = concat!("Stalwart Management UI v", env!("CARGO_
 
 use crate::{
     components::{
-        layout::{Layout, LayoutBuilder},
+        layout::{tenant::init_tenant_scope, Layout, LayoutBuilder},
         messages::{alert::init_alerts, modal::init_modals},
     },
     core::oauth::oauth_refresh_token,
     pages::{
         account::{crypto::ManageCrypto, password::ChangePassword},
         authorize::Authorize,
-        config::{edit::SettingsEdit, list::SettingsList, search::SettingsSearch},
+        config::{
+            edit::SettingsEdit, list::SettingsList, search::SettingsSearch, setup::SetupWizard,
+        },
         login::Login,
         manage::{logs::Logs, maintenance::Maintenance},
         notfound::NotFound,
@@ -74,6 +82,14 @@ pub mod pages;
 
 pub const STATE_STORAGE_KEY: &str = "webadmin_state";
 pub const STATE_LOGIN_NAME_KEY: &str = "webadmin_login_name";
+pub const ONBOARDING_STORAGE_KEY: &str = "webadmin_domain_onboarding";
+pub const DRAFT_STORAGE_KEY: &str = "webadmin_draft";
+pub const BULK_SELECTION_STORAGE_KEY: &str = "webadmin_bulk_selection";
+pub const TENANT_SCOPE_STORAGE_KEY: &str = "webadmin_tenant_scope";
+pub const DNS_RESOLVER_STORAGE_KEY: &str = "webadmin_dns_resolver";
+pub const IMPERSONATION_STORAGE_KEY: &str = "webadmin_impersonation";
+pub const CRON_THRESHOLD_STORAGE_KEY: &str = "webadmin_cron_min_interval";
+pub const DKIM_ROTATION_STORAGE_KEY: &str = "webadmin_dkim_rotation";
 
 fn main() {
     _ = console_log::init_with_level(log::Level::Debug);
@@ -84,7 +100,8 @@ fn main() {
 #[component]
 pub fn App() -> impl IntoView {
     let auth_token = create_rw_signal(
-        SessionStorage::get::<AccessToken>(STATE_STORAGE_KEY)
+        SessionStorage::get::<AccessToken>(IMPERSONATION_STORAGE_KEY)
+            .or_else(|_| SessionStorage::get::<AccessToken>(STATE_STORAGE_KEY))
             .map(|mut t| {
                 // Force token refresh on reload
                 t.is_valid = false;
@@ -97,6 +114,7 @@ pub fn App() -> impl IntoView {
     provide_context(build_schemas());
     init_alerts();
     init_modals();
+    init_tenant_scope();
 
     // Create a resource to refresh the OAuth token
     let _refresh_token_resource = create_resource(
@@ -119,7 +137,7 @@ pub fn App() -> impl IntoView {
                             auth_token.is_valid = true;
 
                             if let Err(err) =
-                                SessionStorage::set(STATE_STORAGE_KEY, auth_token.clone())
+                                SessionStorage::set(auth_token.storage_key(), auth_token.clone())
                             {
                                 log::error!(
                                     "Failed to save authorization token to session storage: {}",
@@ -205,6 +223,7 @@ pub fn App() -> impl IntoView {
                                             Permission::MailingListList,
                                             Permission::OauthClientList,
                                             Permission::ApiKeyList,
+                                            Permission::PrincipalList,
                                         ],
                                     )
                                 })
@@ -229,12 +248,235 @@ pub fn App() -> impl IntoView {
                                             Permission::MailingListList,
                                             Permission::OauthClientList,
                                             Permission::ApiKeyList,
+                                            Permission::PrincipalList,
+                                        ],
+                                    )
+                                })
+                        }
+                    />
+
+                    <ProtectedRoute
+                        path="/directory/oauth-clients/:id?/edit"
+                        view=OAuthClientEdit
+                        redirect_path="/login"
+                        condition=move || {
+                            permissions
+                                .get()
+                                .is_some_and(|p| {
+                                    p.has_access_any(
+                                        &[
+                                            Permission::OauthClientCreate,
+                                            Permission::OauthClientUpdate,
+                                        ],
+                                    )
+                                })
+                        }
+                    />
+
+                    <ProtectedRoute
+                        path="/directory/domains/onboard"
+                        view=DomainOnboarding
+                        redirect_path="/login"
+                        condition=move || {
+                            permissions
+                                .get()
+                                .is_some_and(|p| {
+                                    p.has_access_all(
+                                        &[Permission::DomainCreate, Permission::DomainGet],
+                                    )
+                                })
+                        }
+                    />
+
+                    <ProtectedRoute
+                        path="/directory/:object/export"
+                        view=PrincipalExport
+                        redirect_path="/login"
+                        condition=move || {
+                            permissions
+                                .get()
+                                .is_some_and(|p| {
+                                    p.has_access_any(
+                                        &[
+                                            Permission::IndividualList,
+                                            Permission::GroupList,
+                                            Permission::RoleList,
+                                            Permission::TenantList,
+                                            Permission::DomainList,
+                                            Permission::MailingListList,
+                                            Permission::OauthClientList,
+                                            Permission::ApiKeyList,
+                                        ],
+                                    )
+                                })
+                        }
+                    />
+
+                    <ProtectedRoute
+                        path="/directory/domains/:id/addresses"
+                        view=DomainAddresses
+                        redirect_path="/login"
+                        condition=move || {
+                            permissions
+                                .get()
+                                .is_some_and(|p| {
+                                    p.has_access_all(
+                                        &[Permission::DomainList, Permission::IndividualList],
+                                    )
+                                })
+                        }
+                    />
+
+                    <ProtectedRoute
+                        path="/directory/domains/:id/dkim"
+                        view=DkimKeys
+                        redirect_path="/login"
+                        condition=move || {
+                            permissions
+                                .get()
+                                .is_some_and(|p| {
+                                    p.has_access_all(
+                                        &[
+                                            Permission::DkimSignatureCreate,
+                                            Permission::SettingsList,
+                                            Permission::SettingsUpdate,
+                                            Permission::SettingsReload,
+                                        ],
+                                    )
+                                })
+                        }
+                    />
+
+                    <ProtectedRoute
+                        path="/directory/lists/:id/subscribers"
+                        view=ListSubscribers
+                        redirect_path="/login"
+                        condition=move || {
+                            permissions
+                                .get()
+                                .is_some_and(|p| {
+                                    p.has_access_all(
+                                        &[Permission::MailingListGet, Permission::MailingListUpdate],
+                                    )
+                                })
+                        }
+                    />
+
+                    <ProtectedRoute
+                        path="/directory/api-keys/lifecycle"
+                        view=ApiKeyManager
+                        redirect_path="/login"
+                        condition=move || {
+                            permissions
+                                .get()
+                                .is_some_and(|p| {
+                                    p.has_access_all(
+                                        &[
+                                            Permission::ApiKeyList,
+                                            Permission::ApiKeyCreate,
+                                            Permission::ApiKeyUpdate,
+                                            Permission::SettingsList,
+                                            Permission::SettingsUpdate,
+                                        ],
+                                    )
+                                })
+                        }
+                    />
+
+                    <ProtectedRoute
+                        path="/directory/quota-report"
+                        view=QuotaReport
+                        redirect_path="/login"
+                        condition=move || {
+                            permissions
+                                .get()
+                                .is_some_and(|p| p.has_access(Permission::IndividualList))
+                        }
+                    />
+
+                    <ProtectedRoute
+                        path="/directory/graph"
+                        view=MembershipGraphView
+                        redirect_path="/login"
+                        condition=move || {
+                            permissions
+                                .get()
+                                .is_some_and(|p| {
+                                    p.has_access_any(
+                                        &[
+                                            Permission::IndividualGet,
+                                            Permission::GroupGet,
+                                            Permission::MailingListGet,
+                                            Permission::RoleGet,
+                                        ],
+                                    )
+                                })
+                        }
+                    />
+
+                    <ProtectedRoute
+                        path="/directory/roles/matrix"
+                        view=RoleMatrix
+                        redirect_path="/login"
+                        condition=move || {
+                            permissions
+                                .get()
+                                .is_some_and(|p| {
+                                    p.has_access_all(&[Permission::RoleList, Permission::RoleUpdate])
+                                })
+                        }
+                    />
+
+                    <ProtectedRoute
+                        path="/directory/:object/bulk"
+                        view=PrincipalBulkEdit
+                        redirect_path="/login"
+                        condition=move || {
+                            permissions
+                                .get()
+                                .is_some_and(|p| {
+                                    p.has_access_any(
+                                        &[
+                                            Permission::IndividualUpdate,
+                                            Permission::GroupUpdate,
+                                            Permission::RoleUpdate,
+                                            Permission::TenantUpdate,
+                                            Permission::DomainUpdate,
+                                            Permission::MailingListUpdate,
+                                            Permission::OauthClientUpdate,
+                                            Permission::ApiKeyUpdate,
                                         ],
                                     )
                                 })
                         }
                     />
 
+                    <ProtectedRoute
+                        path="/directory/accounts/:id/impersonate"
+                        view=Impersonate
+                        redirect_path="/login"
+                        condition=move || {
+                            permissions
+                                .get()
+                                .is_some_and(|p| {
+                                    p.has_access_all(
+                                        &[Permission::IndividualList, Permission::Impersonate],
+                                    )
+                                })
+                        }
+                    />
+
+                    <ProtectedRoute
+                        path="/directory/accounts/import"
+                        view=PrincipalImport
+                        redirect_path="/login"
+                        condition=move || {
+                            permissions
+                                .get()
+                                .is_some_and(|p| p.has_access(Permission::IndividualCreate))
+                        }
+                    />
+
                     <ProtectedRoute
                         path="/dns/:id/view"
                         view=DnsDisplay
@@ -468,6 +710,21 @@ pub fn App() -> impl IntoView {
                         }
                     />
 
+                    <ProtectedRoute
+                        path="/setup"
+                        view=SetupWizard
+                        redirect_path="/login"
+                        condition=move || {
+                            permissions
+                                .get()
+                                .is_some_and(|p| {
+                                    p.has_access_all(
+                                        &[Permission::SettingsUpdate, Permission::SettingsReload],
+                                    )
+                                })
+                        }
+                    />
+
                     <ProtectedRoute
                         path="/search"
                         view=SettingsSearch
@@ -609,6 +866,18 @@ impl LayoutBuilder {
                 .create("OAuth Clients")
                 .route("/directory/oauth-clients")
                 .insert(permissions.has_access(Permission::OauthClientList))
+                .create("Resources")
+                .route("/directory/resources")
+                .insert(permissions.has_access(Permission::PrincipalList))
+                .create("Locations")
+                .route("/directory/locations")
+                .insert(permissions.has_access(Permission::PrincipalList))
+                .create("Other")
+                .route("/directory/other")
+                .insert(permissions.has_access(Permission::PrincipalList))
+                .create("Quota Report")
+                .route("/directory/quota-report")
+                .insert(permissions.has_access(Permission::IndividualList))
                 .insert(permissions.has_access_any(&[
                     Permission::IndividualList,
                     Permission::GroupList,
@@ -618,6 +887,7 @@ impl LayoutBuilder {
                     Permission::MailingListList,
                     Permission::OauthClientList,
                     Permission::ApiKeyList,
+                    Permission::PrincipalList,
                 ]))
                 .create("Queues")
                 .icon(view! { <IconQueueList/> })
@@ -723,6 +993,18 @@ impl LayoutBuilder {
                 .create("OAuth Clients")
                 .route("/directory/oauth-clients")
                 .insert(permissions.has_access(Permission::OauthClientList))
+                .create("Resources")
+                .route("/directory/resources")
+                .insert(permissions.has_access(Permission::PrincipalList))
+                .create("Locations")
+                .route("/directory/locations")
+                .insert(permissions.has_access(Permission::PrincipalList))
+                .create("Other")
+                .route("/directory/other")
+                .insert(permissions.has_access(Permission::PrincipalList))
+                .create("Quota Report")
+                .route("/directory/quota-report")
+                .insert(permissions.has_access(Permission::IndividualList))
                 .insert(permissions.has_access_any(&[
                     Permission::IndividualList,
                     Permission::GroupList,
@@ -732,6 +1014,7 @@ impl LayoutBuilder {
                     Permission::MailingListList,
                     Permission::OauthClientList,
                     Permission::ApiKeyList,
+                    Permission::PrincipalList,
                 ]))
                 .create("Queues")
                 .icon(view! { <IconQueueList/> })
@@ -830,6 +1113,17 @@ pub fn build_schemas() -> Arc<Schemas> {
     Schemas::builder()
         .build_login()
         .build_principals()
+        .build_onboarding()
+        .build_principal_import()
+        .build_principal_export()
+        .build_principal_bulk()
+        .build_quota_report()
+        .build_list_subscribers()
+        .build_dkim_rotation()
+        .build_api_key_issue()
+        .build_oauth_client()
+        .build_impersonate()
+        .build_address_move()
         .build_store()
         .build_directory()
         .build_authentication()
@@ -14,6 +14,7 @@ use crate::{
     components::{
         form::{
             button::Button,
+            draft::{clear_draft, draft_key, DraftRecovery},
             input::{InputPassword, InputSwitch, InputText, TextArea},
             select::Select,
             Form, FormButtonBar, FormElement, FormItem, FormSection,
@@ -84,6 +85,10 @@ pub fn ManageCrypto() -> impl IntoView {
     let data = expect_context::<Arc<Schemas>>()
         .build_form("crypto-at-rest")
         .into_signal();
+    let draft = store_value(draft_key(
+        "crypto-at-rest",
+        auth.get_untracked().username.as_str(),
+    ));
 
     let save_changes = create_action(move |(changes, password): &(EncryptionType, String)| {
         let changes = changes.clone();
@@ -106,6 +111,7 @@ pub fn ManageCrypto() -> impl IntoView {
             alert.set(match result {
                 Ok(_) => {
                     show_totp.set(false);
+                    draft.with_value(|draft| clear_draft(draft));
 
                     if !is_disable {
                         Alert::success("Encryption-at-rest enabled").with_details(concat!(
@@ -163,6 +169,11 @@ pub fn ManageCrypto() -> impl IntoView {
                         });
                         Some(
                             view! {
+                                <DraftRecovery
+                                    key=draft.get_value()
+                                    data=data
+                                    exclude=&["password", "totp-code"]
+                                />
                                 <FormSection>
                                     <Show when=move || show_totp.get()>
                                         <FormItem label="TOTP Token">
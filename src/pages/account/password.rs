@@ -35,6 +35,7 @@ pub fn ChangePassword() -> impl IntoView {
 
     let data = expect_context::<Arc<Schemas>>()
         .build_form("change-pass")
+        .with_value("login", auth.get_untracked().username.to_string())
         .into_signal();
     let show_totp = create_rw_signal(false);
 
@@ -90,7 +91,7 @@ pub fn ChangePassword() -> impl IntoView {
                         <InputPassword element=FormElement::new("old-password", data)/>
                     </FormItem>
                     <FormItem label="New Password">
-                        <InputPassword element=FormElement::new("new-password", data)/>
+                        <InputPassword element=FormElement::new("new-password", data) strength_meter=true/>
                     </FormItem>
                 </Show>
 
@@ -142,7 +143,18 @@ impl Builder<Schemas, ()> {
             .new_field("old-password")
             .typ(Type::Secret)
             .input_check([], [Validator::Required])
+            .build()
             .new_field("new-password")
+            .typ(Type::Secret)
+            .input_check(
+                [],
+                [
+                    Validator::Required,
+                    Validator::IsStrongPassword {
+                        personal: &["login"],
+                    },
+                ],
+            )
             .build()
             .new_field("totp-code")
             .input_check([Transformer::Trim], [])
@@ -14,7 +14,9 @@ use crate::{
     components::{
         form::{
             button::Button,
+            draft::{clear_draft, draft_key, DraftRecovery},
             expression::InputExpression,
+            history::UndoRedo,
             input::{
                 InputDuration, InputPassword, InputRate, InputSize, InputSwitch, InputText,
                 TextArea,
@@ -37,7 +39,9 @@ use crate::{
         form::{ExternalSources, FormData},
         http::{self, HttpRequest},
         oauth::use_authorization,
+        password::{load_password_policy, reset_password_policy},
         schema::{ArrayType, SelectType},
+        AccessToken,
     },
     pages::{
         config::{ReloadSettings, Schema, SchemaType, Schemas, Settings, Type, UpdateSettings},
@@ -94,39 +98,7 @@ pub fn SettingsEdit() -> impl IntoView {
             let is_create = name.is_empty();
 
             async move {
-                // Fetch external sources
-                let mut external_sources = ExternalSources::new();
-                for (schema, field) in current_schema.external_sources() {
-                    let schema = schema.unwrap_or_else(|| current_schema.clone());
-                    let source_key = format!("{}_{}", schema.id, field.id);
-                    if !external_sources.contains_key(&source_key) {
-                        let items = HttpRequest::get("/api/settings/group")
-                            .with_authorization(&auth)
-                            .with_parameter("prefix", schema.unwrap_prefix())
-                            .with_parameter(
-                                "suffix",
-                                schema.try_unwrap_suffix().unwrap_or_default(),
-                            )
-                            .with_parameter("field", field.id)
-                            .send::<List<Settings>>()
-                            .await?
-                            .items;
-
-                        external_sources.insert(
-                            source_key,
-                            items
-                                .into_iter()
-                                .filter_map(|mut item| {
-                                    (
-                                        item.remove("_id")?,
-                                        item.remove(field.id).unwrap_or_default(),
-                                    )
-                                        .into()
-                                })
-                                .collect::<Vec<_>>(),
-                        );
-                    }
-                }
+                let external_sources = fetch_external_sources(&auth, &current_schema).await?;
 
                 // Fetch settings
                 match current_schema.typ {
@@ -217,6 +189,16 @@ pub fn SettingsEdit() -> impl IntoView {
     );
     let (pending, set_pending) = create_signal(false);
     let data = FormData::default().into_signal();
+    let draft = create_memo(move |_| {
+        draft_key(
+            current_schema.get().id,
+            params
+                .get()
+                .get("id")
+                .map(|id| id.as_str())
+                .unwrap_or_default(),
+        )
+    });
 
     let save_changes = create_action(
         move |(changes, reload): &(Arc<Vec<UpdateSettings>>, bool)| {
@@ -224,6 +206,7 @@ pub fn SettingsEdit() -> impl IntoView {
             let reload = *reload;
             let auth = auth.get();
             let schema = current_schema.get();
+            let draft = draft.get();
 
             async move {
                 set_pending.set(true);
@@ -236,6 +219,11 @@ pub fn SettingsEdit() -> impl IntoView {
                     .map(|_| ())
                 {
                     Ok(_) => {
+                        clear_draft(&draft);
+                        if schema.id == "authentication" {
+                            reset_password_policy();
+                            let _ = load_password_policy(&auth).await;
+                        }
                         if reload {
                             match HttpRequest::get(format!(
                                 "/api/reload/{}",
@@ -343,303 +331,16 @@ pub fn SettingsEdit() -> impl IntoView {
                             FetchResult::NotFound => unreachable!(),
                         };
                         let schema = current_schema.get();
-                        let sections = schema.form.sections.iter().cloned();
-                        let is_enterprise = auth.get().is_enterprise();
                         data.set(
                             FormData::from_settings(schema.clone(), settings)
                                 .with_external_sources(external_sources),
                         );
                         Some(
-                            sections
-                                .map(|section| {
-                                    let title = section.title.map(|s| s.to_string());
-                                    let section_ = section.clone();
-                                    let hide_section = create_memo(move |_| {
-                                        !section_.display(&data.get())
-                                    });
-                                    #[cfg(feature = "enterprise")]
-                                    let fields = section.fields.iter().cloned();
-                                    #[cfg(not(feature = "enterprise"))]
-                                    let fields = section
-                                        .fields
-                                        .iter()
-                                        .filter(|field| !field.enterprise)
-                                        .cloned();
-                                    let components = fields
-                                        .map(|field| {
-                                            let is_disabled = (field.readonly && !is_create)
-                                                || (!is_enterprise && field.enterprise);
-                                            let field_label = field.label_form;
-                                            let help = field.help;
-                                            let field_ = field.clone();
-                                            let hide_label = create_memo(move |_| {
-                                                !field_.display(&data.get())
-                                            });
-                                            let field_ = field.clone();
-                                            let is_optional = create_memo(move |_| {
-                                                !field_.is_required(&data.get())
-                                            });
-                                            let is_switch = matches!(field.typ_, Type::Boolean);
-                                            let component = match field.typ_ {
-                                                Type::Input => {
-                                                    view! {
-                                                        // SPDX-SnippetBegin
-                                                        // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
-                                                        // SPDX-License-Identifier: LicenseRef-SEL
-                                                        // SPDX-SnippetEnd
-                                                        <InputText
-                                                            element=FormElement::new(field.id, data)
-                                                            placeholder=create_memo(move |_| {
-                                                                field
-                                                                    .placeholder(&data.get())
-                                                                    .unwrap_or_default()
-                                                                    .to_string()
-                                                            })
-
-                                                            disabled=is_disabled
-                                                        />
-                                                    }
-                                                        .into_view()
-                                                }
-                                                Type::Array(ArrayType::Text) => {
-                                                    view! {
-                                                        // SPDX-SnippetBegin
-                                                        // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
-                                                        // SPDX-License-Identifier: LicenseRef-SEL
-                                                        // SPDX-SnippetEnd
-
-                                                        <StackedInput
-                                                            add_button_text="Add".to_string()
-                                                            element=FormElement::new(field.id, data)
-                                                            placeholder=create_memo(move |_| {
-                                                                field
-                                                                    .placeholder(&data.get())
-                                                                    .unwrap_or_default()
-                                                                    .to_string()
-                                                            })
-                                                        />
-                                                    }
-                                                        .into_view()
-                                                }
-                                                Type::Array(ArrayType::Duration) => {
-                                                    view! {
-                                                        // SPDX-SnippetBegin
-                                                        // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
-                                                        // SPDX-License-Identifier: LicenseRef-SEL
-                                                        // SPDX-SnippetEnd
-
-                                                        <StackedDuration
-                                                            add_button_text="Add".to_string()
-                                                            element=FormElement::new(field.id, data)
-                                                        />
-                                                    }
-                                                        .into_view()
-                                                }
-                                                Type::Secret => {
-                                                    view! {
-                                                        // SPDX-SnippetBegin
-                                                        // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
-                                                        // SPDX-License-Identifier: LicenseRef-SEL
-                                                        // SPDX-SnippetEnd
-
-                                                        <InputPassword
-                                                            element=FormElement::new(field.id, data)
-                                                            disabled=is_disabled
-                                                        />
-                                                    }
-                                                        .into_view()
-                                                }
-                                                Type::Select { typ: SelectType::Single, .. } => {
-                                                    view! {
-                                                        // SPDX-SnippetBegin
-                                                        // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
-                                                        // SPDX-License-Identifier: LicenseRef-SEL
-                                                        // SPDX-SnippetEnd
-
-                                                        <Select
-                                                            element=FormElement::new(field.id, data)
-                                                            disabled=is_disabled
-                                                        />
-                                                    }
-                                                        .into_view()
-                                                }
-                                                Type::Select { typ: SelectType::Many, .. } => {
-                                                    view! {
-                                                        // SPDX-SnippetBegin
-                                                        // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
-                                                        // SPDX-License-Identifier: LicenseRef-SEL
-                                                        // SPDX-SnippetEnd
-
-                                                        <CheckboxGroup
-                                                            element=FormElement::new(field.id, data)
-                                                            disabled=is_disabled
-                                                        />
-                                                    }
-                                                        .into_view()
-                                                }
-                                                Type::Select { typ: SelectType::ManyWithSearch, .. } => {
-                                                    view! {
-                                                        // SPDX-SnippetBegin
-                                                        // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
-                                                        // SPDX-License-Identifier: LicenseRef-SEL
-                                                        // SPDX-SnippetEnd
-
-                                                        <StackedBadge
-                                                            element=FormElement::new(field.id, data)
-                                                            add_button_text="Add Item"
-                                                            color=Color::Green
-                                                        />
-                                                    }
-                                                        .into_view()
-                                                }
-                                                Type::Size => {
-                                                    view! {
-                                                        // SPDX-SnippetBegin
-                                                        // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
-                                                        // SPDX-License-Identifier: LicenseRef-SEL
-                                                        // SPDX-SnippetEnd
-
-                                                        <InputSize
-                                                            element=FormElement::new(field.id, data)
-                                                            disabled=is_disabled
-                                                        />
-                                                    }
-                                                        .into_view()
-                                                }
-                                                Type::Boolean => {
-                                                    view! {
-                                                        // SPDX-SnippetBegin
-                                                        // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
-                                                        // SPDX-License-Identifier: LicenseRef-SEL
-                                                        // SPDX-SnippetEnd
-
-                                                        <InputSwitch
-                                                            label=field_label
-                                                            tooltip=help.unwrap_or_default()
-                                                            element=FormElement::new(field.id, data)
-                                                            disabled=is_disabled
-                                                        />
-                                                    }
-                                                        .into_view()
-                                                }
-                                                Type::Duration => {
-                                                    view! {
-                                                        // SPDX-SnippetBegin
-                                                        // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
-                                                        // SPDX-License-Identifier: LicenseRef-SEL
-                                                        // SPDX-SnippetEnd
-
-                                                        <InputDuration
-                                                            element=FormElement::new(field.id, data)
-                                                            disabled=is_disabled
-                                                        />
-                                                    }
-                                                        .into_view()
-                                                }
-                                                Type::Rate => {
-                                                    view! {
-                                                        // SPDX-SnippetBegin
-                                                        // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
-                                                        // SPDX-License-Identifier: LicenseRef-SEL
-                                                        // SPDX-SnippetEnd
-
-                                                        <InputRate
-                                                            element=FormElement::new(field.id, data)
-                                                            disabled=is_disabled
-                                                        />
-                                                    }
-                                                        .into_view()
-                                                }
-                                                Type::Expression => {
-                                                    view! {
-                                                        // SPDX-SnippetBegin
-                                                        // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
-                                                        // SPDX-License-Identifier: LicenseRef-SEL
-                                                        // SPDX-SnippetEnd
-
-                                                        <InputExpression element=FormElement::new(field.id, data)/>
-                                                    }
-                                                        .into_view()
-                                                }
-                                                Type::Cron => {
-                                                    view! {
-                                                        // SPDX-SnippetBegin
-                                                        // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
-                                                        // SPDX-License-Identifier: LicenseRef-SEL
-                                                        // SPDX-SnippetEnd
-
-                                                        <SelectCron element=FormElement::new(field.id, data)/>
-                                                    }
-                                                        .into_view()
-                                                }
-                                                Type::Text => {
-                                                    view! {
-                                                        // SPDX-SnippetBegin
-                                                        // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
-                                                        // SPDX-License-Identifier: LicenseRef-SEL
-                                                        // SPDX-SnippetEnd
-
-                                                        <TextArea
-                                                            element=FormElement::new(field.id, data)
-                                                            placeholder=create_memo(move |_| {
-                                                                field
-                                                                    .placeholder(&data.get())
-                                                                    .unwrap_or_default()
-                                                                    .to_string()
-                                                            })
-
-                                                            disabled=is_disabled
-                                                        />
-                                                    }
-                                                        .into_view()
-                                                }
-                                            };
-                                            if !is_switch {
-                                                view! {
-                                                    // SPDX-SnippetBegin
-                                                    // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
-                                                    // SPDX-License-Identifier: LicenseRef-SEL
-                                                    // SPDX-SnippetEnd
-
-                                                    <FormItem
-                                                        label=field_label
-                                                        hide=hide_label
-                                                        is_optional=is_optional
-                                                        tooltip=help.unwrap_or_default()
-                                                    >
-                                                        {component}
-                                                    </FormItem>
-                                                }
-                                            } else {
-                                                view! {
-                                                    // SPDX-SnippetBegin
-                                                    // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
-                                                    // SPDX-License-Identifier: LicenseRef-SEL
-                                                    // SPDX-SnippetEnd
-
-                                                    <FormItem label="" hide=hide_label is_optional=is_optional>
-                                                        {component}
-                                                    </FormItem>
-                                                }
-                                            }
-                                        })
-                                        .collect_view();
-                                    view! {
-                                        // SPDX-SnippetBegin
-                                        // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
-                                        // SPDX-License-Identifier: LicenseRef-SEL
-                                        // SPDX-SnippetEnd
-
-                                        <FormSection
-                                            title=title.unwrap_or_default()
-                                            hide=hide_section
-                                        >
-                                            {components}
-                                        </FormSection>
-                                    }
-                                        .into_view()
-                                })
-                                .collect_view(),
+                            view! {
+                                <DraftRecovery key=draft.get_untracked() data=data/>
+                                <SchemaFormSections schema=schema data=data is_create=is_create/>
+                            }
+                                .into_view(),
                         )
                     }
                 }}
@@ -647,6 +348,7 @@ pub fn SettingsEdit() -> impl IntoView {
             </Transition>
 
             <FormButtonBar>
+                <UndoRedo data=data/>
                 <Button
                     text="Cancel"
                     color=Color::Gray
@@ -694,6 +396,357 @@ pub fn SettingsEdit() -> impl IntoView {
     }
 }
 
+#[component]
+pub fn SchemaFormSections(
+    schema: Arc<Schema>,
+    data: RwSignal<FormData>,
+    #[prop(optional)] is_create: bool,
+) -> impl IntoView {
+    let sections = schema.form.sections.iter().cloned();
+    let is_enterprise = use_authorization().get_untracked().is_enterprise();
+
+    sections
+        .map(|section| {
+            let title = section.title.map(|s| s.to_string());
+            let section_ = section.clone();
+            let hide_section = create_memo(move |_| !section_.display(&data.get()));
+            #[cfg(feature = "enterprise")]
+            let fields = section.fields.iter().cloned();
+            #[cfg(not(feature = "enterprise"))]
+            let fields = section
+                .fields
+                .iter()
+                .filter(|field| !field.enterprise)
+                .cloned();
+            let components = fields
+                .map(|field| {
+                    let is_disabled =
+                        (field.readonly && !is_create) || (!is_enterprise && field.enterprise);
+                    let field_label = field.label_form;
+                    let help = field.help;
+                    let field_ = field.clone();
+                    let hide_label = create_memo(move |_| !field_.display(&data.get()));
+                    let field_ = field.clone();
+                    let is_optional = create_memo(move |_| !field_.is_required(&data.get()));
+                    let is_switch = matches!(field.typ_, Type::Boolean);
+                    let reset = FormElement::new(field.id, data);
+                    let component = match field.typ_ {
+                        Type::Input => {
+                            view! {
+                                // SPDX-SnippetBegin
+                                // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+                                // SPDX-License-Identifier: LicenseRef-SEL
+                                // SPDX-SnippetEnd
+                                <InputText
+                                    element=FormElement::new(field.id, data)
+                                    placeholder=create_memo(move |_| {
+                                        field
+                                            .placeholder(&data.get())
+                                            .unwrap_or_default()
+                                            .to_string()
+                                    })
+
+                                    disabled=is_disabled
+                                />
+                            }
+                            .into_view()
+                        }
+                        Type::Array(ArrayType::Text) => {
+                            view! {
+                                // SPDX-SnippetBegin
+                                // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+                                // SPDX-License-Identifier: LicenseRef-SEL
+                                // SPDX-SnippetEnd
+
+                                <StackedInput
+                                    add_button_text="Add".to_string()
+                                    element=FormElement::new(field.id, data)
+                                    placeholder=create_memo(move |_| {
+                                        field
+                                            .placeholder(&data.get())
+                                            .unwrap_or_default()
+                                            .to_string()
+                                    })
+                                />
+                            }
+                            .into_view()
+                        }
+                        Type::Array(ArrayType::Duration) => {
+                            view! {
+                                // SPDX-SnippetBegin
+                                // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+                                // SPDX-License-Identifier: LicenseRef-SEL
+                                // SPDX-SnippetEnd
+
+                                <StackedDuration
+                                    add_button_text="Add".to_string()
+                                    element=FormElement::new(field.id, data)
+                                />
+                            }
+                            .into_view()
+                        }
+                        Type::Secret => {
+                            view! {
+                                // SPDX-SnippetBegin
+                                // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+                                // SPDX-License-Identifier: LicenseRef-SEL
+                                // SPDX-SnippetEnd
+
+                                <InputPassword
+                                    element=FormElement::new(field.id, data)
+                                    disabled=is_disabled
+                                />
+                            }
+                            .into_view()
+                        }
+                        Type::Select {
+                            typ: SelectType::Single,
+                            ..
+                        } => {
+                            view! {
+                                // SPDX-SnippetBegin
+                                // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+                                // SPDX-License-Identifier: LicenseRef-SEL
+                                // SPDX-SnippetEnd
+
+                                <Select
+                                    element=FormElement::new(field.id, data)
+                                    disabled=is_disabled
+                                />
+                            }
+                            .into_view()
+                        }
+                        Type::Select {
+                            typ: SelectType::Many,
+                            ..
+                        } => {
+                            view! {
+                                // SPDX-SnippetBegin
+                                // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+                                // SPDX-License-Identifier: LicenseRef-SEL
+                                // SPDX-SnippetEnd
+
+                                <CheckboxGroup
+                                    element=FormElement::new(field.id, data)
+                                    disabled=is_disabled
+                                />
+                            }
+                            .into_view()
+                        }
+                        Type::Select {
+                            typ: SelectType::ManyWithSearch,
+                            ..
+                        } => {
+                            view! {
+                                // SPDX-SnippetBegin
+                                // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+                                // SPDX-License-Identifier: LicenseRef-SEL
+                                // SPDX-SnippetEnd
+
+                                <StackedBadge
+                                    element=FormElement::new(field.id, data)
+                                    add_button_text="Add Item"
+                                    color=Color::Green
+                                />
+                            }
+                            .into_view()
+                        }
+                        Type::Size => {
+                            view! {
+                                // SPDX-SnippetBegin
+                                // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+                                // SPDX-License-Identifier: LicenseRef-SEL
+                                // SPDX-SnippetEnd
+
+                                <InputSize
+                                    element=FormElement::new(field.id, data)
+                                    disabled=is_disabled
+                                />
+                            }
+                            .into_view()
+                        }
+                        Type::Boolean => {
+                            view! {
+                                // SPDX-SnippetBegin
+                                // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+                                // SPDX-License-Identifier: LicenseRef-SEL
+                                // SPDX-SnippetEnd
+
+                                <InputSwitch
+                                    label=field_label
+                                    tooltip=help.unwrap_or_default()
+                                    element=FormElement::new(field.id, data)
+                                    disabled=is_disabled
+                                />
+                            }
+                            .into_view()
+                        }
+                        Type::Duration => {
+                            view! {
+                                // SPDX-SnippetBegin
+                                // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+                                // SPDX-License-Identifier: LicenseRef-SEL
+                                // SPDX-SnippetEnd
+
+                                <InputDuration
+                                    element=FormElement::new(field.id, data)
+                                    disabled=is_disabled
+                                />
+                            }
+                            .into_view()
+                        }
+                        Type::Rate => {
+                            view! {
+                                // SPDX-SnippetBegin
+                                // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+                                // SPDX-License-Identifier: LicenseRef-SEL
+                                // SPDX-SnippetEnd
+
+                                <InputRate
+                                    element=FormElement::new(field.id, data)
+                                    disabled=is_disabled
+                                />
+                            }
+                            .into_view()
+                        }
+                        Type::Expression => {
+                            view! {
+                                // SPDX-SnippetBegin
+                                // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+                                // SPDX-License-Identifier: LicenseRef-SEL
+                                // SPDX-SnippetEnd
+
+                                <InputExpression element=FormElement::new(field.id, data)/>
+                            }
+                            .into_view()
+                        }
+                        Type::Cron => {
+                            view! {
+                                // SPDX-SnippetBegin
+                                // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+                                // SPDX-License-Identifier: LicenseRef-SEL
+                                // SPDX-SnippetEnd
+
+                                <SelectCron
+                                    element=FormElement::new(field.id, data)
+                                    min_interval=field.min_interval
+                                />
+                            }
+                            .into_view()
+                        }
+                        Type::Text => {
+                            view! {
+                                // SPDX-SnippetBegin
+                                // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+                                // SPDX-License-Identifier: LicenseRef-SEL
+                                // SPDX-SnippetEnd
+
+                                <TextArea
+                                    element=FormElement::new(field.id, data)
+                                    placeholder=create_memo(move |_| {
+                                        field
+                                            .placeholder(&data.get())
+                                            .unwrap_or_default()
+                                            .to_string()
+                                    })
+
+                                    disabled=is_disabled
+                                />
+                            }
+                            .into_view()
+                        }
+                    };
+                    if !is_switch {
+                        view! {
+                            // SPDX-SnippetBegin
+                            // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+                            // SPDX-License-Identifier: LicenseRef-SEL
+                            // SPDX-SnippetEnd
+
+                            <FormItem
+                                label=field_label
+                                hide=hide_label
+                                is_optional=is_optional
+                                tooltip=help.unwrap_or_default()
+                                reset=reset
+                            >
+                                {component}
+                            </FormItem>
+                        }
+                    } else {
+                        view! {
+                            // SPDX-SnippetBegin
+                            // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+                            // SPDX-License-Identifier: LicenseRef-SEL
+                            // SPDX-SnippetEnd
+
+                            <FormItem
+                                label=""
+                                hide=hide_label
+                                is_optional=is_optional
+                                reset=reset
+                            >
+                                {component}
+                            </FormItem>
+                        }
+                    }
+                })
+                .collect_view();
+            view! {
+                // SPDX-SnippetBegin
+                // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+                // SPDX-License-Identifier: LicenseRef-SEL
+                // SPDX-SnippetEnd
+
+                <FormSection
+                    title=title.unwrap_or_default()
+                    hide=hide_section
+                >
+                    {components}
+                </FormSection>
+            }
+            .into_view()
+        })
+        .collect_view()
+}
+
+pub async fn fetch_external_sources(
+    auth: &AccessToken,
+    current_schema: &Arc<Schema>,
+) -> http::Result<ExternalSources> {
+    let mut external_sources = ExternalSources::new();
+    for (schema, field) in current_schema.external_sources() {
+        let schema = schema.unwrap_or_else(|| current_schema.clone());
+        let source_key = format!("{}_{}", schema.id, field.id);
+        if !external_sources.contains_key(&source_key) {
+            let items = HttpRequest::get("/api/settings/group")
+                .with_authorization(auth)
+                .with_parameter("prefix", schema.unwrap_prefix())
+                .with_parameter("suffix", schema.try_unwrap_suffix().unwrap_or_default())
+                .with_parameter("field", field.id)
+                .send::<List<Settings>>()
+                .await?
+                .items;
+
+            external_sources.insert(
+                source_key,
+                items
+                    .into_iter()
+                    .filter_map(|mut item| {
+                        (
+                            item.remove("_id")?,
+                            item.remove(field.id).unwrap_or_default(),
+                        )
+                            .into()
+                    })
+                    .collect::<Vec<_>>(),
+            );
+        }
+    }
+
+    Ok(external_sources)
+}
+
 impl Schema {
     fn list_path(&self) -> Option<String> {
         if !matches!(self.typ, SchemaType::List) {
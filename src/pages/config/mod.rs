@@ -8,6 +8,7 @@ pub mod edit;
 pub mod list;
 pub mod schema;
 pub mod search;
+pub mod setup;
 
 use std::{collections::BTreeMap, str::FromStr};
 
@@ -277,6 +278,10 @@ impl LayoutBuilder {
             // Server
             .create("Server")
             .icon(view! { <IconServerStack/> })
+            // Setup
+            .create("Setup Wizard")
+            .route("/setup")
+            .insert(true)
             // Network
             .create("Network")
             .route("/network/edit")
@@ -59,6 +59,109 @@ impl Builder<Schemas, ()> {
             .typ(Type::Secret)
             .input_check([Transformer::Trim, Transformer::HashSecret], [])
             .build()
+            // Password policy, only enforced by the web admin
+            .new_field("authentication.password.min-length")
+            .label("Minimum length")
+            .help(concat!(
+                "Minimum number of characters required in account passwords. The policy ",
+                "is enforced when passwords are set through the web admin, the server ",
+                "does not check it"
+            ))
+            .typ(Type::Input)
+            .default("8")
+            .input_check(
+                [Transformer::Trim],
+                [Validator::Required, Validator::MinValue(1.into())],
+            )
+            .build()
+            .new_field("authentication.password.require-lowercase")
+            .label("Require lowercase")
+            .help("Whether passwords must contain at least one lowercase letter")
+            .typ(Type::Boolean)
+            .default("false")
+            .build()
+            .new_field("authentication.password.require-uppercase")
+            .label("Require uppercase")
+            .help("Whether passwords must contain at least one uppercase letter")
+            .typ(Type::Boolean)
+            .default("false")
+            .build()
+            .new_field("authentication.password.require-digit")
+            .label("Require digit")
+            .help("Whether passwords must contain at least one digit")
+            .typ(Type::Boolean)
+            .default("false")
+            .build()
+            .new_field("authentication.password.require-symbol")
+            .label("Require symbol")
+            .help("Whether passwords must contain at least one symbol")
+            .typ(Type::Boolean)
+            .default("false")
+            .build()
+            .new_field("authentication.password.deny-personal")
+            .label("Reject personal details")
+            .help("Whether to reject passwords that contain the login name or e-mail address")
+            .typ(Type::Boolean)
+            .default("false")
+            .build()
+            .new_field("authentication.password.dictionary")
+            .label("Reject common passwords")
+            .help(concat!(
+                "Whether to reject passwords found in the list of common and breached ",
+                "passwords shipped with the web admin"
+            ))
+            .typ(Type::Boolean)
+            .default("true")
+            .build()
+            .new_field("authentication.password.hash.algorithm")
+            .label("Hashing algorithm")
+            .help(concat!(
+                "Algorithm used by the web admin to hash passwords set by administrators. ",
+                "Existing hashes are not changed until the password is reset"
+            ))
+            .typ(Type::Select {
+                typ: SelectType::Single,
+                source: Source::Static(&[("sha512-crypt", "SHA-512-crypt"), ("bcrypt", "bcrypt")]),
+            })
+            .default("sha512-crypt")
+            .input_check([], [Validator::Required])
+            .build()
+            .new_field("authentication.password.hash.rounds")
+            .label("Rounds")
+            .help(concat!(
+                "Number of SHA-512-crypt rounds, higher values are slower to brute-force. ",
+                "Passwords are hashed in the browser, so large values freeze the page"
+            ))
+            .typ(Type::Input)
+            .default("5000")
+            .display_if_eq("authentication.password.hash.algorithm", ["sha512-crypt"])
+            .input_check(
+                [Transformer::Trim],
+                [
+                    Validator::Required,
+                    Validator::MinValue(1000.into()),
+                    Validator::MaxValue(5000000.into()),
+                ],
+            )
+            .build()
+            .new_field("authentication.password.hash.cost")
+            .label("Cost")
+            .help(concat!(
+                "bcrypt cost factor, each increment doubles the hashing time. ",
+                "Passwords are hashed in the browser, so large values freeze the page"
+            ))
+            .typ(Type::Input)
+            .default("12")
+            .display_if_eq("authentication.password.hash.algorithm", ["bcrypt"])
+            .input_check(
+                [Transformer::Trim],
+                [
+                    Validator::Required,
+                    Validator::MinValue(4.into()),
+                    Validator::MaxValue(15.into()),
+                ],
+            )
+            .build()
             .new_form_section()
             .title("Authentication")
             .fields(["storage.directory"])
@@ -74,6 +177,26 @@ impl Builder<Schemas, ()> {
             .title("Master User")
             .fields(["authentication.master.user", "authentication.master.secret"])
             .build()
+            .new_form_section()
+            .title("Password Policy (Web Admin)")
+            .fields([
+                "authentication.password.min-length",
+                "authentication.password.require-lowercase",
+                "authentication.password.require-uppercase",
+                "authentication.password.require-digit",
+                "authentication.password.require-symbol",
+                "authentication.password.deny-personal",
+                "authentication.password.dictionary",
+            ])
+            .build()
+            .new_form_section()
+            .title("Password Hashing")
+            .fields([
+                "authentication.password.hash.algorithm",
+                "authentication.password.hash.rounds",
+                "authentication.password.hash.cost",
+            ])
+            .build()
             .build()
             // OAuth
             .new_schema("oauth")
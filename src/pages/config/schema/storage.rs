@@ -4,6 +4,8 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use std::time::Duration;
+
 use super::*;
 
 impl Builder<Schemas, ()> {
@@ -187,6 +189,7 @@ impl Builder<Schemas, ()> {
             ))
             .default("0 0 *")
             .typ(Type::Cron)
+            .min_interval(Duration::from_secs(24 * 60 * 60))
             .input_check([], [Validator::Required])
             .build()
             .new_field("changes.max-history")
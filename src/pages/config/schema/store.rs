@@ -4,6 +4,8 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use std::time::Duration;
+
 use super::*;
 
 const PGSQL_NAME: &str =
@@ -200,6 +202,7 @@ impl Builder<Schemas, ()> {
             .display_if_ne("type", ["redis", "memory", "elasticsearch", "meilisearch"])
             .default("0 3 *")
             .typ(Type::Cron)
+            .min_interval(Duration::from_secs(24 * 60 * 60))
             .input_check([Transformer::Trim], [Validator::Required])
             .build()
             // Workers
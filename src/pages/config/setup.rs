@@ -0,0 +1,601 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::sync::Arc;
+
+use leptos::*;
+use leptos_router::use_navigate;
+
+use crate::{
+    components::{
+        form::{button::Button, steps::StepProgress, Form, FormButtonBar, FormSection},
+        icon::IconRefresh,
+        list::table::{Table, TableRow},
+        messages::{
+            alert::{use_alerts, Alert},
+            modal::{use_modals, Modal},
+        },
+        skeleton::Skeleton,
+        Color,
+    },
+    core::{
+        form::{ExternalSources, FormData},
+        http::{self, HttpRequest},
+        oauth::use_authorization,
+    },
+    pages::config::{ReloadSettings, Schema, Schemas, Type, UpdateSettings},
+};
+
+use super::edit::{fetch_external_sources, SchemaFormSections, DEFAULT_SETTINGS_URL};
+
+const RECOMMENDED_LISTENERS: &[(&str, &str, &str, bool)] = &[
+    ("smtp", "smtp", "[::]:25", false),
+    ("submissions", "smtp", "[::]:465", true),
+    ("imaps", "imap", "[::]:993", true),
+    ("https", "http", "[::]:443", true),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SetupStep {
+    Network,
+    Store,
+    Storage,
+    Directory,
+    Tls,
+    Listener,
+    Summary,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum TlsMode {
+    #[default]
+    Acme,
+    Certificate,
+}
+
+#[derive(Debug, Clone)]
+struct SetupEntry {
+    step: SetupStep,
+    data: FormData,
+}
+
+#[component]
+pub fn SetupWizard() -> impl IntoView {
+    let auth = use_authorization();
+    let alert = use_alerts();
+    let modal = use_modals();
+    let schemas = store_value(expect_context::<Arc<Schemas>>());
+
+    let step = create_rw_signal(SetupStep::Network);
+    let tls_mode = create_rw_signal(TlsMode::default());
+    let entries: RwSignal<Vec<SetupEntry>> = create_rw_signal(vec![]);
+    let restored: StoredValue<Option<FormData>> = store_value(None);
+    let revision = create_rw_signal(0u32);
+    let data = FormData::default().into_signal();
+    let (pending, set_pending) = create_signal(false);
+
+    let current_schema = move || {
+        step.get_untracked()
+            .schema_id(tls_mode.get_untracked())
+            .and_then(|id| schemas.with_value(|schemas| schemas.schemas.get(id).cloned()))
+    };
+    let load_step = create_resource(
+        move || revision.get(),
+        move |_| {
+            let auth = auth.get_untracked();
+            let schema = current_schema();
+
+            async move {
+                match schema {
+                    Some(schema) => fetch_external_sources(&auth, &schema).await.map(Some),
+                    None => Ok(None),
+                }
+            }
+        },
+    );
+
+    let go_to = move |next: SetupStep, keep_current: bool, restore: bool| {
+        let current = step.get_untracked();
+        entries.update(|entries| {
+            if keep_current && current != SetupStep::Summary {
+                entries.push(SetupEntry {
+                    step: current,
+                    data: data.get_untracked(),
+                });
+                entries.sort_by_key(|entry| entry.step as usize);
+            }
+            let previous = restore
+                .then(|| entries.iter().rposition(|entry| entry.step == next))
+                .flatten()
+                .map(|idx| entries.remove(idx).data);
+            if let Some(previous) = &previous {
+                match previous.schema.id {
+                    "acme" => tls_mode.set(TlsMode::Acme),
+                    "certificate" => tls_mode.set(TlsMode::Certificate),
+                    _ => {}
+                }
+            }
+            restored.set_value(previous);
+        });
+        step.set(next);
+        revision.update(|revision| *revision += 1);
+    };
+
+    let apply_changes = create_action(move |changes: &Arc<Vec<UpdateSettings>>| {
+        let changes = changes.clone();
+        let auth = auth.get();
+
+        async move {
+            set_pending.set(true);
+            let result = async {
+                HttpRequest::post("/api/settings")
+                    .with_authorization(&auth)
+                    .with_body(changes)
+                    .unwrap()
+                    .send::<Option<String>>()
+                    .await?;
+                let dry_run = HttpRequest::get("/api/reload")
+                    .with_authorization(&auth)
+                    .with_parameter("dry-run", "true")
+                    .send::<ReloadSettings>()
+                    .await?;
+                if !dry_run.errors.is_empty() {
+                    return Ok(dry_run);
+                }
+                HttpRequest::get("/api/reload")
+                    .with_authorization(&auth)
+                    .send::<ReloadSettings>()
+                    .await
+            }
+            .await;
+            set_pending.set(false);
+
+            match result {
+                Ok(result) if result.errors.is_empty() => {
+                    modal.set(
+                        Modal::with_title("Setup complete")
+                            .with_message(concat!(
+                                "Your settings have been saved and reloaded. ",
+                                "Changes to listeners or stores take effect ",
+                                "after the server is restarted."
+                            ))
+                            .with_button("OK"),
+                    );
+                    use_navigate()(DEFAULT_SETTINGS_URL, Default::default());
+                }
+                Ok(result) => {
+                    alert.set(Alert::from(result));
+                }
+                Err(http::Error::Unauthorized) => {
+                    use_navigate()("/login", Default::default());
+                }
+                Err(err) => {
+                    alert.set(Alert::from(err));
+                }
+            }
+        }
+    });
+
+    view! {
+        <Form
+            title="Server Setup"
+            subtitle=Signal::derive(move || step.get().subtitle().to_string())
+        >
+
+            <StepProgress
+                steps=SetupStep::TITLES
+                current=Signal::derive(move || step.get() as usize)
+            />
+
+            <Show when=move || step.get() == SetupStep::Tls>
+                <div class="mb-6 flex gap-x-2">
+                    <Button
+                        text="ACME provider"
+                        color=Signal::derive(move || {
+                            if tls_mode.get() == TlsMode::Acme { Color::Blue } else { Color::Gray }
+                        })
+
+                        on_click=move |_| {
+                            tls_mode.set(TlsMode::Acme);
+                            go_to(SetupStep::Tls, false, false);
+                        }
+                    />
+
+                    <Button
+                        text="Certificate"
+                        color=Signal::derive(move || {
+                            if tls_mode.get() == TlsMode::Certificate {
+                                Color::Blue
+                            } else {
+                                Color::Gray
+                            }
+                        })
+
+                        on_click=move |_| {
+                            tls_mode.set(TlsMode::Certificate);
+                            go_to(SetupStep::Tls, false, false);
+                        }
+                    />
+
+                </div>
+            </Show>
+
+            <Transition fallback=Skeleton set_pending>
+
+                {move || match load_step.get() {
+                    None => None,
+                    Some(Err(http::Error::Unauthorized)) => {
+                        use_navigate()("/login", Default::default());
+                        Some(view! { <div></div> }.into_view())
+                    }
+                    Some(Err(err)) => {
+                        alert.set(Alert::from(err));
+                        Some(view! { <div></div> }.into_view())
+                    }
+                    Some(Ok(Some(external_sources))) => {
+                        let schema = current_schema()?;
+                        let entries = entries.get_untracked();
+                        let form = restored
+                            .get_value()
+                            .unwrap_or_else(|| recommended_settings(schema.clone(), &entries));
+                        data.set(
+                            form
+                                .with_external_sources(
+                                    with_pending_sources(external_sources, &schema, &entries),
+                                ),
+                        );
+                        Some(
+                            view! { <SchemaFormSections schema=schema data=data is_create=true/> }
+                                .into_view(),
+                        )
+                    }
+                    Some(Ok(None)) => {
+                        let entries = entries.get();
+                        if entries.is_empty() {
+                            return Some(
+                                view! {
+                                    <p class="text-sm text-gray-600 dark:text-gray-400">
+                                        "All steps were skipped, there are no changes to apply."
+                                    </p>
+                                }
+                                    .into_view(),
+                            );
+                        }
+                        Some(
+                            entries
+                                .into_iter()
+                                .map(|entry| {
+                                    let title = match entry.data.value::<String>("_id") {
+                                        Some(id) => format!("{} '{id}'", entry.step.title()),
+                                        None => entry.step.title().to_string(),
+                                    };
+                                    let rows = describe_updates(&entry.data)
+                                        .into_iter()
+                                        .map(|(key, value)| {
+                                            view! {
+                                                <TableRow>
+                                                    <span class="font-mono">{key}</span>
+                                                    <span class="font-mono">{value}</span>
+                                                </TableRow>
+                                            }
+                                        })
+                                        .collect_view();
+                                    view! {
+                                        <FormSection title=title>
+                                            <div class="sm:col-span-12">
+                                                <Table headers=vec![
+                                                    "Setting".to_string(),
+                                                    "Value".to_string(),
+                                                ]>{rows}</Table>
+                                            </div>
+                                        </FormSection>
+                                    }
+                                })
+                                .collect_view(),
+                        )
+                    }
+                }}
+
+            </Transition>
+
+            <FormButtonBar>
+                <Show when=move || step.get() != SetupStep::Network>
+                    <Button
+                        text="Back"
+                        color=Color::Gray
+                        on_click=move |_| {
+                            go_to(step.get().previous(), true, true);
+                        }
+                    />
+
+                </Show>
+
+                {move || match step.get() {
+                    SetupStep::Summary => {
+                        view! {
+                            <Button
+                                text="Apply & Reload"
+                                color=Color::Blue
+                                on_click=move |_| {
+                                    let changes = entries
+                                        .get()
+                                        .into_iter()
+                                        .flat_map(|entry| entry.data.build_update())
+                                        .collect::<Vec<_>>();
+                                    apply_changes.dispatch(Arc::new(changes));
+                                }
+
+                                disabled=Signal::derive(move || {
+                                    pending.get() || entries.get().is_empty()
+                                })
+                            >
+
+                                <IconRefresh/>
+                            </Button>
+                        }
+                            .into_view()
+                    }
+                    current => {
+                        let has_more_listeners = current == SetupStep::Listener
+                            && entries
+                                .get()
+                                .iter()
+                                .filter(|entry| entry.step == SetupStep::Listener)
+                                .count() + 1 < RECOMMENDED_LISTENERS.len();
+                        view! {
+                            <Button
+                                text="Skip"
+                                color=Color::Gray
+                                on_click=move |_| {
+                                    go_to(current.next(), false, true);
+                                }
+                            />
+
+                            <Show when=move || has_more_listeners>
+                                <Button
+                                    text="Add another listener"
+                                    color=Color::Gray
+                                    on_click=Callback::new(move |_| {
+                                        if data.try_update(|data| data.validate_form()).unwrap_or_default() {
+                                            go_to(SetupStep::Listener, true, false);
+                                        }
+                                    })
+
+                                    disabled=pending
+                                />
+                            </Show>
+
+                            <Button
+                                text="Next"
+                                color=Color::Blue
+                                on_click=Callback::new(move |_| {
+                                    if data.try_update(|data| data.validate_form()).unwrap_or_default() {
+                                        go_to(current.next(), true, true);
+                                    }
+                                })
+
+                                disabled=pending
+                            />
+                        }
+                            .into_view()
+                    }
+                }}
+
+            </FormButtonBar>
+        </Form>
+    }
+}
+
+fn recommended_settings(schema: Arc<Schema>, entries: &[SetupEntry]) -> FormData {
+    let mut data = FormData::from_settings(schema.clone(), None);
+    let hostname = entries
+        .iter()
+        .find(|entry| entry.step == SetupStep::Network)
+        .and_then(|entry| entry.data.value::<String>("server.hostname"));
+    let store_id = entries
+        .iter()
+        .find(|entry| entry.step == SetupStep::Store)
+        .and_then(|entry| entry.data.value::<String>("_id"));
+
+    match schema.id {
+        "store" => {
+            data.set("_id", "rocksdb");
+            data.set("type", "rocksdb");
+            data.set("path", "/opt/stalwart/data");
+        }
+        "storage" => {
+            if let Some(store_id) = store_id {
+                for key in [
+                    "storage.data",
+                    "storage.blob",
+                    "storage.fts",
+                    "storage.lookup",
+                ] {
+                    data.set(key, store_id.as_str());
+                }
+            }
+        }
+        "directory" => {
+            data.set("_id", "internal");
+            data.set("type", "internal");
+            if let Some(store_id) = store_id {
+                data.set("store", store_id);
+            }
+        }
+        "acme" => {
+            data.set("_id", "letsencrypt");
+            data.set("default", "true");
+            if let Some(hostname) = hostname {
+                let domain = hostname
+                    .split_once('.')
+                    .map(|(_, domain)| domain)
+                    .filter(|domain| domain.contains('.'))
+                    .unwrap_or(&hostname);
+                data.array_set("contact", [format!("postmaster@{domain}")]);
+                data.array_set("domains", [hostname]);
+            }
+        }
+        "certificate" => {
+            data.set("_id", "default");
+            data.set("default", "true");
+            if let Some(hostname) = hostname {
+                data.array_set("subjects", [hostname]);
+            }
+        }
+        "listener" => {
+            let listener_count = entries
+                .iter()
+                .filter(|entry| entry.step == SetupStep::Listener)
+                .count();
+            if let Some((id, protocol, bind, implicit_tls)) =
+                RECOMMENDED_LISTENERS.get(listener_count)
+            {
+                data.set("_id", *id);
+                data.set("protocol", *protocol);
+                data.array_set("bind", [*bind]);
+                if *implicit_tls {
+                    data.set("tls.implicit", "true");
+                }
+            }
+        }
+        _ => {}
+    }
+
+    data
+}
+
+// Records created in earlier steps are not saved yet, add them
+// to the select sources so later steps can reference them.
+fn with_pending_sources(
+    mut external_sources: ExternalSources,
+    schema: &Arc<Schema>,
+    entries: &[SetupEntry],
+) -> ExternalSources {
+    for (source_schema, field) in schema.external_sources() {
+        let source_schema = source_schema.unwrap_or_else(|| schema.clone());
+        let items = external_sources
+            .entry(format!("{}_{}", source_schema.id, field.id))
+            .or_default();
+
+        for entry in entries {
+            if entry.data.schema.id != source_schema.id {
+                continue;
+            }
+            if let Some(id) = entry.data.value::<String>("_id") {
+                if !items.iter().any(|(item_id, _)| item_id == &id) {
+                    items.push((id, entry.data.value(field.id).unwrap_or_default()));
+                }
+            }
+        }
+    }
+
+    external_sources
+}
+
+fn describe_updates(data: &FormData) -> Vec<(String, String)> {
+    let is_secret = |key: &str| {
+        [Some(key), key.rsplit_once('.').map(|(key, _)| key)]
+            .into_iter()
+            .flatten()
+            .filter_map(|key| data.schema.fields.get(key))
+            .any(|field| matches!(field.typ_, Type::Secret))
+    };
+    let mut lines = Vec::new();
+
+    for update in data.build_update() {
+        match update {
+            UpdateSettings::Insert { prefix, values, .. } => {
+                for (key, value) in values {
+                    let value = if is_secret(&key) {
+                        "********".to_string()
+                    } else {
+                        value
+                    };
+                    let key = match &prefix {
+                        Some(prefix) => format!("{prefix}.{key}"),
+                        None => key,
+                    };
+                    lines.push((key, value));
+                }
+            }
+            UpdateSettings::Delete { keys } => {
+                for key in keys {
+                    lines.push((key, "(deleted)".to_string()));
+                }
+            }
+            UpdateSettings::Clear { prefix, .. } => {
+                lines.push((format!("{prefix}*"), "(cleared)".to_string()));
+            }
+        }
+    }
+
+    lines
+}
+
+impl SetupStep {
+    const TITLES: &'static [&'static str] = &[
+        "Network",
+        "Store",
+        "Storage",
+        "Directory",
+        "TLS",
+        "Listeners",
+        "Summary",
+    ];
+
+    fn schema_id(&self, tls_mode: TlsMode) -> Option<&'static str> {
+        match self {
+            SetupStep::Network => Some("network"),
+            SetupStep::Store => Some("store"),
+            SetupStep::Storage => Some("storage"),
+            SetupStep::Directory => Some("directory"),
+            SetupStep::Tls => match tls_mode {
+                TlsMode::Acme => Some("acme"),
+                TlsMode::Certificate => Some("certificate"),
+            },
+            SetupStep::Listener => Some("listener"),
+            SetupStep::Summary => None,
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        Self::TITLES[*self as usize]
+    }
+
+    fn subtitle(&self) -> &'static str {
+        match self {
+            SetupStep::Network => "Configure the hostname of this server",
+            SetupStep::Store => "Create the store where all data will be kept",
+            SetupStep::Storage => "Choose which stores hold data, blobs and indexes",
+            SetupStep::Directory => "Create the directory used to authenticate users",
+            SetupStep::Tls => "Obtain certificates through ACME or upload your own",
+            SetupStep::Listener => "Create the listeners that accept connections",
+            SetupStep::Summary => "Review the changes before applying them",
+        }
+    }
+
+    fn next(&self) -> SetupStep {
+        match self {
+            SetupStep::Network => SetupStep::Store,
+            SetupStep::Store => SetupStep::Storage,
+            SetupStep::Storage => SetupStep::Directory,
+            SetupStep::Directory => SetupStep::Tls,
+            SetupStep::Tls => SetupStep::Listener,
+            SetupStep::Listener | SetupStep::Summary => SetupStep::Summary,
+        }
+    }
+
+    fn previous(&self) -> SetupStep {
+        match self {
+            SetupStep::Network | SetupStep::Store => SetupStep::Network,
+            SetupStep::Storage => SetupStep::Store,
+            SetupStep::Directory => SetupStep::Storage,
+            SetupStep::Tls => SetupStep::Directory,
+            SetupStep::Listener => SetupStep::Tls,
+            SetupStep::Summary => SetupStep::Listener,
+        }
+    }
+}
@@ -0,0 +1,782 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::sync::Arc;
+
+use ahash::{AHashMap, AHashSet};
+use leptos::*;
+use leptos_router::{use_navigate, use_params_map};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::{
+        badge::Badge,
+        form::{
+            button::Button, select::Select, Form, FormButtonBar, FormElement, FormItem, FormSection,
+        },
+        layout::tenant::use_tenant_scope,
+        list::table::{Table, TableRow},
+        messages::alert::{use_alerts, Alert},
+        skeleton::Skeleton,
+        Color,
+    },
+    core::{
+        http::{self, HttpRequest},
+        oauth::use_authorization,
+        schema::{Builder, Schemas, SelectType, Source, Type, Validator},
+        AccessToken,
+    },
+    pages::{
+        directory::{Principal, PrincipalField, PrincipalType, PrincipalUpdate},
+        maybe_plural, List,
+    },
+};
+
+const FETCH_PAGE_SIZE: usize = 250;
+
+// RFC 2142 mailbox names that are expected to be deliverable on every domain
+const ROLE_MAILBOXES: &[&str] = &["postmaster", "abuse"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum AddressKind {
+    Primary,
+    Alias,
+    List,
+    CatchAll,
+    Unowned,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct DomainPrincipal {
+    name: String,
+    typ: PrincipalType,
+    description: String,
+    emails: Vec<String>,
+    external_members: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct AddressOwner {
+    name: String,
+    typ: PrincipalType,
+    is_primary: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct AddressEntry {
+    address: String,
+    kind: AddressKind,
+    owners: Vec<AddressOwner>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct AddressMove {
+    address: String,
+    from: String,
+}
+
+#[component]
+pub fn DomainAddresses() -> impl IntoView {
+    let auth = use_authorization();
+    let alert = use_alerts();
+    let params = use_params_map();
+    let tenant_scope = use_tenant_scope();
+    let domain = create_memo(move |_| {
+        params
+            .get()
+            .get("id")
+            .map(|id| id.to_lowercase())
+            .unwrap_or_default()
+    });
+    let data = expect_context::<Arc<Schemas>>()
+        .build_form("address-move")
+        .into_signal();
+    let principals: RwSignal<Vec<DomainPrincipal>> = create_rw_signal(Vec::new());
+    let moving: RwSignal<Option<AddressMove>> = create_rw_signal(None);
+    let filter = create_rw_signal(String::new());
+    let problems_only = create_rw_signal(false);
+
+    let fetch_principals = create_resource(
+        move || domain.get(),
+        move |domain| {
+            let auth = auth.get_untracked();
+            let tenant = tenant_scope.get_untracked();
+
+            async move { fetch_domain_principals(&auth, &domain, tenant).await }
+        },
+    );
+
+    let entries =
+        create_memo(move |_| principals.with(|principals| address_book(&domain.get(), principals)));
+    let rows = create_memo(move |_| {
+        let filter = filter.get().to_lowercase();
+        let problems_only = problems_only.get();
+        entries.with(|entries| {
+            entries
+                .iter()
+                .filter(|entry| {
+                    (!problems_only || entry.has_problem())
+                        && (filter.is_empty()
+                            || entry.address.contains(&filter)
+                            || entry
+                                .owners
+                                .iter()
+                                .any(|owner| owner.name.contains(&filter)))
+                })
+                .cloned()
+                .collect::<Vec<_>>()
+        })
+    });
+    let target_options = create_memo(move |_| {
+        let from = moving.with(|moving| moving.as_ref().map(|m| m.from.clone()));
+        principals.with(|principals| {
+            principals
+                .iter()
+                .filter(|principal| Some(&principal.name) != from.as_ref())
+                .map(|principal| {
+                    let label = if principal.description.is_empty() {
+                        principal.name.clone()
+                    } else {
+                        format!("{} ({})", principal.description, principal.name)
+                    };
+                    (principal.name.clone(), label)
+                })
+                .collect::<Vec<_>>()
+        })
+    });
+
+    let move_address = create_action(move |(change, to): &(AddressMove, String)| {
+        let auth = auth.get();
+        let change = change.clone();
+        let to = to.clone();
+
+        async move {
+            match move_alias(&auth, &change, &to).await {
+                Ok(_) => {
+                    moving.set(None);
+                    principals.update(|principals| {
+                        for principal in principals.iter_mut() {
+                            if principal.name == change.from {
+                                principal.emails.retain(|email| email != &change.address);
+                            } else if principal.name == to {
+                                principal.emails.push(change.address.clone());
+                            }
+                        }
+                    });
+                    alert.set(Alert::success(format!(
+                        "Moved {} from {} to {to}.",
+                        change.address, change.from
+                    )));
+                }
+                Err(MoveError::Failed(http::Error::Unauthorized)) => {
+                    use_navigate()("/login", Default::default());
+                }
+                Err(MoveError::Failed(err)) => {
+                    alert.set(Alert::from(err));
+                }
+                Err(MoveError::NotRestored(err)) => {
+                    moving.set(None);
+                    principals.update(|principals| {
+                        if let Some(principal) = principals
+                            .iter_mut()
+                            .find(|principal| principal.name == change.from)
+                        {
+                            principal.emails.retain(|email| email != &change.address);
+                        }
+                    });
+                    alert.set(
+                        Alert {
+                            message: format!(
+                                "{} was removed from {} but could not be added to {to} or put back, add it again manually.",
+                                change.address, change.from
+                            ),
+                            ..Alert::from(err)
+                        }
+                        .without_timeout(),
+                    );
+                }
+            }
+        }
+    });
+
+    view! {
+        <Form
+            title="Address book"
+            subtitle=Signal::derive(move || {
+                format!("Every address under {}, including aliases and catch-alls", domain.get())
+            })
+        >
+
+            <FormSection>
+                <div class="sm:col-span-12">
+                    <Transition fallback=Skeleton>
+                        {move || match fetch_principals.get() {
+                            None => None,
+                            Some(Err(http::Error::Unauthorized)) => {
+                                use_navigate()("/login", Default::default());
+                                Some(view! { <div></div> }.into_view())
+                            }
+                            Some(Err(err)) => {
+                                alert.set(Alert::from(err));
+                                Some(view! { <div></div> }.into_view())
+                            }
+                            Some(Ok(fetched)) => {
+                                principals.set(fetched);
+                                moving.set(None);
+                                Some(view! { <div></div> }.into_view())
+                            }
+                        }}
+
+                    </Transition>
+                    <div class="flex flex-wrap items-center justify-between gap-4 mb-4">
+                        <p class="text-sm text-gray-600 dark:text-gray-400">
+                            {move || {
+                                entries
+                                    .with(|entries| {
+                                        let count = |kind: AddressKind| {
+                                            entries.iter().filter(|entry| entry.kind == kind).count()
+                                        };
+                                        format!(
+                                            "{}, {}, {} and {} without an owner.",
+                                            maybe_plural(
+                                                entries.len() - count(AddressKind::Unowned),
+                                                "address",
+                                                "addresses",
+                                            ),
+                                            maybe_plural(count(AddressKind::Alias), "alias", "aliases"),
+                                            maybe_plural(
+                                                entries.iter().filter(|entry| entry.is_collision()).count(),
+                                                "collision",
+                                                "collisions",
+                                            ),
+                                            count(AddressKind::Unowned),
+                                        )
+                                    })
+                            }}
+
+                        </p>
+                        <div class="flex items-center gap-x-4">
+                            <input
+                                type="text"
+                                class="py-2 px-3 block w-64 border-gray-200 shadow-sm text-sm rounded-lg focus:border-blue-500 focus:ring-blue-500 dark:bg-slate-900 dark:border-gray-700 dark:text-gray-400 dark:focus:ring-gray-600"
+                                placeholder="Search addresses"
+                                prop:value=move || filter.get()
+                                on:input=move |ev| filter.set(event_target_value(&ev))
+                            />
+                            <label class="flex items-center text-sm text-gray-500 dark:text-gray-400">
+                                <input
+                                    type="checkbox"
+                                    class="shrink-0 me-2 border-gray-200 rounded text-blue-600 focus:ring-blue-500 dark:bg-gray-800 dark:border-gray-700 dark:checked:bg-blue-500 dark:checked:border-blue-500 dark:focus:ring-offset-gray-800"
+                                    prop:checked=move || problems_only.get()
+                                    on:change=move |ev| problems_only.set(event_target_checked(&ev))
+                                />
+                                "Problems only"
+                            </label>
+                        </div>
+                    </div>
+
+                    <Table headers=vec![
+                        "Address".to_string(),
+                        "Type".to_string(),
+                        "Owner".to_string(),
+                        "".to_string(),
+                    ]>
+                        <For
+                            each=move || rows.get()
+                            key=|entry| entry.clone()
+                            children=move |entry| {
+                                let (label, color) = entry.badge();
+                                let can_move = entry.can_move();
+                                let change = entry
+                                    .owners
+                                    .first()
+                                    .map(|owner| AddressMove {
+                                        address: entry.address.clone(),
+                                        from: owner.name.clone(),
+                                    });
+                                view! {
+                                    <TableRow>
+                                        <span class="font-mono">{entry.address.clone()}</span>
+                                        <div class="flex flex-wrap gap-1">
+                                            <Badge color=color>{label}</Badge>
+                                            {entry
+                                                .is_collision()
+                                                .then(|| {
+                                                    view! { <Badge color=Color::Red>"Collision"</Badge> }
+                                                })}
+
+                                        </div>
+                                        <div class="flex flex-col">
+                                            {if entry.owners.is_empty() {
+                                                view! {
+                                                    <span class="text-gray-500 dark:text-gray-500">
+                                                        {entry.unowned_reason()}
+                                                    </span>
+                                                }
+                                                    .into_view()
+                                            } else {
+                                                entry
+                                                    .owners
+                                                    .iter()
+                                                    .map(|owner| {
+                                                        view! {
+                                                            <a
+                                                                class="text-blue-600 decoration-2 hover:underline dark:text-blue-500"
+                                                                href=format!(
+                                                                    "/manage/directory/{}/{}/edit",
+                                                                    owner.typ.resource_name(),
+                                                                    owner.name,
+                                                                )
+                                                            >
+
+                                                                {owner.name.clone()}
+                                                            </a>
+                                                        }
+                                                    })
+                                                    .collect_view()
+                                            }}
+
+                                        </div>
+                                        <div class="text-end">
+                                            {can_move
+                                                .then(|| {
+                                                    let change = change.clone();
+                                                    view! {
+                                                        <button
+                                                            type="button"
+                                                            class="text-sm font-semibold text-blue-600 hover:underline dark:text-blue-500"
+                                                            on:click=move |_| {
+                                                                data.update(|data| data.reset());
+                                                                moving.set(change.clone());
+                                                            }
+                                                        >
+
+                                                            "Move"
+                                                        </button>
+                                                    }
+                                                })}
+
+                                        </div>
+                                    </TableRow>
+                                }
+                            }
+                        />
+
+                    </Table>
+                </div>
+            </FormSection>
+
+            <Show when=move || moving.get().is_some()>
+                <FormSection>
+                    <FormItem label="Address">
+                        <p class="py-2 text-sm text-gray-800 dark:text-gray-200">
+                            {move || {
+                                moving
+                                    .get()
+                                    .map(|change| {
+                                        format!("{}, currently on {}", change.address, change.from)
+                                    })
+                            }}
+
+                        </p>
+                    </FormItem>
+                    <FormItem label="New owner">
+                        <Select element=FormElement::new("target", data) options=target_options/>
+                    </FormItem>
+                </FormSection>
+                <FormButtonBar>
+                    <Button
+                        text="Cancel"
+                        color=Color::Gray
+                        on_click=move |_| {
+                            moving.set(None);
+                        }
+                    />
+
+                    <Button
+                        text="Move address"
+                        color=Color::Blue
+                        on_click=move |_| {
+                            if !data.try_update(|data| data.validate_form()).unwrap_or_default() {
+                                return;
+                            }
+                            let to = data
+                                .with_untracked(|data| data.value::<String>("target"))
+                                .unwrap_or_default();
+                            if let Some(change) = moving.get_untracked() {
+                                move_address.dispatch((change, to));
+                            }
+                        }
+
+                        disabled=move_address.pending()
+                    />
+
+                </FormButtonBar>
+            </Show>
+        </Form>
+    }
+}
+
+async fn fetch_domain_principals(
+    auth: &AccessToken,
+    domain: &str,
+    tenant: Option<String>,
+) -> http::Result<Vec<DomainPrincipal>> {
+    let mut principals = Vec::new();
+    let mut page = 1;
+
+    loop {
+        let list = HttpRequest::get("/api/principal")
+            .with_authorization(auth)
+            .with_parameter("page", page.to_string())
+            .with_parameter("limit", FETCH_PAGE_SIZE.to_string())
+            .with_parameter("types", "individual,group,list,resource,location,other")
+            .with_parameter("filter", domain)
+            .with_parameter("fields", "name,type,description,emails,externalMembers")
+            .with_optional_parameter("tenant", tenant.clone())
+            .send::<List<Principal>>()
+            .await?;
+        let fetched = list.items.len();
+        principals.extend(list.items.into_iter().filter_map(|principal| {
+            Some(DomainPrincipal {
+                typ: principal.typ?,
+                description: principal
+                    .description
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                emails: principal.emails.as_string_list().to_vec(),
+                external_members: principal.external_members.as_string_list().to_vec(),
+                name: principal.name.try_unwrap_string()?,
+            })
+        }));
+
+        if fetched == 0 || (page * FETCH_PAGE_SIZE) as u64 >= list.total {
+            break;
+        }
+        page += 1;
+    }
+
+    principals.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(principals)
+}
+
+enum MoveError {
+    Failed(http::Error),
+    // The alias was removed from its owner and is now unassigned
+    NotRestored(http::Error),
+}
+
+// The alias is removed first since the server rejects an address that is
+// already in use. If adding it to the new owner fails it is put back.
+async fn move_alias(auth: &AccessToken, change: &AddressMove, to: &str) -> Result<(), MoveError> {
+    HttpRequest::patch(("/api/principal", &change.from))
+        .with_authorization(auth)
+        .with_body(vec![PrincipalUpdate::remove_item(
+            PrincipalField::Emails,
+            &change.address,
+        )])
+        .unwrap()
+        .send::<serde_json::Value>()
+        .await
+        .map_err(MoveError::Failed)?;
+
+    match HttpRequest::patch(("/api/principal", to))
+        .with_authorization(auth)
+        .with_body(vec![PrincipalUpdate::add_item(
+            PrincipalField::Emails,
+            &change.address,
+        )])
+        .unwrap()
+        .send::<serde_json::Value>()
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(err) => {
+            match HttpRequest::patch(("/api/principal", &change.from))
+                .with_authorization(auth)
+                .with_body(vec![PrincipalUpdate::add_item(
+                    PrincipalField::Emails,
+                    &change.address,
+                )])
+                .unwrap()
+                .send::<serde_json::Value>()
+                .await
+            {
+                Ok(_) => Err(MoveError::Failed(err)),
+                Err(rollback) => {
+                    log::error!(
+                        "Failed to restore {} on {}: {rollback:?}",
+                        change.address,
+                        change.from
+                    );
+                    Err(MoveError::NotRestored(err))
+                }
+            }
+        }
+    }
+}
+
+fn address_book(domain: &str, principals: &[DomainPrincipal]) -> Vec<AddressEntry> {
+    let mut addresses: AHashMap<String, AddressEntry> = AHashMap::new();
+
+    for principal in principals {
+        for (pos, email) in principal.emails.iter().enumerate() {
+            let address = email.trim().to_lowercase();
+            if !in_domain(&address, domain) {
+                continue;
+            }
+            let kind = if is_catch_all(&address) {
+                AddressKind::CatchAll
+            } else if principal.typ == PrincipalType::List {
+                AddressKind::List
+            } else if pos == 0 {
+                AddressKind::Primary
+            } else {
+                AddressKind::Alias
+            };
+            let entry = addresses
+                .entry(address.clone())
+                .or_insert_with(|| AddressEntry {
+                    address,
+                    kind,
+                    owners: Vec::new(),
+                });
+            // A primary address outranks the same address used as an alias elsewhere
+            entry.kind = entry.kind.min(kind);
+            if !entry
+                .owners
+                .iter()
+                .any(|owner| owner.name == principal.name)
+            {
+                entry.owners.push(AddressOwner {
+                    name: principal.name.clone(),
+                    typ: principal.typ,
+                    is_primary: pos == 0,
+                });
+            }
+        }
+    }
+
+    // Addresses that are referenced but not claimed by any principal
+    let catch_all = addresses
+        .values()
+        .filter(|entry| entry.kind == AddressKind::CatchAll)
+        .map(|entry| entry.address.clone())
+        .collect::<Vec<_>>();
+    let referenced = principals
+        .iter()
+        .flat_map(|principal| principal.external_members.iter())
+        .map(|address| address.trim().to_lowercase())
+        .chain(
+            ROLE_MAILBOXES
+                .iter()
+                .map(|mailbox| format!("{mailbox}@{domain}")),
+        )
+        .collect::<AHashSet<_>>();
+    for address in referenced {
+        if in_domain(&address, domain)
+            && !addresses.contains_key(&address)
+            && !catch_all
+                .iter()
+                .any(|pattern| matches_catch_all(pattern, &address))
+        {
+            addresses.insert(
+                address.clone(),
+                AddressEntry {
+                    address,
+                    kind: AddressKind::Unowned,
+                    owners: Vec::new(),
+                },
+            );
+        }
+    }
+
+    let mut entries = addresses.into_values().collect::<Vec<_>>();
+    entries.sort_by(|a, b| a.address.cmp(&b.address));
+    entries
+}
+
+fn in_domain(address: &str, domain: &str) -> bool {
+    address
+        .rsplit_once('@')
+        .is_some_and(|(_, address_domain)| address_domain == domain)
+}
+
+// Catch-all entries are stored as "@domain", wildcards use '*' in the local part
+fn is_catch_all(address: &str) -> bool {
+    address.starts_with('@') || address.split('@').next().is_some_and(|l| l.contains('*'))
+}
+
+fn matches_catch_all(pattern: &str, address: &str) -> bool {
+    let (Some((pattern_local, _)), Some((local, _))) =
+        (pattern.rsplit_once('@'), address.rsplit_once('@'))
+    else {
+        return false;
+    };
+    if pattern_local.is_empty() {
+        return true;
+    }
+
+    let mut parts = pattern_local.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = local.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts = parts.collect::<Vec<_>>();
+    let last = parts.pop();
+    for part in parts {
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    last.is_none_or(|last| rest.ends_with(last))
+}
+
+impl AddressEntry {
+    fn is_collision(&self) -> bool {
+        self.owners.len() > 1
+    }
+
+    fn has_problem(&self) -> bool {
+        self.is_collision() || self.kind == AddressKind::Unowned
+    }
+
+    fn can_move(&self) -> bool {
+        matches!(self.owners.as_slice(), [owner] if !owner.is_primary)
+    }
+
+    fn badge(&self) -> (&'static str, Color) {
+        match self.kind {
+            AddressKind::Primary => ("Primary", Color::Blue),
+            AddressKind::Alias => ("Alias", Color::Gray),
+            AddressKind::List => ("Mailing list", Color::Green),
+            AddressKind::CatchAll => ("Catch-all", Color::Yellow),
+            AddressKind::Unowned => ("No owner", Color::Red),
+        }
+    }
+
+    fn unowned_reason(&self) -> &'static str {
+        let local = self.address.split('@').next().unwrap_or_default();
+        if ROLE_MAILBOXES.contains(&local) {
+            "Required role address, not delivered to anyone"
+        } else {
+            "Subscribed to a mailing list, not delivered to anyone"
+        }
+    }
+}
+
+impl Builder<Schemas, ()> {
+    pub fn build_address_move(self) -> Self {
+        self.new_schema("address-move")
+            .new_field("target")
+            .typ(Type::Select {
+                typ: SelectType::Single,
+                source: Source::Static(&[]),
+            })
+            .input_check([], [Validator::Required])
+            .build()
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn principal(name: &str, typ: PrincipalType, emails: &[&str]) -> DomainPrincipal {
+        DomainPrincipal {
+            name: name.to_string(),
+            typ,
+            description: String::new(),
+            emails: emails.iter().map(|email| email.to_string()).collect(),
+            external_members: vec![],
+        }
+    }
+
+    #[test]
+    fn matches_catch_all_patterns() {
+        assert!(matches_catch_all("@example.org", "anything@example.org"));
+        assert!(matches_catch_all(
+            "sales-*@example.org",
+            "sales-eu@example.org"
+        ));
+        assert!(matches_catch_all(
+            "*-noreply@example.org",
+            "billing-noreply@example.org"
+        ));
+        assert!(matches_catch_all(
+            "a*b*c@example.org",
+            "axxbyyc@example.org"
+        ));
+        assert!(!matches_catch_all(
+            "sales-*@example.org",
+            "support@example.org"
+        ));
+        assert!(!matches_catch_all(
+            "a*b*c@example.org",
+            "axxcyyb@example.org"
+        ));
+        assert!(!matches_catch_all("invalid", "john@example.org"));
+    }
+
+    #[test]
+    fn builds_address_book() {
+        let principals = [
+            principal(
+                "john",
+                PrincipalType::Individual,
+                &["john@example.org", "Sales@example.org", "john@other.org"],
+            ),
+            principal(
+                "jane",
+                PrincipalType::Individual,
+                &["jane@example.org", "sales@example.org"],
+            ),
+            principal("team", PrincipalType::List, &["team@example.org"]),
+            principal(
+                "catch",
+                PrincipalType::Individual,
+                &["catch@example.org", "post*@example.org"],
+            ),
+            DomainPrincipal {
+                external_members: vec!["ghost@example.org".to_string()],
+                ..principal("ext", PrincipalType::List, &[])
+            },
+        ];
+        let book = address_book("example.org", &principals);
+        let entry = |address: &str| book.iter().find(|entry| entry.address == address);
+
+        assert!(entry("john@other.org").is_none());
+        assert_eq!(
+            entry("john@example.org").unwrap().kind,
+            AddressKind::Primary
+        );
+        assert!(!entry("john@example.org").unwrap().can_move());
+        assert!(entry("jane@example.org").is_some());
+
+        let sales = entry("sales@example.org").unwrap();
+        assert_eq!(sales.kind, AddressKind::Alias);
+        assert!(sales.is_collision());
+
+        assert_eq!(entry("team@example.org").unwrap().kind, AddressKind::List);
+        assert_eq!(
+            entry("post*@example.org").unwrap().kind,
+            AddressKind::CatchAll
+        );
+
+        // Referenced addresses without an owner, unless a catch-all covers them
+        let ghost = entry("ghost@example.org").unwrap();
+        assert_eq!(ghost.kind, AddressKind::Unowned);
+        assert!(ghost.has_problem());
+        assert!(entry("postmaster@example.org").is_none());
+        assert_eq!(
+            entry("abuse@example.org").unwrap().kind,
+            AddressKind::Unowned
+        );
+    }
+}
@@ -0,0 +1,922 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::sync::Arc;
+
+use ahash::AHashMap;
+use base64::{engine::general_purpose, Engine};
+use chrono::{DateTime, Duration, Utc};
+use leptos::*;
+use leptos_router::use_navigate;
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+use web_sys::{
+    js_sys::{Function, Reflect},
+    wasm_bindgen::JsCast,
+};
+
+use crate::{
+    components::{
+        badge::Badge,
+        form::{
+            button::Button,
+            input::{InputSwitch, InputText},
+            select::{CheckboxGroup, Select},
+            Form, FormButtonBar, FormElement, FormItem, FormSection,
+        },
+        layout::tenant::use_tenant_scope,
+        list::table::{Table, TableRow},
+        messages::{
+            alert::{use_alerts, Alert},
+            modal::{use_modals, Modal},
+        },
+        skeleton::Skeleton,
+        Color,
+    },
+    core::{
+        http::{self, HttpRequest},
+        oauth::use_authorization,
+        schema::{Builder, Schemas, SelectType, Source, Transformer, Type, Validator},
+        AccessToken, Permission,
+    },
+    pages::{
+        config::UpdateSettings,
+        directory::{
+            Principal, PrincipalField, PrincipalType, PrincipalUpdate, PrincipalValue, PERMISSIONS,
+        },
+        maybe_plural, FormatDateTime, List,
+    },
+};
+
+// SPDX-SnippetBegin
+// SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+// SPDX-License-Identifier: LicenseRef-SEL
+#[cfg(feature = "enterprise")]
+use crate::pages::{directory::spawn_workers, enterprise::tracing::activity::fetch_last_used};
+// SPDX-SnippetEnd
+
+// Principals have no notion of expiry, so the server never disables a key on
+// its own. Issue and rotation dates are kept in the configuration keyed by
+// the API key name and only serve as reminders.
+const LIFETIME_PREFIX: &str = "authentication.api-key";
+const EXPIRY_WARNING_DAYS: i64 = 14;
+#[cfg(feature = "enterprise")]
+const LAST_USED_WORKERS: usize = 4;
+
+struct KeyTemplate {
+    id: &'static str,
+    label: &'static str,
+    permissions: &'static [&'static str],
+}
+
+static KEY_TEMPLATES: &[KeyTemplate] = &[
+    KeyTemplate {
+        id: "send",
+        label: "Send e-mail",
+        permissions: &["authenticate", "email-send"],
+    },
+    KeyTemplate {
+        id: "metrics",
+        label: "Metrics collection",
+        permissions: &["authenticate", "metrics-list", "metrics-live"],
+    },
+    KeyTemplate {
+        id: "queue",
+        label: "Queue monitoring",
+        permissions: &["authenticate", "message-queue-list", "message-queue-update"],
+    },
+    KeyTemplate {
+        id: "provisioning",
+        label: "Account provisioning",
+        permissions: &[
+            "authenticate",
+            "individual-list",
+            "individual-get",
+            "individual-create",
+            "individual-update",
+            "individual-delete",
+            "domain-list",
+            "domain-get",
+        ],
+    },
+];
+
+static KEY_TEMPLATE_OPTIONS: &[(&str, &str)] = &[
+    ("send", "Send e-mail"),
+    ("metrics", "Metrics collection"),
+    ("queue", "Queue monitoring"),
+    ("provisioning", "Account provisioning"),
+    ("custom", "Custom permissions"),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct ApiKeyInfo {
+    name: String,
+    description: String,
+    permissions: Vec<String>,
+    roles: Vec<String>,
+    has_secret: bool,
+    issued: Option<DateTime<Utc>>,
+    rotate_by: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyStatus {
+    Active,
+    RotationDue,
+    RotationOverdue,
+    Revoked,
+}
+
+#[component]
+pub fn ApiKeyManager() -> impl IntoView {
+    let auth = use_authorization();
+    let alert = use_alerts();
+    let modal = use_modals();
+    let tenant_scope = use_tenant_scope();
+    let data = expect_context::<Arc<Schemas>>()
+        .build_form("api-key-issue")
+        .into_signal();
+    data.update(|data| data.apply_defaults(false));
+    let refresh = create_rw_signal(0u32);
+    let issued_token: RwSignal<Option<(String, String)>> = create_rw_signal(None);
+    let last_used: RwSignal<AHashMap<String, DateTime<Utc>>> = create_rw_signal(AHashMap::new());
+    let (pending, set_pending) = create_signal(false);
+
+    let keys = create_resource(
+        move || (tenant_scope.get(), refresh.get()),
+        move |(tenant, _)| {
+            let auth = auth.get_untracked();
+
+            async move { fetch_api_keys(&auth, tenant).await }
+        },
+    );
+
+    // SPDX-SnippetBegin
+    // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+    // SPDX-License-Identifier: LicenseRef-SEL
+    #[cfg(feature = "enterprise")]
+    create_effect(move |_| {
+        let auth = auth.get_untracked();
+        if let Some(Ok(keys)) = keys.get() {
+            if auth.is_enterprise() && auth.permissions().has_access(Permission::TracingList) {
+                let names = keys.into_iter().map(|key| key.name).collect::<Vec<_>>();
+                spawn_workers(names, LAST_USED_WORKERS, move |name| {
+                    let auth = auth.clone();
+                    async move {
+                        if let Ok(Some(used)) = fetch_last_used(&auth, &name).await {
+                            last_used.update(|last_used| {
+                                last_used.insert(name, used);
+                            });
+                        }
+                        true
+                    }
+                });
+            }
+        }
+    });
+    // SPDX-SnippetEnd
+
+    let issue_key = create_action(move |(principal, validity): &(Principal, Option<i64>)| {
+        let auth = auth.get();
+        let principal = principal.clone().with_tenant(tenant_scope.get_untracked());
+        let validity = *validity;
+
+        async move {
+            set_pending.set(true);
+            let name = principal.name().unwrap_or_default().to_string();
+            let secret = principal
+                .secrets
+                .as_string_list()
+                .first()
+                .cloned()
+                .unwrap_or_default();
+            let result = async {
+                HttpRequest::post("/api/principal")
+                    .with_authorization(&auth)
+                    .with_body(&principal)
+                    .unwrap()
+                    .send::<u32>()
+                    .await?;
+                let issued = Utc::now();
+                save_lifetime(
+                    &auth,
+                    &name,
+                    issued,
+                    validity.map(|days| issued + Duration::days(days)),
+                )
+                .await
+            }
+            .await;
+            set_pending.set(false);
+
+            match result {
+                Ok(_) => {
+                    issued_token.set(Some((name.clone(), bearer_token(&name, &secret))));
+                    data.update(|data| {
+                        data.set("name", "");
+                        data.set("description", "");
+                    });
+                    refresh.update(|r| *r += 1);
+                }
+                Err(http::Error::Unauthorized) => {
+                    use_navigate()("/login", Default::default());
+                }
+                Err(err) => {
+                    alert.set(Alert::from(err));
+                }
+            }
+        }
+    });
+
+    let rotate_key = create_action(move |key: &ApiKeyInfo| {
+        let auth = auth.get();
+        let key = key.clone();
+
+        async move {
+            set_pending.set(true);
+            let secret = generate_api_secret();
+            let result = async {
+                HttpRequest::patch(("/api/principal", &key.name))
+                    .with_authorization(&auth)
+                    .with_body(vec![PrincipalUpdate::set(
+                        PrincipalField::Secrets,
+                        PrincipalValue::StringList(vec![secret.clone()]),
+                    )])
+                    .unwrap()
+                    .send::<serde_json::Value>()
+                    .await?;
+
+                // The next reminder keeps the interval of the secret it replaces
+                let issued = Utc::now();
+                let rotate_by = match (key.issued, key.rotate_by) {
+                    (Some(prev_issued), Some(prev_rotate_by)) => {
+                        Some(issued + (prev_rotate_by - prev_issued))
+                    }
+                    _ => key.rotate_by.filter(|rotate_by| *rotate_by > issued),
+                };
+                save_lifetime(&auth, &key.name, issued, rotate_by).await
+            }
+            .await;
+            set_pending.set(false);
+
+            match result {
+                Ok(_) => {
+                    issued_token.set(Some((key.name.clone(), bearer_token(&key.name, &secret))));
+                    refresh.update(|r| *r += 1);
+                }
+                Err(http::Error::Unauthorized) => {
+                    use_navigate()("/login", Default::default());
+                }
+                Err(err) => {
+                    alert.set(Alert::from(err));
+                }
+            }
+        }
+    });
+
+    let revoke_key = create_action(move |name: &String| {
+        let auth = auth.get();
+        let name = name.clone();
+
+        async move {
+            set_pending.set(true);
+            let result = HttpRequest::patch(("/api/principal", &name))
+                .with_authorization(&auth)
+                .with_body(vec![PrincipalUpdate::set(
+                    PrincipalField::Secrets,
+                    PrincipalValue::StringList(vec![]),
+                )])
+                .unwrap()
+                .send::<serde_json::Value>()
+                .await;
+            set_pending.set(false);
+
+            match result {
+                Ok(_) => {
+                    alert.set(Alert::success(format!(
+                        "API key {name} was revoked, rotate it to issue a new secret."
+                    )));
+                    refresh.update(|r| *r += 1);
+                }
+                Err(http::Error::Unauthorized) => {
+                    use_navigate()("/login", Default::default());
+                }
+                Err(err) => {
+                    alert.set(Alert::from(err));
+                }
+            }
+        }
+    });
+
+    let is_custom =
+        create_memo(move |_| data.get().value::<String>("template").as_deref() == Some("custom"));
+
+    view! {
+        <Form title="API Key Lifecycle" subtitle="Issue, rotate and review API keys">
+
+            <FormSection title="Issue API Key".to_string()>
+                <FormItem label="Key Id">
+                    <InputText placeholder="metrics-collector" element=FormElement::new("name", data)/>
+                </FormItem>
+                <FormItem label="Description" is_optional=true>
+                    <InputText element=FormElement::new("description", data)/>
+                </FormItem>
+                <FormItem label="Scope">
+                    <Select element=FormElement::new("template", data)/>
+                </FormItem>
+                <FormItem label="Permissions" hide=Signal::derive(move || !is_custom.get())>
+                    <div class="max-h-72 overflow-y-auto">
+                        <CheckboxGroup element=FormElement::new("permissions", data)/>
+                    </div>
+                </FormItem>
+                <FormItem label="Rotation reminder">
+                    <Select element=FormElement::new("validity", data)/>
+                </FormItem>
+            </FormSection>
+
+            {move || {
+                issued_token
+                    .get()
+                    .map(|(name, token)| {
+                        view! {
+                            <RevealOnce
+                                title=format!("Bearer token for {name}")
+                                secret=token
+                                on_done=move |_| issued_token.set(None)
+                            />
+                        }
+                    })
+            }}
+
+            <FormButtonBar>
+                <Button
+                    text="Close"
+                    color=Color::Gray
+                    on_click=move |_| {
+                        use_navigate()("/manage/directory/api-keys", Default::default());
+                    }
+                />
+
+                <Button
+                    text="Issue key"
+                    color=Color::Blue
+                    on_click=Callback::new(move |_| {
+                        data.update(|data| {
+                            if !data.validate_form() {
+                                return;
+                            }
+                            let template = data.value::<String>("template").unwrap_or_default();
+                            let permissions = KEY_TEMPLATES
+                                .iter()
+                                .find(|t| t.id == template)
+                                .map(|t| {
+                                    t.permissions
+                                        .iter()
+                                        .map(|permission| permission.to_string())
+                                        .collect::<Vec<_>>()
+                                })
+                                .unwrap_or_else(|| {
+                                    data.array_value("permissions")
+                                        .map(|permission| permission.to_string())
+                                        .collect()
+                                });
+                            if permissions.is_empty() {
+                                alert.set(Alert::error("Select at least one permission."));
+                                return;
+                            }
+                            let validity = data
+                                .value::<String>("validity")
+                                .and_then(|days| days.parse::<i64>().ok());
+                            issue_key
+                                .dispatch((
+                                    Principal {
+                                        typ: Some(PrincipalType::ApiKey),
+                                        name: PrincipalValue::String(
+                                            data.value::<String>("name").unwrap_or_default(),
+                                        ),
+                                        description: PrincipalValue::String(
+                                            data.value::<String>("description").unwrap_or_default(),
+                                        ),
+                                        secrets: PrincipalValue::StringList(
+                                            vec![generate_api_secret()],
+                                        ),
+                                        enabled_permissions: PrincipalValue::StringList(permissions),
+                                        ..Default::default()
+                                    },
+                                    validity,
+                                ));
+                        });
+                    })
+
+                    disabled=pending
+                />
+            </FormButtonBar>
+
+            <FormSection title="API Keys".to_string()>
+                <FormItem label="">
+                    <InputSwitch
+                        label="Needs attention"
+                        tooltip="Only list keys that are due for rotation or have administrative permissions"
+                        element=FormElement::new("attention-only", data)
+                    />
+                </FormItem>
+            </FormSection>
+
+            <p class="pb-4 text-sm text-gray-600 dark:text-neutral-400">
+                "Rotation dates are reminders only, keys keep working until they are rotated or revoked."
+            </p>
+
+            <Transition fallback=Skeleton>
+                {move || match keys.get() {
+                    None => None,
+                    Some(Err(http::Error::Unauthorized)) => {
+                        use_navigate()("/login", Default::default());
+                        Some(view! { <div></div> }.into_view())
+                    }
+                    Some(Err(err)) => {
+                        alert.set(Alert::from(err));
+                        Some(view! { <div></div> }.into_view())
+                    }
+                    Some(Ok(keys)) => {
+                        let attention_only = data
+                            .get()
+                            .value::<bool>("attention-only")
+                            .unwrap_or_default();
+                        let now = Utc::now();
+                        let rows = keys
+                            .into_iter()
+                            .filter(|key| !attention_only || key.needs_attention(now))
+                            .map(|key| {
+                                let (color, status) = key.status(now).badge();
+                                let is_broad = key.is_broad();
+                                let scope = key.scope();
+                                let rotate_by = key
+                                    .rotate_by
+                                    .map(|rotate_by| rotate_by.format_date_time())
+                                    .unwrap_or_else(|| "Never".to_string());
+                                let key_name = key.name.clone();
+                                let used = move || {
+                                    last_used
+                                        .with(|last_used| {
+                                            last_used
+                                                .get(&key_name)
+                                                .map(|used| used.format_date_time())
+                                                .unwrap_or_else(|| "Unknown".to_string())
+                                        })
+                                };
+                                let edit_url = format!("/manage/directory/api-keys/{}/edit", key.name);
+                                let name = key.name.clone();
+                                let description = key.description.clone();
+                                let key = store_value(key);
+                                view! {
+                                    <TableRow>
+                                        <a
+                                            class="text-blue-600 decoration-2 hover:underline font-medium"
+                                            href=edit_url
+                                            title=description
+                                        >
+                                            {name}
+                                        </a>
+                                        <span class="flex items-center gap-x-2">
+                                            {scope}
+                                            {is_broad
+                                                .then(|| {
+                                                    view! { <Badge color=Color::Red>"Broad scope"</Badge> }
+                                                })}
+
+                                        </span>
+                                        <span>{rotate_by}</span>
+                                        <span>{used}</span>
+                                        <Badge color=color>{status}</Badge>
+                                        <span class="flex gap-x-2">
+                                            <Button
+                                                text="Rotate"
+                                                color=Color::Gray
+                                                on_click=move |_| {
+                                                    rotate_key.dispatch(key.get_value());
+                                                }
+
+                                                disabled=pending
+                                            />
+                                            <Button
+                                                text="Revoke"
+                                                color=Color::Red
+                                                on_click=move |_| {
+                                                    let name = key.with_value(|key| key.name.clone());
+                                                    modal
+                                                        .set(
+                                                            Modal::with_title("Confirm revocation")
+                                                                .with_message(
+                                                                    format!(
+                                                                        "Are you sure you want to revoke API key {name}? Clients using it will stop working until the key is rotated.",
+                                                                    ),
+                                                                )
+                                                                .with_button(format!("Revoke {name}"))
+                                                                .with_dangerous_callback(move || {
+                                                                    revoke_key.dispatch(name.clone());
+                                                                }),
+                                                        );
+                                                }
+
+                                                disabled=Signal::derive(move || {
+                                                    pending.get() || !key.with_value(|key| key.has_secret)
+                                                })
+                                            />
+                                        </span>
+                                    </TableRow>
+                                }
+                            })
+                            .collect_view();
+
+                        Some(
+                            view! {
+                                <Table headers=vec![
+                                    "Key Id".to_string(),
+                                    "Scope".to_string(),
+                                    "Rotate by".to_string(),
+                                    "Last used".to_string(),
+                                    "Status".to_string(),
+                                    "".to_string(),
+                                ]>{rows}</Table>
+                            }
+                                .into_view(),
+                        )
+                    }
+                }}
+
+            </Transition>
+        </Form>
+    }
+}
+
+#[component]
+pub(super) fn RevealOnce(
+    #[prop(into)] title: String,
+    secret: String,
+    #[prop(into)] on_done: Callback<()>,
+) -> impl IntoView {
+    let alert = use_alerts();
+    let secret_ = secret.clone();
+
+    view! {
+        <div class="mt-5 p-4 bg-yellow-50 border border-yellow-200 text-sm text-yellow-800 rounded-lg dark:bg-yellow-800/10 dark:border-yellow-900 dark:text-yellow-500">
+            <p class="font-semibold">
+                {format!("{title}. Copy it now, it will not be shown again.")}
+            </p>
+            <p class="mt-2 font-mono break-all">{secret}</p>
+            <div class="mt-3 flex gap-x-2">
+                <Button
+                    text="Copy"
+                    color=Color::Gray
+                    on_click=move |_| {
+                        if copy_to_clipboard(&secret_) {
+                            alert.set(Alert::success("Copied to clipboard."));
+                        } else {
+                            alert.set(Alert::error("Could not access the clipboard."));
+                        }
+                    }
+                />
+
+                <Button
+                    text="Done"
+                    color=Color::Blue
+                    on_click=move |_| {
+                        on_done.call(());
+                    }
+                />
+
+            </div>
+        </div>
+    }
+}
+
+async fn fetch_api_keys(
+    auth: &AccessToken,
+    tenant: Option<String>,
+) -> http::Result<Vec<ApiKeyInfo>> {
+    let principals = HttpRequest::get("/api/principal")
+        .with_authorization(auth)
+        .with_parameter("types", PrincipalType::ApiKey.id())
+        .with_parameter(
+            "fields",
+            "name,description,secrets,roles,enabledPermissions",
+        )
+        .with_optional_parameter("tenant", tenant)
+        .send::<List<Principal>>()
+        .await?;
+    let lifetimes = HttpRequest::get("/api/settings/keys")
+        .with_authorization(auth)
+        .with_parameter("prefixes", LIFETIME_PREFIX)
+        .send::<AHashMap<String, Option<String>>>()
+        .await?;
+    let lifetime = |name: &str, key: &str| {
+        lifetimes
+            .get(&format!("{LIFETIME_PREFIX}.{name}.{key}"))
+            .and_then(|value| value.as_deref())
+            .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+            .map(|value| value.with_timezone(&Utc))
+    };
+
+    let mut keys = Vec::with_capacity(principals.items.len());
+    for principal in principals.items {
+        let Some(name) = principal.name().map(|name| name.to_string()) else {
+            continue;
+        };
+        keys.push(ApiKeyInfo {
+            issued: lifetime(&name, "issued"),
+            rotate_by: lifetime(&name, "rotate-by"),
+            description: principal.description().unwrap_or_default().to_string(),
+            permissions: principal.enabled_permissions.as_string_list().to_vec(),
+            roles: principal.roles.as_string_list().to_vec(),
+            has_secret: !principal.secrets.as_string_list().is_empty(),
+            name,
+        });
+    }
+
+    Ok(keys)
+}
+
+async fn save_lifetime(
+    auth: &AccessToken,
+    name: &str,
+    issued: DateTime<Utc>,
+    rotate_by: Option<DateTime<Utc>>,
+) -> http::Result<()> {
+    let mut values = vec![("issued".to_string(), issued.to_rfc3339())];
+    if let Some(rotate_by) = rotate_by {
+        values.push(("rotate-by".to_string(), rotate_by.to_rfc3339()));
+    }
+
+    HttpRequest::post("/api/settings")
+        .with_authorization(auth)
+        .with_body(vec![
+            UpdateSettings::Clear {
+                prefix: format!("{LIFETIME_PREFIX}.{name}."),
+                filter: None,
+            },
+            UpdateSettings::Insert {
+                prefix: Some(format!("{LIFETIME_PREFIX}.{name}")),
+                values,
+                assert_empty: false,
+            },
+        ])
+        .unwrap()
+        .send::<Option<String>>()
+        .await
+        .map(|_| ())
+}
+
+// Called when API keys are deleted so that no reminders are left behind.
+pub(super) async fn clear_lifetimes(auth: &AccessToken, names: &[String]) -> http::Result<()> {
+    if names.is_empty() {
+        return Ok(());
+    }
+
+    HttpRequest::post("/api/settings")
+        .with_authorization(auth)
+        .with_body(lifetime_updates(names))
+        .unwrap()
+        .send::<Option<String>>()
+        .await
+        .map(|_| ())
+}
+
+fn lifetime_updates(names: &[String]) -> Vec<UpdateSettings> {
+    names
+        .iter()
+        .map(|name| UpdateSettings::Clear {
+            prefix: format!("{LIFETIME_PREFIX}.{name}."),
+            filter: None,
+        })
+        .collect()
+}
+
+pub(super) fn generate_api_secret() -> String {
+    thread_rng()
+        .sample_iter(Alphanumeric)
+        .take(30)
+        .map(char::from)
+        .collect()
+}
+
+pub(super) fn bearer_token(name: &str, secret: &str) -> String {
+    format!(
+        "api_{}",
+        general_purpose::STANDARD.encode(format!("{}:{}", name, secret).as_bytes())
+    )
+}
+
+// The async clipboard API is not exposed by web-sys without unstable flags,
+// so it is called through reflection.
+pub(super) fn copy_to_clipboard(text: &str) -> bool {
+    let navigator = window().navigator();
+    Reflect::get(&navigator, &"clipboard".into())
+        .ok()
+        .filter(|clipboard| !clipboard.is_undefined())
+        .and_then(|clipboard| {
+            Reflect::get(&clipboard, &"writeText".into())
+                .ok()?
+                .dyn_into::<Function>()
+                .ok()?
+                .call1(&clipboard, &text.into())
+                .ok()
+        })
+        .is_some()
+}
+
+impl ApiKeyInfo {
+    fn status(&self, now: DateTime<Utc>) -> KeyStatus {
+        match self.rotate_by {
+            _ if !self.has_secret => KeyStatus::Revoked,
+            Some(rotate_by) if rotate_by <= now => KeyStatus::RotationOverdue,
+            Some(rotate_by) if rotate_by - now <= Duration::days(EXPIRY_WARNING_DAYS) => {
+                KeyStatus::RotationDue
+            }
+            _ => KeyStatus::Active,
+        }
+    }
+
+    fn is_broad(&self) -> bool {
+        self.roles
+            .iter()
+            .any(|role| role == "admin" || role == "tenant-admin")
+            || self.permissions.iter().any(|permission| {
+                serde_json::from_value::<Permission>(serde_json::Value::String(
+                    permission.to_string(),
+                ))
+                .is_ok_and(|permission| permission.is_admin_permission())
+            })
+    }
+
+    fn needs_attention(&self, now: DateTime<Utc>) -> bool {
+        self.is_broad()
+            || matches!(
+                self.status(now),
+                KeyStatus::RotationDue | KeyStatus::RotationOverdue
+            )
+    }
+
+    fn scope(&self) -> String {
+        let mut permissions = self.permissions.clone();
+        permissions.sort_unstable();
+        KEY_TEMPLATES
+            .iter()
+            .find(|template| {
+                let mut expected = template.permissions.to_vec();
+                expected.sort_unstable();
+                self.roles.is_empty() && expected == permissions
+            })
+            .map(|template| template.label.to_string())
+            .unwrap_or_else(|| match (self.roles.len(), permissions.len()) {
+                (0, n) => maybe_plural(n, "permission", "permissions"),
+                (r, n) => format!(
+                    "{}, {}",
+                    maybe_plural(r, "role", "roles"),
+                    maybe_plural(n, "permission", "permissions")
+                ),
+            })
+    }
+}
+
+impl KeyStatus {
+    fn badge(&self) -> (Color, &'static str) {
+        match self {
+            KeyStatus::Active => (Color::Green, "Active"),
+            KeyStatus::RotationDue => (Color::Yellow, "Rotation due"),
+            KeyStatus::RotationOverdue => (Color::Red, "Rotation overdue"),
+            KeyStatus::Revoked => (Color::Gray, "Revoked"),
+        }
+    }
+}
+
+impl Builder<Schemas, ()> {
+    pub fn build_api_key_issue(self) -> Self {
+        self.new_schema("api-key-issue")
+            .new_field("name")
+            .typ(Type::Input)
+            .input_check(
+                [Transformer::RemoveSpaces, Transformer::Lowercase],
+                [Validator::Required, Validator::IsId],
+            )
+            .build()
+            .new_field("description")
+            .typ(Type::Input)
+            .input_check([Transformer::Trim], [])
+            .build()
+            .new_field("template")
+            .typ(Type::Select {
+                typ: SelectType::Single,
+                source: Source::Static(KEY_TEMPLATE_OPTIONS),
+            })
+            .default("send")
+            .build()
+            .new_field("permissions")
+            .typ(Type::Select {
+                typ: SelectType::Many,
+                source: Source::Static(PERMISSIONS),
+            })
+            .build()
+            .new_field("validity")
+            .typ(Type::Select {
+                typ: SelectType::Single,
+                source: Source::Static(&[
+                    ("30", "30 days"),
+                    ("90", "90 days"),
+                    ("180", "180 days"),
+                    ("365", "1 year"),
+                    ("never", "No reminder"),
+                ]),
+            })
+            .default("90")
+            .build()
+            .new_field("attention-only")
+            .typ(Type::Boolean)
+            .default("false")
+            .build()
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(rotate_by: Option<DateTime<Utc>>) -> ApiKeyInfo {
+        ApiKeyInfo {
+            name: "mailer".to_string(),
+            description: String::new(),
+            permissions: vec!["email-send".to_string(), "authenticate".to_string()],
+            roles: vec![],
+            has_secret: true,
+            issued: None,
+            rotate_by,
+        }
+    }
+
+    #[test]
+    fn reminds_when_rotation_is_due() {
+        let now = Utc::now();
+
+        assert_eq!(key(None).status(now), KeyStatus::Active);
+        assert_eq!(
+            key(Some(now + Duration::days(30))).status(now),
+            KeyStatus::Active
+        );
+        assert_eq!(
+            key(Some(now + Duration::days(3))).status(now),
+            KeyStatus::RotationDue
+        );
+        assert_eq!(
+            key(Some(now - Duration::days(1))).status(now),
+            KeyStatus::RotationOverdue
+        );
+        assert!(key(Some(now - Duration::days(1))).needs_attention(now));
+
+        let revoked = ApiKeyInfo {
+            has_secret: false,
+            ..key(Some(now - Duration::days(1)))
+        };
+        assert_eq!(revoked.status(now), KeyStatus::Revoked);
+    }
+
+    #[test]
+    fn describes_scope() {
+        let key = key(None);
+        assert_eq!(key.scope(), "Send e-mail");
+        assert!(!key.is_broad());
+
+        let admin = ApiKeyInfo {
+            roles: vec!["admin".to_string()],
+            ..key
+        };
+        assert_eq!(admin.scope(), "1 role, 2 permissions");
+        assert!(admin.is_broad());
+
+        let custom = ApiKeyInfo {
+            roles: vec!["user".to_string(), "admin".to_string()],
+            permissions: vec!["authenticate".to_string()],
+            ..admin
+        };
+        assert_eq!(custom.scope(), "2 roles, 1 permission");
+    }
+
+    #[test]
+    fn clears_lifetimes_of_deleted_keys() {
+        let updates = lifetime_updates(&["a".to_string(), "b".to_string()]);
+        let prefixes = updates
+            .iter()
+            .filter_map(|update| match update {
+                UpdateSettings::Clear { prefix, .. } => Some(prefix.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            prefixes,
+            ["authentication.api-key.a.", "authentication.api-key.b."]
+        );
+    }
+}
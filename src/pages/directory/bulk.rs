@@ -0,0 +1,622 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::sync::Arc;
+
+use gloo_storage::{SessionStorage, Storage};
+use leptos::*;
+use leptos_router::{use_navigate, use_params_map};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::{
+        badge::Badge,
+        form::{
+            button::Button, input::InputText, select::Select, Form, FormButtonBar, FormElement,
+            FormItem, FormSection,
+        },
+        list::table::{Table, TableRow},
+        messages::alert::{use_alerts, Alert},
+        skeleton::Skeleton,
+        Color,
+    },
+    core::{
+        form::FormData,
+        http::{self, HttpRequest},
+        oauth::use_authorization,
+        schema::{Builder, Schemas, SelectType, Source, Transformer, Type, Validator},
+        AccessToken,
+    },
+    pages::{
+        directory::{
+            edit::LOCALES,
+            import::{describe_error, parse_size},
+            spawn_workers, Principal, PrincipalField, PrincipalType, PrincipalUpdate,
+            PrincipalValue, PERMISSIONS,
+        },
+        maybe_plural, List,
+    },
+    BULK_SELECTION_STORAGE_KEY,
+};
+
+const BULK_CONCURRENCY: usize = 4;
+const BULK_PAGE_SIZE: u32 = 100;
+
+const BULK_ACTIONS: &[(&str, &str)] = &[
+    ("add-group", "Add to group"),
+    ("remove-group", "Remove from group"),
+    ("add-list", "Add to mailing list"),
+    ("remove-list", "Remove from mailing list"),
+    ("add-role", "Assign role"),
+    ("remove-role", "Revoke role"),
+    ("set-quota", "Set quota"),
+    ("set-tenant", "Set tenant"),
+    ("set-locale", "Set locale"),
+    ("enable-permission", "Enable permission"),
+    ("disable-permission", "Disable permission"),
+];
+
+// Principals selected in the directory listing, handed over to the bulk edit page
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BulkSelection {
+    pub typ: PrincipalType,
+    pub names: Vec<String>,
+    pub all: bool,
+    pub filter: Option<String>,
+    #[serde(default)]
+    pub tenant: Option<String>,
+}
+
+type BulkResult = Option<Result<(), String>>;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct BulkTargets {
+    groups: Vec<(String, String)>,
+    lists: Vec<(String, String)>,
+    roles: Vec<(String, String)>,
+}
+
+#[component]
+pub fn PrincipalBulkEdit() -> impl IntoView {
+    let auth = use_authorization();
+    let alert = use_alerts();
+    let params = use_params_map();
+    let selected_type = create_memo(move |_| {
+        match params
+            .get()
+            .get("object")
+            .map(|id| id.as_str())
+            .unwrap_or_default()
+        {
+            "accounts" => PrincipalType::Individual,
+            "groups" => PrincipalType::Group,
+            "lists" => PrincipalType::List,
+            "tenants" => PrincipalType::Tenant,
+            "domains" => PrincipalType::Domain,
+            "roles" => PrincipalType::Role,
+            "api-keys" => PrincipalType::ApiKey,
+            "oauth-clients" => PrincipalType::OauthClient,
+            "resources" => PrincipalType::Resource,
+            "locations" => PrincipalType::Location,
+            "other" => PrincipalType::Other,
+            _ => PrincipalType::Individual,
+        }
+    });
+
+    let data = expect_context::<Arc<Schemas>>()
+        .build_form("principal-bulk")
+        .into_signal();
+    let targets = create_rw_signal(BulkTargets::default());
+    let names: RwSignal<Arc<Vec<String>>> = create_rw_signal(Arc::new(Vec::new()));
+    let results: RwSignal<Vec<BulkResult>> = create_rw_signal(Vec::new());
+    let updates = store_value(Arc::new(Vec::<PrincipalUpdate>::new()));
+
+    let selection = create_resource(
+        move || (auth.get(), selected_type.get()),
+        move |(auth, typ)| async move {
+            let selection = SessionStorage::get::<BulkSelection>(BULK_SELECTION_STORAGE_KEY)
+                .ok()
+                .filter(|selection| selection.typ == typ)
+                .unwrap_or_else(|| BulkSelection {
+                    typ,
+                    ..Default::default()
+                });
+            let targets = fetch_targets(&auth, selection.tenant.as_deref()).await?;
+            let selected = if selection.all {
+                fetch_names(&auth, &selection).await?
+            } else {
+                selection.names
+            };
+
+            Ok((selected, targets))
+        },
+    );
+
+    let run = move |queue: Vec<usize>| {
+        let names = names.get_untracked();
+        let updates = updates.get_value();
+        let auth = auth.get_untracked();
+
+        results.update(|results| {
+            for idx in &queue {
+                results[*idx] = None;
+            }
+        });
+        spawn_workers(queue, BULK_CONCURRENCY, move |idx| {
+            let names = names.clone();
+            let updates = updates.clone();
+            let auth = auth.clone();
+
+            async move {
+                let result = HttpRequest::patch(("/api/principal", &names[idx]))
+                    .with_authorization(&auth)
+                    .with_body(updates.as_ref())
+                    .unwrap()
+                    .send::<serde_json::Value>()
+                    .await
+                    .map(|_| ())
+                    .map_err(describe_error);
+                results
+                    .try_update(|results| results[idx] = Some(result))
+                    .is_some()
+            }
+        });
+    };
+
+    let total_completed = create_memo(move |_| results.with(|r| r.iter().flatten().count()));
+    let total_failed = create_memo(move |_| {
+        results.with(|r| r.iter().filter(|r| matches!(r, Some(Err(_)))).count())
+    });
+    let is_running = create_memo(move |_| results.with(|r| r.iter().any(|r| r.is_none())));
+    let has_results = create_memo(move |_| results.with(|r| !r.is_empty()));
+    let action = create_memo(move |_| data.get().value::<String>("action").unwrap_or_default());
+    let target_options = create_memo(move |_| {
+        targets.with(|targets| match action.get().as_str() {
+            "add-group" | "remove-group" => targets.groups.clone(),
+            "add-list" | "remove-list" => targets.lists.clone(),
+            "add-role" | "remove-role" => targets.roles.clone(),
+            _ => Vec::new(),
+        })
+    });
+
+    let title = Signal::derive(move || {
+        format!(
+            "Bulk edit {}",
+            selected_type.get().item_name(true).to_lowercase()
+        )
+    });
+    let subtitle = Signal::derive(move || {
+        let total = names.with(|names| names.len());
+        format!(
+            "Apply the same change to {}",
+            maybe_plural(
+                total,
+                selected_type.get().item_name(false),
+                selected_type.get().item_name(true),
+            )
+        )
+    });
+
+    view! {
+        <Form title=title subtitle=subtitle>
+            <Transition fallback=Skeleton>
+
+                {move || match selection.get() {
+                    None => None,
+                    Some(Err(http::Error::Unauthorized)) => {
+                        use_navigate()("/login", Default::default());
+                        Some(view! { <div></div> }.into_view())
+                    }
+                    Some(Err(err)) => {
+                        alert.set(Alert::from(err));
+                        Some(view! { <div></div> }.into_view())
+                    }
+                    Some(Ok((selected, fetched_targets))) => {
+                        if selected.is_empty() {
+                            alert
+                                .set(
+                                    Alert::warning("No principals selected")
+                                        .with_details(
+                                            "Select one or more principals in the directory listing first.",
+                                        ),
+                                );
+                        }
+                        names.set(Arc::new(selected));
+                        targets.set(fetched_targets);
+                        Some(view! { <div></div> }.into_view())
+                    }
+                }}
+
+            </Transition>
+
+            <FormSection hide=has_results>
+                <FormItem label="Action">
+                    <Select element=FormElement::new("action", data)/>
+                </FormItem>
+                <FormItem
+                    label=Signal::derive(move || {
+                        match action.get().as_str() {
+                            "add-group" | "remove-group" => "Group",
+                            "add-list" | "remove-list" => "Mailing list",
+                            _ => "Role",
+                        }
+                            .to_string()
+                    })
+
+                    hide=Signal::derive(move || {
+                        !matches!(
+                            action.get().as_str(),
+                            "add-group"
+                            | "remove-group"
+                            | "add-list"
+                            | "remove-list"
+                            | "add-role"
+                            | "remove-role"
+                        )
+                    })
+                >
+
+                    <Select element=FormElement::new("target", data) options=target_options/>
+                </FormItem>
+                <FormItem
+                    label="Quota"
+                    tooltip="Disk quota in bytes, or using a K, M, G or T suffix"
+                    hide=Signal::derive(move || action.get() != "set-quota")
+                >
+                    <InputText element=FormElement::new("quota", data) placeholder="1G"/>
+                </FormItem>
+                <FormItem label="Tenant" hide=Signal::derive(move || action.get() != "set-tenant")>
+                    <InputText element=FormElement::new("tenant", data)/>
+                </FormItem>
+                <FormItem label="Locale" hide=Signal::derive(move || action.get() != "set-locale")>
+                    <Select element=FormElement::new("locale", data)/>
+                </FormItem>
+                <FormItem
+                    label="Permission"
+                    hide=Signal::derive(move || {
+                        !matches!(action.get().as_str(), "enable-permission" | "disable-permission")
+                    })
+                >
+
+                    <Select element=FormElement::new("permission", data)/>
+                </FormItem>
+            </FormSection>
+
+            // Progress and per-principal report
+            <FormSection hide=Signal::derive(move || !has_results.get())>
+                <div class="sm:col-span-12">
+                    <div class="flex w-full h-2 bg-gray-200 rounded-full overflow-hidden dark:bg-gray-700">
+                        <div
+                            class=move || {
+                                if total_failed.get() > 0 {
+                                    "flex flex-col justify-center overflow-hidden bg-red-600 transition duration-500"
+                                } else {
+                                    "flex flex-col justify-center overflow-hidden bg-blue-600 transition duration-500"
+                                }
+                            }
+
+                            style=move || {
+                                let total = results.with(|r| r.len()).max(1);
+                                format!("width: {}%;", total_completed.get() * 100 / total)
+                            }
+                        >
+                        </div>
+                    </div>
+                    <p class="my-4 text-sm text-gray-600 dark:text-gray-400">
+                        {move || {
+                            let total = results.with(|r| r.len());
+                            if is_running.get() {
+                                format!("Updated {} of {total} principals...", total_completed.get())
+                            } else if total_failed.get() > 0 {
+                                format!(
+                                    "Completed with {} out of {total} failed.",
+                                    maybe_plural(total_failed.get(), "update", "updates"),
+                                )
+                            } else {
+                                format!(
+                                    "Successfully updated {}.",
+                                    maybe_plural(total, "principal", "principals"),
+                                )
+                            }
+                        }}
+
+                    </p>
+                    <Table headers=vec![
+                        "Name".to_string(),
+                        "Result".to_string(),
+                        "Details".to_string(),
+                    ]>
+                        <For
+                            each=move || 0..names.with(|names| names.len())
+                            key=|idx| *idx
+                            children=move |idx| {
+                                let name = names.with_untracked(|names| names[idx].clone());
+                                let result = create_memo(move |_| {
+                                    results.with(|r| r.get(idx).cloned().flatten())
+                                });
+                                view! {
+                                    <TableRow>
+                                        <span>{name}</span>
+                                        {move || {
+                                            let (color, label) = match result.get() {
+                                                Some(Ok(_)) => (Color::Green, "Updated"),
+                                                Some(Err(_)) => (Color::Red, "Failed"),
+                                                None => (Color::Gray, "Pending"),
+                                            };
+                                            view! { <Badge color=color>{label}</Badge> }
+                                        }}
+
+                                        <span>
+                                            {move || {
+                                                match result.get() {
+                                                    Some(Err(err)) => err,
+                                                    _ => String::new(),
+                                                }
+                                            }}
+
+                                        </span>
+                                    </TableRow>
+                                }
+                            }
+                        />
+
+                    </Table>
+                </div>
+            </FormSection>
+
+            <FormButtonBar>
+                <Button
+                    text=Signal::derive(move || {
+                        if has_results.get() { "Close" } else { "Cancel" }.to_string()
+                    })
+
+                    color=Color::Gray
+                    on_click=move |_| {
+                        use_navigate()(
+                            &format!("/manage/directory/{}", selected_type.get().resource_name()),
+                            Default::default(),
+                        );
+                    }
+
+                    disabled=is_running
+                />
+
+                <Show when=move || has_results.get()>
+                    <Button
+                        text="Retry failed"
+                        color=Color::Red
+                        on_click=move |_| {
+                            run(
+                                results
+                                    .with_untracked(|r| {
+                                        r.iter()
+                                            .enumerate()
+                                            .filter(|(_, result)| matches!(result, Some(Err(_))))
+                                            .map(|(idx, _)| idx)
+                                            .collect()
+                                    }),
+                            );
+                        }
+
+                        disabled=Signal::derive(move || is_running.get() || total_failed.get() == 0)
+                    />
+                </Show>
+
+                <Show when=move || !has_results.get()>
+                    <Button
+                        text="Apply"
+                        color=Color::Blue
+                        on_click=move |_| {
+                            if names.with_untracked(|names| names.is_empty()) {
+                                alert.set(Alert::error("No principals were selected."));
+                                return;
+                            }
+                            if !data.try_update(|data| data.validate_form()).unwrap_or_default() {
+                                return;
+                            }
+                            match data.with_untracked(build_updates) {
+                                Ok(bulk_updates) => {
+                                    let total = names.with_untracked(|names| names.len());
+                                    updates.set_value(Arc::new(bulk_updates));
+                                    results.set(vec![None; total]);
+                                    run((0..total).collect());
+                                }
+                                Err(err) => {
+                                    alert.set(Alert::error(err));
+                                }
+                            }
+                        }
+                    />
+
+                </Show>
+            </FormButtonBar>
+        </Form>
+    }
+}
+
+fn build_updates(data: &FormData) -> Result<Vec<PrincipalUpdate>, String> {
+    let action = data.value::<String>("action").unwrap_or_default();
+    let value = |id: &str| data.value::<String>(id).unwrap_or_default();
+
+    Ok(match action.as_str() {
+        "add-group" => vec![PrincipalUpdate::add_item(
+            PrincipalField::MemberOf,
+            value("target"),
+        )],
+        "remove-group" => vec![PrincipalUpdate::remove_item(
+            PrincipalField::MemberOf,
+            value("target"),
+        )],
+        "add-list" => vec![PrincipalUpdate::add_item(
+            PrincipalField::Lists,
+            value("target"),
+        )],
+        "remove-list" => vec![PrincipalUpdate::remove_item(
+            PrincipalField::Lists,
+            value("target"),
+        )],
+        "add-role" => vec![PrincipalUpdate::add_item(
+            PrincipalField::Roles,
+            value("target"),
+        )],
+        "remove-role" => vec![PrincipalUpdate::remove_item(
+            PrincipalField::Roles,
+            value("target"),
+        )],
+        "set-quota" => {
+            let quota = parse_size(&value("quota"))
+                .ok_or_else(|| format!("Invalid quota {:?}.", value("quota")))?;
+            vec![PrincipalUpdate::set(
+                PrincipalField::Quota,
+                PrincipalValue::Integer(quota),
+            )]
+        }
+        "set-tenant" => vec![PrincipalUpdate::set(
+            PrincipalField::Tenant,
+            PrincipalValue::String(value("tenant")),
+        )],
+        "set-locale" => vec![PrincipalUpdate::set(
+            PrincipalField::Locale,
+            PrincipalValue::String(value("locale")),
+        )],
+        "enable-permission" => vec![
+            PrincipalUpdate::remove_item(PrincipalField::DisabledPermissions, value("permission")),
+            PrincipalUpdate::add_item(PrincipalField::EnabledPermissions, value("permission")),
+        ],
+        "disable-permission" => vec![
+            PrincipalUpdate::remove_item(PrincipalField::EnabledPermissions, value("permission")),
+            PrincipalUpdate::add_item(PrincipalField::DisabledPermissions, value("permission")),
+        ],
+        _ => return Err("Please select an action.".to_string()),
+    })
+}
+
+async fn fetch_names(auth: &AccessToken, selection: &BulkSelection) -> http::Result<Vec<String>> {
+    let mut names = Vec::new();
+    let mut page = 1;
+
+    loop {
+        let list = HttpRequest::get("/api/principal")
+            .with_authorization(auth)
+            .with_parameter("page", page.to_string())
+            .with_parameter("limit", BULK_PAGE_SIZE.to_string())
+            .with_parameter("types", selection.typ.id())
+            .with_parameter("fields", "name")
+            .with_optional_parameter("filter", selection.filter.clone())
+            .with_optional_parameter("tenant", selection.tenant.clone())
+            .send::<List<Principal>>()
+            .await?;
+        let fetched = list.items.len();
+        names.extend(
+            list.items
+                .into_iter()
+                .filter_map(|principal| principal.name.try_unwrap_string()),
+        );
+
+        if fetched == 0 || names.len() as u64 >= list.total {
+            break;
+        }
+        page += 1;
+    }
+
+    Ok(names)
+}
+
+async fn fetch_targets(auth: &AccessToken, tenant: Option<&str>) -> http::Result<BulkTargets> {
+    let mut targets = BulkTargets {
+        roles: ["admin", "tenant-admin", "user"]
+            .into_iter()
+            .map(|role| (role.to_string(), role.to_string()))
+            .collect(),
+        ..Default::default()
+    };
+
+    for principal in HttpRequest::get("/api/principal")
+        .with_authorization(auth)
+        .with_parameter("types", "group,list,role")
+        .with_parameter("fields", "name,description,type")
+        .with_optional_parameter("tenant", tenant)
+        .send::<List<Principal>>()
+        .await?
+        .items
+    {
+        let Some(name) = principal.name.as_str() else {
+            continue;
+        };
+        let label = match principal.description.as_str() {
+            Some(description) if !description.is_empty() => format!("{description} ({name})"),
+            _ => name.to_string(),
+        };
+        let item = (name.to_string(), label);
+
+        match principal.typ {
+            Some(PrincipalType::Group) => targets.groups.push(item),
+            Some(PrincipalType::List) => targets.lists.push(item),
+            Some(PrincipalType::Role) => targets.roles.push(item),
+            _ => {}
+        }
+    }
+
+    Ok(targets)
+}
+
+impl Builder<Schemas, ()> {
+    pub fn build_principal_bulk(self) -> Self {
+        self.new_schema("principal-bulk")
+            .new_field("action")
+            .typ(Type::Select {
+                typ: SelectType::Single,
+                source: Source::Static(BULK_ACTIONS),
+            })
+            .input_check([], [Validator::Required])
+            .build()
+            .new_field("target")
+            .typ(Type::Select {
+                typ: SelectType::Single,
+                source: Source::Static(&[]),
+            })
+            .display_if_eq(
+                "action",
+                [
+                    "add-group",
+                    "remove-group",
+                    "add-list",
+                    "remove-list",
+                    "add-role",
+                    "remove-role",
+                ],
+            )
+            .input_check([], [Validator::Required])
+            .build()
+            .new_field("quota")
+            .typ(Type::Input)
+            .display_if_eq("action", ["set-quota"])
+            .input_check([Transformer::Trim], [Validator::Required])
+            .build()
+            .new_field("tenant")
+            .typ(Type::Input)
+            .display_if_eq("action", ["set-tenant"])
+            .input_check([Transformer::Trim], [])
+            .build()
+            .new_field("locale")
+            .typ(Type::Select {
+                typ: SelectType::Single,
+                source: Source::Static(LOCALES),
+            })
+            .display_if_eq("action", ["set-locale"])
+            .input_check([], [Validator::Required])
+            .build()
+            .new_field("permission")
+            .typ(Type::Select {
+                typ: SelectType::Single,
+                source: Source::Static(PERMISSIONS),
+            })
+            .display_if_eq("action", ["enable-permission", "disable-permission"])
+            .input_check([], [Validator::Required])
+            .build()
+            .build()
+    }
+}
@@ -0,0 +1,1088 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::sync::Arc;
+
+use ahash::AHashMap;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use gloo_storage::{LocalStorage, Storage};
+use leptos::*;
+use leptos_router::{use_navigate, use_params_map};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::{
+        badge::Badge,
+        form::{
+            button::Button, select::Select, steps::StepProgress, Form, FormButtonBar, FormElement,
+            FormItem, FormSection,
+        },
+        list::table::{Table, TableRow},
+        messages::alert::{use_alerts, Alert},
+        skeleton::Skeleton,
+        Color,
+    },
+    core::{
+        form::{Expression, ExpressionIfThen, FormData, FormValue},
+        http::{self, HttpRequest},
+        oauth::use_authorization,
+        schema::{Builder, Schema, Schemas, SelectType, Source, Type},
+        AccessToken,
+    },
+    pages::{
+        config::{ReloadSettings, Settings, UpdateSettings},
+        FormatDateTime, List,
+    },
+    DKIM_ROTATION_STORAGE_KEY, DNS_RESOLVER_STORAGE_KEY,
+};
+
+use super::{
+    dns::{
+        check_record, resolve_record, DnsRecord, RecordCheck, RecordStatus, DEFAULT_DNS_RESOLVER,
+    },
+    edit::{Algorithm, DkimSignature},
+};
+
+const SIGNING_KEY: &str = "auth.dkim.sign";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+enum RotationStep {
+    #[default]
+    Generate,
+    Publish,
+    Switch,
+    Grace,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct Rotation {
+    step: RotationStep,
+    new: Vec<String>,
+    old: Vec<String>,
+    retire_after: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct SignatureInfo {
+    id: String,
+    algorithm: String,
+    selector: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct DkimOverview {
+    signatures: Vec<SignatureInfo>,
+    active: Vec<String>,
+    rotation: Option<Rotation>,
+    records: Vec<DnsRecord>,
+}
+
+#[component]
+pub fn DkimKeys() -> impl IntoView {
+    let auth = use_authorization();
+    let alert = use_alerts();
+    let params = use_params_map();
+    let schemas = expect_context::<Arc<Schemas>>();
+    let dkim_schema = schemas.get("dkim");
+    let data = schemas.build_form("dkim-rotation").into_signal();
+    data.update(|data| data.apply_defaults(false));
+
+    let domain = create_memo(move |_| params.get().get("id").cloned().unwrap_or_default());
+    let refresh = create_rw_signal(0u32);
+    let checks: RwSignal<Vec<Option<RecordCheck>>> = create_rw_signal(Vec::new());
+    let (pending, set_pending) = create_signal(false);
+
+    let overview = create_resource(
+        move || (domain.get(), refresh.get()),
+        move |(domain, _)| {
+            let auth = auth.get_untracked();
+            let dkim_schema = dkim_schema.clone();
+
+            async move { fetch_overview(&auth, &domain, dkim_schema).await }
+        },
+    );
+
+    let generate_keys = create_action(move |active: &Vec<String>| {
+        let auth = auth.get();
+        let domain = domain.get();
+        let old = active.clone();
+        let existing = untrack(|| {
+            overview.with(|overview| {
+                overview
+                    .as_ref()
+                    .and_then(|overview| overview.as_ref().ok())
+                    .map(|overview| overview.signatures.clone())
+                    .unwrap_or_default()
+            })
+        });
+
+        async move {
+            let signatures = rotation_signatures(&domain, Utc::now());
+            if let Some(id) = colliding_signature(&signatures, &existing) {
+                alert.set(Alert::error(format!(
+                    "A DKIM key with ID or selector {id:?} already exists, try again in a moment."
+                )));
+                return;
+            }
+
+            set_pending.set(true);
+            let mut new = Vec::new();
+            for signature in signatures {
+                let id = signature.id.clone().unwrap_or_default();
+                match HttpRequest::post("/api/dkim")
+                    .with_authorization(&auth)
+                    .with_body(signature)
+                    .unwrap()
+                    .send::<serde_json::Value>()
+                    .await
+                {
+                    Ok(_) => {
+                        new.push(id);
+                    }
+                    Err(err) => {
+                        // A rotation needs both keys, so a partial one is not kept
+                        if let Err(err) = apply_updates(&auth, clear_signatures(&new)).await {
+                            log::error!("Failed to remove DKIM keys {new:?}: {err:?}");
+                        }
+                        alert.set(Alert::from(err));
+                        new.clear();
+                        break;
+                    }
+                }
+            }
+
+            if !new.is_empty() {
+                save_rotation(
+                    &domain,
+                    Some(&Rotation {
+                        step: RotationStep::Publish,
+                        new,
+                        old,
+                        retire_after: None,
+                    }),
+                );
+            }
+            set_pending.set(false);
+            checks.set(Vec::new());
+            refresh.update(|r| *r += 1);
+        }
+    });
+
+    let verify_records = create_action(move |records: &Vec<DnsRecord>| {
+        let records = records.clone();
+        let resolver = LocalStorage::get::<String>(DNS_RESOLVER_STORAGE_KEY)
+            .unwrap_or_else(|_| DEFAULT_DNS_RESOLVER.to_string());
+
+        async move {
+            checks.set(vec![None; records.len()]);
+            for (idx, record) in records.iter().enumerate() {
+                let check = match resolve_record(&resolver, &record.name, &record.typ).await {
+                    Ok(published) => check_record(record, published),
+                    Err(err) => RecordCheck {
+                        status: RecordStatus::Failed,
+                        published: vec![err],
+                    },
+                };
+                checks.update(|checks| {
+                    if let Some(entry) = checks.get_mut(idx) {
+                        *entry = Some(check);
+                    }
+                });
+            }
+        }
+    });
+
+    let update_rotation = move |rotation: Option<Rotation>| {
+        save_rotation(&domain.get(), rotation.as_ref());
+        refresh.update(|r| *r += 1);
+    };
+
+    let switch_signing = create_action(move |(rotation, grace_days): &(Rotation, i64)| {
+        let auth = auth.get();
+        let domain = domain.get();
+        let dkim_schema = schemas.get("dkim");
+        let rotation = Rotation {
+            step: RotationStep::Grace,
+            retire_after: Some(Utc::now() + Duration::days(*grace_days)),
+            ..rotation.clone()
+        };
+
+        async move {
+            set_pending.set(true);
+            let result = async {
+                let mut signing = FormData::from_settings(
+                    dkim_schema.clone(),
+                    Some(fetch_settings(&auth, &dkim_schema).await?),
+                );
+                set_signing_ids(&mut signing, &domain, &rotation.new);
+                apply_updates(&auth, signing_update(&signing)).await
+            }
+            .await;
+            set_pending.set(false);
+
+            match result {
+                Ok(reload) if !reload.errors.is_empty() => {
+                    alert.set(Alert::from(reload));
+                }
+                Ok(_) => {
+                    save_rotation(&domain, Some(&rotation));
+                    alert.set(Alert::success(format!(
+                        "Messages from {domain} are now signed with the new keys."
+                    )));
+                }
+                Err(err) => {
+                    alert.set(Alert::from(err));
+                }
+            }
+            refresh.update(|r| *r += 1);
+        }
+    });
+
+    // Removes the signatures listed and closes the rotation, used both to
+    // retire the old keys and to abandon a rotation before the switch.
+    let remove_keys = create_action(move |(ids, message): &(Vec<String>, String)| {
+        let auth = auth.get();
+        let domain = domain.get();
+        let ids = ids.clone();
+        let message = message.clone();
+
+        async move {
+            set_pending.set(true);
+            let result = if !ids.is_empty() {
+                apply_updates(&auth, clear_signatures(&ids)).await.map(Some)
+            } else {
+                Ok(None)
+            };
+            set_pending.set(false);
+
+            match result {
+                Ok(Some(reload)) if !reload.errors.is_empty() => {
+                    alert.set(Alert::from(reload));
+                }
+                Ok(_) => {
+                    save_rotation(&domain, None);
+                    alert.set(Alert::success(message));
+                }
+                Err(err) => {
+                    alert.set(Alert::from(err));
+                }
+            }
+            checks.set(Vec::new());
+            refresh.update(|r| *r += 1);
+        }
+    });
+
+    view! {
+        <Form
+            title=Signal::derive(move || format!("DKIM Keys for '{}'", domain.get()))
+            subtitle="Review the signing keys of the domain and rotate them"
+        >
+            <Transition fallback=Skeleton>
+                {move || match overview.get() {
+                    None => None,
+                    Some(Err(http::Error::Unauthorized)) => {
+                        use_navigate()("/login", Default::default());
+                        Some(view! { <div></div> }.into_view())
+                    }
+                    Some(Err(err)) => {
+                        alert.set(Alert::from(err));
+                        Some(view! { <div></div> }.into_view())
+                    }
+                    Some(Ok(overview)) => {
+                        let rotation = overview.rotation.clone().unwrap_or_default();
+                        let step = rotation.step;
+                        let grace_over = rotation
+                            .retire_after
+                            .is_some_and(|retire_after| Utc::now() >= retire_after);
+                        let new_selectors = overview
+                            .signatures
+                            .iter()
+                            .filter(|signature| rotation.new.contains(&signature.id))
+                            .map(|signature| format!("{}._domainkey.", signature.selector))
+                            .collect::<Vec<_>>();
+                        let new_records = overview
+                            .records
+                            .iter()
+                            .filter(|record| {
+                                new_selectors.iter().any(|selector| record.name.starts_with(selector))
+                            })
+                            .cloned()
+                            .collect::<Vec<_>>();
+                        let old_selectors = overview
+                            .signatures
+                            .iter()
+                            .filter(|signature| rotation.old.contains(&signature.id))
+                            .map(|signature| signature.selector.clone())
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        let all_match = create_memo(move |_| {
+                            checks
+                                .with(|checks| {
+                                    !checks.is_empty()
+                                        && checks
+                                            .iter()
+                                            .all(|check| {
+                                                check
+                                                    .as_ref()
+                                                    .is_some_and(|check| check.status == RecordStatus::Match)
+                                            })
+                                })
+                        });
+                        let rotation = store_value(rotation);
+                        let records = store_value(new_records.clone());
+                        let active = store_value(overview.active.clone());
+                        let today = Utc::now().date_naive();
+                        let signatures = overview
+                            .signatures
+                            .iter()
+                            .map(|signature| {
+                                let (color, status) = overview.status(&signature.id);
+                                let age = signature
+                                    .created()
+                                    .map(|created| {
+                                        let days = (today - created).num_days();
+                                        if days == 1 { "1 day".to_string() } else { format!("{days} days") }
+                                    })
+                                    .unwrap_or_else(|| "Unknown".to_string());
+                                let id = signature.id.clone();
+                                let selector = signature.selector.clone();
+                                let algorithm = algorithm_label(&signature.algorithm);
+                                view! {
+                                    <TableRow>
+                                        <span>{id}</span>
+                                        <span>{selector}</span>
+                                        <span>{algorithm}</span>
+                                        <span>{age}</span>
+                                        <Badge color=color>{status}</Badge>
+                                    </TableRow>
+                                }
+                            })
+                            .collect_view();
+                        let step_view = match step {
+                            RotationStep::Generate => {
+                                view! {
+                                    <p class="sm:col-span-12 text-sm text-gray-600 dark:text-gray-400">
+                                        "A new Ed25519 and RSA key will be generated under a fresh selector. The current keys keep signing messages until the new DNS records are published and the signing expression is switched over."
+                                    </p>
+                                }
+                                    .into_view()
+                            }
+                            RotationStep::Publish => {
+                                view! {
+                                    <p class="sm:col-span-12 text-sm text-gray-600 dark:text-gray-400">
+                                        "Publish the following TXT records and verify that they resolve before continuing."
+                                    </p>
+                                    <div class="sm:col-span-12">
+                                        <Table headers=vec![
+                                            "Name".to_string(),
+                                            "Contents".to_string(),
+                                            "Status".to_string(),
+                                        ]>
+                                            {new_records
+                                                .into_iter()
+                                                .enumerate()
+                                                .map(|(idx, record)| {
+                                                    view! {
+                                                        <TableRow>
+                                                            <span>{record.name}</span>
+                                                            <span class="break-all font-mono text-xs">
+                                                                {record.content}
+                                                            </span>
+                                                            {move || {
+                                                                let (color, label) = match checks
+                                                                    .with(|checks| checks.get(idx).cloned())
+                                                                {
+                                                                    None => (Color::Gray, "Not verified"),
+                                                                    Some(None) => (Color::Gray, "Checking"),
+                                                                    Some(Some(check)) => check.status.badge(),
+                                                                };
+                                                                view! { <Badge color=color>{label}</Badge> }
+                                                            }}
+
+                                                        </TableRow>
+                                                    }
+                                                })
+                                                .collect_view()}
+                                        </Table>
+                                    </div>
+                                }
+                                    .into_view()
+                            }
+                            RotationStep::Switch => {
+                                view! {
+                                    <p class="sm:col-span-12 text-sm text-gray-600 dark:text-gray-400">
+                                        "The new records resolve. Switching updates the signing expression so that messages from this domain are signed with the new keys, while the old keys stay published for the grace period."
+                                    </p>
+                                    <FormItem label="Grace period">
+                                        <Select element=FormElement::new("grace", data)/>
+                                    </FormItem>
+                                }
+                                    .into_view()
+                            }
+                            RotationStep::Grace => {
+                                let retire_after = rotation
+                                    .with_value(|rotation| rotation.retire_after)
+                                    .map(|retire_after| retire_after.format_date_time())
+                                    .unwrap_or_default();
+                                view! {
+                                    <p class="sm:col-span-12 text-sm text-gray-600 dark:text-gray-400">
+                                        {if grace_over {
+                                            format!(
+                                                "The grace period is over. Retire the old keys and remove the TXT records of the selectors {old_selectors} from DNS.",
+                                            )
+                                        } else {
+                                            format!(
+                                                "The old keys stay published so that messages already in transit can still be verified. They can be retired after {retire_after}.",
+                                            )
+                                        }}
+
+                                    </p>
+                                }
+                                    .into_view()
+                            }
+                        };
+
+                        Some(
+                            view! {
+                                <FormSection title="Signatures".to_string()>
+                                    <div class="sm:col-span-12">
+                                        <Table headers=vec![
+                                            "ID".to_string(),
+                                            "Selector".to_string(),
+                                            "Algorithm".to_string(),
+                                            "Age".to_string(),
+                                            "Status".to_string(),
+                                        ]>{signatures}</Table>
+                                    </div>
+                                </FormSection>
+                                <FormSection title="Key Rotation".to_string()>
+                                    <div class="sm:col-span-12">
+                                        <StepProgress
+                                            steps=RotationStep::TITLES
+                                            current=Signal::derive(move || {
+                                                if step == RotationStep::Grace && grace_over {
+                                                    RotationStep::TITLES.len() - 1
+                                                } else {
+                                                    step as usize
+                                                }
+                                            })
+                                        />
+                                    </div>
+                                    {step_view}
+                                </FormSection>
+                                <FormButtonBar>
+                                    <Button
+                                        text="Close"
+                                        color=Color::Gray
+                                        on_click=move |_| {
+                                            use_navigate()("/manage/directory/domains", Default::default());
+                                        }
+                                    />
+
+                                    {match step {
+                                        RotationStep::Generate => {
+                                            view! {
+                                                <Button
+                                                    text="Generate new keys"
+                                                    color=Color::Blue
+                                                    on_click=move |_| {
+                                                        generate_keys.dispatch(active.get_value());
+                                                    }
+
+                                                    disabled=pending
+                                                />
+                                            }
+                                                .into_view()
+                                        }
+                                        RotationStep::Publish | RotationStep::Switch => {
+                                            view! {
+                                                <Button
+                                                    text="Cancel rotation"
+                                                    color=Color::Red
+                                                    on_click=move |_| {
+                                                        remove_keys
+                                                            .dispatch((
+                                                                rotation.with_value(|rotation| rotation.new.clone()),
+                                                                "The rotation was cancelled and the new keys were removed."
+                                                                    .to_string(),
+                                                            ));
+                                                    }
+
+                                                    disabled=pending
+                                                />
+                                                {if step == RotationStep::Publish {
+                                                    view! {
+                                                        <Button
+                                                            text="Check DNS"
+                                                            color=Color::Gray
+                                                            on_click=move |_| {
+                                                                verify_records.dispatch(records.get_value());
+                                                            }
+
+                                                            disabled=verify_records.pending()
+                                                        />
+                                                        <Button
+                                                            text="Continue"
+                                                            color=Color::Blue
+                                                            on_click=move |_| {
+                                                                update_rotation(
+                                                                    Some(Rotation {
+                                                                        step: RotationStep::Switch,
+                                                                        ..rotation.get_value()
+                                                                    }),
+                                                                );
+                                                            }
+
+                                                            disabled=Signal::derive(move || {
+                                                                pending.get() || !all_match.get()
+                                                            })
+                                                        />
+                                                    }
+                                                        .into_view()
+                                                } else {
+                                                    view! {
+                                                        <Button
+                                                            text="Switch signing"
+                                                            color=Color::Blue
+                                                            on_click=move |_| {
+                                                                let grace_days = data
+                                                                    .get_untracked()
+                                                                    .value::<i64>("grace")
+                                                                    .unwrap_or(7);
+                                                                switch_signing
+                                                                    .dispatch((rotation.get_value(), grace_days));
+                                                            }
+
+                                                            disabled=pending
+                                                        />
+                                                    }
+                                                        .into_view()
+                                                }}
+                                            }
+                                                .into_view()
+                                        }
+                                        RotationStep::Grace => {
+                                            view! {
+                                                <Button
+                                                    text="Retire old keys"
+                                                    color=Color::Blue
+                                                    on_click=move |_| {
+                                                        remove_keys
+                                                            .dispatch((
+                                                                rotation.with_value(|rotation| rotation.old.clone()),
+                                                                "The old keys were retired.".to_string(),
+                                                            ));
+                                                    }
+
+                                                    disabled=Signal::derive(move || {
+                                                        pending.get() || !grace_over
+                                                    })
+                                                />
+                                            }
+                                                .into_view()
+                                        }
+                                    }}
+
+                                </FormButtonBar>
+                            }
+                                .into_view(),
+                        )
+                    }
+                }}
+
+            </Transition>
+        </Form>
+    }
+}
+
+async fn fetch_overview(
+    auth: &AccessToken,
+    domain: &str,
+    dkim_schema: Arc<Schema>,
+) -> http::Result<DkimOverview> {
+    let signatures = HttpRequest::get("/api/settings/group")
+        .with_authorization(auth)
+        .with_parameter("prefix", "signature")
+        .with_parameter("suffix", "algorithm")
+        .with_parameter("filter", domain)
+        .with_parameter("page", "1")
+        .with_parameter("limit", "100")
+        .send::<List<Settings>>()
+        .await?
+        .items
+        .into_iter()
+        .filter(|settings| settings.get("domain").is_some_and(|d| d == domain))
+        .filter_map(|mut settings| {
+            Some(SignatureInfo {
+                id: settings.remove("_id")?,
+                algorithm: settings.remove("algorithm").unwrap_or_default(),
+                selector: settings.remove("selector").unwrap_or_default(),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let rotation = load_rotation(domain);
+
+    let signing = FormData::from_settings(
+        dkim_schema.clone(),
+        Some(fetch_settings(auth, &dkim_schema).await?),
+    );
+    let active = signing_ids(&signing, domain).unwrap_or_else(|| {
+        // Without a domain specific clause the default expression signs
+        // with the keys created along with the domain
+        let defaults = [Algorithm::Ed25519, Algorithm::Rsa]
+            .map(|algorithm| format!("{}-{domain}", algorithm.id()));
+        signatures
+            .iter()
+            .filter(|signature| defaults.contains(&signature.id))
+            .map(|signature| signature.id.clone())
+            .collect()
+    });
+
+    let records = HttpRequest::get(("/api/dns/records", domain))
+        .with_authorization(auth)
+        .send::<Vec<DnsRecord>>()
+        .await?
+        .into_iter()
+        .filter(|record| record.typ == "TXT" && record.name.contains("._domainkey."))
+        .collect();
+
+    Ok(DkimOverview {
+        signatures,
+        active,
+        rotation,
+        records,
+    })
+}
+
+pub(super) async fn fetch_settings(auth: &AccessToken, schema: &Schema) -> http::Result<Settings> {
+    let mut keys = Vec::new();
+    let mut prefixes = Vec::new();
+    for field in schema.fields.values() {
+        if field.is_multivalue() {
+            prefixes.push(field.id);
+        }
+        keys.push(field.id);
+    }
+
+    HttpRequest::get("/api/settings/keys")
+        .with_authorization(auth)
+        .with_parameter("keys", keys.join(","))
+        .with_parameter("prefixes", prefixes.join(","))
+        .send::<AHashMap<String, Option<String>>>()
+        .await
+        .map(|settings| {
+            settings
+                .into_iter()
+                .filter_map(|(key, value)| Some((key, value?)))
+                .collect()
+        })
+}
+
+async fn apply_updates(
+    auth: &AccessToken,
+    updates: Vec<UpdateSettings>,
+) -> http::Result<ReloadSettings> {
+    HttpRequest::post("/api/settings")
+        .with_authorization(auth)
+        .with_body(updates)
+        .unwrap()
+        .send::<Option<String>>()
+        .await?;
+
+    HttpRequest::get("/api/reload")
+        .with_authorization(auth)
+        .send::<ReloadSettings>()
+        .await
+}
+
+// Rotation progress is only needed to guide the administrator through the
+// steps, so it is kept in the browser instead of the server configuration.
+// IDs and selectors carry the time of generation so that rotating twice on
+// the same day does not reuse the keys that are currently signing.
+fn rotation_signatures(domain: &str, now: DateTime<Utc>) -> Vec<DkimSignature> {
+    let stamp = now.format("%Y%m%d%H%M%S");
+    [Algorithm::Ed25519, Algorithm::Rsa]
+        .into_iter()
+        .map(|algorithm| DkimSignature {
+            id: Some(format!("{}-{domain}-{stamp}", algorithm.id())),
+            algorithm,
+            domain: domain.to_string(),
+            selector: Some(format!("{stamp}{}", algorithm.selector_suffix())),
+        })
+        .collect()
+}
+
+fn colliding_signature<'x>(
+    signatures: &'x [DkimSignature],
+    existing: &[SignatureInfo],
+) -> Option<&'x str> {
+    signatures.iter().find_map(|signature| {
+        [signature.id.as_deref(), signature.selector.as_deref()]
+            .into_iter()
+            .flatten()
+            .find(|value| {
+                existing
+                    .iter()
+                    .any(|info| info.id == *value || info.selector == *value)
+            })
+    })
+}
+
+fn rotation_storage_key(domain: &str) -> String {
+    format!("{DKIM_ROTATION_STORAGE_KEY}:{domain}")
+}
+
+fn load_rotation(domain: &str) -> Option<Rotation> {
+    LocalStorage::get::<Rotation>(rotation_storage_key(domain)).ok()
+}
+
+fn save_rotation(domain: &str, rotation: Option<&Rotation>) {
+    let key = rotation_storage_key(domain);
+    match rotation {
+        Some(rotation) => {
+            if let Err(err) = LocalStorage::set(&key, rotation) {
+                log::error!("Failed to save DKIM rotation: {err:?}");
+            }
+        }
+        None => LocalStorage::delete(&key),
+    }
+}
+
+fn clear_signatures(ids: &[String]) -> Vec<UpdateSettings> {
+    ids.iter()
+        .map(|id| UpdateSettings::Clear {
+            prefix: format!("signature.{id}."),
+            filter: None,
+        })
+        .collect()
+}
+
+// Only the signing expression is written back, the remaining DKIM settings
+// are left as they are on the server.
+fn signing_update(signing: &FormData) -> Vec<UpdateSettings> {
+    let mut expression = FormData {
+        values: signing
+            .values
+            .get(SIGNING_KEY)
+            .map(|value| (SIGNING_KEY.to_string(), value.clone()))
+            .into_iter()
+            .collect(),
+        is_update: false,
+        ..signing.clone()
+    }
+    .build_update();
+    expression.splice(
+        0..0,
+        [
+            UpdateSettings::Clear {
+                prefix: format!("{SIGNING_KEY}."),
+                filter: None,
+            },
+            UpdateSettings::Delete {
+                keys: vec![SIGNING_KEY.to_string()],
+            },
+        ],
+    );
+    expression
+}
+
+fn signing_clause(domain: &str) -> String {
+    format!("sender_domain == '{domain}'")
+}
+
+fn signing_ids(signing: &FormData, domain: &str) -> Option<Vec<String>> {
+    let clause = signing_clause(domain);
+    signing
+        .expr_if_thens(SIGNING_KEY)
+        .find(|if_then| if_then.if_ == clause)
+        .map(|if_then| {
+            if_then
+                .then_
+                .split('\'')
+                .skip(1)
+                .step_by(2)
+                .map(|id| id.to_string())
+                .collect()
+        })
+}
+
+// The domain clause goes first so that it takes precedence over the
+// generic rules, any previous clause for the domain is replaced.
+fn set_signing_ids(signing: &mut FormData, domain: &str, ids: &[String]) {
+    let clause = signing_clause(domain);
+    let mut if_thens = signing
+        .expr_if_thens(SIGNING_KEY)
+        .filter(|if_then| if_then.if_ != clause)
+        .cloned()
+        .collect::<Vec<_>>();
+    if_thens.insert(
+        0,
+        ExpressionIfThen {
+            if_: clause,
+            then_: format!(
+                "[{}]",
+                ids.iter()
+                    .map(|id| format!("'{id}'"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        },
+    );
+    let else_ = signing
+        .expr_else(SIGNING_KEY)
+        .unwrap_or("false")
+        .to_string();
+    signing.set(
+        SIGNING_KEY,
+        FormValue::Expression(Expression { if_thens, else_ }),
+    );
+}
+
+fn algorithm_label(algorithm: &str) -> &'static str {
+    match algorithm {
+        "ed25519-sha256" => "Ed25519 SHA-256",
+        "rsa-sha256" => "RSA SHA-256",
+        "rsa-sha1" => "RSA SHA-1",
+        _ => "Unknown",
+    }
+}
+
+impl DkimOverview {
+    fn status(&self, id: &String) -> (Color, &'static str) {
+        if let Some(rotation) = &self.rotation {
+            let switched = rotation.step == RotationStep::Grace;
+            if rotation.new.contains(id) {
+                return if switched {
+                    (Color::Green, "Active")
+                } else {
+                    (Color::Yellow, "Pending")
+                };
+            } else if rotation.old.contains(id) {
+                return if switched {
+                    (Color::Yellow, "Retiring")
+                } else {
+                    (Color::Green, "Active")
+                };
+            }
+        }
+
+        if self.active.contains(id) {
+            (Color::Green, "Active")
+        } else {
+            (Color::Gray, "Unused")
+        }
+    }
+}
+
+impl SignatureInfo {
+    // Keys carry no creation time, it is recovered from the date embedded in
+    // the signature ID by rotations or in the selector generated by the server.
+    fn created(&self) -> Option<NaiveDate> {
+        self.id
+            .rsplit_once('-')
+            .and_then(|(_, stamp)| stamp.get(..8))
+            .and_then(|date| NaiveDate::parse_from_str(date, "%Y%m%d").ok())
+            .or_else(|| {
+                self.selector
+                    .get(..6)
+                    .and_then(|date| NaiveDate::parse_from_str(&format!("{date}01"), "%Y%m%d").ok())
+            })
+    }
+}
+
+impl RotationStep {
+    const TITLES: &'static [&'static str] =
+        &["Generate", "Publish", "Switch", "Grace period", "Retire"];
+}
+
+impl Algorithm {
+    fn id(&self) -> &'static str {
+        match self {
+            Algorithm::Rsa => "rsa",
+            Algorithm::Ed25519 => "ed25519",
+        }
+    }
+
+    fn selector_suffix(&self) -> &'static str {
+        match self {
+            Algorithm::Rsa => "r",
+            Algorithm::Ed25519 => "e",
+        }
+    }
+}
+
+impl Builder<Schemas, ()> {
+    pub fn build_dkim_rotation(self) -> Self {
+        self.new_schema("dkim-rotation")
+            .new_field("grace")
+            .typ(Type::Select {
+                typ: SelectType::Single,
+                source: Source::Static(&[
+                    ("1", "1 day"),
+                    ("3", "3 days"),
+                    ("7", "7 days"),
+                    ("14", "14 days"),
+                    ("30", "30 days"),
+                ]),
+            })
+            .default("7")
+            .build()
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signature(id: &str, selector: &str) -> SignatureInfo {
+        SignatureInfo {
+            id: id.to_string(),
+            algorithm: "ed25519-sha256".to_string(),
+            selector: selector.to_string(),
+        }
+    }
+
+    #[test]
+    fn updates_only_the_signing_expression() {
+        let mut signing = crate::build_schemas().build_form("dkim");
+        signing.apply_defaults(false);
+        set_signing_ids(
+            &mut signing,
+            "example.org",
+            &["ed25519-example.org-20250101".to_string()],
+        );
+        assert_eq!(
+            signing_ids(&signing, "example.org"),
+            Some(vec!["ed25519-example.org-20250101".to_string()])
+        );
+        assert_eq!(signing_ids(&signing, "example.com"), None);
+        let clauses = signing.expr_if_thens(SIGNING_KEY).count();
+
+        set_signing_ids(
+            &mut signing,
+            "example.org",
+            &["rsa-example.org".to_string()],
+        );
+        assert_eq!(signing.expr_if_thens(SIGNING_KEY).count(), clauses);
+
+        for update in signing_update(&signing) {
+            match update {
+                UpdateSettings::Clear { prefix, .. } => assert_eq!(prefix, "auth.dkim.sign."),
+                UpdateSettings::Delete { keys } => assert_eq!(keys, [SIGNING_KEY]),
+                UpdateSettings::Insert { values, .. } => {
+                    assert!(values.iter().all(|(key, _)| key.starts_with(SIGNING_KEY)));
+                    assert!(values
+                        .iter()
+                        .any(|(_, value)| value == "['rsa-example.org']"));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn reports_key_status_during_rotation() {
+        let mut overview = DkimOverview {
+            signatures: vec![],
+            active: vec!["old".to_string()],
+            rotation: Some(Rotation {
+                step: RotationStep::Publish,
+                new: vec!["new".to_string()],
+                old: vec!["old".to_string()],
+                retire_after: None,
+            }),
+            records: vec![],
+        };
+        let status = |overview: &DkimOverview, id: &str| overview.status(&id.to_string()).1;
+
+        assert_eq!(status(&overview, "new"), "Pending");
+        assert_eq!(status(&overview, "old"), "Active");
+        assert_eq!(status(&overview, "other"), "Unused");
+
+        overview.rotation.as_mut().unwrap().step = RotationStep::Grace;
+        assert_eq!(status(&overview, "new"), "Active");
+        assert_eq!(status(&overview, "old"), "Retiring");
+
+        overview.rotation = None;
+        assert_eq!(status(&overview, "new"), "Unused");
+        assert_eq!(status(&overview, "old"), "Active");
+    }
+
+    #[test]
+    fn rotation_state_round_trips() {
+        let rotation = Rotation {
+            step: RotationStep::Grace,
+            new: vec!["ed25519-example.org-20250101".to_string()],
+            old: vec!["ed25519-example.org".to_string()],
+            retire_after: Some(Utc::now()),
+        };
+        let json = serde_json::to_string(&rotation).unwrap();
+        assert_eq!(serde_json::from_str::<Rotation>(&json).unwrap(), rotation);
+        assert_eq!(
+            rotation_storage_key("example.org"),
+            format!("{DKIM_ROTATION_STORAGE_KEY}:example.org")
+        );
+    }
+
+    #[test]
+    fn recovers_key_creation_date() {
+        let date = |year, month, day| NaiveDate::from_ymd_opt(year, month, day);
+
+        assert_eq!(
+            signature("ed25519-example.org-20250102", "20250102e").created(),
+            date(2025, 1, 2)
+        );
+        assert_eq!(
+            signature("ed25519-example.org", "202403e").created(),
+            date(2024, 3, 1)
+        );
+        assert_eq!(
+            signature("rsa-example.org-20250102153000", "20250102153000r").created(),
+            date(2025, 1, 2)
+        );
+        assert_eq!(signature("custom", "mail").created(), None);
+    }
+
+    #[test]
+    fn generates_unique_signatures() {
+        let now = DateTime::parse_from_rfc3339("2025-01-02T15:30:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let signatures = rotation_signatures("example.org", now);
+        assert_eq!(
+            signatures
+                .iter()
+                .map(|signature| (signature.id.as_deref(), signature.selector.as_deref()))
+                .collect::<Vec<_>>(),
+            [
+                (
+                    Some("ed25519-example.org-20250102153000"),
+                    Some("20250102153000e")
+                ),
+                (
+                    Some("rsa-example.org-20250102153000"),
+                    Some("20250102153000r")
+                )
+            ]
+        );
+
+        let later = rotation_signatures("example.org", now + Duration::seconds(1));
+        let existing = signatures
+            .iter()
+            .map(|s| signature(s.id.as_deref().unwrap(), s.selector.as_deref().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(colliding_signature(&later, &existing), None);
+        assert_eq!(
+            colliding_signature(&signatures, &existing),
+            Some("ed25519-example.org-20250102153000")
+        );
+        assert_eq!(
+            colliding_signature(&signatures, &[signature("other", "20250102153000r")]),
+            Some("20250102153000r")
+        );
+    }
+}
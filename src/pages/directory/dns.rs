@@ -3,6 +3,9 @@
  *
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
+use ahash::AHashMap;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use gloo_storage::{LocalStorage, Storage};
 use leptos::*;
 use leptos_router::{use_navigate, use_params_map};
 use serde::{Deserialize, Serialize};
@@ -10,6 +13,7 @@ use std::ops::Add;
 
 use crate::{
     components::{
+        badge::Badge,
         card::{Card, CardItem},
         form::button::Button,
         icon::{IconEnvelope, IconShieldCheck, IconUserGroup},
@@ -23,18 +27,53 @@ use crate::{
         http::{self, HttpRequest},
         oauth::use_authorization,
     },
-    pages::{directory::Principal, List},
+    pages::{
+        directory::{import::describe_error, zone::ZoneFormat, Principal},
+        List,
+    },
+    DNS_RESOLVER_STORAGE_KEY,
 };
 
+pub(super) const DEFAULT_DNS_RESOLVER: &str = "https://cloudflare-dns.com/dns-query";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum RecordStatus {
+    Match,
+    Mismatch,
+    Missing,
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct RecordCheck {
+    pub status: RecordStatus,
+    pub published: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DohResponse {
+    #[serde(rename = "Status")]
+    status: u32,
+    #[serde(rename = "Answer", default)]
+    answer: Vec<DohAnswer>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DohAnswer {
+    #[serde(rename = "type")]
+    typ: u16,
+    data: String,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
-struct DnsRecord {
+pub(super) struct DnsRecord {
     #[serde(rename = "type")]
-    typ: String,
-    name: String,
-    content: String,
+    pub typ: String,
+    pub name: String,
+    pub content: String,
 }
 
-fn format_zonefile(records: &[DnsRecord], domain: &str) -> String {
+pub(super) fn format_zonefile(records: &[DnsRecord], domain: &str) -> String {
     let formatted_records: Vec<[&str; 3]> = records
         .iter()
         .filter_map(|record| {
@@ -84,6 +123,55 @@ pub fn DnsDisplay() -> impl IntoView {
     let alert = use_alerts();
 
     let params = use_params_map();
+    let resolver = create_rw_signal(
+        LocalStorage::get::<String>(DNS_RESOLVER_STORAGE_KEY)
+            .unwrap_or_else(|_| DEFAULT_DNS_RESOLVER.to_string()),
+    );
+    let checks: RwSignal<Vec<Option<RecordCheck>>> = create_rw_signal(Vec::new());
+    let zone_format = create_rw_signal(ZoneFormat::Bind);
+    let verify_records = create_action(move |records: &Vec<DnsRecord>| {
+        let records = records.clone();
+        let resolver = resolver.get().trim().to_string();
+
+        async move {
+            if let Err(err) = LocalStorage::set(DNS_RESOLVER_STORAGE_KEY, &resolver) {
+                log::error!("Failed to save DNS resolver to local storage: {}", err);
+            }
+            checks.set(vec![None; records.len()]);
+
+            // Several expected records can share a name and type, resolve each pair once
+            let mut answers: AHashMap<(String, String), Result<Vec<String>, String>> =
+                AHashMap::new();
+            for (idx, record) in records.iter().enumerate() {
+                let key = (record.name.clone(), record.typ.clone());
+                let published = match answers.get(&key) {
+                    Some(published) => published.clone(),
+                    None => {
+                        let published = resolve_record(&resolver, &record.name, &record.typ).await;
+                        answers.insert(key, published.clone());
+                        published
+                    }
+                };
+                let check = match published {
+                    Ok(published) => check_record(record, published),
+                    Err(err) => RecordCheck {
+                        status: RecordStatus::Failed,
+                        published: vec![err],
+                    },
+                };
+                if checks
+                    .try_update(|checks| {
+                        if let Some(entry) = checks.get_mut(idx) {
+                            *entry = Some(check);
+                        }
+                    })
+                    .is_none()
+                {
+                    return;
+                }
+            }
+        }
+    });
     let domain_details = create_resource(
         move || params.get().get("id").cloned().unwrap_or_default(),
         move |name| {
@@ -129,19 +217,17 @@ pub fn DnsDisplay() -> impl IntoView {
                     Some(view! { <div></div> }.into_view())
                 }
                 Some(Ok((records, user_count))) => {
+                    checks.set(Vec::new());
+                    let records_ = store_value(records.clone());
                     let signature_count = records
                         .iter()
                         .filter(|r| r.typ == "TXT" && r.content.contains("DKIM"))
                         .count()
                         .to_string();
-                    let zonefile = format_zonefile(
-                        &records,
-                        &format!(
-                            "{}.",
-                            params.get().get("id").map(|d| d.as_str()).unwrap_or_default(),
-                        ),
-                    );
-                    log::debug!("zomefile: {}", zonefile);
+                    let domain = params.get().get("id").cloned().unwrap_or_default();
+                    let zonefile = create_memo(move |_| {
+                        records_.with_value(|records| zone_format.get().format(records, &domain))
+                    });
                     Some(
                         view! {
                             <Card>
@@ -165,24 +251,74 @@ pub fn DnsDisplay() -> impl IntoView {
                             <ReportView>
 
                                 <div class="gap-2 sm:gap-4 py-8 first:pt-0 last:pb-0 border-t first:border-transparent border-gray-200 dark:border-gray-700 dark:first:border-transparent">
-                                    <div class="sm:col-span-12 pb-4">
+                                    <div class="sm:col-span-12 pb-4 flex flex-wrap items-center justify-between gap-2">
                                         <h2 class="text-lg font-semibold text-gray-800 dark:text-gray-200">
                                             DNS Records
                                         </h2>
+                                        <div class="flex items-center gap-x-2">
+                                            <input
+                                                type="text"
+                                                class="py-2 px-3 block w-80 border-gray-200 rounded-lg text-sm focus:border-blue-500 focus:ring-blue-500 dark:bg-slate-900 dark:border-gray-700 dark:text-gray-400 dark:focus:ring-gray-600"
+                                                title="DNS-over-HTTPS resolver used to verify the published records"
+                                                placeholder=DEFAULT_DNS_RESOLVER
+                                                prop:value=move || resolver.get()
+                                                on:change=move |ev| {
+                                                    resolver.set(event_target_value(&ev));
+                                                }
+                                            />
+
+                                            <Button
+                                                text="Verify"
+                                                color=Color::Gray
+                                                on_click=move |_| {
+                                                    if resolver.get_untracked().trim().is_empty() {
+                                                        resolver.set(DEFAULT_DNS_RESOLVER.to_string());
+                                                    }
+                                                    verify_records.dispatch(records_.get_value());
+                                                }
+
+                                                disabled=verify_records.pending()
+                                            />
+                                        </div>
                                     </div>
                                     <Table headers=vec![
                                         "Type".to_string(),
                                         "Name".to_string(),
                                         "Contents".to_string(),
+                                        "Status".to_string(),
+                                        "Published".to_string(),
                                     ]>
                                         {records
                                             .into_iter()
-                                            .map(|record| {
+                                            .enumerate()
+                                            .map(|(idx, record)| {
+                                                let check = create_memo(move |_| {
+                                                    checks.with(|checks| checks.get(idx).cloned())
+                                                });
                                                 view! {
                                                     <TableRow>
                                                         <span>{record.typ}</span>
                                                         <span>{record.name}</span>
-                                                        <span>{record.content}</span>
+                                                        <span class="break-all">{record.content}</span>
+                                                        {move || {
+                                                            let (color, label) = match check.get() {
+                                                                None => (Color::Gray, "Not verified"),
+                                                                Some(None) => (Color::Gray, "Checking"),
+                                                                Some(Some(check)) => check.status.badge(),
+                                                            };
+                                                            view! { <Badge color=color>{label}</Badge> }
+                                                        }}
+
+                                                        <span class="break-all">
+                                                            {move || {
+                                                                check
+                                                                    .get()
+                                                                    .flatten()
+                                                                    .map(|check| check.published.join("\n"))
+                                                                    .unwrap_or_default()
+                                                            }}
+
+                                                        </span>
 
                                                     </TableRow>
                                                 }
@@ -190,16 +326,66 @@ pub fn DnsDisplay() -> impl IntoView {
                                             .collect_view()}
 
                                     </Table>
-                                    <div class="sm:col-span-12 pb-4 pt-10">
+                                    <div class="sm:col-span-12 pb-4 pt-10 flex flex-wrap items-center justify-between gap-2">
                                         <h2 class="text-lg font-semibold text-gray-800 dark:text-gray-200">
                                             Zonefile
                                         </h2>
+                                        <div class="flex items-center gap-x-2">
+                                            <select
+                                                class="py-2 px-3 pe-9 block border-gray-200 rounded-lg text-sm focus:border-blue-500 focus:ring-blue-500 dark:bg-slate-900 dark:border-gray-700 dark:text-gray-400 dark:focus:ring-gray-600"
+                                                title="Export format"
+                                                on:change=move |ev| {
+                                                    zone_format
+                                                        .set(ZoneFormat::from_id(&event_target_value(&ev)));
+                                                }
+                                            >
+
+                                                {ZoneFormat::ALL
+                                                    .iter()
+                                                    .map(|format| {
+                                                        view! {
+                                                            <option
+                                                                selected=*format == zone_format.get_untracked()
+                                                                value=format.id()
+                                                            >
+                                                                {format.label()}
+                                                            </option>
+                                                        }
+                                                    })
+                                                    .collect_view()}
+                                            </select>
+                                            <a
+                                                class="py-2 px-3 inline-flex items-center gap-x-2 text-sm font-semibold rounded-lg border border-gray-200 bg-white text-gray-800 shadow-sm hover:bg-gray-50 dark:bg-slate-900 dark:border-gray-700 dark:text-white dark:hover:bg-gray-800"
+                                                download=move || {
+                                                    zone_format
+                                                        .get()
+                                                        .file_name(
+                                                            params
+                                                                .get()
+                                                                .get("id")
+                                                                .map(|d| d.as_str())
+                                                                .unwrap_or_default(),
+                                                        )
+                                                }
+
+                                                href=move || {
+                                                    format!(
+                                                        "data:{};base64,{}",
+                                                        zone_format.get().mime_type(),
+                                                        STANDARD.encode(zonefile.get()),
+                                                    )
+                                                }
+                                            >
+
+                                                "Download"
+                                            </a>
+                                        </div>
                                     </div>
                                     <textarea
                                         class="py-3 px-4 block w-full border-gray-200 rounded-lg text-sm font-mono text-nowrap focus:border-blue-500 focus:ring-blue-500 disabled:opacity-50 disabled:pointer-events-none dark:bg-slate-900 dark:border-gray-700 dark:text-gray-400 dark:focus:ring-gray-600"
                                         readonly=true
                                         rows=20
-                                        prop:value=zonefile
+                                        prop:value=move || zonefile.get()
                                     ></textarea>
 
                                 </div>
@@ -228,3 +414,256 @@ pub fn DnsDisplay() -> impl IntoView {
         </Transition>
     }
 }
+
+pub(super) async fn resolve_record(
+    resolver: &str,
+    name: &str,
+    typ: &str,
+) -> Result<Vec<String>, String> {
+    let response = HttpRequest::get(resolver)
+        .with_parameter("name", name)
+        .with_parameter("type", typ)
+        .with_header("Accept", "application/dns-json")
+        .send_raw()
+        .await
+        .map_err(describe_error)?;
+    let response = serde_json::from_slice::<DohResponse>(&response)
+        .map_err(|err| format!("Invalid resolver response: {err}"))?;
+
+    published_values(response, typ)
+}
+
+fn published_values(response: DohResponse, typ: &str) -> Result<Vec<String>, String> {
+    match response.status {
+        // NXDOMAIN is reported as a missing record rather than an error
+        0 | 3 => {
+            let typ = record_type_id(typ);
+            Ok(response
+                .answer
+                .into_iter()
+                .filter(|answer| typ.is_none_or(|typ| answer.typ == typ))
+                .map(|answer| answer.data)
+                .collect())
+        }
+        code => Err(format!("Resolver returned response code {code}")),
+    }
+}
+
+pub(super) fn check_record(record: &DnsRecord, published: Vec<String>) -> RecordCheck {
+    let is_txt = record.typ == "TXT";
+    let published = published
+        .into_iter()
+        .map(|value| {
+            if is_txt {
+                join_txt_chunks(&value)
+            } else {
+                value
+            }
+        })
+        .collect::<Vec<_>>();
+    let expected = normalize_record(&record.typ, &record.content);
+
+    if published
+        .iter()
+        .any(|value| normalize_record(&record.typ, value) == expected)
+    {
+        return RecordCheck {
+            status: RecordStatus::Match,
+            published,
+        };
+    }
+
+    // Names can hold unrelated TXT records (site verification tokens and the
+    // like), only those with the same version tag count as a mismatch
+    let published = if is_txt {
+        let tag = txt_tag(&record.content);
+        published
+            .into_iter()
+            .filter(|value| txt_tag(value) == tag)
+            .collect::<Vec<_>>()
+    } else {
+        published
+    };
+
+    RecordCheck {
+        status: if published.is_empty() {
+            RecordStatus::Missing
+        } else {
+            RecordStatus::Mismatch
+        },
+        published,
+    }
+}
+
+// Resolvers return TXT data as one or more quoted character strings, which
+// have to be concatenated before comparing them with the expected value.
+fn join_txt_chunks(data: &str) -> String {
+    let data = data.trim();
+    if !data.starts_with('"') {
+        return data.to_string();
+    }
+
+    let mut value = String::with_capacity(data.len());
+    let mut in_quotes = false;
+    let mut chars = data.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' => in_quotes = !in_quotes,
+            '\\' if in_quotes => {
+                if let Some(ch) = chars.next() {
+                    value.push(ch);
+                }
+            }
+            ch if in_quotes => value.push(ch),
+            _ => {}
+        }
+    }
+    value
+}
+
+fn normalize_record(typ: &str, value: &str) -> String {
+    if typ == "TXT" {
+        value
+            .split(';')
+            .map(|part| part.trim())
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(";")
+    } else {
+        value
+            .split_whitespace()
+            .map(|part| part.trim_end_matches('.').to_lowercase())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+// The version tag ("v=spf1", "v=DKIM1", ...) is the first token, SPF records
+// separate it with a space instead of a semicolon.
+fn txt_tag(value: &str) -> String {
+    value
+        .trim()
+        .split(|ch: char| ch == ';' || ch.is_whitespace())
+        .next()
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+fn record_type_id(typ: &str) -> Option<u16> {
+    match typ {
+        "A" => Some(1),
+        "NS" => Some(2),
+        "CNAME" => Some(5),
+        "MX" => Some(15),
+        "TXT" => Some(16),
+        "AAAA" => Some(28),
+        "SRV" => Some(33),
+        "TLSA" => Some(52),
+        "CAA" => Some(257),
+        _ => None,
+    }
+}
+
+impl RecordStatus {
+    pub fn badge(&self) -> (Color, &'static str) {
+        match self {
+            RecordStatus::Match => (Color::Green, "Match"),
+            RecordStatus::Mismatch => (Color::Yellow, "Mismatch"),
+            RecordStatus::Missing => (Color::Red, "Missing"),
+            RecordStatus::Failed => (Color::Red, "Lookup failed"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(typ: &str, content: &str) -> DnsRecord {
+        DnsRecord {
+            typ: typ.to_string(),
+            name: "example.org.".to_string(),
+            content: content.to_string(),
+        }
+    }
+
+    fn answers(typ: &str, json: &str) -> Vec<String> {
+        published_values(serde_json::from_str::<DohResponse>(json).unwrap(), typ).unwrap()
+    }
+
+    #[test]
+    fn joins_txt_chunks() {
+        assert_eq!(join_txt_chunks("v=spf1 -all"), "v=spf1 -all");
+        assert_eq!(join_txt_chunks("\"v=spf1 -all\""), "v=spf1 -all");
+        assert_eq!(
+            join_txt_chunks("\"v=DKIM1; k=rsa; p=MIIB\" \"IjANBg\""),
+            "v=DKIM1; k=rsa; p=MIIBIjANBg"
+        );
+        assert_eq!(
+            join_txt_chunks(r#""say \"hi\"" "back\\slash""#),
+            r#"say "hi"back\slash"#
+        );
+    }
+
+    #[test]
+    fn normalizes_records() {
+        assert_eq!(
+            normalize_record("MX", "10  Mail.Example.org."),
+            normalize_record("MX", "10 mail.example.org")
+        );
+        assert_eq!(
+            normalize_record("TXT", "v=DMARC1; p=reject;"),
+            normalize_record("TXT", "v=DMARC1;p=reject")
+        );
+        assert_ne!(
+            normalize_record("TXT", "v=DMARC1; p=Reject"),
+            normalize_record("TXT", "v=DMARC1; p=reject")
+        );
+    }
+
+    #[test]
+    fn checks_published_records() {
+        let txt = answers(
+            "TXT",
+            r#"{"Status":0,"Answer":[
+                {"name":"example.org.","type":16,"TTL":300,"data":"\"google-site-verification=abc\""},
+                {"name":"example.org.","type":16,"TTL":300,"data":"\"v=spf1 mx \" \"-all\""},
+                {"name":"example.org.","type":5,"TTL":300,"data":"alias.example.org."}
+            ]}"#,
+        );
+        assert_eq!(txt.len(), 2);
+
+        let check = check_record(&record("TXT", "v=spf1 mx -all"), txt.clone());
+        assert_eq!(check.status, RecordStatus::Match);
+
+        // Only the SPF record is reported, not the verification token
+        let check = check_record(&record("TXT", "v=spf1 a -all"), txt.clone());
+        assert_eq!(check.status, RecordStatus::Mismatch);
+        assert_eq!(check.published, ["v=spf1 mx -all"]);
+
+        let check = check_record(&record("TXT", "v=DMARC1; p=reject"), txt);
+        assert_eq!(check.status, RecordStatus::Missing);
+        assert!(check.published.is_empty());
+
+        let missing = answers("MX", r#"{"Status":3}"#);
+        assert_eq!(
+            check_record(&record("MX", "10 mail.example.org."), missing).status,
+            RecordStatus::Missing
+        );
+
+        let mx = answers(
+            "MX",
+            r#"{"Status":0,"Answer":[{"name":"example.org.","type":15,"TTL":300,"data":"10 Mail.Example.org."}]}"#,
+        );
+        assert_eq!(
+            check_record(&record("MX", "10 mail.example.org."), mx).status,
+            RecordStatus::Match
+        );
+
+        assert!(published_values(
+            serde_json::from_str::<DohResponse>(r#"{"Status":2}"#).unwrap(),
+            "MX"
+        )
+        .is_err());
+    }
+}
@@ -7,19 +7,19 @@
 use std::{sync::Arc, vec};
 
 use ahash::{AHashMap, AHashSet};
-use base64::{engine::general_purpose, Engine};
 use chrono::Utc;
 use humansize::{format_size, DECIMAL};
 use leptos::*;
 use leptos_router::{use_navigate, use_params_map};
-use pwhash::sha512_crypt;
-use rand::{distributions::Alphanumeric, thread_rng, Rng};
 use serde::{Deserialize, Serialize};
 
 use crate::{
     components::{
+        badge::Badge,
         form::{
             button::Button,
+            draft::{clear_draft, draft_key, DraftRecovery},
+            history::UndoRedo,
             input::{InputPassword, InputSize, InputText},
             select::Select,
             stacked_badge::StackedBadge,
@@ -27,6 +27,7 @@ use crate::{
             tab::Tab,
             Form, FormButtonBar, FormElement, FormItem, FormSection, ValidateCb,
         },
+        layout::tenant::use_tenant_scope,
         messages::alert::{use_alerts, Alert},
         skeleton::Skeleton,
         Color,
@@ -35,32 +36,47 @@ use crate::{
         form::FormData,
         http::{self, HttpRequest},
         oauth::use_authorization,
+        password::{hash_secret, password_policy, HashInfo},
         schema::{ArrayType, Builder, Schemas, Transformer, Type, Validator},
         Permission,
     },
     pages::{
-        directory::{Principal, PrincipalType, PrincipalValue, PERMISSIONS},
+        directory::{
+            effective::EffectivePermissions, Principal, PrincipalType, PrincipalValue, PERMISSIONS,
+        },
         List,
     },
 };
 
-use super::{build_app_password, parse_app_password, SpecialSecrets};
+// SPDX-SnippetBegin
+// SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+// SPDX-License-Identifier: LicenseRef-SEL
+#[cfg(feature = "enterprise")]
+use crate::pages::enterprise::tracing::activity::AccountActivity;
+// SPDX-SnippetEnd
+
+use super::{
+    api_key::{bearer_token, copy_to_clipboard, generate_api_secret},
+    build_app_password,
+    dkim::fetch_settings,
+    parse_app_password, SpecialSecrets,
+};
 
 type PrincipalMap = AHashMap<PrincipalType, Vec<(String, String)>>;
 
-#[derive(Debug, Serialize, Deserialize, Default)]
-enum Algorithm {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub(super) enum Algorithm {
     #[default]
     Rsa,
     Ed25519,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default)]
-struct DkimSignature {
-    id: Option<String>,
-    algorithm: Algorithm,
-    domain: String,
-    selector: Option<String>,
+pub(super) struct DkimSignature {
+    pub id: Option<String>,
+    pub algorithm: Algorithm,
+    pub domain: String,
+    pub selector: Option<String>,
 }
 
 #[component]
@@ -83,6 +99,9 @@ pub fn PrincipalEdit() -> impl IntoView {
             "roles" => PrincipalType::Role,
             "api-keys" => PrincipalType::ApiKey,
             "oauth-clients" => PrincipalType::OauthClient,
+            "resources" => PrincipalType::Resource,
+            "locations" => PrincipalType::Location,
+            "other" => PrincipalType::Other,
             _ => PrincipalType::Individual,
         }
     });
@@ -91,16 +110,75 @@ pub fn PrincipalEdit() -> impl IntoView {
         .get_untracked()
         .permissions()
         .has_access(Permission::TenantList);
+    // SPDX-SnippetBegin
+    // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+    // SPDX-License-Identifier: LicenseRef-SEL
+    #[cfg(feature = "enterprise")]
+    let show_activity = is_enterprise
+        && auth
+            .get_untracked()
+            .permissions()
+            .has_access_all(&[Permission::TracingList, Permission::TracingGet]);
+    // SPDX-SnippetEnd
+    let tenant_scope = use_tenant_scope();
     let principals: RwSignal<Arc<PrincipalMap>> = create_rw_signal(Arc::new(AHashMap::new()));
-    let data = expect_context::<Arc<Schemas>>()
-        .build_form("principals")
-        .into_signal();
+    let schemas = expect_context::<Arc<Schemas>>();
+    let data = schemas.build_form("principals").into_signal();
+    let can_view_scheduling = auth
+        .get_untracked()
+        .permissions()
+        .has_access(Permission::SettingsList);
+    let can_edit_scheduling = auth
+        .get_untracked()
+        .permissions()
+        .has_access(Permission::SettingsUpdate);
+
+    // Rooms, equipment and locations follow the server-wide scheduling and
+    // calendar settings, there are no per-resource booking settings.
+    let scheduling = create_resource(
+        move || selected_type.get(),
+        move |typ| {
+            let auth = auth.get_untracked();
+            let schemas = schemas.clone();
+
+            async move {
+                if !typ.is_bookable() {
+                    return Vec::new();
+                } else if !can_view_scheduling {
+                    return vec![
+                        "You are not allowed to view the server-wide scheduling settings."
+                            .to_string(),
+                    ];
+                }
+                let mut forms = Vec::new();
+                for id in ["scheduling", "calendar"] {
+                    let schema = schemas.get(id);
+                    match fetch_settings(&auth, &schema).await {
+                        Ok(settings) => {
+                            forms.push(FormData::from_settings(schema, Some(settings)));
+                        }
+                        Err(err) => {
+                            log::debug!("Failed to fetch {id} settings: {err:?}");
+                            return vec![
+                                "The server-wide scheduling settings could not be loaded."
+                                    .to_string(),
+                            ];
+                        }
+                    }
+                }
+                scheduling_summary(typ, &forms[0], &forms[1])
+            }
+        },
+    );
     let fetch_principal = create_resource(
         move || params.get().get("id").cloned().unwrap_or_default(),
         move |name| {
             let auth = auth.get_untracked();
             let permissions = auth.permissions();
             let selected_type = selected_type.get();
+            let tenant = tenant_scope
+                .get_untracked()
+                .filter(|_| is_enterprise && selected_type != PrincipalType::Tenant);
 
             let needed_types = match selected_type {
                 PrincipalType::Individual => &[
@@ -123,10 +201,10 @@ pub fn PrincipalEdit() -> impl IntoView {
                     PrincipalType::Group,
                     PrincipalType::Tenant,
                 ][..],
-                PrincipalType::Resource
-                | PrincipalType::Location
-                | PrincipalType::Other
-                | PrincipalType::OauthClient => &[][..],
+                PrincipalType::Resource | PrincipalType::Location | PrincipalType::Other => {
+                    &[PrincipalType::Tenant][..]
+                }
+                PrincipalType::OauthClient => &[][..],
             };
             let mut fetch_types = String::new();
             for typ in needed_types {
@@ -174,11 +252,7 @@ pub fn PrincipalEdit() -> impl IntoView {
                             ]);
                         }
                         PrincipalType::ApiKey => {
-                            let api_key = thread_rng()
-                                .sample_iter(Alphanumeric)
-                                .take(30)
-                                .map(char::from)
-                                .collect::<String>();
+                            let api_key = generate_api_secret();
                             data.update(|data| {
                                 data.set("api_secret", &api_key);
                             });
@@ -191,7 +265,7 @@ pub fn PrincipalEdit() -> impl IntoView {
                         _ => {}
                     }
 
-                    principal
+                    principal.with_tenant(tenant)
                 };
 
                 // Add default roles
@@ -242,11 +316,23 @@ pub fn PrincipalEdit() -> impl IntoView {
     let current_principal = create_rw_signal(Principal::default());
     let add_app_passwords: RwSignal<AHashMap<String, String>> = create_rw_signal(AHashMap::new());
 
+    let draft = create_memo(move |_| {
+        draft_key(
+            "principals",
+            params
+                .get()
+                .get("id")
+                .map(|id| id.as_str())
+                .unwrap_or_default(),
+        )
+    });
+
     let save_changes = create_action(move |changes: &Principal| {
         let current = current_principal.get();
         let changes = changes.clone();
         let auth = auth.get();
         let selected_type = selected_type.get();
+        let draft = draft.get();
 
         async move {
             set_pending.set(true);
@@ -297,10 +383,12 @@ pub fn PrincipalEdit() -> impl IntoView {
 
                 result
             };
+
             set_pending.set(false);
 
             match result {
                 Ok(_) => {
+                    clear_draft(&draft);
                     use_navigate()(
                         &format!("/manage/directory/{}", selected_type.resource_name()),
                         Default::default(),
@@ -340,7 +428,15 @@ pub fn PrincipalEdit() -> impl IntoView {
                 PrincipalType::OauthClient => {
                     format!("Update '{name}' OAuth Client")
                 }
-                _ => unreachable!(),
+                PrincipalType::Resource => {
+                    format!("Update '{name}' Resource")
+                }
+                PrincipalType::Location => {
+                    format!("Update '{name}' Location")
+                }
+                PrincipalType::Other => {
+                    format!("Update '{name}' Principal")
+                }
             }
         } else {
             match selected_type.get() {
@@ -352,7 +448,9 @@ pub fn PrincipalEdit() -> impl IntoView {
                 PrincipalType::Role => "Create Role",
                 PrincipalType::ApiKey => "Create API Key",
                 PrincipalType::OauthClient => "Create OAuth Client",
-                _ => unreachable!(),
+                PrincipalType::Resource => "Create Resource",
+                PrincipalType::Location => "Create Location",
+                PrincipalType::Other => "Create Principal",
             }
             .to_string()
         }
@@ -387,10 +485,34 @@ pub fn PrincipalEdit() -> impl IntoView {
                         });
                         let used_quota = principal.used_quota.as_int().unwrap_or_default();
                         let total_quota = principal.quota.as_int().unwrap_or_default();
+                        // SPDX-SnippetBegin
+                        // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+                        // SPDX-License-Identifier: LicenseRef-SEL
+                        #[cfg(feature = "enterprise")]
+                        let activity = (show_activity
+                            && selected_type.get() == PrincipalType::Individual
+                            && !principal.name.is_none())
+                            .then(|| {
+                                view! {
+                                    <AccountActivity
+                                        account=principal.name_or_empty()
+                                        emails=principal.emails.as_string_list().to_vec()
+                                    />
+                                }
+                            });
+                        #[cfg(not(feature = "enterprise"))]
+                        let activity: Option<View> = None;
+                        // SPDX-SnippetEnd
+                        let has_activity = activity.is_some();
                         current_principal.set(principal);
                         let typ = selected_type.get();
                         Some(
                             view! {
+                                <DraftRecovery
+                                    key=draft.get_untracked()
+                                    data=data
+                                    exclude=&["password", "api_secret", "app_passwords", "otpauth_url"]
+                                />
                                 <Tab tabs=Signal::derive(move || {
                                     vec![
                                         Some("Details".to_string()),
@@ -399,17 +521,24 @@ pub fn PrincipalEdit() -> impl IntoView {
                                             PrincipalType::Individual | PrincipalType::ApiKey
                                         )
                                             .then_some("Authentication".to_string()),
-                                        matches!(
-                                            typ,
-                                            PrincipalType::Individual | PrincipalType::Tenant
-                                        )
-                                            .then_some("Limits".to_string()),
+                                        if typ.is_bookable() {
+                                            Some("Scheduling".to_string())
+                                        } else {
+                                            matches!(
+                                                typ,
+                                                PrincipalType::Individual | PrincipalType::Tenant
+                                            )
+                                                .then_some("Limits".to_string())
+                                        },
                                         (!matches!(
                                             typ,
                                             PrincipalType::Tenant
                                             | PrincipalType::Domain
                                             | PrincipalType::OauthClient
                                             | PrincipalType::ApiKey
+                                            | PrincipalType::Resource
+                                            | PrincipalType::Location
+                                            | PrincipalType::Other
                                         ))
                                             .then_some("Memberships".to_string()),
                                         matches!(
@@ -421,11 +550,22 @@ pub fn PrincipalEdit() -> impl IntoView {
                                             | PrincipalType::ApiKey
                                         )
                                             .then_some("Permissions".to_string()),
+                                        matches!(
+                                            typ,
+                                            PrincipalType::Individual
+                                            | PrincipalType::Group
+                                            | PrincipalType::Role
+                                            | PrincipalType::Tenant
+                                            | PrincipalType::ApiKey
+                                        )
+                                            .then_some("Effective permissions".to_string()),
+                                        has_activity.then_some("Activity".to_string()),
                                     ]
                                 })>
 
                                     <FormSection stacked=true>
                                         <FormItem
+                                            reset=FormElement::new("name", data)
                                             stacked=true
                                             label=Signal::derive(move || {
                                                 match selected_type.get() {
@@ -454,6 +594,7 @@ pub fn PrincipalEdit() -> impl IntoView {
                                         </FormItem>
 
                                         <FormItem
+                                            reset=FormElement::new("description", data)
                                             stacked=true
                                             label=Signal::derive(move || {
                                                 match selected_type.get() {
@@ -480,6 +621,7 @@ pub fn PrincipalEdit() -> impl IntoView {
                                         </FormItem>
 
                                         <FormItem
+                                            reset=FormElement::new("tenant", data)
                                             stacked=true
                                             label="Tenant"
 
@@ -508,6 +650,7 @@ pub fn PrincipalEdit() -> impl IntoView {
                                         </FormItem>
 
                                         <FormItem
+                                            reset=FormElement::new("email", data)
                                             stacked=true
                                             label="Email"
                                             hide=Signal::derive(move || {
@@ -517,6 +660,9 @@ pub fn PrincipalEdit() -> impl IntoView {
                                                     | PrincipalType::Group
                                                     | PrincipalType::List
                                                     | PrincipalType::OauthClient
+                                                    | PrincipalType::Resource
+                                                    | PrincipalType::Location
+                                                    | PrincipalType::Other
                                                 )
                                             })
                                         >
@@ -528,6 +674,7 @@ pub fn PrincipalEdit() -> impl IntoView {
                                         </FormItem>
 
                                         <FormItem
+                                            reset=FormElement::new("aliases", data)
                                             stacked=true
                                             label="Aliases"
                                             hide=Signal::derive(move || {
@@ -536,6 +683,9 @@ pub fn PrincipalEdit() -> impl IntoView {
                                                     PrincipalType::Individual
                                                     | PrincipalType::Group
                                                     | PrincipalType::List
+                                                    | PrincipalType::Resource
+                                                    | PrincipalType::Location
+                                                    | PrincipalType::Other
                                                 )
                                             })
                                         >
@@ -548,6 +698,7 @@ pub fn PrincipalEdit() -> impl IntoView {
                                         </FormItem>
 
                                         <FormItem
+                                            reset=FormElement::new("locale", data)
                                             stacked=true
                                             label="Locale"
                                             hide=Signal::derive(move || {
@@ -569,6 +720,7 @@ pub fn PrincipalEdit() -> impl IntoView {
                                         </FormItem>
 
                                         <FormItem
+                                            reset=FormElement::new("picture", data)
                                             stacked=true
                                             label="Logo URL"
                                             hide=Signal::derive(move || {
@@ -588,6 +740,7 @@ pub fn PrincipalEdit() -> impl IntoView {
                                         </FormItem>
 
                                         <FormItem
+                                            reset=FormElement::new("urls", data)
                                             stacked=true
                                             label="Redirect URIs"
                                             hide=Signal::derive(move || {
@@ -614,7 +767,73 @@ pub fn PrincipalEdit() -> impl IntoView {
                                             })
                                         >
 
-                                            <InputPassword element=FormElement::new("password", data)/>
+                                            <InputPassword
+                                                element=FormElement::new("password", data)
+                                                strength_meter=true
+                                            />
+                                            <button
+                                                type="button"
+                                                class="mt-2 inline-block text-sm font-semibold text-blue-600 hover:underline dark:text-blue-500"
+                                                on:click=move |_| {
+                                                    let password = match password_policy() {
+                                                        Ok(policy) => policy.generate(),
+                                                        Err(err) => {
+                                                            alert.set(Alert::error(err));
+                                                            return;
+                                                        }
+                                                    };
+                                                    data.update(|data| {
+                                                        data.update("password", password.clone());
+                                                    });
+                                                    alert
+                                                        .set(
+                                                            if copy_to_clipboard(&password) {
+                                                                Alert::success(
+                                                                    "Password generated and copied to the clipboard",
+                                                                )
+                                                            } else {
+                                                                Alert::warning(
+                                                                    "Password generated, use the eye icon to reveal it",
+                                                                )
+                                                            },
+                                                        );
+                                                }
+                                            >
+
+                                                "Generate password"
+                                            </button>
+                                            {move || {
+                                                let info = current_principal
+                                                    .get()
+                                                    .secrets
+                                                    .as_string_list()
+                                                    .iter()
+                                                    .filter(|secret| secret.is_password())
+                                                    .find_map(|secret| HashInfo::parse(secret))?;
+                                                let policy = password_policy().ok()?.hash;
+                                                let is_weaker = info.is_weaker_than(&policy);
+                                                Some(
+                                                    view! {
+                                                        <p class="mt-2 flex items-center gap-x-2 text-sm text-gray-500 dark:text-neutral-500">
+                                                            {format!("Current hash: {info}")}
+                                                            {is_weaker
+                                                                .then(|| {
+                                                                    view! {
+                                                                        <span title=format!(
+                                                                            "The policy requires {}. Reset this password to upgrade the hash.",
+                                                                            policy.description(),
+                                                                        )>
+                                                                            <Badge color=Color::Yellow>
+                                                                                "Weaker than policy"
+                                                                            </Badge>
+                                                                        </span>
+                                                                    }
+                                                                })}
+
+                                                        </p>
+                                                    },
+                                                )
+                                            }}
                                         </FormItem>
 
                                         <FormItem
@@ -647,21 +866,23 @@ pub fn PrincipalEdit() -> impl IntoView {
                                                     (value, cb): (String, ValidateCb)|
                                                 {
                                                     let password = value.trim();
-                                                    if !password.is_empty() {
-                                                        let app_pass_name = format!(
-                                                            "app_pass${}",
-                                                            Utc::now().to_rfc3339(),
-                                                        );
-                                                        add_app_passwords
-                                                            .update(|map| {
-                                                                map.insert(
-                                                                    app_pass_name.clone(),
-                                                                    sha512_crypt::hash(password).unwrap(),
-                                                                );
-                                                            });
-                                                        cb.call(Ok(app_pass_name));
-                                                    } else {
-                                                        cb.call(Err("Please enter a valid password".to_string()))
+                                                    if password.is_empty() {
+                                                        cb.call(Err("Please enter a valid password".to_string()));
+                                                        return;
+                                                    }
+                                                    match hash_secret(password) {
+                                                        Ok(hash) => {
+                                                            let app_pass_name = format!(
+                                                                "app_pass${}",
+                                                                Utc::now().to_rfc3339(),
+                                                            );
+                                                            add_app_passwords
+                                                                .update(|map| {
+                                                                    map.insert(app_pass_name.clone(), hash);
+                                                                });
+                                                            cb.call(Ok(app_pass_name));
+                                                        }
+                                                        Err(err) => cb.call(Err(err.to_string())),
                                                     }
                                                 })
                                             />
@@ -685,11 +906,7 @@ pub fn PrincipalEdit() -> impl IntoView {
                                                         .value::<String>("api_secret")
                                                         .unwrap_or_default();
                                                     if !name.is_empty() && !secret.is_empty() {
-                                                        format!(
-                                                            "api_{}",
-                                                            general_purpose::STANDARD
-                                                                .encode(format!("{}:{}", name, secret).as_bytes()),
-                                                        )
+                                                        bearer_token(&name, &secret)
                                                     } else {
                                                         "N/A".to_string()
                                                     }
@@ -703,6 +920,7 @@ pub fn PrincipalEdit() -> impl IntoView {
 
                                     <FormSection stacked=true>
                                         <FormItem
+                                            reset=FormElement::new("quota", data)
                                             stacked=true
                                             label="Disk quota"
                                             hide=Signal::derive(move || {
@@ -736,6 +954,7 @@ pub fn PrincipalEdit() -> impl IntoView {
                                         </FormItem>
 
                                         <FormItem
+                                            reset=FormElement::new("max_accounts", data)
                                             stacked=true
                                             label="Maximum number of Accounts"
                                             hide=Signal::derive(move || {
@@ -746,6 +965,7 @@ pub fn PrincipalEdit() -> impl IntoView {
                                             <InputText element=FormElement::new("max_accounts", data)/>
                                         </FormItem>
                                         <FormItem
+                                            reset=FormElement::new("max_domains", data)
                                             stacked=true
                                             label="Maximum number of Domains"
                                             hide=Signal::derive(move || {
@@ -756,6 +976,7 @@ pub fn PrincipalEdit() -> impl IntoView {
                                             <InputText element=FormElement::new("max_domains", data)/>
                                         </FormItem>
                                         <FormItem
+                                            reset=FormElement::new("max_groups", data)
                                             stacked=true
                                             label="Maximum number of Groups"
                                             hide=Signal::derive(move || {
@@ -766,6 +987,7 @@ pub fn PrincipalEdit() -> impl IntoView {
                                             <InputText element=FormElement::new("max_groups", data)/>
                                         </FormItem>
                                         <FormItem
+                                            reset=FormElement::new("max_lists", data)
                                             stacked=true
                                             label="Maximum number of Lists"
                                             hide=Signal::derive(move || {
@@ -776,6 +998,7 @@ pub fn PrincipalEdit() -> impl IntoView {
                                             <InputText element=FormElement::new("max_lists", data)/>
                                         </FormItem>
                                         <FormItem
+                                            reset=FormElement::new("max_roles", data)
                                             stacked=true
                                             label="Maximum number of Roles"
                                             hide=Signal::derive(move || {
@@ -786,6 +1009,7 @@ pub fn PrincipalEdit() -> impl IntoView {
                                             <InputText element=FormElement::new("max_roles", data)/>
                                         </FormItem>
                                         <FormItem
+                                            reset=FormElement::new("max_api_keys", data)
                                             stacked=true
                                             label="Maximum number of API Keys"
                                             hide=Signal::derive(move || {
@@ -795,11 +1019,44 @@ pub fn PrincipalEdit() -> impl IntoView {
 
                                             <InputText element=FormElement::new("max_api_keys", data)/>
                                         </FormItem>
+                                        <FormItem
+                                            stacked=true
+                                            label="Booking requests"
+                                            tooltip="Scheduling settings are shared by every account, room, equipment and location on this server"
+                                            hide=Signal::derive(move || !selected_type.get().is_bookable())
+                                        >
+
+                                            <Transition>
+                                                {move || {
+                                                    let lines = scheduling.get().unwrap_or_default();
+                                                    view! {
+                                                        <ul class="list-disc ps-5 space-y-1 text-sm text-gray-600 dark:text-neutral-400">
+                                                            {lines
+                                                                .into_iter()
+                                                                .map(|line| view! { <li>{line}</li> })
+                                                                .collect_view()}
+                                                        </ul>
+                                                    }
+                                                }}
+
+                                            </Transition>
+                                            <a
+                                                class="mt-2 inline-block text-sm font-semibold text-blue-600 hover:underline dark:text-blue-500"
+                                                class:hidden=!can_edit_scheduling
+                                                href="/settings/scheduling/edit"
+                                            >
+
+                                                "Edit scheduling settings"
+                                            </a>
+
+                                        </FormItem>
+
 
                                     </FormSection>
 
                                     <FormSection>
                                         <FormItem
+                                            reset=FormElement::new("members", data)
                                             label="Members"
                                             hide=Signal::derive(move || {
                                                 !matches!(
@@ -843,6 +1100,7 @@ pub fn PrincipalEdit() -> impl IntoView {
                                         </FormItem>
 
                                         <FormItem
+                                            reset=FormElement::new("member-of", data)
                                             label="Member of"
                                             hide=Signal::derive(move || {
                                                 !matches!(
@@ -868,6 +1126,7 @@ pub fn PrincipalEdit() -> impl IntoView {
                                         </FormItem>
 
                                         <FormItem
+                                            reset=FormElement::new("lists", data)
                                             label="Mailing lists"
                                             hide=Signal::derive(move || {
                                                 !matches!(
@@ -892,6 +1151,7 @@ pub fn PrincipalEdit() -> impl IntoView {
 
                                         </FormItem>
                                         <FormItem
+                                            reset=FormElement::new("external-members", data)
                                             label="External Members"
                                             hide=Signal::derive(move || {
                                                 !matches!(selected_type.get(), PrincipalType::List)
@@ -913,12 +1173,30 @@ pub fn PrincipalEdit() -> impl IntoView {
                                                     }
                                                 })
                                             />
+                                            <a
+                                                class="mt-2 inline-block text-sm font-semibold text-blue-600 hover:underline dark:text-blue-500"
+                                                class:hidden=move || {
+                                                    params
+                                                        .with(|p| p.get("id").is_none_or(|id| id.is_empty()))
+                                                }
+
+                                                href=move || {
+                                                    format!(
+                                                        "/manage/directory/lists/{}/subscribers",
+                                                        params.with(|p| p.get("id").cloned().unwrap_or_default()),
+                                                    )
+                                                }
+                                            >
+
+                                                "Manage subscribers"
+                                            </a>
 
                                         </FormItem>
                                     </FormSection>
 
                                     <FormSection>
                                         <FormItem
+                                            reset=FormElement::new("roles", data)
                                             label="Roles"
                                             hide=Signal::derive(move || {
                                                 !matches!(
@@ -1064,6 +1342,16 @@ pub fn PrincipalEdit() -> impl IntoView {
 
                                     </FormSection>
 
+                                    <FormSection>
+                                        <div class="sm:col-span-12">
+                                            <EffectivePermissions data=data/>
+                                        </div>
+                                    </FormSection>
+
+                                    <FormSection>
+                                        <div class="sm:col-span-12">{activity}</div>
+                                    </FormSection>
+
                                 </Tab>
                             }
                                 .into_view(),
@@ -1074,6 +1362,30 @@ pub fn PrincipalEdit() -> impl IntoView {
             </Transition>
 
             <FormButtonBar>
+                <UndoRedo
+                    data=data
+                    exclude=&["password", "api_secret", "app_passwords", "otpauth_url"]
+                />
+                <Show when=move || {
+                    selected_type.get() == PrincipalType::Individual
+                        && params.with(|p| p.get("id").is_some_and(|id| !id.is_empty()))
+                        && auth.get().permissions().has_access(Permission::Impersonate)
+                }>
+                    <Button
+                        text="Impersonate"
+                        color=Color::Gray
+                        on_click=move |_| {
+                            use_navigate()(
+                                &format!(
+                                    "/manage/directory/accounts/{}/impersonate",
+                                    params.with(|p| p.get("id").cloned().unwrap_or_default()),
+                                ),
+                                Default::default(),
+                            );
+                        }
+                    />
+
+                </Show>
                 <Button
                     text="Cancel"
                     color=Color::Gray
@@ -1104,6 +1416,46 @@ pub fn PrincipalEdit() -> impl IntoView {
     }
 }
 
+// Describes how the server answers booking requests sent to a resource,
+// based on the "scheduling" and "calendar" settings.
+fn scheduling_summary(
+    typ: PrincipalType,
+    scheduling: &FormData,
+    calendar: &FormData,
+) -> Vec<String> {
+    let name = match typ {
+        PrincipalType::Other => "principal",
+        typ => typ.item_name(false),
+    };
+    if !scheduling
+        .value::<bool>("calendar.scheduling.enable")
+        .unwrap_or_default()
+    {
+        return vec![format!(
+            "Scheduling is disabled, invitations sent to this {name} are not processed."
+        )];
+    }
+
+    let mut lines = vec![if scheduling
+        .value::<bool>("calendar.scheduling.inbound.auto-add")
+        .unwrap_or_default()
+    {
+        format!("Invitations sent to this {name} are added to its calendar automatically.")
+    } else {
+        format!("Invitations sent to this {name} wait in its scheduling inbox until answered.")
+    }];
+    if let Some(attendees) = calendar.value::<u64>("calendar.max-attendees-per-instance") {
+        lines.push(format!(
+            "Events are limited to {attendees} attendees per instance."
+        ));
+    }
+    lines.push(
+        "Capacity, per-resource auto-accept policies and booking delegates are not supported by the server."
+            .to_string(),
+    );
+    lines
+}
+
 #[allow(clippy::wrong_self_convention)]
 impl FormData {
     fn from_principal(&mut self, principal: &Principal, default_type: PrincipalType) {
@@ -1189,6 +1541,7 @@ impl FormData {
         if !app_passwords.is_empty() {
             self.array_set("app_passwords", app_passwords);
         }
+        self.mark_saved();
     }
 
     fn to_principal(
@@ -1207,10 +1560,17 @@ impl FormData {
                         .unwrap_or_default(),
                 ));
             }
-            if let Some(password) = self.value::<String>("password") {
-                secrets.push(sha512_crypt::hash(password).unwrap());
-            } else if let Some(password) = self.value::<String>("api_secret") {
-                secrets.push(sha512_crypt::hash(password).unwrap());
+            for id in ["password", "api_secret"] {
+                if let Some(password) = self.value::<String>(id) {
+                    match hash_secret(password) {
+                        Ok(hash) => secrets.push(hash),
+                        Err(err) => {
+                            self.new_error(id, err);
+                            return None;
+                        }
+                    }
+                    break;
+                }
             }
 
             if let Some(otpauth_url) = self.value::<String>("otpauth_url") {
@@ -1318,12 +1678,21 @@ impl Builder<Schemas, ()> {
             .build()
             .new_field("urls")
             .typ(Type::Array(ArrayType::Text))
-            .input_check([Transformer::Trim], [Validator::IsUrl])
+            .input_check([Transformer::Trim], [Validator::IsRedirectUri])
             .build()
             .new_field("description")
             .typ(Type::Input)
             .input_check([Transformer::Trim], [])
             .build()
+            .new_field("password")
+            .typ(Type::Secret)
+            .input_check(
+                [],
+                [Validator::IsStrongPassword {
+                    personal: &["name", "email"],
+                }],
+            )
+            .build()
             .new_field("otpauth_url")
             .typ(Type::Input)
             .input_check([Transformer::Trim], [Validator::IsUrl])
@@ -1332,7 +1701,7 @@ impl Builder<Schemas, ()> {
     }
 }
 
-const LOCALES: &[(&str, &str)] = &[
+pub(super) const LOCALES: &[(&str, &str)] = &[
     ("en", "English"),
     ("es", "Spanish"),
     ("fr", "French"),
@@ -0,0 +1,512 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use ahash::{AHashMap, AHashSet};
+use leptos::*;
+use leptos_router::use_navigate;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::{
+        badge::Badge,
+        list::table::{Table, TableRow},
+        messages::alert::{use_alerts, Alert},
+        skeleton::Skeleton,
+        Color,
+    },
+    core::{
+        form::FormData,
+        http::{self, HttpRequest},
+        oauth::use_authorization,
+        AccessToken,
+    },
+    pages::directory::{Principal, PrincipalType, PERMISSIONS},
+};
+
+// Guards against runaway graphs, role chains are rarely more than a few levels deep
+const MAX_PRINCIPALS: usize = 250;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct PermissionNode {
+    typ: Option<PrincipalType>,
+    roles: Vec<String>,
+    member_of: Vec<String>,
+    enabled: AHashSet<String>,
+    disabled: AHashSet<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct PermissionGraph {
+    nodes: AHashMap<String, PermissionNode>,
+    missing: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PermissionTrace {
+    granted: bool,
+    path: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct EffectivePermission {
+    id: &'static str,
+    name: &'static str,
+    granted: bool,
+    trace: Vec<PermissionTrace>,
+}
+
+#[component]
+pub fn EffectivePermissions(data: RwSignal<FormData>) -> impl IntoView {
+    let auth = use_authorization();
+    let alert = use_alerts();
+    let filter = create_rw_signal(String::new());
+    let granted_only = create_rw_signal(true);
+
+    let root = create_memo(move |_| {
+        data.with(|data| {
+            let list = |id: &str| data.array_value(id).map(|v| v.to_string()).collect();
+            (
+                data.value::<String>("name").unwrap_or_default(),
+                PermissionNode {
+                    typ: None,
+                    roles: list("roles"),
+                    member_of: list("member-of"),
+                    enabled: data
+                        .array_value("enabled-permissions")
+                        .map(|v| v.to_string())
+                        .collect(),
+                    disabled: data
+                        .array_value("disabled-permissions")
+                        .map(|v| v.to_string())
+                        .collect(),
+                },
+            )
+        })
+    });
+    let graph = create_resource(
+        move || {
+            root.with(|(_, node)| {
+                node.roles
+                    .iter()
+                    .chain(node.member_of.iter())
+                    .cloned()
+                    .collect::<Vec<_>>()
+            })
+        },
+        move |parents| {
+            let auth = auth.get_untracked();
+
+            async move { fetch_graph(&auth, parents).await }
+        },
+    );
+
+    view! {
+        <Transition fallback=Skeleton>
+            {move || match graph.get() {
+                None => None,
+                Some(Err(http::Error::Unauthorized)) => {
+                    use_navigate()("/login", Default::default());
+                    Some(view! { <div></div> }.into_view())
+                }
+                Some(Err(err)) => {
+                    alert.set(Alert::from(err));
+                    Some(view! { <div></div> }.into_view())
+                }
+                Some(Ok(graph)) => {
+                    let (name, node) = root.get();
+                    let permissions = graph.effective_permissions(&name, &node);
+                    let total_granted = permissions.iter().filter(|p| p.granted).count();
+                    let total = permissions.len();
+                    let missing = graph.missing.clone();
+                    let rows = Signal::derive(move || {
+                        let filter = filter.get().to_lowercase();
+                        let granted_only = granted_only.get();
+                        permissions
+                            .iter()
+                            .filter(|p| {
+                                (!granted_only || p.granted)
+                                    && (filter.is_empty() || p.id.contains(&filter)
+                                        || p.name.to_lowercase().contains(&filter))
+                            })
+                            .cloned()
+                            .collect::<Vec<_>>()
+                    });
+                    let graph = StoredValue::new(graph);
+                    Some(
+                        view! {
+                            <div class="flex flex-wrap items-center justify-between gap-4 mb-4">
+                                <p class="text-sm text-gray-600 dark:text-gray-400">
+                                    {format!(
+                                        "{total_granted} of {total} permissions are granted, including inherited roles and groups.",
+                                    )}
+                                    <br/>
+                                    <span class="text-xs text-gray-500 dark:text-gray-500">
+                                        "This is an approximation: the built-in admin, tenant-admin and user roles are estimated by the web admin and tenant limits are not applied, the server remains authoritative."
+                                    </span>
+                                </p>
+                                <div class="flex items-center gap-x-4">
+                                    <input
+                                        type="text"
+                                        class="py-2 px-3 block w-64 border-gray-200 shadow-sm text-sm rounded-lg focus:border-blue-500 focus:ring-blue-500 dark:bg-slate-900 dark:border-gray-700 dark:text-gray-400 dark:focus:ring-gray-600"
+                                        placeholder="Search permissions"
+                                        prop:value=move || filter.get()
+                                        on:input=move |ev| filter.set(event_target_value(&ev))
+                                    />
+                                    <label class="flex items-center text-sm text-gray-500 dark:text-gray-400">
+                                        <input
+                                            type="checkbox"
+                                            class="shrink-0 me-2 border-gray-200 rounded text-blue-600 focus:ring-blue-500 dark:bg-gray-800 dark:border-gray-700 dark:checked:bg-blue-500 dark:checked:border-blue-500 dark:focus:ring-offset-gray-800"
+                                            prop:checked=move || granted_only.get()
+                                            on:change=move |ev| granted_only.set(event_target_checked(&ev))
+                                        />
+                                        "Granted only"
+                                    </label>
+                                </div>
+                            </div>
+                            {(!missing.is_empty())
+                                .then(|| {
+                                    view! {
+                                        <p class="mb-4 text-sm text-yellow-700 dark:text-yellow-500">
+                                            {format!(
+                                                "Could not resolve {}, the permissions they grant are not included.",
+                                                missing.join(", "),
+                                            )}
+
+                                        </p>
+                                    }
+                                })}
+
+                            <Table headers=vec![
+                                "Permission".to_string(),
+                                "Effective".to_string(),
+                                "Explanation".to_string(),
+                            ]>
+                                <For
+                                    each=move || rows.get()
+                                    key=|permission| (permission.id, permission.granted)
+                                    children=move |permission| {
+                                        let explanation = graph
+                                            .with_value(|graph| graph.explain(&permission));
+                                        view! {
+                                            <TableRow>
+                                                <div class="flex flex-col">
+                                                    <span>{permission.name}</span>
+                                                    <span class="text-xs text-gray-500 dark:text-gray-500">
+                                                        {permission.id}
+                                                    </span>
+                                                </div>
+                                                {if permission.granted {
+                                                    view! { <Badge color=Color::Green>"Granted"</Badge> }
+                                                } else {
+                                                    view! { <Badge color=Color::Red>"Denied"</Badge> }
+                                                }}
+
+                                                <ul class="text-sm text-gray-600 dark:text-gray-400">
+                                                    {explanation
+                                                        .into_iter()
+                                                        .map(|line| view! { <li>{line}</li> })
+                                                        .collect_view()}
+                                                </ul>
+                                            </TableRow>
+                                        }
+                                    }
+                                />
+
+                            </Table>
+                        }
+                            .into_view(),
+                    )
+                }
+            }}
+
+        </Transition>
+    }
+}
+
+impl PermissionGraph {
+    fn effective_permissions(&self, name: &str, root: &PermissionNode) -> Vec<EffectivePermission> {
+        let mut path = Vec::new();
+        if !name.is_empty() {
+            path.push(name.to_string());
+        }
+
+        PERMISSIONS
+            .iter()
+            .map(|(id, description)| {
+                let mut trace = Vec::new();
+                let granted = self.evaluate(root, id, &mut path, &mut trace);
+                EffectivePermission {
+                    id,
+                    name: description,
+                    granted,
+                    trace: trace
+                        .into_iter()
+                        .map(|mut trace| {
+                            // The principal being edited is always the first element
+                            if !name.is_empty() {
+                                trace.path.remove(0);
+                            }
+                            trace
+                        })
+                        .collect(),
+                }
+            })
+            .collect()
+    }
+
+    // Roles and groups are applied first, then the principal's own enabled
+    // permissions and finally its disabled permissions, which always win.
+    fn evaluate(
+        &self,
+        node: &PermissionNode,
+        permission: &str,
+        path: &mut Vec<String>,
+        trace: &mut Vec<PermissionTrace>,
+    ) -> bool {
+        let mut granted = false;
+
+        for parent in node.roles.iter().chain(node.member_of.iter()) {
+            if path.contains(parent) {
+                continue;
+            }
+            path.push(parent.clone());
+            if let Some(grants) = builtin_role(parent) {
+                if grants(permission) {
+                    trace.push(PermissionTrace {
+                        granted: true,
+                        path: path.clone(),
+                    });
+                    granted = true;
+                }
+            } else if let Some(parent) = self
+                .nodes
+                .get(parent)
+                .filter(|parent| parent.typ != Some(PrincipalType::Tenant))
+            {
+                // Tenant permissions restrict what their members can be granted, they are not grants
+                granted |= self.evaluate(parent, permission, path, trace);
+            }
+            path.pop();
+        }
+
+        if node.enabled.contains(permission) {
+            trace.push(PermissionTrace {
+                granted: true,
+                path: path.clone(),
+            });
+            granted = true;
+        }
+        if node.disabled.contains(permission) {
+            trace.push(PermissionTrace {
+                granted: false,
+                path: path.clone(),
+            });
+            granted = false;
+        }
+
+        granted
+    }
+
+    fn explain(&self, permission: &EffectivePermission) -> Vec<String> {
+        if permission.trace.is_empty() {
+            return vec!["Not granted by any role or group".to_string()];
+        }
+
+        permission
+            .trace
+            .iter()
+            .map(|trace| {
+                let action = if trace.granted { "Granted" } else { "Revoked" };
+                match trace.path.split_last() {
+                    None => format!("{action} directly on this principal"),
+                    Some((source, [])) => format!("{action} by {}", self.label(source)),
+                    Some((source, via)) => format!(
+                        "{action} by {} via {}",
+                        self.label(source),
+                        via.iter()
+                            .map(|name| self.label(name))
+                            .collect::<Vec<_>>()
+                            .join(" → ")
+                    ),
+                }
+            })
+            .collect()
+    }
+
+    fn label(&self, name: &str) -> String {
+        let typ = if is_builtin_role(name) {
+            "built-in role"
+        } else {
+            match self.nodes.get(name).and_then(|node| node.typ) {
+                Some(PrincipalType::Role) => "role",
+                Some(PrincipalType::Group) => "group",
+                Some(PrincipalType::List) => "list",
+                Some(PrincipalType::Tenant) => "tenant",
+                _ => "principal",
+            }
+        };
+
+        format!("{typ} {name}")
+    }
+}
+
+async fn fetch_graph(auth: &AccessToken, parents: Vec<String>) -> http::Result<PermissionGraph> {
+    let mut graph = PermissionGraph::default();
+    let mut queue = parents;
+    let mut seen = AHashSet::new();
+
+    while let Some(name) = queue.pop() {
+        if is_builtin_role(&name) || !seen.insert(name.clone()) {
+            continue;
+        }
+        if graph.nodes.len() >= MAX_PRINCIPALS {
+            graph.missing.push(name);
+            continue;
+        }
+
+        match HttpRequest::get(("/api/principal", &name))
+            .with_authorization(auth)
+            .send::<Principal>()
+            .await
+        {
+            Ok(principal) => {
+                let node = PermissionNode {
+                    typ: principal.typ,
+                    roles: principal.roles.as_string_list().to_vec(),
+                    member_of: principal.member_of.as_string_list().to_vec(),
+                    enabled: principal
+                        .enabled_permissions
+                        .as_string_list()
+                        .iter()
+                        .cloned()
+                        .collect(),
+                    disabled: principal
+                        .disabled_permissions
+                        .as_string_list()
+                        .iter()
+                        .cloned()
+                        .collect(),
+                };
+                queue.extend(node.roles.iter().chain(node.member_of.iter()).cloned());
+                graph.nodes.insert(name, node);
+            }
+            Err(http::Error::NotFound | http::Error::Forbidden) => {
+                graph.missing.push(name);
+            }
+            Err(err) => return Err(err),
+        }
+    }
+
+    graph.missing.sort_unstable();
+    Ok(graph)
+}
+
+// The names of the built-in roles are fixed by the server, only what they grant is estimated
+pub(super) fn is_builtin_role(name: &str) -> bool {
+    matches!(name, "admin" | "tenant-admin" | "user")
+}
+
+// Approximates the roles that are built into the server, which cannot be
+// fetched through the API. Results derived from it are labelled as estimates.
+fn builtin_role(name: &str) -> Option<fn(&str) -> bool> {
+    match name {
+        "admin" => Some(|_| true),
+        "tenant-admin" => Some(|permission| {
+            is_user_permission(permission) || is_tenant_admin_permission(permission)
+        }),
+        "user" => Some(is_user_permission),
+        _ => None,
+    }
+}
+
+fn is_user_permission(permission: &str) -> bool {
+    ["imap-", "jmap-", "pop3-", "sieve-", "dav-", "calendar-"]
+        .iter()
+        .any(|prefix| permission.starts_with(prefix))
+        || matches!(
+            permission,
+            "authenticate"
+                | "authenticate-oauth"
+                | "email-send"
+                | "email-receive"
+                | "manage-encryption"
+                | "manage-passwords"
+                | "spam-filter-train"
+        )
+}
+
+fn is_tenant_admin_permission(permission: &str) -> bool {
+    [
+        "individual-",
+        "group-",
+        "mailing-list-",
+        "role-",
+        "domain-",
+        "dkim-signature-",
+        "api-key-",
+        "oauth-client-",
+        "principal-",
+        "message-queue-",
+        "incoming-report-",
+        "outgoing-report-",
+    ]
+    .iter()
+    .any(|prefix| permission.starts_with(prefix))
+        || matches!(permission, "undelete" | "purge-account")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(typ: PrincipalType, roles: &[&str], enabled: &[&str]) -> PermissionNode {
+        PermissionNode {
+            typ: Some(typ),
+            roles: roles.iter().map(|r| r.to_string()).collect(),
+            enabled: enabled.iter().map(|p| p.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn granted(graph: &PermissionGraph, root: &PermissionNode, permission: &str) -> bool {
+        graph
+            .effective_permissions("jdoe", root)
+            .into_iter()
+            .find(|p| p.id == permission)
+            .unwrap()
+            .granted
+    }
+
+    #[test]
+    fn resolves_roles_and_overrides() {
+        let mut graph = PermissionGraph::default();
+        graph.nodes.insert(
+            "support".to_string(),
+            node(PrincipalType::Role, &["user"], &["individual-list"]),
+        );
+        let mut root = node(PrincipalType::Individual, &["support"], &[]);
+
+        assert!(granted(&graph, &root, "individual-list"));
+        assert!(granted(&graph, &root, "email-send"));
+        assert!(!granted(&graph, &root, "settings-update"));
+
+        root.disabled.insert("email-send".to_string());
+        assert!(!granted(&graph, &root, "email-send"));
+    }
+
+    #[test]
+    fn tenants_do_not_grant() {
+        let mut graph = PermissionGraph::default();
+        graph.nodes.insert(
+            "acme".to_string(),
+            node(PrincipalType::Tenant, &[], &["individual-list"]),
+        );
+        let mut root = node(PrincipalType::Individual, &[], &[]);
+        root.member_of.push("acme".to_string());
+
+        assert!(!granted(&graph, &root, "individual-list"));
+    }
+}
@@ -0,0 +1,631 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::sync::Arc;
+
+use ahash::AHashMap;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::Utc;
+use humansize::{format_size, DECIMAL};
+use leptos::*;
+use leptos_router::{use_navigate, use_params_map, use_query_map};
+use serde_json::{Map, Value};
+
+use crate::{
+    components::{
+        form::{
+            button::Button,
+            input::{InputSwitch, InputText},
+            select::{CheckboxGroup, Select},
+            Form, FormButtonBar, FormElement, FormItem, FormSection,
+        },
+        layout::tenant::use_tenant_scope,
+        messages::alert::{use_alerts, Alert},
+        Color,
+    },
+    core::{
+        http::{self, HttpRequest},
+        oauth::use_authorization,
+        schema::{Builder, Schemas, SelectType, Source, Type},
+        url::UrlBuilder,
+        AccessToken, Permission,
+    },
+    pages::{
+        directory::{import::csv_escape, Principal, PrincipalField, PrincipalType},
+        maybe_plural, List,
+    },
+};
+
+const EXPORT_PAGE_SIZE: u32 = 100;
+
+// Secrets are never offered for export
+const EXPORT_FIELDS: [PrincipalField; 17] = [
+    PrincipalField::Name,
+    PrincipalField::Type,
+    PrincipalField::Description,
+    PrincipalField::Emails,
+    PrincipalField::Quota,
+    PrincipalField::UsedQuota,
+    PrincipalField::MemberOf,
+    PrincipalField::Members,
+    PrincipalField::Roles,
+    PrincipalField::Lists,
+    PrincipalField::Tenant,
+    PrincipalField::EnabledPermissions,
+    PrincipalField::DisabledPermissions,
+    PrincipalField::ExternalMembers,
+    PrincipalField::Urls,
+    PrincipalField::Locale,
+    PrincipalField::Picture,
+];
+
+const EXPORT_OPTIONS: [(&str, &str); EXPORT_FIELDS.len()] = {
+    let mut options = [("", ""); EXPORT_FIELDS.len()];
+    let mut i = 0;
+    while i < EXPORT_FIELDS.len() {
+        options[i] = (EXPORT_FIELDS[i].id(), EXPORT_FIELDS[i].name());
+        i += 1;
+    }
+    options
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ExportFile {
+    name: String,
+    url: String,
+    total: usize,
+}
+
+#[derive(Debug, Clone)]
+struct ExportRequest {
+    typ: PrincipalType,
+    format: String,
+    fields: Vec<PrincipalField>,
+    filter: Option<String>,
+    tenant: Option<String>,
+    resolve_names: bool,
+    human_sizes: bool,
+}
+
+#[component]
+pub fn PrincipalExport() -> impl IntoView {
+    let auth = use_authorization();
+    let alert = use_alerts();
+    let params = use_params_map();
+    let query = use_query_map();
+    let selected_type = create_memo(move |_| {
+        match params
+            .get()
+            .get("object")
+            .map(|id| id.as_str())
+            .unwrap_or_default()
+        {
+            "accounts" => PrincipalType::Individual,
+            "groups" => PrincipalType::Group,
+            "lists" => PrincipalType::List,
+            "tenants" => PrincipalType::Tenant,
+            "domains" => PrincipalType::Domain,
+            "roles" => PrincipalType::Role,
+            "api-keys" => PrincipalType::ApiKey,
+            "oauth-clients" => PrincipalType::OauthClient,
+            "resources" => PrincipalType::Resource,
+            "locations" => PrincipalType::Location,
+            "other" => PrincipalType::Other,
+            _ => PrincipalType::Individual,
+        }
+    });
+    let show_tenant = auth.get_untracked().is_enterprise()
+        && auth
+            .get_untracked()
+            .permissions()
+            .has_access(Permission::TenantList);
+
+    let mut data = expect_context::<Arc<Schemas>>().build_form("principal-export");
+    data.apply_defaults(false);
+    data.array_set(
+        "fields",
+        default_fields(selected_type.get_untracked())
+            .iter()
+            .map(|field| field.id()),
+    );
+    if let Some(filter) = query.get_untracked().get("filter") {
+        data.set("filter", filter);
+    }
+    if let Some(tenant) = use_tenant_scope()
+        .get_untracked()
+        .filter(|_| show_tenant && selected_type.get_untracked() != PrincipalType::Tenant)
+    {
+        data.set("tenant", tenant);
+    }
+    let data = data.into_signal();
+
+    let progress: RwSignal<Option<(usize, u64)>> = create_rw_signal(None);
+    let file: RwSignal<Option<ExportFile>> = create_rw_signal(None);
+    let (pending, set_pending) = create_signal(false);
+
+    let export = create_action(move |request: &Arc<ExportRequest>| {
+        let request = request.clone();
+        let auth = auth.get();
+
+        async move {
+            set_pending.set(true);
+            file.set(None);
+            progress.set(Some((0, 0)));
+            let result = fetch_principals(&auth, &request, progress).await;
+            set_pending.set(false);
+            progress.set(None);
+
+            match result {
+                Ok((principals, names)) => {
+                    let (contents, mime, extension) = if request.format == "json" {
+                        let items = principals
+                            .iter()
+                            .map(|principal| {
+                                request
+                                    .fields
+                                    .iter()
+                                    .map(|field| {
+                                        (
+                                            field.id().to_string(),
+                                            export_value(principal, *field, &request, &names),
+                                        )
+                                    })
+                                    .collect::<Map<_, _>>()
+                            })
+                            .collect::<Vec<_>>();
+                        (
+                            serde_json::to_string_pretty(&items).unwrap_or_default(),
+                            "application/json",
+                            "json",
+                        )
+                    } else {
+                        let mut csv = request
+                            .fields
+                            .iter()
+                            .map(|field| csv_escape(field.name()))
+                            .collect::<Vec<_>>()
+                            .join(",");
+                        csv.push('\n');
+                        for principal in &principals {
+                            csv.push_str(
+                                &request
+                                    .fields
+                                    .iter()
+                                    .map(|field| {
+                                        csv_escape(&csv_cell(csv_value(export_value(
+                                            principal, *field, &request, &names,
+                                        ))))
+                                    })
+                                    .collect::<Vec<_>>()
+                                    .join(","),
+                            );
+                            csv.push('\n');
+                        }
+                        (csv, "text/csv", "csv")
+                    };
+
+                    file.set(Some(ExportFile {
+                        name: format!(
+                            "{}-{}.{extension}",
+                            request.typ.resource_name(),
+                            Utc::now().format("%Y%m%d")
+                        ),
+                        url: format!("data:{mime};base64,{}", STANDARD.encode(contents)),
+                        total: principals.len(),
+                    }));
+                }
+                Err(http::Error::Unauthorized) => {
+                    use_navigate()("/login", Default::default());
+                }
+                Err(err) => {
+                    alert.set(Alert::from(err));
+                }
+            }
+        }
+    });
+
+    let title = Signal::derive(move || {
+        format!(
+            "Export {}",
+            match selected_type.get() {
+                PrincipalType::Individual => "Accounts",
+                PrincipalType::Group => "Groups",
+                PrincipalType::List => "Mailing Lists",
+                PrincipalType::Tenant => "Tenants",
+                PrincipalType::Domain => "Domains",
+                PrincipalType::Role => "Roles",
+                PrincipalType::ApiKey => "API Keys",
+                PrincipalType::OauthClient => "OAuth Clients",
+                PrincipalType::Resource => "Resources",
+                PrincipalType::Location => "Locations",
+                PrincipalType::Other => "Other Principals",
+            }
+        )
+    });
+
+    view! {
+        <Form title=title subtitle="Download a listing of the directory in CSV or JSON format">
+
+            <FormSection>
+                <FormItem label="Format">
+                    <Select element=FormElement::new("format", data)/>
+                </FormItem>
+                <FormItem label="Fields">
+                    <CheckboxGroup element=FormElement::new("fields", data)/>
+                </FormItem>
+                <FormItem label="Filter" is_optional=true>
+                    <InputText element=FormElement::new("filter", data)/>
+                </FormItem>
+                <FormItem label="Tenant" is_optional=true hide=!show_tenant>
+                    <InputText element=FormElement::new("tenant", data)/>
+                </FormItem>
+                <FormItem label="">
+                    <InputSwitch
+                        label="Resolve names"
+                        tooltip="Show the description next to the name of groups, roles, lists and members"
+                        element=FormElement::new("resolve-names", data)
+                    />
+                </FormItem>
+                <FormItem label="">
+                    <InputSwitch
+                        label="Human-readable sizes"
+                        tooltip="Format quotas as human-readable sizes instead of bytes"
+                        element=FormElement::new("human-sizes", data)
+                    />
+                </FormItem>
+            </FormSection>
+
+            {move || {
+                progress
+                    .get()
+                    .map(|(fetched, total)| {
+                        view! {
+                            <p class="mt-5 text-sm text-gray-600 dark:text-gray-400">
+                                {format!("Fetched {fetched} of {total} principals...")}
+                            </p>
+                        }
+                    })
+            }}
+
+            {move || {
+                file.get()
+                    .map(|file| {
+                        view! {
+                            <div class="mt-5 flex items-center justify-between gap-x-4 bg-teal-50 border border-teal-200 text-sm text-teal-800 rounded-lg p-4 dark:bg-teal-800/10 dark:border-teal-900 dark:text-teal-500">
+                                <span>
+                                    {format!(
+                                        "Exported {}.",
+                                        maybe_plural(
+                                            file.total,
+                                            selected_type.get().item_name(false),
+                                            selected_type.get().item_name(true),
+                                        ),
+                                    )}
+
+                                </span>
+                                <a
+                                    class="py-2 px-3 inline-flex items-center gap-x-2 text-sm font-semibold rounded-lg border border-transparent bg-blue-600 text-white hover:bg-blue-700"
+                                    download=file.name.clone()
+                                    href=file.url
+                                >
+                                    {format!("Download {}", file.name)}
+                                </a>
+                            </div>
+                        }
+                    })
+            }}
+
+            <FormButtonBar>
+                <Button
+                    text="Cancel"
+                    color=Color::Gray
+                    on_click=move |_| {
+                        use_navigate()(
+                            &format!("/manage/directory/{}", selected_type.get().resource_name()),
+                            Default::default(),
+                        );
+                    }
+                />
+
+                <Button
+                    text="Export"
+                    color=Color::Blue
+                    on_click=move |_| {
+                        let request = data
+                            .with_untracked(|data| ExportRequest {
+                                typ: selected_type.get_untracked(),
+                                format: data.value::<String>("format").unwrap_or_default(),
+                                fields: data
+                                    .array_value("fields")
+                                    .filter_map(|id| {
+                                        serde_json::from_value::<PrincipalField>(id.into()).ok()
+                                    })
+                                    .filter(|field| *field != PrincipalField::Secrets)
+                                    .collect(),
+                                filter: data.value::<String>("filter").filter(|f| !f.is_empty()),
+                                tenant: data.value::<String>("tenant").filter(|t| !t.is_empty()),
+                                resolve_names: data.value::<bool>("resolve-names").unwrap_or_default(),
+                                human_sizes: data.value::<bool>("human-sizes").unwrap_or_default(),
+                            });
+                        if !request.fields.is_empty() {
+                            export.dispatch(Arc::new(request));
+                        } else {
+                            alert.set(Alert::error("Please select at least one field to export."));
+                        }
+                    }
+
+                    disabled=pending
+                />
+            </FormButtonBar>
+        </Form>
+    }
+}
+
+async fn fetch_principals(
+    auth: &AccessToken,
+    request: &ExportRequest,
+    progress: RwSignal<Option<(usize, u64)>>,
+) -> http::Result<(Vec<Principal>, AHashMap<String, String>)> {
+    let fields = [PrincipalField::Name]
+        .iter()
+        .chain(
+            request
+                .fields
+                .iter()
+                .filter(|field| **field != PrincipalField::Name),
+        )
+        .map(|field| field.id())
+        .collect::<Vec<_>>()
+        .join(",");
+    let mut principals = Vec::new();
+    let mut page = 1;
+
+    loop {
+        let list = HttpRequest::get("/api/principal")
+            .with_authorization(auth)
+            .with_parameter("page", page.to_string())
+            .with_parameter("limit", EXPORT_PAGE_SIZE.to_string())
+            .with_parameter("types", request.typ.id())
+            .with_parameter("fields", &fields)
+            .with_optional_parameter("filter", request.filter.clone())
+            .with_optional_parameter("tenant", request.tenant.clone())
+            .send::<List<Principal>>()
+            .await?;
+        let fetched = list.items.len();
+        principals.extend(list.items.into_iter().map(|mut principal| {
+            principal.secrets = Default::default();
+            principal
+        }));
+        progress.set(Some((principals.len(), list.total)));
+
+        if fetched == 0 || principals.len() as u64 >= list.total {
+            break;
+        }
+        page += 1;
+    }
+
+    // Obtain the descriptions of the referenced principals
+    let mut names = AHashMap::new();
+    let resolve_types = request
+        .fields
+        .iter()
+        .flat_map(|field| match field {
+            PrincipalField::MemberOf => &[PrincipalType::Group, PrincipalType::List][..],
+            PrincipalField::Members => &[PrincipalType::Individual, PrincipalType::Group][..],
+            PrincipalField::Roles => &[PrincipalType::Role][..],
+            PrincipalField::Lists => &[PrincipalType::List][..],
+            _ => &[][..],
+        })
+        .map(|typ| typ.id())
+        .collect::<Vec<_>>();
+    if request.resolve_names && !resolve_types.is_empty() {
+        for principal in HttpRequest::get("/api/principal")
+            .with_authorization(auth)
+            .with_parameter("types", resolve_types.join(","))
+            .with_parameter("fields", "name,description")
+            .with_optional_parameter("tenant", request.tenant.clone())
+            .send::<List<Principal>>()
+            .await?
+            .items
+        {
+            if let (Some(name), Some(description)) =
+                (principal.name.as_str(), principal.description.as_str())
+            {
+                names.insert(name.to_string(), format!("{description} ({name})"));
+            }
+        }
+    }
+
+    Ok((principals, names))
+}
+
+fn export_value(
+    principal: &Principal,
+    field: PrincipalField,
+    request: &ExportRequest,
+    names: &AHashMap<String, String>,
+) -> Value {
+    let value = match field {
+        PrincipalField::Name => &principal.name,
+        PrincipalField::Description => &principal.description,
+        PrincipalField::Tenant => &principal.tenant,
+        PrincipalField::Picture => &principal.picture,
+        PrincipalField::Locale => &principal.locale,
+        PrincipalField::Emails => &principal.emails,
+        PrincipalField::MemberOf => &principal.member_of,
+        PrincipalField::Members => &principal.members,
+        PrincipalField::Roles => &principal.roles,
+        PrincipalField::Lists => &principal.lists,
+        PrincipalField::EnabledPermissions => &principal.enabled_permissions,
+        PrincipalField::DisabledPermissions => &principal.disabled_permissions,
+        PrincipalField::Urls => &principal.urls,
+        PrincipalField::ExternalMembers => &principal.external_members,
+        PrincipalField::Type => {
+            return Value::String(principal.typ.unwrap_or(request.typ).name().to_string());
+        }
+        PrincipalField::Quota | PrincipalField::UsedQuota => {
+            let size = if field == PrincipalField::Quota {
+                principal.quota.as_int()
+            } else {
+                principal.used_quota.as_int()
+            };
+            return match size {
+                Some(size) if request.human_sizes => Value::String(format_size(size, DECIMAL)),
+                Some(size) => Value::from(size),
+                None => Value::Null,
+            };
+        }
+        PrincipalField::Secrets => return Value::Null,
+    };
+
+    match field {
+        PrincipalField::Emails
+        | PrincipalField::MemberOf
+        | PrincipalField::Members
+        | PrincipalField::Roles
+        | PrincipalField::Lists
+        | PrincipalField::EnabledPermissions
+        | PrincipalField::DisabledPermissions
+        | PrincipalField::Urls
+        | PrincipalField::ExternalMembers => Value::Array(
+            value
+                .as_string_list()
+                .iter()
+                .map(|item| Value::String(names.get(item).unwrap_or(item).clone()))
+                .collect(),
+        ),
+        _ => value
+            .as_str()
+            .map_or(Value::Null, |value| Value::String(value.to_string())),
+    }
+}
+
+fn csv_value(value: Value) -> String {
+    match value {
+        Value::String(value) => value,
+        Value::Array(items) => items
+            .into_iter()
+            .map(csv_value)
+            .collect::<Vec<_>>()
+            .join(", "),
+        Value::Null => String::new(),
+        value => value.to_string(),
+    }
+}
+
+// Values starting with these characters are run as formulas by spreadsheet applications
+fn csv_cell(value: String) -> String {
+    if value.starts_with(['=', '+', '-', '@']) {
+        format!("'{value}")
+    } else {
+        value
+    }
+}
+
+fn default_fields(typ: PrincipalType) -> &'static [PrincipalField] {
+    match typ {
+        PrincipalType::Individual => &[
+            PrincipalField::Name,
+            PrincipalField::Description,
+            PrincipalField::Emails,
+            PrincipalField::Quota,
+            PrincipalField::UsedQuota,
+            PrincipalField::MemberOf,
+            PrincipalField::Roles,
+        ],
+        PrincipalType::Group => &[
+            PrincipalField::Name,
+            PrincipalField::Description,
+            PrincipalField::Emails,
+            PrincipalField::Members,
+            PrincipalField::MemberOf,
+        ],
+        PrincipalType::List => &[
+            PrincipalField::Name,
+            PrincipalField::Description,
+            PrincipalField::Emails,
+            PrincipalField::Members,
+        ],
+        _ => &[PrincipalField::Name, PrincipalField::Description],
+    }
+}
+
+pub fn export_url(typ: PrincipalType, filter: Option<String>) -> String {
+    UrlBuilder::new(format!("/manage/directory/{}/export", typ.resource_name()))
+        .with_optional_parameter("filter", filter)
+        .finish()
+}
+
+impl Builder<Schemas, ()> {
+    pub fn build_principal_export(self) -> Self {
+        self.new_schema("principal-export")
+            .new_field("format")
+            .typ(Type::Select {
+                typ: SelectType::Single,
+                source: Source::Static(&[("csv", "CSV"), ("json", "JSON")]),
+            })
+            .default("csv")
+            .build()
+            .new_field("fields")
+            .typ(Type::Select {
+                typ: SelectType::Many,
+                source: Source::Static(&EXPORT_OPTIONS),
+            })
+            .build()
+            .new_field("filter")
+            .typ(Type::Input)
+            .build()
+            .new_field("tenant")
+            .typ(Type::Input)
+            .build()
+            .new_field("resolve-names")
+            .typ(Type::Boolean)
+            .default("false")
+            .build()
+            .new_field("human-sizes")
+            .typ(Type::Boolean)
+            .default("true")
+            .build()
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn neutralizes_formulas() {
+        assert_eq!(csv_cell("=SUM(A1)".to_string()), "'=SUM(A1)");
+        assert_eq!(csv_cell("@cmd".to_string()), "'@cmd");
+        assert_eq!(csv_cell("+1".to_string()), "'+1");
+        assert_eq!(csv_cell("-1".to_string()), "'-1");
+        assert_eq!(csv_cell("jdoe".to_string()), "jdoe");
+        assert_eq!(
+            csv_escape(&csv_cell("=a,b".to_string())),
+            "\"'=a,b\"".to_string()
+        );
+    }
+
+    #[test]
+    fn flattens_values() {
+        assert_eq!(
+            csv_value(Value::Array(vec![
+                Value::String("a".to_string()),
+                Value::String("b".to_string())
+            ])),
+            "a, b"
+        );
+        assert_eq!(csv_value(Value::Null), "");
+        assert_eq!(csv_value(Value::from(42)), "42");
+    }
+
+    #[test]
+    fn labels_match_fields() {
+        assert_eq!(EXPORT_OPTIONS[3], ("emails", "E-mail Addresses"));
+        assert!(!EXPORT_OPTIONS.iter().any(|(id, _)| *id == "secrets"));
+    }
+}
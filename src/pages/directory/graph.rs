@@ -0,0 +1,893 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+};
+
+use ahash::{AHashMap, AHashSet};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use leptos::*;
+use leptos_router::{use_navigate, use_query_map};
+use serde::{Deserialize, Serialize};
+use web_sys::wasm_bindgen::JsCast;
+
+use crate::{
+    components::{
+        form::{button::Button, Form, FormButtonBar, FormSection},
+        messages::alert::{use_alerts, Alert},
+        skeleton::Skeleton,
+        Color,
+    },
+    core::{
+        http::{self, HttpRequest},
+        oauth::use_authorization,
+        url::UrlBuilder,
+        AccessToken,
+    },
+    pages::{
+        directory::{effective::is_builtin_role, Principal, PrincipalType},
+        List,
+    },
+};
+
+// Large domains are truncated, nodes past this limit can still be expanded one by one
+const MAX_NODES: usize = 250;
+
+const NODE_WIDTH: usize = 180;
+const NODE_HEIGHT: usize = 44;
+const COLUMN_GAP: usize = 24;
+const ROW_GAP: usize = 72;
+const MARGIN: usize = 16;
+const MAX_LABEL: usize = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+enum EdgeKind {
+    Member,
+    Role,
+    List,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct GraphNode {
+    typ: Option<PrincipalType>,
+    description: String,
+    expanded: bool,
+    missing: bool,
+}
+
+// Edges point from the member to the group, list or role it belongs to.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct MembershipGraph {
+    root: String,
+    nodes: BTreeMap<String, GraphNode>,
+    edges: BTreeSet<(String, String, EdgeKind)>,
+    truncated: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum GraphTarget {
+    Principal(String),
+    Domain(String),
+}
+
+struct GraphLayout<'x> {
+    positions: AHashMap<&'x str, (usize, usize)>,
+    width: usize,
+    height: usize,
+}
+
+#[component]
+pub fn MembershipGraphView() -> impl IntoView {
+    let auth = use_authorization();
+    let alert = use_alerts();
+    let query = use_query_map();
+    let graph: RwSignal<MembershipGraph> = create_rw_signal(MembershipGraph::default());
+    let target = create_memo(move |_| {
+        query.with(|query| {
+            query
+                .get("domain")
+                .filter(|domain| !domain.is_empty())
+                .map(|domain| GraphTarget::Domain(domain.clone()))
+                .or_else(|| {
+                    query
+                        .get("principal")
+                        .filter(|name| !name.is_empty())
+                        .map(|name| GraphTarget::Principal(name.clone()))
+                })
+        })
+    });
+    let is_empty = create_memo(move |_| graph.with(|graph| graph.nodes.is_empty()));
+    let search = create_rw_signal(String::new());
+    let search_domain = create_rw_signal(false);
+
+    let fetch_graph = create_resource(
+        move || target.get(),
+        move |target| {
+            let auth = auth.get_untracked();
+
+            async move {
+                let graph_ = match target {
+                    Some(GraphTarget::Principal(name)) => {
+                        fetch_principal_graph(&auth, name).await?
+                    }
+                    Some(GraphTarget::Domain(domain)) => fetch_domain_graph(&auth, domain).await?,
+                    None => MembershipGraph::default(),
+                };
+                graph.set(graph_);
+                Ok(())
+            }
+        },
+    );
+
+    let expand = create_action(move |name: &String| {
+        let name = name.clone();
+        let auth = auth.get();
+
+        async move {
+            match HttpRequest::get(("/api/principal", &name))
+                .with_authorization(&auth)
+                .send::<Principal>()
+                .await
+            {
+                Ok(principal) => {
+                    graph.update(|graph| graph.add_principal(&name, &principal));
+                }
+                Err(http::Error::NotFound | http::Error::Forbidden) => {
+                    graph.update(|graph| graph.mark_missing(&name));
+                }
+                Err(http::Error::Unauthorized) => {
+                    use_navigate()("/login", Default::default());
+                }
+                Err(err) => {
+                    alert.set(Alert::from(err));
+                }
+            }
+        }
+    });
+
+    let show_target = move || {
+        let value = search.get_untracked().trim().to_string();
+        if !value.is_empty() {
+            let param = if search_domain.get_untracked() {
+                "domain"
+            } else {
+                "principal"
+            };
+            use_navigate()(
+                &UrlBuilder::new("/manage/directory/graph")
+                    .with_parameter(param, value)
+                    .finish(),
+                Default::default(),
+            );
+        }
+    };
+
+    view! {
+        <Form
+            title="Membership graph"
+            subtitle="Explore how accounts, groups, mailing lists and roles reference each other"
+        >
+            <FormSection>
+                <div class="sm:col-span-12">
+                    <div class="flex flex-wrap items-center gap-4 mb-4">
+                        <input
+                            type="text"
+                            class="py-2 px-3 block w-64 border-gray-200 shadow-sm text-sm rounded-lg focus:border-blue-500 focus:ring-blue-500 dark:bg-slate-900 dark:border-gray-700 dark:text-gray-400 dark:focus:ring-gray-600"
+                            placeholder=move || {
+                                if search_domain.get() { "Domain name" } else { "Principal name" }
+                            }
+
+                            prop:value=move || search.get()
+                            on:input=move |ev| search.set(event_target_value(&ev))
+                            on:keyup=move |ev| {
+                                if ev.unchecked_ref::<web_sys::KeyboardEvent>().key_code() == 13 {
+                                    show_target();
+                                }
+                            }
+                        />
+
+                        <label class="flex items-center text-sm text-gray-500 dark:text-gray-400">
+                            <input
+                                type="checkbox"
+                                class="shrink-0 me-2 border-gray-200 rounded text-blue-600 focus:ring-blue-500 dark:bg-gray-800 dark:border-gray-700 dark:checked:bg-blue-500 dark:checked:border-blue-500 dark:focus:ring-offset-gray-800"
+                                prop:checked=move || search_domain.get()
+                                on:change=move |ev| search_domain.set(event_target_checked(&ev))
+                            />
+                            "Whole domain"
+                        </label>
+                        <Button text="Show" color=Color::Gray on_click=move |_| show_target()/>
+                    </div>
+
+                    <Transition fallback=Skeleton>
+                        {move || match fetch_graph.get() {
+                            None => None,
+                            Some(Err(http::Error::Unauthorized)) => {
+                                use_navigate()("/login", Default::default());
+                                Some(view! { <div></div> }.into_view())
+                            }
+                            Some(Err(err)) => {
+                                alert.set(Alert::from(err));
+                                Some(view! { <div></div> }.into_view())
+                            }
+                            Some(Ok(_)) if is_empty.get() => {
+                                Some(
+                                    view! {
+                                        <p class="text-sm text-gray-600 dark:text-gray-400">
+                                            {if target.get().is_some() {
+                                                "No principals found."
+                                            } else {
+                                                "Enter a principal or domain name to display its memberships."
+                                            }}
+
+                                        </p>
+                                    }
+                                        .into_view(),
+                                )
+                            }
+                            Some(Ok(_)) => {
+                                Some(
+                                    view! {
+                                        <p class="mb-4 text-sm text-gray-600 dark:text-gray-400">
+                                            "Arrows point from a member to the group, list or role it belongs to. Click a dashed node to load its memberships."
+                                        </p>
+                                        {move || {
+                                            graph
+                                                .with(|graph| {
+                                                    let cycles = graph.cycles();
+                                                    (!cycles.is_empty())
+                                                        .then(|| {
+                                                            view! {
+                                                                <ul class="mb-4 text-sm text-red-700 dark:text-red-500">
+                                                                    {cycles
+                                                                        .into_iter()
+                                                                        .map(|cycle| {
+                                                                            view! {
+                                                                                <li>
+                                                                                    {format!(
+                                                                                        "Circular membership between {}",
+                                                                                        cycle.join(", "),
+                                                                                    )}
+
+                                                                                </li>
+                                                                            }
+                                                                        })
+                                                                        .collect_view()}
+                                                                </ul>
+                                                            }
+                                                        })
+                                                })
+                                        }}
+
+                                        {move || {
+                                            graph
+                                                .with(|graph| graph.truncated)
+                                                .then(|| {
+                                                    view! {
+                                                        <p class="mb-4 text-sm text-yellow-700 dark:text-yellow-500">
+                                                            {format!(
+                                                                "Only the first {MAX_NODES} principals are shown.",
+                                                            )}
+
+                                                        </p>
+                                                    }
+                                                })
+                                        }}
+
+                                        <div
+                                            class="overflow-auto border border-gray-200 rounded-lg dark:border-gray-700"
+                                            inner_html=move || graph.with(|graph| graph.to_svg())
+                                            on:click=move |ev| {
+                                                let name = ev
+                                                    .target()
+                                                    .and_then(|target| {
+                                                        target
+                                                            .unchecked_into::<web_sys::Element>()
+                                                            .closest("[data-node]")
+                                                            .ok()
+                                                            .flatten()
+                                                    })
+                                                    .and_then(|node| node.get_attribute("data-node"));
+                                                if let Some(name) = name {
+                                                    let can_expand = graph
+                                                        .with_untracked(|graph| graph.can_expand(&name));
+                                                    if can_expand && !expand.pending().get_untracked() {
+                                                        expand.dispatch(name);
+                                                    }
+                                                }
+                                            }
+                                        >
+                                        </div>
+                                    }
+                                        .into_view(),
+                                )
+                            }
+                        }}
+
+                    </Transition>
+                </div>
+            </FormSection>
+
+            <FormButtonBar>
+                <a
+                    class="py-2 px-3 inline-flex items-center gap-x-2 text-sm font-semibold rounded-lg border border-gray-200 bg-white text-gray-800 shadow-sm hover:bg-gray-50 dark:bg-slate-900 dark:border-gray-700 dark:text-white dark:hover:bg-gray-800"
+                    class:hidden=move || is_empty.get()
+                    download=move || graph.with(|graph| graph.file_name("dot"))
+                    href=move || {
+                        format!(
+                            "data:text/vnd.graphviz;base64,{}",
+                            STANDARD.encode(graph.with(|graph| graph.to_dot())),
+                        )
+                    }
+                >
+
+                    "Export DOT"
+                </a>
+                <a
+                    class="py-2 px-3 inline-flex items-center gap-x-2 text-sm font-semibold rounded-lg border border-gray-200 bg-white text-gray-800 shadow-sm hover:bg-gray-50 dark:bg-slate-900 dark:border-gray-700 dark:text-white dark:hover:bg-gray-800"
+                    class:hidden=move || is_empty.get()
+                    download=move || graph.with(|graph| graph.file_name("svg"))
+                    href=move || {
+                        format!(
+                            "data:image/svg+xml;base64,{}",
+                            STANDARD.encode(graph.with(|graph| graph.to_svg())),
+                        )
+                    }
+                >
+
+                    "Export SVG"
+                </a>
+            </FormButtonBar>
+        </Form>
+    }
+}
+
+async fn fetch_principal_graph(auth: &AccessToken, name: String) -> http::Result<MembershipGraph> {
+    let mut graph = MembershipGraph {
+        root: name.clone(),
+        ..Default::default()
+    };
+    let principal = HttpRequest::get(("/api/principal", &name))
+        .with_authorization(auth)
+        .send::<Principal>()
+        .await?;
+    graph.add_principal(&name, &principal);
+
+    Ok(graph)
+}
+
+async fn fetch_domain_graph(auth: &AccessToken, domain: String) -> http::Result<MembershipGraph> {
+    let suffix = format!("@{domain}");
+    let list = HttpRequest::get("/api/principal")
+        .with_authorization(auth)
+        .with_parameter("types", "individual,group,list")
+        .with_parameter("filter", &domain)
+        .with_parameter(
+            "fields",
+            "name,type,description,emails,memberOf,members,roles,lists",
+        )
+        .with_parameter("limit", MAX_NODES.to_string())
+        .send::<List<Principal>>()
+        .await?;
+
+    let mut graph = MembershipGraph {
+        root: domain,
+        truncated: list.total as usize > list.items.len(),
+        ..Default::default()
+    };
+    for principal in list.items {
+        // The filter also matches descriptions, only keep principals that belong to the domain
+        let in_domain = principal
+            .name()
+            .into_iter()
+            .chain(principal.emails.as_string_list().iter().map(String::as_str))
+            .any(|address| address.ends_with(&suffix));
+        if let Some(name) = principal.name().filter(|_| in_domain) {
+            graph.add_principal(name, &principal);
+        }
+    }
+
+    Ok(graph)
+}
+
+impl MembershipGraph {
+    fn add_principal(&mut self, name: &str, principal: &Principal) {
+        let node = self.node(name);
+        node.expanded = true;
+        node.typ = principal.typ.or(node.typ);
+        if let Some(description) = principal.description.as_str() {
+            node.description = description.to_string();
+        }
+
+        for (names, kind, typ, outgoing) in [
+            (
+                principal.member_of.as_string_list(),
+                EdgeKind::Member,
+                None,
+                true,
+            ),
+            (
+                principal.members.as_string_list(),
+                EdgeKind::Member,
+                None,
+                false,
+            ),
+            (
+                principal.roles.as_string_list(),
+                EdgeKind::Role,
+                Some(PrincipalType::Role),
+                true,
+            ),
+            (
+                principal.lists.as_string_list(),
+                EdgeKind::List,
+                Some(PrincipalType::List),
+                true,
+            ),
+        ] {
+            for other in names {
+                let node = self.node(other);
+                if node.typ.is_none() {
+                    node.typ = typ;
+                }
+                if is_builtin_role(other) {
+                    // Built-in roles cannot be fetched and have no memberships
+                    node.typ = Some(PrincipalType::Role);
+                    node.expanded = true;
+                }
+
+                let edge = if outgoing {
+                    (name.to_string(), other.clone(), kind)
+                } else {
+                    (other.clone(), name.to_string(), kind)
+                };
+                self.edges.insert(edge);
+            }
+        }
+    }
+
+    fn mark_missing(&mut self, name: &str) {
+        let node = self.node(name);
+        node.missing = true;
+        node.expanded = true;
+    }
+
+    fn node(&mut self, name: &str) -> &mut GraphNode {
+        if !self.nodes.contains_key(name) {
+            self.nodes.insert(name.to_string(), GraphNode::default());
+        }
+        self.nodes.get_mut(name).unwrap()
+    }
+
+    fn can_expand(&self, name: &str) -> bool {
+        self.nodes.get(name).is_some_and(|node| !node.expanded) && self.nodes.len() < MAX_NODES
+    }
+
+    // Two principals are part of the same cycle when each one can reach the
+    // other, graphs are small enough to compute reachability for every node.
+    fn cycles(&self) -> Vec<Vec<String>> {
+        let mut adjacency: AHashMap<&str, Vec<&str>> = AHashMap::new();
+        for (from, to, _) in &self.edges {
+            adjacency
+                .entry(from.as_str())
+                .or_default()
+                .push(to.as_str());
+        }
+        let reachable = self
+            .nodes
+            .keys()
+            .map(|name| {
+                let mut seen = AHashSet::new();
+                let mut queue = adjacency.get(name.as_str()).cloned().unwrap_or_default();
+                while let Some(next) = queue.pop() {
+                    if seen.insert(next) {
+                        queue.extend(adjacency.get(next).into_iter().flatten());
+                    }
+                }
+                (name.as_str(), seen)
+            })
+            .collect::<AHashMap<_, _>>();
+
+        let mut assigned = AHashSet::new();
+        let mut cycles = Vec::new();
+        for name in self.nodes.keys() {
+            if assigned.contains(name.as_str()) || !reachable[name.as_str()].contains(name.as_str())
+            {
+                continue;
+            }
+            let cycle = self
+                .nodes
+                .keys()
+                .filter(|other| {
+                    reachable[name.as_str()].contains(other.as_str())
+                        && reachable[other.as_str()].contains(name.as_str())
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            assigned.extend(cycle.iter().cloned());
+            cycles.push(cycle);
+        }
+
+        cycles
+    }
+
+    // Members are placed below the principals they belong to. Edges inside a
+    // cycle are ignored while ranking so the longest-path search terminates.
+    fn layout(&self, cyclic: &AHashSet<&str>) -> GraphLayout<'_> {
+        let mut rank: AHashMap<&str, usize> =
+            self.nodes.keys().map(|name| (name.as_str(), 0)).collect();
+        for _ in 0..self.nodes.len() {
+            let mut changed = false;
+            for (from, to, _) in &self.edges {
+                if cyclic.contains(from.as_str()) && cyclic.contains(to.as_str()) {
+                    continue;
+                }
+                let min_rank = rank[from.as_str()] + 1;
+                if rank[to.as_str()] < min_rank {
+                    rank.insert(to.as_str(), min_rank);
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        let max_rank = rank.values().copied().max().unwrap_or_default();
+        let mut rows = vec![Vec::new(); max_rank + 1];
+        for name in self.nodes.keys() {
+            rows[max_rank - rank[name.as_str()]].push(name.as_str());
+        }
+        let columns = rows.iter().map(Vec::len).max().unwrap_or_default();
+        let row_width =
+            |count: usize| (count * (NODE_WIDTH + COLUMN_GAP)).saturating_sub(COLUMN_GAP);
+        let width = row_width(columns) + 2 * MARGIN;
+        let height = rows.len() * (NODE_HEIGHT + ROW_GAP) - ROW_GAP + 2 * MARGIN;
+
+        let mut positions = AHashMap::new();
+        for (row_num, row) in rows.iter().enumerate() {
+            let offset = MARGIN + (row_width(columns) - row_width(row.len())) / 2;
+            for (col_num, name) in row.iter().enumerate() {
+                positions.insert(
+                    *name,
+                    (
+                        offset + col_num * (NODE_WIDTH + COLUMN_GAP),
+                        MARGIN + row_num * (NODE_HEIGHT + ROW_GAP),
+                    ),
+                );
+            }
+        }
+
+        GraphLayout {
+            positions,
+            width,
+            height,
+        }
+    }
+
+    fn to_svg(&self) -> String {
+        let cycles = self.cycles();
+        let cyclic = cycles
+            .iter()
+            .flatten()
+            .map(String::as_str)
+            .collect::<AHashSet<_>>();
+        let layout = self.layout(&cyclic);
+        let mut svg = String::new();
+
+        let _ = write!(
+            svg,
+            concat!(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" ",
+                "viewBox=\"0 0 {w} {h}\" font-family=\"sans-serif\" font-size=\"12\">",
+                "<defs><marker id=\"arrow\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" ",
+                "markerWidth=\"8\" markerHeight=\"8\" orient=\"auto-start-reverse\">",
+                "<path d=\"M 0 0 L 10 5 L 0 10 z\" fill=\"#6b7280\"/></marker></defs>",
+                "<rect width=\"100%\" height=\"100%\" fill=\"#ffffff\"/>"
+            ),
+            w = layout.width,
+            h = layout.height
+        );
+
+        for (from, to, kind) in &self.edges {
+            let (Some(&(x1, y1)), Some(&(x2, y2))) = (
+                layout.positions.get(from.as_str()),
+                layout.positions.get(to.as_str()),
+            ) else {
+                continue;
+            };
+            let in_cycle = cyclic.contains(from.as_str()) && cyclic.contains(to.as_str());
+            let color = if in_cycle { "#dc2626" } else { kind.color() };
+            let (start, end) = if y1 == y2 {
+                // Same row, only possible inside a cycle
+                let (x1, x2) = if x1 < x2 {
+                    (x1 + NODE_WIDTH, x2)
+                } else {
+                    (x1, x2 + NODE_WIDTH)
+                };
+                ((x1, y1 + NODE_HEIGHT / 2), (x2, y2 + NODE_HEIGHT / 2))
+            } else if y1 > y2 {
+                (
+                    (x1 + NODE_WIDTH / 2, y1),
+                    (x2 + NODE_WIDTH / 2, y2 + NODE_HEIGHT),
+                )
+            } else {
+                (
+                    (x1 + NODE_WIDTH / 2, y1 + NODE_HEIGHT),
+                    (x2 + NODE_WIDTH / 2, y2),
+                )
+            };
+            let _ = write!(
+                svg,
+                concat!(
+                    "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"{}\" ",
+                    "stroke-width=\"1.5\"{} marker-end=\"url(#arrow)\"/>"
+                ),
+                start.0,
+                start.1,
+                end.0,
+                end.1,
+                color,
+                if *kind == EdgeKind::Member {
+                    ""
+                } else {
+                    " stroke-dasharray=\"6 3\""
+                }
+            );
+        }
+
+        for (name, node) in &self.nodes {
+            let Some(&(x, y)) = layout.positions.get(name.as_str()) else {
+                continue;
+            };
+            let (fill, stroke) = node.colors();
+            let _ = write!(
+                svg,
+                concat!(
+                    "<g data-node=\"{name}\" style=\"cursor:{cursor}\"><title>{title}</title>",
+                    "<rect x=\"{x}\" y=\"{y}\" width=\"{w}\" height=\"{h}\" rx=\"8\" ",
+                    "fill=\"{fill}\" stroke=\"{stroke}\" stroke-width=\"{sw}\"{dash}/>",
+                    "<text x=\"{tx}\" y=\"{ty1}\" text-anchor=\"middle\" fill=\"#1f2937\" ",
+                    "font-weight=\"600\">{label}</text>",
+                    "<text x=\"{tx}\" y=\"{ty2}\" text-anchor=\"middle\" fill=\"#6b7280\" ",
+                    "font-size=\"10\">{typ}</text></g>"
+                ),
+                name = escape_xml(name),
+                cursor = if self.can_expand(name) {
+                    "pointer"
+                } else {
+                    "default"
+                },
+                title = escape_xml(if node.description.is_empty() {
+                    name
+                } else {
+                    &node.description
+                }),
+                x = x,
+                y = y,
+                w = NODE_WIDTH,
+                h = NODE_HEIGHT,
+                fill = fill,
+                stroke = stroke,
+                sw = if *name == self.root { 3 } else { 1 },
+                dash = if node.expanded {
+                    ""
+                } else {
+                    " stroke-dasharray=\"4 3\""
+                },
+                tx = x + NODE_WIDTH / 2,
+                ty1 = y + 19,
+                ty2 = y + 34,
+                label = escape_xml(&truncate(name)),
+                typ = node.type_label(name),
+            );
+        }
+
+        svg.push_str("</svg>");
+        svg
+    }
+
+    fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph membership {\n  rankdir=BT;\n  node [shape=box, style=\"rounded,filled\", fontname=\"sans-serif\"];\n");
+        let cyclic = self.cycles().into_iter().flatten().collect::<AHashSet<_>>();
+
+        for (name, node) in &self.nodes {
+            let (fill, stroke) = node.colors();
+            let _ = writeln!(
+                dot,
+                "  \"{}\" [label=\"{}\\n{}\", fillcolor=\"{}\", color=\"{}\"{}];",
+                escape_dot(name),
+                escape_dot(name),
+                node.type_label(name),
+                fill,
+                stroke,
+                if *name == self.root {
+                    ", penwidth=3"
+                } else {
+                    ""
+                }
+            );
+        }
+        for (from, to, kind) in &self.edges {
+            let in_cycle = cyclic.contains(from) && cyclic.contains(to);
+            let _ = writeln!(
+                dot,
+                "  \"{}\" -> \"{}\" [label=\"{}\", color=\"{}\"];",
+                escape_dot(from),
+                escape_dot(to),
+                kind.label(),
+                if in_cycle { "#dc2626" } else { kind.color() }
+            );
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    fn file_name(&self, extension: &str) -> String {
+        format!(
+            "membership-{}.{extension}",
+            self.root
+                .chars()
+                .map(|ch| if ch.is_alphanumeric() { ch } else { '-' })
+                .collect::<String>()
+        )
+    }
+}
+
+impl GraphNode {
+    fn colors(&self) -> (&'static str, &'static str) {
+        if self.missing {
+            return ("#fee2e2", "#dc2626");
+        }
+        match self.typ {
+            Some(PrincipalType::Individual) => ("#ffffff", "#6b7280"),
+            Some(PrincipalType::Group) => ("#dbeafe", "#2563eb"),
+            Some(PrincipalType::List) => ("#ccfbf1", "#0d9488"),
+            Some(PrincipalType::Role) => ("#fef9c3", "#ca8a04"),
+            _ => ("#f3f4f6", "#9ca3af"),
+        }
+    }
+
+    fn type_label(&self, name: &str) -> &'static str {
+        if self.missing {
+            "Not found"
+        } else if is_builtin_role(name) {
+            "Built-in role"
+        } else {
+            self.typ.map(|typ| typ.name()).unwrap_or("Principal")
+        }
+    }
+}
+
+impl EdgeKind {
+    fn color(&self) -> &'static str {
+        match self {
+            EdgeKind::Member => "#6b7280",
+            EdgeKind::Role => "#ca8a04",
+            EdgeKind::List => "#0d9488",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            EdgeKind::Member => "member of",
+            EdgeKind::Role => "role",
+            EdgeKind::List => "list",
+        }
+    }
+}
+
+fn truncate(name: &str) -> String {
+    if name.chars().count() > MAX_LABEL {
+        format!("{}…", name.chars().take(MAX_LABEL - 1).collect::<String>())
+    } else {
+        name.to_string()
+    }
+}
+
+fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for ch in value.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+fn escape_dot(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pages::directory::PrincipalValue;
+
+    fn build(memberships: &[(&str, &[&str])]) -> MembershipGraph {
+        let mut graph = MembershipGraph {
+            root: memberships[0].0.to_string(),
+            ..Default::default()
+        };
+        for (name, member_of) in memberships {
+            graph.add_principal(
+                name,
+                &Principal {
+                    typ: Some(PrincipalType::Group),
+                    member_of: PrincipalValue::StringList(
+                        member_of.iter().map(|name| name.to_string()).collect(),
+                    ),
+                    ..Default::default()
+                },
+            );
+        }
+        graph
+    }
+
+    #[test]
+    fn finds_cycles() {
+        let graph = build(&[
+            ("a", &["b"]),
+            ("b", &["c"]),
+            ("c", &["a", "d"]),
+            ("d", &[]),
+            ("e", &["e"]),
+        ]);
+
+        assert_eq!(
+            graph.cycles(),
+            vec![
+                vec!["a".to_string(), "b".to_string(), "c".to_string()],
+                vec!["e".to_string()]
+            ]
+        );
+        assert!(build(&[("a", &["b"]), ("b", &["c"])]).cycles().is_empty());
+    }
+
+    #[test]
+    fn ranks_members_below_their_groups() {
+        let graph = build(&[("a", &["b"]), ("b", &["c"]), ("c", &["a"]), ("d", &["a"])]);
+        let cyclic = graph.cycles().into_iter().flatten().collect::<Vec<_>>();
+        let cyclic = cyclic.iter().map(String::as_str).collect::<AHashSet<_>>();
+        let layout = graph.layout(&cyclic);
+
+        // The layout terminates despite the cycle and keeps "a" above "d"
+        assert!(layout.positions["a"].1 < layout.positions["d"].1);
+        assert_eq!(layout.positions.len(), 4);
+    }
+
+    #[test]
+    fn links_builtin_roles_without_fetching_them() {
+        let mut graph = MembershipGraph::default();
+        graph.add_principal(
+            "john",
+            &Principal {
+                typ: Some(PrincipalType::Individual),
+                roles: PrincipalValue::StringList(vec!["admin".to_string()]),
+                ..Default::default()
+            },
+        );
+
+        assert!(!graph.can_expand("admin"));
+        assert_eq!(graph.nodes["admin"].typ, Some(PrincipalType::Role));
+        assert!(graph
+            .edges
+            .contains(&("john".to_string(), "admin".to_string(), EdgeKind::Role)));
+    }
+
+    #[test]
+    fn escapes_exported_labels() {
+        assert_eq!(escape_dot(r#"a"b\c"#), r#"a\"b\\c"#);
+        assert_eq!(escape_xml("<a & 'b'>"), "&lt;a &amp; &#39;b&#39;&gt;");
+    }
+}
@@ -0,0 +1,190 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{sync::Arc, time::Duration};
+
+use gloo_storage::{SessionStorage, Storage};
+use leptos::*;
+use leptos_router::{use_navigate, use_params_map};
+
+use crate::{
+    components::{
+        form::{
+            button::Button,
+            input::{InputPassword, InputText},
+            Form, FormButtonBar, FormElement, FormItem, FormSection,
+        },
+        messages::alert::{use_alerts, Alert},
+        Color,
+    },
+    core::{
+        oauth::{oauth_authenticate, use_authorization, AuthenticationResult},
+        password::reset_password_policy,
+        schema::{Builder, Schemas, Transformer, Type, Validator},
+        AccessToken, Permission, Permissions,
+    },
+    IMPERSONATION_STORAGE_KEY,
+};
+
+#[component]
+pub fn Impersonate() -> impl IntoView {
+    let auth = use_authorization();
+    let alert = use_alerts();
+    let params = use_params_map();
+    let account = create_memo(move |_| params.get().get("id").cloned().unwrap_or_default());
+    let show_totp = create_rw_signal(false);
+    let (pending, set_pending) = create_signal(false);
+    let data = expect_context::<Arc<Schemas>>()
+        .build_form("impersonate")
+        .into_signal();
+
+    let start_session = create_action(move |password: &String| {
+        let password = password.clone();
+        let admin = auth.get();
+        let account = account.get();
+        // The server grants a session for "account%admin" when the admin
+        // credentials are valid and carry the impersonate permission. The
+        // token is issued to that login, so every request made during the
+        // session is attributed to both accounts server-side without any
+        // extra tagging from the client.
+        let login = format!("{account}%{}", admin.username);
+
+        async move {
+            set_pending.set(true);
+            let result = oauth_authenticate(&admin.base_url, &login, &password).await;
+            set_pending.set(false);
+
+            match result {
+                AuthenticationResult::Success(response) => {
+                    let permissions = Permissions::new(response.permissions);
+                    let url = if permissions.has_access(Permission::ManageEncryption) {
+                        "/account/crypto"
+                    } else if permissions.has_access(Permission::ManagePasswords) {
+                        "/account/password"
+                    } else {
+                        alert.set(Alert::warning(format!(
+                            "Account {account} is not allowed to manage its own settings."
+                        )));
+                        return;
+                    };
+
+                    let refresh_token = response.grant.refresh_token.unwrap_or_default();
+                    let session = AccessToken {
+                        base_url: admin.base_url.clone(),
+                        access_token: response.grant.access_token.into(),
+                        refresh_token: refresh_token.clone().into(),
+                        username: login.into(),
+                        is_valid: true,
+                        is_enterprise: response.is_enterprise,
+                        permissions,
+                        impersonator: Some(admin.username.clone()),
+                    };
+
+                    // The admin session is left as is so it can be restored later
+                    if let Err(err) = SessionStorage::set(IMPERSONATION_STORAGE_KEY, &session) {
+                        log::error!(
+                            "Failed to save impersonation state to session storage: {}",
+                            err
+                        );
+                    }
+                    reset_password_policy();
+                    auth.set(session);
+
+                    if response.grant.expires_in > 0 && !refresh_token.is_empty() {
+                        set_timeout(
+                            move || {
+                                auth.update(|auth| {
+                                    auth.is_valid = false;
+                                });
+                            },
+                            Duration::from_secs(response.grant.expires_in),
+                        );
+                    }
+
+                    use_navigate()(url, Default::default());
+                }
+                AuthenticationResult::TotpRequired => {
+                    show_totp.set(true);
+                }
+                AuthenticationResult::Error(err) => {
+                    alert.set(err);
+                }
+            }
+        }
+    });
+
+    view! {
+        <Form
+            title=Signal::derive(move || format!("Impersonate '{}'", account.get()))
+            subtitle="Open the account section as this user. Confirm with your own credentials to continue."
+        >
+            <FormSection>
+                <Show when=move || !show_totp.get()>
+                    <FormItem label="Your password">
+                        <InputPassword element=FormElement::new("password", data)/>
+                    </FormItem>
+                </Show>
+
+                <Show when=move || show_totp.get()>
+                    <FormItem label="TOTP Token">
+                        <InputText element=FormElement::new("totp-code", data)/>
+                    </FormItem>
+                </Show>
+
+            </FormSection>
+
+            <FormButtonBar>
+                <Button
+                    text="Cancel"
+                    color=Color::Gray
+                    on_click=move |_| {
+                        use_navigate()(
+                            &format!("/manage/directory/accounts/{}/edit", account.get()),
+                            Default::default(),
+                        );
+                    }
+                />
+
+                <Button
+                    text="Start session"
+                    color=Color::Blue
+                    on_click=Callback::new(move |_| {
+                        data.update(|data| {
+                            if data.validate_form() {
+                                start_session
+                                    .dispatch(
+                                        match (
+                                            data.value::<String>("password").unwrap_or_default(),
+                                            data.value::<String>("totp-code"),
+                                        ) {
+                                            (password, Some(totp)) => format!("{}${}", password, totp),
+                                            (password, None) => password,
+                                        },
+                                    );
+                            }
+                        });
+                    })
+
+                    disabled=pending
+                />
+            </FormButtonBar>
+        </Form>
+    }
+}
+
+impl Builder<Schemas, ()> {
+    pub fn build_impersonate(self) -> Self {
+        self.new_schema("impersonate")
+            .new_field("password")
+            .typ(Type::Secret)
+            .input_check([], [Validator::Required])
+            .build()
+            .new_field("totp-code")
+            .input_check([Transformer::Trim], [])
+            .build()
+            .build()
+    }
+}
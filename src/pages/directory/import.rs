@@ -0,0 +1,1456 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::sync::Arc;
+
+use ahash::{AHashMap, AHashSet};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use leptos::*;
+use leptos_router::use_navigate;
+
+use crate::{
+    components::{
+        badge::Badge,
+        form::{
+            button::Button,
+            input::{InputText, TextArea},
+            select::Select,
+            steps::StepProgress,
+            Form, FormButtonBar, FormElement, FormItem, FormSection,
+        },
+        list::table::{Table, TableRow},
+        messages::alert::{use_alerts, Alert},
+        Color,
+    },
+    core::{
+        form::{is_hashed_secret, FormData},
+        http::{self, HttpRequest, ManagementApiError},
+        oauth::use_authorization,
+        password::{load_password_policy, password_policy},
+        schema::{Builder, Schemas, SelectType, Source, Transformer, Type, Validator},
+        AccessToken,
+    },
+    pages::{
+        directory::{spawn_workers, Principal, PrincipalType, PrincipalValue, SpecialSecrets},
+        maybe_plural, List,
+    },
+};
+
+const IMPORT_TYPES: &[PrincipalType] = &[
+    PrincipalType::Individual,
+    PrincipalType::Group,
+    PrincipalType::List,
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum ImportStep {
+    #[default]
+    Source,
+    Mapping,
+    Review,
+    Import,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ImportField {
+    Ignore,
+    Name,
+    Type,
+    Email,
+    Aliases,
+    Description,
+    Quota,
+    Password,
+    MemberOf,
+    Members,
+    Roles,
+    Lists,
+}
+
+#[derive(Debug, Clone)]
+struct ImportRecord {
+    line: usize,
+    values: Vec<(ImportField, String)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ImportAction {
+    Create,
+    Update,
+    Skip,
+    Invalid,
+}
+
+#[derive(Debug, Clone)]
+struct ImportRow {
+    line: usize,
+    principal: Principal,
+    password: Option<String>,
+    errors: Vec<String>,
+    action: ImportAction,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ImportOutcome {
+    Created,
+    Updated,
+    Unchanged,
+    Skipped,
+    Failed(String),
+}
+
+#[component]
+pub fn PrincipalImport() -> impl IntoView {
+    let auth = use_authorization();
+    let alert = use_alerts();
+    let schemas = expect_context::<Arc<Schemas>>();
+    let principal_form = store_value(schemas.build_form("principals"));
+    let mut data = schemas.build_form("principal-import");
+    data.apply_defaults(false);
+    let data = data.into_signal();
+
+    let step = create_rw_signal(ImportStep::default());
+    let headers: RwSignal<Vec<String>> = create_rw_signal(vec![]);
+    let mapping: RwSignal<Vec<ImportField>> = create_rw_signal(vec![]);
+    let csv_rows: StoredValue<Vec<(usize, Vec<String>)>> = store_value(vec![]);
+    let rows: RwSignal<Arc<Vec<ImportRow>>> = create_rw_signal(Arc::new(vec![]));
+    let results: RwSignal<Vec<Option<ImportOutcome>>> = create_rw_signal(vec![]);
+    let (pending, set_pending) = create_signal(false);
+
+    create_effect(move |_| {
+        let step = step.get();
+        data.update(|data| {
+            data.set("step", step.id());
+        });
+    });
+
+    let dry_run = create_action(move |records: &Arc<Vec<ImportRecord>>| {
+        let records = records.clone();
+        let auth = auth.get();
+        let (default_type, update_existing) = data.with_untracked(|data| {
+            (
+                data.value::<PrincipalType>("type")
+                    .unwrap_or(PrincipalType::Individual),
+                data.value::<String>("existing").unwrap_or_default() == "update",
+            )
+        });
+
+        async move {
+            set_pending.set(true);
+            let result = HttpRequest::get("/api/principal")
+                .with_authorization(&auth)
+                .with_parameter(
+                    "types",
+                    IMPORT_TYPES
+                        .iter()
+                        .map(|typ| typ.id())
+                        .collect::<Vec<_>>()
+                        .join(","),
+                )
+                .with_parameter("fields", "name")
+                .send::<List<Principal>>()
+                .await;
+            set_pending.set(false);
+
+            match result {
+                Ok(existing) => {
+                    let existing = existing
+                        .items
+                        .into_iter()
+                        .filter_map(|principal| principal.name.try_unwrap_string())
+                        .collect::<AHashSet<_>>();
+                    let form = principal_form.get_value();
+                    rows.set(Arc::new(build_rows(
+                        &form,
+                        &records,
+                        default_type,
+                        update_existing,
+                        &existing,
+                    )));
+                    step.set(ImportStep::Review);
+                }
+                Err(http::Error::Unauthorized) => {
+                    use_navigate()("/login", Default::default());
+                }
+                Err(err) => {
+                    alert.set(Alert::from(err));
+                }
+            }
+        }
+    });
+
+    let parse_source = move || {
+        let (is_ldif, contents) = data.with_untracked(|data| {
+            (
+                data.value::<String>("format").unwrap_or_default() == "ldif",
+                data.value::<String>("contents").unwrap_or_default(),
+            )
+        });
+
+        if is_ldif {
+            let records = parse_ldif(&contents)
+                .into_iter()
+                .map(|(line, attributes)| ImportRecord {
+                    line,
+                    values: map_ldif_entry(&attributes),
+                })
+                .collect::<Vec<_>>();
+            if !records.is_empty() {
+                dry_run.dispatch(Arc::new(records));
+            } else {
+                alert.set(Alert::error("No LDIF entries were found."));
+            }
+        } else {
+            let mut records = parse_csv(&contents).into_iter();
+            match records.next() {
+                Some((_, columns)) if records.len() > 0 => {
+                    mapping.set(columns.iter().map(|c| ImportField::guess(c)).collect());
+                    headers.set(columns);
+                    csv_rows.set_value(records.collect());
+                    step.set(ImportStep::Mapping);
+                }
+                _ => {
+                    alert.set(Alert::error(
+                        "The CSV file must contain a header row followed by at least one record.",
+                    ));
+                }
+            }
+        }
+    };
+
+    let start_import = move || {
+        let rows_ = rows.get_untracked();
+        let concurrency = data
+            .with_untracked(|data| data.value::<usize>("concurrency"))
+            .unwrap_or(4);
+        let queue = rows_
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| matches!(row.action, ImportAction::Create | ImportAction::Update))
+            .map(|(idx, _)| idx)
+            .collect::<Vec<_>>();
+        results.set(
+            rows_
+                .iter()
+                .map(|row| match row.action {
+                    ImportAction::Skip => Some(ImportOutcome::Skipped),
+                    ImportAction::Invalid => Some(ImportOutcome::Failed(row.errors.join("; "))),
+                    ImportAction::Create | ImportAction::Update => None,
+                })
+                .collect(),
+        );
+        step.set(ImportStep::Import);
+
+        let auth = auth.get_untracked();
+        spawn_workers(queue, concurrency, move |idx| {
+            let rows = rows_.clone();
+            let auth = auth.clone();
+
+            async move {
+                let outcome = import_row(&auth, &rows[idx]).await;
+                results
+                    .try_update(|results| results[idx] = Some(outcome))
+                    .is_some()
+            }
+        });
+    };
+
+    let total_completed = create_memo(move |_| results.with(|r| r.iter().flatten().count()));
+    let is_finished = create_memo(move |_| {
+        results.with(|r| !r.is_empty() && r.iter().all(|result| result.is_some()))
+    });
+    let total_actions = create_memo(move |_| {
+        rows.with(|rows| {
+            rows.iter()
+                .filter(|row| matches!(row.action, ImportAction::Create | ImportAction::Update))
+                .count()
+        })
+    });
+
+    view! {
+        <Form
+            title="Import Principals"
+            subtitle=Signal::derive(move || step.get().subtitle().to_string())
+        >
+
+            <StepProgress
+                steps=ImportStep::TITLES
+                current=Signal::derive(move || step.get() as usize)
+            />
+
+            // Source
+            <FormSection hide=Signal::derive(move || step.get() != ImportStep::Source)>
+                <FormItem label="Format">
+                    <Select element=FormElement::new("format", data)/>
+                </FormItem>
+                <FormItem label="Contents">
+                    <TextArea
+                        element=FormElement::new("contents", data)
+                        placeholder=create_memo(move |_| {
+                            if data.get().value::<String>("format").unwrap_or_default() == "ldif" {
+                                "dn: uid=jdoe,ou=people,dc=example,dc=org\nobjectClass: inetOrgPerson\nuid: jdoe\nmail: jdoe@example.org"
+                            } else {
+                                "name,email,description,quota,password\njdoe,jdoe@example.org,John Doe,1073741824,{SSHA}..."
+                            }
+                                .to_string()
+                        })
+                    />
+
+                </FormItem>
+                <FormItem label="Default type">
+                    <Select element=FormElement::new("type", data)/>
+                </FormItem>
+                <FormItem label="Existing principals">
+                    <Select element=FormElement::new("existing", data)/>
+                </FormItem>
+                <FormItem
+                    label="Concurrent requests"
+                    tooltip="Maximum number of principals to create or update at the same time"
+                >
+                    <InputText element=FormElement::new("concurrency", data)/>
+                </FormItem>
+            </FormSection>
+
+            // Column mapping
+            <FormSection hide=Signal::derive(move || step.get() != ImportStep::Mapping)>
+                <div class="sm:col-span-12">
+                    <Table headers=vec![
+                        "Column".to_string(),
+                        "Sample".to_string(),
+                        "Field".to_string(),
+                    ]>
+                        {move || {
+                            let sample = csv_rows
+                                .with_value(|rows| {
+                                    rows.first().map(|(_, row)| row.clone()).unwrap_or_default()
+                                });
+                            headers
+                                .get()
+                                .into_iter()
+                                .enumerate()
+                                .map(|(idx, header)| {
+                                    let selected = mapping
+                                        .with_untracked(|m| {
+                                            m.get(idx).copied().unwrap_or(ImportField::Ignore)
+                                        });
+                                    let sample = sample.get(idx).cloned().unwrap_or_default();
+                                    view! {
+                                        <TableRow>
+                                            <span>{header}</span>
+                                            <span class="text-gray-500">{sample}</span>
+                                            <select
+                                                class="py-2 px-3 pe-9 block w-full border-gray-200 rounded-lg text-sm focus:border-blue-500 focus:ring-blue-500 dark:bg-slate-900 dark:border-gray-700 dark:text-gray-400 dark:focus:ring-gray-600"
+                                                on:change=move |ev| {
+                                                    let field = ImportField::from_id(
+                                                        &event_target_value(&ev),
+                                                    );
+                                                    mapping
+                                                        .update(|m| {
+                                                            if let Some(m) = m.get_mut(idx) {
+                                                                *m = field;
+                                                            }
+                                                        });
+                                                }
+                                            >
+
+                                                {ImportField::ALL
+                                                    .iter()
+                                                    .map(|field| {
+                                                        view! {
+                                                            <option
+                                                                selected=*field == selected
+                                                                value=field.id()
+                                                            >
+                                                                {field.label()}
+                                                            </option>
+                                                        }
+                                                    })
+                                                    .collect_view()}
+                                            </select>
+                                        </TableRow>
+                                    }
+                                })
+                                .collect_view()
+                        }}
+
+                    </Table>
+                </div>
+            </FormSection>
+
+            // Dry-run report
+            <FormSection hide=Signal::derive(move || step.get() != ImportStep::Review)>
+                <div class="sm:col-span-12">
+                    {move || {
+                        let rows = rows.get();
+                        let count = |action: ImportAction| {
+                            rows.iter().filter(|row| row.action == action).count()
+                        };
+                        let summary = format!(
+                            "{} will be created, {} updated and {} skipped. {} contain errors and will not be imported.",
+                            maybe_plural(count(ImportAction::Create), "principal", "principals"),
+                            count(ImportAction::Update),
+                            count(ImportAction::Skip),
+                            maybe_plural(count(ImportAction::Invalid), "row", "rows"),
+                        );
+                        let hashed = rows
+                            .iter()
+                            .filter(|row| row.action != ImportAction::Invalid)
+                            .filter_map(|row| row.password.as_deref())
+                            .filter(|password| is_hashed_secret(password))
+                            .count();
+                        let plain = rows
+                            .iter()
+                            .filter(|row| row.action != ImportAction::Invalid)
+                            .filter(|row| row.password.is_some())
+                            .count() - hashed;
+                        let scheme = password_policy()
+                            .map(|policy| policy.hash.description())
+                            .unwrap_or_else(|_| "the configured scheme".to_string());
+                        view! {
+                            <p class="mb-4 text-sm text-gray-600 dark:text-gray-400">
+                                {summary}
+                                {(hashed + plain > 0)
+                                    .then(|| {
+                                        format!(
+                                            " {hashed} of the imported passwords are already hashed, the remaining {plain} will be hashed using {scheme}.",
+                                        )
+                                    })}
+
+                            </p>
+                            <Table headers=vec![
+                                "Line".to_string(),
+                                "Name".to_string(),
+                                "Type".to_string(),
+                                "Action".to_string(),
+                                "Details".to_string(),
+                            ]>
+                                {rows
+                                    .iter()
+                                    .cloned()
+                                    .map(|row| {
+                                        let (color, label) = row.action.badge();
+                                        view! {
+                                            <TableRow>
+                                                <span>{row.line}</span>
+                                                <span>{row.principal.name_or_empty()}</span>
+                                                <span>
+                                                    {row.principal.typ.unwrap_or_default().item_name(false)}
+                                                </span>
+                                                <Badge color=color>{label}</Badge>
+                                                <span>{row.details()}</span>
+                                            </TableRow>
+                                        }
+                                    })
+                                    .collect_view()}
+                            </Table>
+                        }
+                    }}
+
+                </div>
+            </FormSection>
+
+            // Import progress and results
+            <FormSection hide=Signal::derive(move || step.get() != ImportStep::Import)>
+                <div class="sm:col-span-12">
+                    <p class="mb-4 text-sm text-gray-600 dark:text-gray-400">
+                        {move || {
+                            if is_finished.get() {
+                                "Import completed.".to_string()
+                            } else {
+                                format!(
+                                    "Processed {} of {} rows...",
+                                    total_completed.get(),
+                                    results.with(|r| r.len()),
+                                )
+                            }
+                        }}
+
+                    </p>
+                    <Table headers=vec![
+                        "Line".to_string(),
+                        "Name".to_string(),
+                        "Result".to_string(),
+                        "Details".to_string(),
+                    ]>
+                        <For
+                            each=move || 0..rows.with(|rows| rows.len())
+                            key=|idx| *idx
+                            children=move |idx| {
+                                let row = rows.with_untracked(|rows| rows[idx].clone());
+                                let outcome = create_memo(move |_| {
+                                    results.with(|r| r.get(idx).cloned().flatten())
+                                });
+                                view! {
+                                    <TableRow>
+                                        <span>{row.line}</span>
+                                        <span>{row.principal.name_or_empty()}</span>
+                                        {move || {
+                                            let (color, label) = outcome
+                                                .get()
+                                                .map(|outcome| outcome.badge())
+                                                .unwrap_or((Color::Gray, "Pending"));
+                                            view! { <Badge color=color>{label}</Badge> }
+                                        }}
+
+                                        <span>
+                                            {move || {
+                                                outcome
+                                                    .get()
+                                                    .map(|outcome| outcome.details())
+                                                    .unwrap_or_default()
+                                            }}
+
+                                        </span>
+                                    </TableRow>
+                                }
+                            }
+                        />
+
+                    </Table>
+                </div>
+            </FormSection>
+
+            <FormButtonBar>
+                {move || match step.get() {
+                    ImportStep::Source => {
+                        view! {
+                            <Button
+                                text="Cancel"
+                                color=Color::Gray
+                                on_click=move |_| {
+                                    use_navigate()("/manage/directory/accounts", Default::default());
+                                }
+                            />
+
+                            <Button
+                                text="Next"
+                                color=Color::Blue
+                                on_click=move |_| {
+                                    if data.try_update(|data| data.validate_form()).unwrap_or_default()
+                                    {
+                                        parse_source();
+                                    }
+                                }
+
+                                disabled=pending
+                            />
+                        }
+                            .into_view()
+                    }
+                    ImportStep::Mapping => {
+                        view! {
+                            <Button
+                                text="Back"
+                                color=Color::Gray
+                                on_click=move |_| {
+                                    step.set(ImportStep::Source);
+                                }
+                            />
+
+                            <Button
+                                text="Validate"
+                                color=Color::Blue
+                                on_click=move |_| {
+                                    let mapping = mapping.get_untracked();
+                                    if mapping.contains(&ImportField::Name) {
+                                        let records = csv_rows
+                                            .with_value(|rows| {
+                                                rows.iter()
+                                                    .map(|(line, row)| ImportRecord {
+                                                        line: *line,
+                                                        values: mapping
+                                                            .iter()
+                                                            .copied()
+                                                            .zip(row.iter().cloned())
+                                                            .filter(|(field, _)| *field != ImportField::Ignore)
+                                                            .collect(),
+                                                    })
+                                                    .collect::<Vec<_>>()
+                                            });
+                                        dry_run.dispatch(Arc::new(records));
+                                    } else {
+                                        alert
+                                            .set(
+                                                Alert::error(
+                                                    "Please map one of the columns to the principal name.",
+                                                ),
+                                            );
+                                    }
+                                }
+
+                                disabled=pending
+                            />
+                        }
+                            .into_view()
+                    }
+                    ImportStep::Review => {
+                        view! {
+                            <Button
+                                text="Back"
+                                color=Color::Gray
+                                on_click=move |_| {
+                                    step.set(
+                                        if headers.get_untracked().is_empty()
+                                            || data
+                                                .get_untracked()
+                                                .value::<String>("format")
+                                                .unwrap_or_default() == "ldif"
+                                        {
+                                            ImportStep::Source
+                                        } else {
+                                            ImportStep::Mapping
+                                        },
+                                    );
+                                }
+                            />
+
+                            <Button
+                                text=Signal::derive(move || {
+                                    format!(
+                                        "Import {}",
+                                        maybe_plural(total_actions.get(), "principal", "principals"),
+                                    )
+                                })
+
+                                color=Color::Blue
+                                on_click=move |_| {
+                                    start_import();
+                                }
+
+                                disabled=Signal::derive(move || total_actions.get() == 0)
+                            />
+                        }
+                            .into_view()
+                    }
+                    ImportStep::Import => {
+                        view! {
+                            <a
+                                class="py-2 px-3 inline-flex items-center gap-x-2 text-sm font-medium rounded-lg border border-gray-200 bg-white text-gray-800 shadow-sm hover:bg-gray-50 dark:bg-slate-900 dark:border-gray-700 dark:text-white dark:hover:bg-gray-800"
+                                class:pointer-events-none=move || !is_finished.get()
+                                class:opacity-50=move || !is_finished.get()
+                                download="import-results.csv"
+                                href=move || {
+                                    if is_finished.get() {
+                                        format!(
+                                            "data:text/csv;base64,{}",
+                                            STANDARD
+                                                .encode(
+                                                    results.with(|results| {
+                                                        rows.with(|rows| results_csv(rows, results))
+                                                    }),
+                                                ),
+                                        )
+                                    } else {
+                                        "#".to_string()
+                                    }
+                                }
+                            >
+
+                                "Download results"
+                            </a>
+
+                            <Button
+                                text="Done"
+                                color=Color::Blue
+                                on_click=move |_| {
+                                    use_navigate()("/manage/directory/accounts", Default::default());
+                                }
+
+                                disabled=Signal::derive(move || !is_finished.get())
+                            />
+                        }
+                            .into_view()
+                    }
+                }}
+
+            </FormButtonBar>
+        </Form>
+    }
+}
+
+fn build_rows(
+    form: &FormData,
+    records: &[ImportRecord],
+    default_type: PrincipalType,
+    update_existing: bool,
+    existing: &AHashSet<String>,
+) -> Vec<ImportRow> {
+    let mut seen: AHashMap<String, usize> = AHashMap::new();
+
+    records
+        .iter()
+        .map(|record| {
+            let mut row = build_row(form, record, default_type);
+            let name = row.principal.name_or_empty();
+
+            if !row.errors.is_empty() {
+                row.action = ImportAction::Invalid;
+            } else if let Some(line) = seen.get(&name) {
+                row.errors.push(format!("Duplicate of line {line}"));
+                row.action = ImportAction::Invalid;
+            } else if existing.contains(&name) {
+                row.action = if update_existing {
+                    ImportAction::Update
+                } else {
+                    ImportAction::Skip
+                };
+            }
+            seen.entry(name).or_insert(record.line);
+
+            row
+        })
+        .collect()
+}
+
+fn build_row(form: &FormData, record: &ImportRecord, default_type: PrincipalType) -> ImportRow {
+    let mut data = form.clone();
+    let mut row = ImportRow {
+        line: record.line,
+        principal: Principal {
+            typ: Some(default_type),
+            ..Default::default()
+        },
+        password: None,
+        errors: vec![],
+        action: ImportAction::Create,
+    };
+    let mut aliases = vec![];
+    let mut lists: AHashMap<&str, Vec<String>> = AHashMap::new();
+
+    for (field, value) in &record.values {
+        let value = value.trim();
+        if value.is_empty() {
+            continue;
+        }
+
+        match field {
+            ImportField::Name => data.set("name", value),
+            ImportField::Description => data.set("description", value),
+            ImportField::Email if !data.has_value("email") => data.set("email", value),
+            ImportField::Email | ImportField::Aliases => {
+                aliases.extend(split_list(value));
+            }
+            ImportField::Type => match parse_type(value) {
+                Some(typ) => {
+                    row.principal.typ = Some(typ);
+                }
+                None => {
+                    row.errors
+                        .push(format!("Unsupported principal type {value:?}"));
+                }
+            },
+            ImportField::Quota => match parse_size(value) {
+                Some(quota) => {
+                    row.principal.quota = PrincipalValue::Integer(quota);
+                }
+                None => {
+                    row.errors.push(format!("Invalid quota {value:?}"));
+                }
+            },
+            ImportField::Password => {
+                // Plaintext passwords go through the same policy check as the edit form
+                if !is_hashed_secret(value) {
+                    data.set("password", value);
+                }
+                row.password = Some(value.to_string());
+            }
+            ImportField::MemberOf
+            | ImportField::Members
+            | ImportField::Roles
+            | ImportField::Lists => {
+                lists
+                    .entry(field.id())
+                    .or_default()
+                    .extend(split_list(value));
+            }
+            ImportField::Ignore => {}
+        }
+    }
+    if !aliases.is_empty() {
+        data.array_set("aliases", aliases);
+    }
+
+    // Apply the same checks used when editing a principal
+    if data.validate_form() {
+        row.principal.name = PrincipalValue::String(data.value("name").unwrap_or_default());
+        row.principal.description =
+            PrincipalValue::String(data.value("description").unwrap_or_default());
+        let emails = data
+            .value::<String>("email")
+            .into_iter()
+            .chain(data.array_value("aliases").map(|m| m.to_string()))
+            .collect::<Vec<_>>();
+        if !emails.is_empty() {
+            row.principal.emails = PrincipalValue::StringList(emails);
+        }
+    } else {
+        let mut errors = data
+            .errors
+            .iter()
+            .map(|(id, err)| format!("{id}: {}", err.error))
+            .collect::<Vec<_>>();
+        errors.sort();
+        row.errors.extend(errors);
+        row.principal.name = PrincipalValue::String(
+            record
+                .values
+                .iter()
+                .find(|(field, _)| *field == ImportField::Name)
+                .map(|(_, value)| value.trim().to_string())
+                .unwrap_or_default(),
+        );
+    }
+
+    for (id, list) in lists {
+        let list = PrincipalValue::StringList(list);
+        match id {
+            "member-of" => row.principal.member_of = list,
+            "members" => row.principal.members = list,
+            "roles" => row.principal.roles = list,
+            _ => row.principal.lists = list,
+        }
+    }
+
+    row
+}
+
+async fn import_row(auth: &AccessToken, row: &ImportRow) -> ImportOutcome {
+    let scheme = match load_password_policy(auth).await {
+        Ok(policy) => policy.hash,
+        Err(err) => return ImportOutcome::Failed(describe_error(err)),
+    };
+    let secret = row.password.as_deref().map(|password| {
+        if is_hashed_secret(password) {
+            password.to_string()
+        } else {
+            scheme.hash(password)
+        }
+    });
+
+    match row.action {
+        ImportAction::Create => {
+            let mut principal = row.principal.clone();
+            if let Some(secret) = secret {
+                principal.secrets = PrincipalValue::StringList(vec![secret]);
+            }
+
+            match HttpRequest::post("/api/principal")
+                .with_authorization(auth)
+                .with_body(&principal)
+                .unwrap()
+                .send::<u32>()
+                .await
+            {
+                Ok(_) => ImportOutcome::Created,
+                Err(err) => ImportOutcome::Failed(describe_error(err)),
+            }
+        }
+        ImportAction::Update => {
+            let name = row.principal.name_or_empty();
+            let current = match HttpRequest::get(("/api/principal", &name))
+                .with_authorization(auth)
+                .send::<Principal>()
+                .await
+            {
+                Ok(current) => current,
+                Err(err) => return ImportOutcome::Failed(describe_error(err)),
+            };
+            let changes = merge_principal(&current, &row.principal, secret);
+            let updates = current.into_updates(changes);
+
+            if updates.is_empty() {
+                ImportOutcome::Unchanged
+            } else {
+                match HttpRequest::patch(("/api/principal", &name))
+                    .with_authorization(auth)
+                    .with_body(updates)
+                    .unwrap()
+                    .send::<serde_json::Value>()
+                    .await
+                {
+                    Ok(_) => ImportOutcome::Updated,
+                    Err(err) => ImportOutcome::Failed(describe_error(err)),
+                }
+            }
+        }
+        ImportAction::Skip => ImportOutcome::Skipped,
+        ImportAction::Invalid => ImportOutcome::Failed(row.errors.join("; ")),
+    }
+}
+
+// Only the fields present in the imported record replace the current ones.
+fn merge_principal(current: &Principal, imported: &Principal, secret: Option<String>) -> Principal {
+    let mut changes = current.clone();
+
+    if !imported.description.is_none() {
+        changes.description = imported.description.clone();
+    }
+    if imported.quota.as_int().is_some() {
+        changes.quota = imported.quota.clone();
+    }
+    for (change, value) in [
+        (&mut changes.emails, &imported.emails),
+        (&mut changes.member_of, &imported.member_of),
+        (&mut changes.members, &imported.members),
+        (&mut changes.roles, &imported.roles),
+        (&mut changes.lists, &imported.lists),
+    ] {
+        if value.len() > 0 {
+            *change = value.clone();
+        }
+    }
+    if let Some(secret) = secret {
+        changes.secrets = PrincipalValue::StringList(
+            current
+                .secrets
+                .as_string_list()
+                .iter()
+                .filter(|secret| !secret.is_password())
+                .cloned()
+                .chain([secret])
+                .collect(),
+        );
+    }
+
+    changes
+}
+
+pub(super) fn parse_csv(contents: &str) -> Vec<(usize, Vec<String>)> {
+    let header = contents.lines().next().unwrap_or_default();
+    let delimiter = [',', ';', '\t']
+        .into_iter()
+        .max_by_key(|ch| header.matches(*ch).count())
+        .filter(|ch| header.contains(*ch))
+        .unwrap_or(',');
+
+    let mut records = vec![];
+    let mut record = vec![];
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut chars = contents.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' if in_quotes => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    in_quotes = false;
+                }
+            }
+            '"' if field.is_empty() => {
+                in_quotes = true;
+            }
+            '\n' if !in_quotes => {
+                record.push(std::mem::take(&mut field));
+                if record.iter().any(|field| !field.trim().is_empty()) {
+                    records.push((record_line, std::mem::take(&mut record)));
+                } else {
+                    record.clear();
+                }
+                line += 1;
+                record_line = line;
+            }
+            '\r' if !in_quotes => {}
+            ch if ch == delimiter && !in_quotes => {
+                record.push(std::mem::take(&mut field));
+            }
+            ch => {
+                if ch == '\n' {
+                    line += 1;
+                }
+                field.push(ch);
+            }
+        }
+    }
+    record.push(field);
+    if record.iter().any(|field| !field.trim().is_empty()) {
+        records.push((record_line, record));
+    }
+
+    records
+}
+
+fn parse_ldif(contents: &str) -> Vec<(usize, Vec<(String, String)>)> {
+    // Unfold continuation lines
+    let mut lines: Vec<(usize, String)> = vec![];
+    for (line_num, line) in contents.lines().enumerate() {
+        if let Some(continuation) = line.strip_prefix(' ') {
+            if let Some((_, last)) = lines.last_mut().filter(|(_, last)| !last.is_empty()) {
+                last.push_str(continuation);
+                continue;
+            }
+        }
+        lines.push((line_num + 1, line.trim_end().to_string()));
+    }
+
+    let mut entries = vec![];
+    let mut entry: Vec<(String, String)> = vec![];
+    let mut entry_line = 0;
+
+    for (line_num, line) in lines {
+        if line.is_empty() {
+            if !entry.is_empty() {
+                entries.push((entry_line, std::mem::take(&mut entry)));
+            }
+            continue;
+        } else if line.starts_with('#') {
+            continue;
+        }
+
+        if let Some((attribute, value)) = line.split_once(':') {
+            let attribute = attribute
+                .split_once(';')
+                .map_or(attribute, |(attribute, _)| attribute)
+                .trim()
+                .to_lowercase();
+            let value = if let Some(value) = value.strip_prefix(':') {
+                match STANDARD
+                    .decode(value.trim())
+                    .ok()
+                    .and_then(|value| String::from_utf8(value).ok())
+                {
+                    Some(value) => value,
+                    None => {
+                        log::warn!("Ignoring invalid base64 value in LDIF line {line_num}.");
+                        continue;
+                    }
+                }
+            } else if value.starts_with('<') {
+                log::warn!("Ignoring URL reference in LDIF line {line_num}.");
+                continue;
+            } else {
+                value.trim().to_string()
+            };
+
+            if attribute != "version" || !entry.is_empty() {
+                if entry.is_empty() {
+                    entry_line = line_num;
+                }
+                entry.push((attribute, value));
+            }
+        }
+    }
+    if !entry.is_empty() {
+        entries.push((entry_line, entry));
+    }
+
+    entries
+}
+
+fn map_ldif_entry(attributes: &[(String, String)]) -> Vec<(ImportField, String)> {
+    let values = |names| ldif_values(attributes, names);
+    let first = |names| ldif_values(attributes, names).next();
+
+    let is_group = values(&["objectclass"]).any(|class| {
+        matches!(
+            class.to_lowercase().as_str(),
+            "groupofnames" | "groupofuniquenames" | "posixgroup" | "group"
+        )
+    });
+    let is_individual = values(&["objectclass"]).any(|class| {
+        matches!(
+            class.to_lowercase().as_str(),
+            "inetorgperson" | "posixaccount" | "person" | "organizationalperson" | "user"
+        )
+    });
+
+    let mut result = vec![];
+    if is_individual {
+        result.push((ImportField::Type, "individual".to_string()));
+    } else if is_group {
+        result.push((ImportField::Type, "group".to_string()));
+    }
+
+    let name = first(&["uid", "samaccountname"])
+        .filter(|_| is_individual || !is_group)
+        .or_else(|| first(&["cn"]))
+        .or_else(|| first(&["dn"]).map(dn_name));
+    if let Some(name) = name {
+        result.push((ImportField::Name, name.to_string()));
+    }
+    if let Some(description) = first(&["displayname"])
+        .or_else(|| first(&["cn"]).filter(|cn| Some(*cn) != name))
+        .or_else(|| first(&["description"]))
+    {
+        result.push((ImportField::Description, description.to_string()));
+    }
+
+    for (names, field, map) in [
+        (&["mail"][..], ImportField::Email, None),
+        (
+            &["mailalternateaddress", "maillocaladdress"][..],
+            ImportField::Aliases,
+            None,
+        ),
+        (&["userpassword"][..], ImportField::Password, None),
+        (&["mailquota", "quota"][..], ImportField::Quota, None),
+        (
+            &["memberof"][..],
+            ImportField::MemberOf,
+            Some(dn_name as fn(&str) -> &str),
+        ),
+        (
+            &["member", "uniquemember", "memberuid"][..],
+            ImportField::Members,
+            Some(dn_name as fn(&str) -> &str),
+        ),
+    ] {
+        for value in values(names) {
+            result.push((field, map.map_or(value, |map| map(value)).to_string()));
+        }
+    }
+    for value in values(&["proxyaddresses"]) {
+        if let Some(address) = value
+            .strip_prefix("smtp:")
+            .or_else(|| value.strip_prefix("SMTP:"))
+        {
+            result.push((ImportField::Aliases, address.to_string()));
+        }
+    }
+
+    result
+}
+
+fn ldif_values<'x>(
+    attributes: &'x [(String, String)],
+    names: &'x [&'x str],
+) -> impl Iterator<Item = &'x str> + 'x {
+    attributes
+        .iter()
+        .filter(move |(attribute, _)| names.contains(&attribute.as_str()))
+        .map(|(_, value)| value.as_str())
+}
+
+// Returns the value of the first RDN, e.g. "cn=staff,ou=groups" becomes "staff".
+fn dn_name(dn: &str) -> &str {
+    dn.split(',')
+        .next()
+        .and_then(|rdn| rdn.split_once('='))
+        .map_or(dn, |(_, value)| value.trim())
+}
+
+fn split_list(value: &str) -> impl Iterator<Item = String> + '_ {
+    value
+        .split([',', ';', '|', ' ', '\t'])
+        .map(|item| item.trim())
+        .filter(|item| !item.is_empty())
+        .map(|item| item.to_string())
+}
+
+fn parse_type(value: &str) -> Option<PrincipalType> {
+    match value.to_lowercase().as_str() {
+        "individual" | "account" | "user" => Some(PrincipalType::Individual),
+        "group" => Some(PrincipalType::Group),
+        "list" | "mailinglist" | "mailing list" => Some(PrincipalType::List),
+        _ => None,
+    }
+}
+
+pub(super) fn parse_size(value: &str) -> Option<u64> {
+    let value = value.trim().to_lowercase();
+    let value = value.strip_suffix('b').unwrap_or(&value);
+    let (number, multiplier) = match value.char_indices().last()? {
+        (pos, 'k') => (&value[..pos], 1024),
+        (pos, 'm') => (&value[..pos], 1024 * 1024),
+        (pos, 'g') => (&value[..pos], 1024 * 1024 * 1024),
+        (pos, 't') => (&value[..pos], 1024 * 1024 * 1024 * 1024),
+        _ => (value, 1),
+    };
+
+    number.trim().parse::<u64>().ok()?.checked_mul(multiplier)
+}
+
+pub(super) fn describe_error(err: http::Error) -> String {
+    match err {
+        http::Error::Unauthorized => "Unauthorized".to_string(),
+        http::Error::Forbidden => "Forbidden".to_string(),
+        http::Error::NotFound => "Not found".to_string(),
+        http::Error::TotpRequired => "TOTP code required".to_string(),
+        http::Error::Network(details) => format!("Network error: {details}"),
+        http::Error::Serializer { error, .. } => {
+            format!("Failed to deserialize response: {error}")
+        }
+        http::Error::Server(error) => match error {
+            ManagementApiError::FieldAlreadyExists { field, value } => {
+                format!("Another record exists with value {value:?} in field {field:?}")
+            }
+            ManagementApiError::FieldMissing { field } => format!("Field {field} is missing"),
+            ManagementApiError::NotFound { item } => format!("{item} was not found"),
+            ManagementApiError::Unsupported { details } => details,
+            ManagementApiError::AssertFailed => "Assertion failed".to_string(),
+            ManagementApiError::Other { details, reason } => match reason {
+                Some(reason) => format!("{details}: {reason}"),
+                None => details,
+            },
+        },
+    }
+}
+
+fn results_csv(rows: &[ImportRow], results: &[Option<ImportOutcome>]) -> String {
+    let mut csv = "line,name,result,details\n".to_string();
+    for (row, result) in rows.iter().zip(results) {
+        let (result, details) = result
+            .as_ref()
+            .map(|result| (result.badge().1, result.details()))
+            .unwrap_or(("Pending", String::new()));
+        csv.push_str(&format!(
+            "{},{},{},{}\n",
+            row.line,
+            csv_escape(&row.principal.name_or_empty()),
+            result,
+            csv_escape(&details)
+        ));
+    }
+    csv
+}
+
+pub(super) fn csv_escape(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+impl ImportStep {
+    const TITLES: &'static [&'static str] = &["Source", "Mapping", "Review", "Import"];
+
+    fn id(&self) -> &'static str {
+        match self {
+            ImportStep::Source => "source",
+            ImportStep::Mapping => "mapping",
+            ImportStep::Review => "review",
+            ImportStep::Import => "import",
+        }
+    }
+
+    fn subtitle(&self) -> &'static str {
+        match self {
+            ImportStep::Source => "Paste the CSV or LDIF file containing the principals to import",
+            ImportStep::Mapping => "Map the CSV columns to principal fields",
+            ImportStep::Review => "Review the changes before importing",
+            ImportStep::Import => "Creating and updating principals",
+        }
+    }
+}
+
+impl ImportField {
+    const ALL: &'static [ImportField] = &[
+        ImportField::Ignore,
+        ImportField::Name,
+        ImportField::Type,
+        ImportField::Email,
+        ImportField::Aliases,
+        ImportField::Description,
+        ImportField::Quota,
+        ImportField::Password,
+        ImportField::MemberOf,
+        ImportField::Members,
+        ImportField::Roles,
+        ImportField::Lists,
+    ];
+
+    fn id(&self) -> &'static str {
+        match self {
+            ImportField::Ignore => "",
+            ImportField::Name => "name",
+            ImportField::Type => "type",
+            ImportField::Email => "email",
+            ImportField::Aliases => "aliases",
+            ImportField::Description => "description",
+            ImportField::Quota => "quota",
+            ImportField::Password => "password",
+            ImportField::MemberOf => "member-of",
+            ImportField::Members => "members",
+            ImportField::Roles => "roles",
+            ImportField::Lists => "lists",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            ImportField::Ignore => "-- Ignore --",
+            ImportField::Name => "Login name",
+            ImportField::Type => "Type",
+            ImportField::Email => "Email address",
+            ImportField::Aliases => "Email aliases",
+            ImportField::Description => "Name",
+            ImportField::Quota => "Disk quota",
+            ImportField::Password => "Password",
+            ImportField::MemberOf => "Member of",
+            ImportField::Members => "Members",
+            ImportField::Roles => "Roles",
+            ImportField::Lists => "Mailing lists",
+        }
+    }
+
+    fn from_id(id: &str) -> Self {
+        ImportField::ALL
+            .iter()
+            .copied()
+            .find(|field| field.id() == id)
+            .unwrap_or(ImportField::Ignore)
+    }
+
+    fn guess(header: &str) -> Self {
+        match header
+            .trim()
+            .to_lowercase()
+            .replace([' ', '_', '-'], "")
+            .as_str()
+        {
+            "name" | "uid" | "login" | "username" | "account" | "samaccountname" => {
+                ImportField::Name
+            }
+            "type" | "kind" => ImportField::Type,
+            "email" | "mail" | "emailaddress" | "primaryemail" => ImportField::Email,
+            "aliases" | "alias" | "emails" | "mailalternateaddress" | "othermails" => {
+                ImportField::Aliases
+            }
+            "description" | "displayname" | "fullname" | "cn" | "commonname" => {
+                ImportField::Description
+            }
+            "quota" | "mailquota" | "diskquota" => ImportField::Quota,
+            "password" | "userpassword" | "secret" | "passwordhash" => ImportField::Password,
+            "memberof" | "groups" => ImportField::MemberOf,
+            "members" | "member" => ImportField::Members,
+            "roles" | "role" => ImportField::Roles,
+            "lists" | "mailinglists" => ImportField::Lists,
+            _ => ImportField::Ignore,
+        }
+    }
+}
+
+impl ImportAction {
+    fn badge(&self) -> (Color, &'static str) {
+        match self {
+            ImportAction::Create => (Color::Green, "Create"),
+            ImportAction::Update => (Color::Blue, "Update"),
+            ImportAction::Skip => (Color::Gray, "Skip"),
+            ImportAction::Invalid => (Color::Red, "Error"),
+        }
+    }
+}
+
+impl ImportRow {
+    fn details(&self) -> String {
+        if !self.errors.is_empty() {
+            self.errors.join("; ")
+        } else {
+            self.principal.emails.as_string_list().join(", ")
+        }
+    }
+}
+
+impl ImportOutcome {
+    fn badge(&self) -> (Color, &'static str) {
+        match self {
+            ImportOutcome::Created => (Color::Green, "Created"),
+            ImportOutcome::Updated => (Color::Blue, "Updated"),
+            ImportOutcome::Unchanged => (Color::Gray, "Unchanged"),
+            ImportOutcome::Skipped => (Color::Gray, "Skipped"),
+            ImportOutcome::Failed(_) => (Color::Red, "Failed"),
+        }
+    }
+
+    fn details(&self) -> String {
+        match self {
+            ImportOutcome::Failed(reason) => reason.clone(),
+            _ => String::new(),
+        }
+    }
+}
+
+impl Builder<Schemas, ()> {
+    pub fn build_principal_import(self) -> Self {
+        self.new_schema("principal-import")
+            .new_field("step")
+            .typ(Type::Input)
+            .build()
+            .new_field("format")
+            .typ(Type::Select {
+                typ: SelectType::Single,
+                source: Source::Static(&[("csv", "CSV"), ("ldif", "LDIF")]),
+            })
+            .default("csv")
+            .build()
+            .new_field("contents")
+            .typ(Type::Text)
+            .display_if_eq("step", ["source"])
+            .input_check([], [Validator::Required])
+            .build()
+            .new_field("type")
+            .typ(Type::Select {
+                typ: SelectType::Single,
+                source: Source::Static(&[
+                    ("individual", "Account"),
+                    ("group", "Group"),
+                    ("list", "Mailing list"),
+                ]),
+            })
+            .default("individual")
+            .build()
+            .new_field("existing")
+            .typ(Type::Select {
+                typ: SelectType::Single,
+                source: Source::Static(&[
+                    ("skip", "Skip existing principals"),
+                    ("update", "Update existing principals"),
+                ]),
+            })
+            .default("skip")
+            .build()
+            .new_field("concurrency")
+            .typ(Type::Input)
+            .display_if_eq("step", ["source"])
+            .input_check(
+                [Transformer::Trim],
+                [
+                    Validator::Required,
+                    Validator::MinValue(1.into()),
+                    Validator::MaxValue(16.into()),
+                ],
+            )
+            .default("4")
+            .build()
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(password: &str) -> ImportRow {
+        let form = crate::build_schemas().build_form("principals");
+        build_row(
+            &form,
+            &ImportRecord {
+                line: 1,
+                values: vec![
+                    (ImportField::Name, "jdoe".to_string()),
+                    (ImportField::Password, password.to_string()),
+                ],
+            },
+            PrincipalType::Individual,
+        )
+    }
+
+    #[test]
+    fn checks_plaintext_passwords() {
+        crate::core::password::set_password_policy(Default::default());
+        assert!(row("short")
+            .errors
+            .iter()
+            .any(|e| e.starts_with("password")));
+        assert!(row("correct horse battery").errors.is_empty());
+        assert!(row("$6$rounds=5000$salt$hash").errors.is_empty());
+    }
+}
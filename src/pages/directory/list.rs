@@ -7,6 +7,7 @@
 use std::sync::Arc;
 
 use ahash::AHashSet;
+use gloo_storage::{SessionStorage, Storage};
 use humansize::{format_size, DECIMAL};
 use leptos::*;
 use leptos_router::*;
@@ -14,7 +15,11 @@ use leptos_router::*;
 use crate::{
     components::{
         badge::Badge,
-        icon::{IconAdd, IconThreeDots, IconTrash},
+        icon::{
+            IconAdd, IconAdjustmentsHorizontal, IconDocumentText, IconKey, IconSquare2x2,
+            IconThreeDots, IconTrash,
+        },
+        layout::tenant::use_tenant_scope,
         list::{
             header::ColumnList,
             pagination::Pagination,
@@ -34,11 +39,16 @@ use crate::{
         http::{self, HttpRequest},
         oauth::use_authorization,
         url::UrlBuilder,
+        Permission,
     },
     pages::{
-        directory::{Principal, PrincipalType},
+        directory::{
+            api_key::clear_lifetimes, bulk::BulkSelection, export::export_url, Principal,
+            PrincipalType,
+        },
         maybe_plural, List,
     },
+    BULK_SELECTION_STORAGE_KEY,
 };
 
 const PAGE_SIZE: u32 = 10;
@@ -65,6 +75,9 @@ pub fn PrincipalList() -> impl IntoView {
             "roles" => PrincipalType::Role,
             "api-keys" => PrincipalType::ApiKey,
             "oauth-clients" => PrincipalType::OauthClient,
+            "resources" => PrincipalType::Resource,
+            "locations" => PrincipalType::Location,
+            "other" => PrincipalType::Other,
             _ => PrincipalType::Individual,
         }
     });
@@ -92,10 +105,17 @@ pub fn PrincipalList() -> impl IntoView {
     let auth = use_authorization();
     let alert = use_alerts();
     let modal = use_modals();
+    let tenant_scope = use_tenant_scope();
+
+    let tenant = create_memo(move |_| {
+        tenant_scope
+            .get()
+            .filter(|_| selected_type.get() != PrincipalType::Tenant)
+    });
 
     let principals = create_resource(
-        move || (page.get(), filter.get()),
-        move |(page, filter)| {
+        move || (page.get(), filter.get(), tenant.get()),
+        move |(page, filter, tenant)| {
             let auth = auth.get_untracked();
             let selected_type = selected_type.get();
 
@@ -106,6 +126,7 @@ pub fn PrincipalList() -> impl IntoView {
                     .with_parameter("limit", PAGE_SIZE.to_string())
                     .with_parameter("types", selected_type.id())
                     .with_optional_parameter("filter", filter)
+                    .with_optional_parameter("tenant", tenant)
                     .send::<List<Principal>>()
                     .await
             }
@@ -113,6 +134,19 @@ pub fn PrincipalList() -> impl IntoView {
     );
 
     let total_results = create_rw_signal(None::<u32>);
+    let listed = create_rw_signal(Vec::<String>::new());
+    let total_selected = move || {
+        selected
+            .get()
+            .scoped(tenant.get().is_some(), &listed.get())
+            .total_selected(total_results.get())
+    };
+    let take_selection = move || {
+        selected
+            .try_update(std::mem::take)
+            .unwrap_or_default()
+            .scoped(tenant.get_untracked().is_some(), &listed.get_untracked())
+    };
     let delete_action = create_action(move |items: &Arc<ItemSelection>| {
         let items = items.clone();
         let auth = auth.get();
@@ -131,6 +165,12 @@ pub fn PrincipalList() -> impl IntoView {
                             return;
                         }
                     }
+                    if selected_type.get() == PrincipalType::ApiKey {
+                        let names = items.iter().cloned().collect::<Vec<_>>();
+                        if let Err(err) = clear_lifetimes(&auth, &names).await {
+                            log::error!("Failed to clear API key lifetimes: {err:?}");
+                        }
+                    }
                     principals.refetch();
                     alert.set(Alert::success(format!(
                         "Deleted {}.",
@@ -142,6 +182,30 @@ pub fn PrincipalList() -> impl IntoView {
                     )));
                 }
                 ItemSelection::All => {
+                    // The names are collected first as the keys are gone after the delete
+                    let api_keys = if selected_type.get() == PrincipalType::ApiKey {
+                        match HttpRequest::get("/api/principal")
+                            .with_authorization(&auth)
+                            .with_parameter("types", PrincipalType::ApiKey.id())
+                            .with_parameter("fields", "name")
+                            .with_optional_parameter("filter", filter.clone())
+                            .send::<List<Principal>>()
+                            .await
+                        {
+                            Ok(keys) => keys
+                                .items
+                                .iter()
+                                .filter_map(|key| key.name().map(|name| name.to_string()))
+                                .collect::<Vec<_>>(),
+                            Err(err) => {
+                                alert.set(Alert::from(err));
+                                return;
+                            }
+                        }
+                    } else {
+                        vec![]
+                    };
+
                     match HttpRequest::delete("/api/principal")
                         .with_authorization(&auth)
                         .with_parameter("type", selected_type.get().id())
@@ -151,6 +215,9 @@ pub fn PrincipalList() -> impl IntoView {
                         .await
                     {
                         Ok(_) => {
+                            if let Err(err) = clear_lifetimes(&auth, &api_keys).await {
+                                log::error!("Failed to clear API key lifetimes: {err:?}");
+                            }
                             let total = total_results.get_untracked();
                             principals.refetch();
                             alert.set(Alert::success(format!(
@@ -207,7 +274,9 @@ pub fn PrincipalList() -> impl IntoView {
             PrincipalType::Role => "Roles",
             PrincipalType::ApiKey => "API Keys",
             PrincipalType::OauthClient => "OAuth Clients",
-            _ => unreachable!("Invalid type."),
+            PrincipalType::Resource => "Resources",
+            PrincipalType::Location => "Locations",
+            PrincipalType::Other => "Other Principals",
         }
         .to_string()
     });
@@ -221,7 +290,9 @@ pub fn PrincipalList() -> impl IntoView {
             PrincipalType::Role => "Manage roles",
             PrincipalType::ApiKey => "Manage API keys",
             PrincipalType::OauthClient => "Manage OAuth clients",
-            _ => unreachable!("Invalid type."),
+            PrincipalType::Resource => "Manage meeting rooms and equipment",
+            PrincipalType::Location => "Manage bookable locations",
+            PrincipalType::Other => "Manage other bookable principals",
         }
         .to_string()
     });
@@ -251,13 +322,13 @@ pub fn PrincipalList() -> impl IntoView {
 
                     <ToolbarButton
                         text=Signal::derive(move || {
-                            let ns = selected.get().total_selected(total_results.get());
+                            let ns = total_selected();
                             if ns > 0 { format!("Delete ({ns})") } else { "Delete".to_string() }
                         })
 
                         color=Color::Red
                         on_click=Callback::new(move |_| {
-                            let to_delete = selected.get().total_selected(total_results.get());
+                            let to_delete = total_selected();
                             if to_delete > 0 {
                                 let text = maybe_plural(
                                     to_delete,
@@ -274,12 +345,7 @@ pub fn PrincipalList() -> impl IntoView {
                                             )
                                             .with_button(format!("Delete {text}"))
                                             .with_dangerous_callback(move || {
-                                                delete_action
-                                                    .dispatch(
-                                                        Arc::new(
-                                                            selected.try_update(std::mem::take).unwrap_or_default(),
-                                                        ),
-                                                    );
+                                                delete_action.dispatch(Arc::new(take_selection()));
                                             }),
                                     )
                             }
@@ -289,6 +355,146 @@ pub fn PrincipalList() -> impl IntoView {
                         <IconTrash/>
                     </ToolbarButton>
 
+                    <Show when=move || {
+                        selected_type.get() == PrincipalType::Domain
+                            && auth
+                                .get()
+                                .permissions()
+                                .has_access_all(
+                                    &[Permission::DomainCreate, Permission::DomainGet],
+                                )
+                    }>
+                        <ToolbarButton
+                            text="Guided setup".to_string()
+                            color=Color::Gray
+                            on_click=move |_| {
+                                use_navigate()(
+                                    "/manage/directory/domains/onboard",
+                                    Default::default(),
+                                );
+                            }
+                        >
+
+                            <IconAdd size=16 attr:class="flex-shrink-0 size-3"/>
+                        </ToolbarButton>
+                    </Show>
+
+                    <Show when=move || { total_selected() > 0 }>
+                        <ToolbarButton
+                            text=Signal::derive(move || {
+                                format!("Bulk edit ({})", total_selected())
+                            })
+
+                            color=Color::Gray
+                            on_click=move |_| {
+                                let selection = selected
+                                    .get()
+                                    .scoped(tenant.get().is_some(), &listed.get());
+                                let selection = BulkSelection {
+                                    typ: selected_type.get(),
+                                    names: match &selection {
+                                        ItemSelection::Some(names) => {
+                                            let mut names = names.iter().cloned().collect::<Vec<_>>();
+                                            names.sort_unstable();
+                                            names
+                                        }
+                                        _ => Vec::new(),
+                                    },
+                                    all: selection.is_all(),
+                                    filter: filter.get(),
+                                    tenant: tenant.get(),
+                                };
+                                if SessionStorage::set(BULK_SELECTION_STORAGE_KEY, selection).is_ok() {
+                                    use_navigate()(
+                                        &format!(
+                                            "/manage/directory/{}/bulk",
+                                            selected_type.get().resource_name(),
+                                        ),
+                                        Default::default(),
+                                    );
+                                }
+                            }
+                        >
+
+                            <IconAdjustmentsHorizontal size=16 attr:class="flex-shrink-0 size-3"/>
+                        </ToolbarButton>
+                    </Show>
+
+                    <ToolbarButton
+                        text="Export".to_string()
+                        color=Color::Gray
+                        on_click=move |_| {
+                            use_navigate()(
+                                &export_url(selected_type.get(), filter.get()),
+                                Default::default(),
+                            );
+                        }
+                    >
+
+                        <IconDocumentText size=16 attr:class="flex-shrink-0 size-3"/>
+                    </ToolbarButton>
+
+                    <Show when=move || {
+                        selected_type.get() == PrincipalType::Role
+                            && auth.get().permissions().has_access(Permission::RoleUpdate)
+                    }>
+                        <ToolbarButton
+                            text="Permission matrix".to_string()
+                            color=Color::Gray
+                            on_click=move |_| {
+                                use_navigate()("/manage/directory/roles/matrix", Default::default());
+                            }
+                        >
+
+                            <IconSquare2x2 size=16 attr:class="flex-shrink-0 size-3"/>
+                        </ToolbarButton>
+                    </Show>
+
+                    <Show when=move || {
+                        selected_type.get() == PrincipalType::ApiKey
+                            && auth
+                                .get()
+                                .permissions()
+                                .has_access_all(
+                                    &[
+                                        Permission::ApiKeyCreate,
+                                        Permission::ApiKeyUpdate,
+                                        Permission::SettingsList,
+                                        Permission::SettingsUpdate,
+                                    ],
+                                )
+                    }>
+                        <ToolbarButton
+                            text="Lifecycle".to_string()
+                            color=Color::Gray
+                            on_click=move |_| {
+                                use_navigate()(
+                                    "/manage/directory/api-keys/lifecycle",
+                                    Default::default(),
+                                );
+                            }
+                        >
+
+                            <IconKey size=16 attr:class="flex-shrink-0 size-3"/>
+                        </ToolbarButton>
+                    </Show>
+
+                    <Show when=move || {
+                        selected_type.get() == PrincipalType::Individual
+                            && auth.get().permissions().has_access(Permission::IndividualCreate)
+                    }>
+                        <ToolbarButton
+                            text="Import".to_string()
+                            color=Color::Gray
+                            on_click=move |_| {
+                                use_navigate()("/manage/directory/accounts/import", Default::default());
+                            }
+                        >
+
+                            <IconAdd size=16 attr:class="flex-shrink-0 size-3"/>
+                        </ToolbarButton>
+                    </Show>
+
                     <ToolbarButton
                         text=create_memo(move |_| {
                             format!("Create {}", selected_type.get().item_name(false))
@@ -325,6 +531,14 @@ pub fn PrincipalList() -> impl IntoView {
                         }
                         Some(Ok(principals)) if !principals.items.is_empty() => {
                             total_results.set(Some(principals.total as u32));
+                            listed
+                                .set(
+                                    principals
+                                        .items
+                                        .iter()
+                                        .filter_map(|principal| principal.name().map(|name| name.to_string()))
+                                        .collect(),
+                                );
                             let headers = match selected_type.get() {
                                 PrincipalType::Individual => {
                                     vec![
@@ -392,7 +606,17 @@ pub fn PrincipalList() -> impl IntoView {
                                         "".to_string(),
                                     ]
                                 }
-                                _ => unreachable!("Invalid type."),
+                                PrincipalType::Resource
+                                | PrincipalType::Location
+                                | PrincipalType::Other => {
+                                    vec![
+                                        "Name".to_string(),
+                                        "E-mail".to_string(),
+                                        "Type".to_string(),
+                                        "Managers".to_string(),
+                                        "".to_string(),
+                                    ]
+                                }
                             };
                             Some(
                                 view! {
@@ -540,6 +764,9 @@ fn PrincipalItem(principal: Principal, params: Parameters) -> impl IntoView {
                     | PrincipalType::Group
                     | PrincipalType::List
                     | PrincipalType::OauthClient
+                    | PrincipalType::Resource
+                    | PrincipalType::Location
+                    | PrincipalType::Other
                 )
             }>
                 <ListItem class="h-px w-72 whitespace-nowrap">
@@ -563,6 +790,9 @@ fn PrincipalItem(principal: Principal, params: Parameters) -> impl IntoView {
                     PrincipalType::Group => Color::Red,
                     PrincipalType::List => Color::Blue,
                     PrincipalType::Tenant => Color::Yellow,
+                    PrincipalType::Resource | PrincipalType::Location | PrincipalType::Other => {
+                        Color::Gray
+                    }
                     _ => Color::Red,
                 }>
 
@@ -598,6 +828,9 @@ fn PrincipalItem(principal: Principal, params: Parameters) -> impl IntoView {
                     | PrincipalType::Role
                     | PrincipalType::Domain
                     | PrincipalType::Tenant
+                    | PrincipalType::Resource
+                    | PrincipalType::Location
+                    | PrincipalType::Other
                 )
             }>
                 <ListTextItem>
@@ -614,6 +847,11 @@ fn PrincipalItem(principal: Principal, params: Parameters) -> impl IntoView {
                             PrincipalType::Role => {
                                 maybe_plural(num_members, "principal", "principals")
                             }
+                            PrincipalType::Resource
+                            | PrincipalType::Location
+                            | PrincipalType::Other => {
+                                maybe_plural(num_members, "manager", "managers")
+                            }
                             _ => String::new(),
                         }
                     }
@@ -692,6 +930,22 @@ fn PrincipalItem(principal: Principal, params: Parameters) -> impl IntoView {
 
                                 Edit
                             </a>
+                            <a
+                                class="flex items-center gap-x-3 py-2 px-3 rounded-lg text-sm text-gray-800 hover:bg-gray-100 focus:ring-2 focus:ring-blue-500 dark:text-neutral-400 dark:hover:bg-neutral-700 dark:hover:text-neutral-300"
+                                href=move || {
+                                    format!(
+                                        "/manage/directory/lists/{}/subscribers",
+                                        principal.get_untracked().name().unwrap_or_default(),
+                                    )
+                                }
+
+                                class:hidden=move || {
+                                    !matches!(selected_type, PrincipalType::List)
+                                }
+                            >
+
+                                Manage subscribers
+                            </a>
                             <a
                                 class="flex items-center gap-x-3 py-2 px-3 rounded-lg text-sm text-gray-800 hover:bg-gray-100 focus:ring-2 focus:ring-blue-500 dark:text-neutral-400 dark:hover:bg-neutral-700 dark:hover:text-neutral-300"
                                 href=move || {
@@ -708,6 +962,87 @@ fn PrincipalItem(principal: Principal, params: Parameters) -> impl IntoView {
 
                                 View DNS records
                             </a>
+                            <a
+                                class="flex items-center gap-x-3 py-2 px-3 rounded-lg text-sm text-gray-800 hover:bg-gray-100 focus:ring-2 focus:ring-blue-500 dark:text-neutral-400 dark:hover:bg-neutral-700 dark:hover:text-neutral-300"
+                                href=move || {
+                                    format!(
+                                        "/manage/directory/domains/{}/dkim",
+                                        principal.get_untracked().name().unwrap_or_default(),
+                                    )
+                                }
+
+                                class:hidden=move || {
+                                    !matches!(selected_type, PrincipalType::Domain)
+                                }
+                            >
+
+                                Manage DKIM keys
+                            </a>
+                            <a
+                                class="flex items-center gap-x-3 py-2 px-3 rounded-lg text-sm text-gray-800 hover:bg-gray-100 focus:ring-2 focus:ring-blue-500 dark:text-neutral-400 dark:hover:bg-neutral-700 dark:hover:text-neutral-300"
+                                href=move || {
+                                    format!(
+                                        "/manage/directory/domains/{}/addresses",
+                                        principal.get_untracked().name().unwrap_or_default(),
+                                    )
+                                }
+
+                                class:hidden=move || {
+                                    !matches!(selected_type, PrincipalType::Domain)
+                                }
+                            >
+
+                                Address book
+                            </a>
+                            <a
+                                class="flex items-center gap-x-3 py-2 px-3 rounded-lg text-sm text-gray-800 hover:bg-gray-100 focus:ring-2 focus:ring-blue-500 dark:text-neutral-400 dark:hover:bg-neutral-700 dark:hover:text-neutral-300"
+                                href=move || {
+                                    format!(
+                                        "/manage/directory/accounts/{}/impersonate",
+                                        principal.get_untracked().name().unwrap_or_default(),
+                                    )
+                                }
+
+                                class:hidden=move || {
+                                    !matches!(selected_type, PrincipalType::Individual)
+                                        || !use_authorization()
+                                            .get()
+                                            .permissions()
+                                            .has_access(Permission::Impersonate)
+                                }
+                            >
+
+                                Impersonate
+                            </a>
+                            <a
+                                class="flex items-center gap-x-3 py-2 px-3 rounded-lg text-sm text-gray-800 hover:bg-gray-100 focus:ring-2 focus:ring-blue-500 dark:text-neutral-400 dark:hover:bg-neutral-700 dark:hover:text-neutral-300"
+                                href=move || {
+                                    UrlBuilder::new("/manage/directory/graph")
+                                        .with_parameter(
+                                            if selected_type == PrincipalType::Domain {
+                                                "domain"
+                                            } else {
+                                                "principal"
+                                            },
+                                            principal.get_untracked().name().unwrap_or_default(),
+                                        )
+                                        .finish()
+                                }
+
+                                class:hidden=move || {
+                                    !matches!(
+                                        selected_type,
+                                        PrincipalType::Individual
+                                        | PrincipalType::Group
+                                        | PrincipalType::List
+                                        | PrincipalType::Role
+                                        | PrincipalType::Domain
+                                    )
+                                }
+                            >
+
+                                Membership graph
+                            </a>
                             <a
                                 class="flex items-center gap-x-3 py-2 px-3 rounded-lg text-sm text-gray-800 hover:bg-gray-100 focus:ring-2 focus:ring-blue-500 dark:text-neutral-400 dark:hover:bg-neutral-700 dark:hover:text-neutral-300"
                                 href=move || {
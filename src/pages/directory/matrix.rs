@@ -0,0 +1,569 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::sync::Arc;
+
+use ahash::AHashSet;
+use leptos::*;
+use leptos_router::use_navigate;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::{
+        badge::Badge,
+        form::{button::Button, Form, FormButtonBar, FormSection},
+        list::table::Table,
+        messages::alert::{use_alerts, Alert},
+        skeleton::Skeleton,
+        Color,
+    },
+    core::{
+        http::{self, HttpRequest},
+        oauth::use_authorization,
+        Permission,
+    },
+    pages::{
+        directory::{Principal, PrincipalField, PrincipalUpdate, PERMISSIONS},
+        maybe_plural, List,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PermissionGroup {
+    Queue,
+    Reports,
+    Settings,
+    Directory,
+    Tracing,
+    Account,
+    ApiKeys,
+    OauthClients,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CellState {
+    Default,
+    Granted,
+    Denied,
+    Partial,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct MatrixRole {
+    name: String,
+    description: String,
+    tenant_scoped: bool,
+    enabled: AHashSet<String>,
+    disabled: AHashSet<String>,
+    saved_enabled: AHashSet<String>,
+    saved_disabled: AHashSet<String>,
+}
+
+#[component]
+pub fn RoleMatrix() -> impl IntoView {
+    let auth = use_authorization();
+    let alert = use_alerts();
+    let roles: RwSignal<Vec<MatrixRole>> = create_rw_signal(Vec::new());
+
+    let fetch_roles = create_resource(
+        move || (),
+        move |_| {
+            let auth = auth.get_untracked();
+            let has_tenants =
+                auth.is_enterprise() && auth.permissions().has_access(Permission::TenantList);
+
+            async move {
+                let mut roles = HttpRequest::get("/api/principal")
+                    .with_authorization(&auth)
+                    .with_parameter("types", "role")
+                    .with_parameter(
+                        "fields",
+                        "name,description,tenant,enabledPermissions,disabledPermissions",
+                    )
+                    .send::<List<Principal>>()
+                    .await?
+                    .items;
+                roles.sort_by(|a, b| a.name.as_str().cmp(&b.name.as_str()));
+
+                // Roles assigned to tenants are flagged when they grant admin permissions
+                let tenant_roles = if has_tenants {
+                    HttpRequest::get("/api/principal")
+                        .with_authorization(&auth)
+                        .with_parameter("types", "tenant")
+                        .with_parameter("fields", "name,roles")
+                        .send::<List<Principal>>()
+                        .await?
+                        .items
+                        .into_iter()
+                        .flat_map(|tenant| tenant.roles.as_string_list().to_vec())
+                        .collect::<AHashSet<_>>()
+                } else {
+                    AHashSet::new()
+                };
+
+                Ok(roles
+                    .into_iter()
+                    .filter_map(|role| {
+                        let name = role.name.try_unwrap_string()?;
+                        let enabled = role
+                            .enabled_permissions
+                            .as_string_list()
+                            .iter()
+                            .cloned()
+                            .collect::<AHashSet<_>>();
+                        let disabled = role
+                            .disabled_permissions
+                            .as_string_list()
+                            .iter()
+                            .cloned()
+                            .collect::<AHashSet<_>>();
+                        Some(MatrixRole {
+                            tenant_scoped: role.tenant.as_str().is_some_and(|t| !t.is_empty())
+                                || tenant_roles.contains(&name),
+                            name,
+                            description: role.description.as_str().unwrap_or_default().to_string(),
+                            saved_enabled: enabled.clone(),
+                            saved_disabled: disabled.clone(),
+                            enabled,
+                            disabled,
+                        })
+                    })
+                    .collect::<Vec<_>>())
+            }
+        },
+    );
+
+    let save_changes = create_action(move |changes: &Arc<Vec<(String, Vec<PrincipalUpdate>)>>| {
+        let changes = changes.clone();
+        let auth = auth.get();
+
+        async move {
+            for (name, updates) in changes.iter() {
+                match HttpRequest::patch(("/api/principal", name))
+                    .with_authorization(&auth)
+                    .with_body(updates)
+                    .unwrap()
+                    .send::<serde_json::Value>()
+                    .await
+                {
+                    Ok(_) => {
+                        roles.update(|roles| {
+                            if let Some(role) = roles.iter_mut().find(|role| &role.name == name) {
+                                role.saved_enabled = role.enabled.clone();
+                                role.saved_disabled = role.disabled.clone();
+                            }
+                        });
+                    }
+                    Err(http::Error::Unauthorized) => {
+                        use_navigate()("/login", Default::default());
+                        return;
+                    }
+                    Err(err) => {
+                        alert.set(Alert::from(err));
+                        return;
+                    }
+                }
+            }
+
+            alert.set(Alert::success(format!(
+                "Updated {}.",
+                maybe_plural(changes.len(), "role", "roles")
+            )));
+        }
+    });
+
+    let headers = store_value(
+        std::iter::once("Role".to_string())
+            .chain(
+                PermissionGroup::ALL
+                    .iter()
+                    .map(|group| group.name().to_string()),
+            )
+            .collect::<Vec<_>>(),
+    );
+    let total_changed = create_memo(move |_| {
+        roles.with(|roles| roles.iter().filter(|role| role.is_modified()).count())
+    });
+
+    view! {
+        <Form
+            title="Role permission matrix"
+            subtitle="Grant or deny groups of permissions across all roles at once"
+        >
+            <FormSection>
+                <div class="sm:col-span-12">
+                    <Transition fallback=Skeleton>
+                        {move || match fetch_roles.get() {
+                            None => None,
+                            Some(Err(http::Error::Unauthorized)) => {
+                                use_navigate()("/login", Default::default());
+                                Some(view! { <div></div> }.into_view())
+                            }
+                            Some(Err(err)) => {
+                                alert.set(Alert::from(err));
+                                Some(view! { <div></div> }.into_view())
+                            }
+                            Some(Ok(fetched)) => {
+                                roles.set(fetched);
+                                Some(
+                                    view! {
+                                        <p class="mb-4 text-sm text-gray-600 dark:text-gray-400">
+                                            "Click a cell to cycle between the role's default, granting and denying every permission in the group. Roles assigned to tenants that grant administrative permissions are highlighted."
+                                        </p>
+                                        <Table headers=headers.get_value()>
+                                            <For
+                                                each=move || 0..roles.with(|roles| roles.len())
+                                                key=|idx| *idx
+                                                children=move |idx| {
+                                                    view! { <MatrixRow roles=roles idx=idx/> }
+                                                }
+                                            />
+
+                                        </Table>
+                                    }
+                                        .into_view(),
+                                )
+                            }
+                        }}
+
+                    </Transition>
+                </div>
+            </FormSection>
+
+            <FormButtonBar>
+                <Button
+                    text="Cancel"
+                    color=Color::Gray
+                    on_click=move |_| {
+                        use_navigate()("/manage/directory/roles", Default::default());
+                    }
+                />
+
+                <Button
+                    text=Signal::derive(move || {
+                        match total_changed.get() {
+                            0 => "Save changes".to_string(),
+                            n => format!("Save changes ({n})"),
+                        }
+                    })
+
+                    color=Color::Blue
+                    on_click=move |_| {
+                        let changes = roles
+                            .with_untracked(|roles| {
+                                roles
+                                    .iter()
+                                    .filter(|role| role.is_modified())
+                                    .map(|role| (role.name.clone(), role.updates()))
+                                    .collect::<Vec<_>>()
+                            });
+                        if !changes.is_empty() {
+                            save_changes.dispatch(Arc::new(changes));
+                        }
+                    }
+
+                    disabled=Signal::derive(move || {
+                        total_changed.get() == 0 || save_changes.pending().get()
+                    })
+                />
+
+            </FormButtonBar>
+        </Form>
+    }
+}
+
+#[component]
+fn MatrixRow(roles: RwSignal<Vec<MatrixRole>>, idx: usize) -> impl IntoView {
+    let role = create_memo(move |_| roles.with(|roles| roles.get(idx).cloned()));
+    let admin_permissions = create_memo(move |_| {
+        role.get()
+            .filter(|role| role.tenant_scoped)
+            .map(|role| role.admin_permissions())
+            .unwrap_or_default()
+    });
+
+    let cells = PermissionGroup::ALL
+        .iter()
+        .map(|group| {
+            let group = *group;
+            let state = create_memo(move |_| {
+                role.get()
+                    .map(|role| role.state(group))
+                    .unwrap_or((CellState::Default, 0))
+            });
+            let is_modified = create_memo(move |_| {
+                role.get()
+                    .is_some_and(|role| role.is_group_modified(group))
+            });
+
+            view! {
+                <td class="px-6 py-2 whitespace-nowrap text-sm text-gray-800 dark:text-gray-200">
+                    <button
+                        type="button"
+                        class=move || {
+                            if is_modified.get() {
+                                "rounded-full ring-2 ring-blue-500 ring-offset-1"
+                            } else {
+                                "rounded-full"
+                            }
+                        }
+
+                        on:click=move |_| {
+                            roles
+                                .update(|roles| {
+                                    if let Some(role) = roles.get_mut(idx) {
+                                        role.toggle(group);
+                                    }
+                                });
+                        }
+                    >
+
+                        {move || {
+                            let (state, granted) = state.get();
+                            let (color, label) = match state {
+                                CellState::Default => (Color::Gray, "Default".to_string()),
+                                CellState::Granted => (Color::Green, "Granted".to_string()),
+                                CellState::Denied => (Color::Red, "Denied".to_string()),
+                                CellState::Partial => {
+                                    (
+                                        Color::Yellow,
+                                        format!("Partial ({granted}/{})", group.permissions().count()),
+                                    )
+                                }
+                            };
+                            view! { <Badge color=color>{label}</Badge> }
+                        }}
+
+                    </button>
+                </td>
+            }
+        })
+        .collect_view();
+
+    view! {
+        <tr class:bg-red-50=move || !admin_permissions.get().is_empty()>
+            <td class="px-6 py-2 whitespace-nowrap text-sm text-gray-800 dark:text-gray-200">
+                <div class="flex flex-col">
+                    <span class="font-semibold">
+                        {move || role.get().map(|role| role.name).unwrap_or_default()}
+                    </span>
+                    <span class="text-xs text-gray-500">
+                        {move || role.get().map(|role| role.description).unwrap_or_default()}
+                    </span>
+                    {move || {
+                        let admin_permissions = admin_permissions.get();
+                        (!admin_permissions.is_empty())
+                            .then(|| {
+                                view! {
+                                    <span
+                                        class="mt-1 text-xs text-red-600 dark:text-red-500"
+                                        title=admin_permissions.join(", ")
+                                    >
+                                        {format!(
+                                            "Grants {} to a tenant",
+                                            maybe_plural(
+                                                admin_permissions.len(),
+                                                "admin permission",
+                                                "admin permissions",
+                                            ),
+                                        )}
+
+                                    </span>
+                                }
+                            })
+                    }}
+
+                </div>
+            </td>
+            {cells}
+        </tr>
+    }
+}
+
+impl MatrixRole {
+    fn state(&self, group: PermissionGroup) -> (CellState, usize) {
+        let mut total = 0;
+        let mut granted = 0;
+        let mut denied = 0;
+
+        for permission in group.permissions() {
+            total += 1;
+            if self.enabled.contains(permission) {
+                granted += 1;
+            } else if self.disabled.contains(permission) {
+                denied += 1;
+            }
+        }
+
+        let state = if granted == total {
+            CellState::Granted
+        } else if denied == total {
+            CellState::Denied
+        } else if granted == 0 && denied == 0 {
+            CellState::Default
+        } else {
+            CellState::Partial
+        };
+
+        (state, granted)
+    }
+
+    // Default -> Granted -> Denied -> Default, partial groups are granted first
+    fn toggle(&mut self, group: PermissionGroup) {
+        let next = match self.state(group).0 {
+            CellState::Default | CellState::Partial => CellState::Granted,
+            CellState::Granted => CellState::Denied,
+            CellState::Denied => CellState::Default,
+        };
+
+        for permission in group.permissions() {
+            self.enabled.remove(permission);
+            self.disabled.remove(permission);
+            match next {
+                CellState::Granted => {
+                    self.enabled.insert(permission.to_string());
+                }
+                CellState::Denied => {
+                    self.disabled.insert(permission.to_string());
+                }
+                CellState::Default | CellState::Partial => {}
+            }
+        }
+    }
+
+    fn is_modified(&self) -> bool {
+        self.enabled != self.saved_enabled || self.disabled != self.saved_disabled
+    }
+
+    fn is_group_modified(&self, group: PermissionGroup) -> bool {
+        group.permissions().any(|permission| {
+            self.enabled.contains(permission) != self.saved_enabled.contains(permission)
+                || self.disabled.contains(permission) != self.saved_disabled.contains(permission)
+        })
+    }
+
+    fn updates(&self) -> Vec<PrincipalUpdate> {
+        let mut updates = Vec::new();
+        for (field, current, saved) in [
+            (
+                PrincipalField::EnabledPermissions,
+                &self.enabled,
+                &self.saved_enabled,
+            ),
+            (
+                PrincipalField::DisabledPermissions,
+                &self.disabled,
+                &self.saved_disabled,
+            ),
+        ] {
+            updates.extend(
+                saved
+                    .difference(current)
+                    .map(|permission| PrincipalUpdate::remove_item(field, permission)),
+            );
+            updates.extend(
+                current
+                    .difference(saved)
+                    .map(|permission| PrincipalUpdate::add_item(field, permission)),
+            );
+        }
+        updates
+    }
+
+    fn admin_permissions(&self) -> Vec<String> {
+        let mut permissions = self
+            .enabled
+            .iter()
+            .filter(|permission| {
+                serde_json::from_value::<Permission>(serde_json::Value::String(
+                    permission.to_string(),
+                ))
+                .is_ok_and(|permission| permission.is_admin_permission())
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        permissions.sort_unstable();
+        permissions
+    }
+}
+
+impl PermissionGroup {
+    const ALL: [PermissionGroup; 8] = [
+        PermissionGroup::Queue,
+        PermissionGroup::Reports,
+        PermissionGroup::Settings,
+        PermissionGroup::Directory,
+        PermissionGroup::Tracing,
+        PermissionGroup::Account,
+        PermissionGroup::ApiKeys,
+        PermissionGroup::OauthClients,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            PermissionGroup::Queue => "Queue",
+            PermissionGroup::Reports => "Reports",
+            PermissionGroup::Settings => "Settings",
+            PermissionGroup::Directory => "Directory",
+            PermissionGroup::Tracing => "Tracing",
+            PermissionGroup::Account => "Account",
+            PermissionGroup::ApiKeys => "API Keys",
+            PermissionGroup::OauthClients => "OAuth Clients",
+        }
+    }
+
+    fn contains(&self, permission: &str) -> bool {
+        match self {
+            PermissionGroup::Queue => permission.starts_with("message-queue-"),
+            PermissionGroup::Reports => {
+                permission.starts_with("outgoing-report-")
+                    || permission.starts_with("incoming-report-")
+            }
+            PermissionGroup::Settings => {
+                permission.starts_with("settings-")
+                    || matches!(
+                        permission,
+                        "webadmin-update" | "spam-filter-update" | "restart"
+                    )
+            }
+            PermissionGroup::Directory => [
+                "individual-",
+                "group-",
+                "domain-",
+                "tenant-",
+                "mailing-list-",
+                "role-",
+                "principal-",
+                "dkim-signature-",
+            ]
+            .iter()
+            .any(|prefix| permission.starts_with(prefix)),
+            PermissionGroup::Tracing => {
+                permission.starts_with("tracing-")
+                    || permission.starts_with("metrics-")
+                    || matches!(permission, "logs-view" | "troubleshoot")
+            }
+            PermissionGroup::Account => matches!(
+                permission,
+                "authenticate"
+                    | "authenticate-oauth"
+                    | "email-send"
+                    | "email-receive"
+                    | "manage-encryption"
+                    | "manage-passwords"
+            ),
+            PermissionGroup::ApiKeys => permission.starts_with("api-key-"),
+            PermissionGroup::OauthClients => permission.starts_with("oauth-client-"),
+        }
+    }
+
+    fn permissions(self) -> impl Iterator<Item = &'static str> {
+        PERMISSIONS
+            .iter()
+            .map(|(id, _)| *id)
+            .filter(move |id| self.contains(id))
+    }
+}
@@ -4,7 +4,7 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::{fmt, str::FromStr};
+use std::{cell::RefCell, collections::VecDeque, fmt, future::Future, rc::Rc, str::FromStr};
 
 use serde::{
     de::{self, Visitor},
@@ -13,9 +13,24 @@ use serde::{
 
 use base64::{engine::general_purpose::STANDARD, Engine};
 
+pub mod addresses;
+pub mod api_key;
+pub mod bulk;
+pub mod dkim;
 pub mod dns;
 pub mod edit;
+pub mod effective;
+pub mod export;
+pub mod graph;
+pub mod impersonate;
+pub mod import;
 pub mod list;
+pub mod matrix;
+pub mod oauth_client;
+pub mod onboard;
+pub mod quota;
+pub mod subscribers;
+pub mod zone;
 
 #[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Principal {
@@ -328,6 +343,16 @@ impl Principal {
     pub fn description_or_name(&self) -> Option<&str> {
         self.description.as_str().or(self.name.as_str())
     }
+
+    // Places a new principal under the tenant currently being administered
+    pub fn with_tenant(mut self, tenant: Option<String>) -> Self {
+        if let Some(tenant) = tenant {
+            if self.tenant.is_none() && self.typ != Some(PrincipalType::Tenant) {
+                self.tenant = PrincipalValue::String(tenant);
+            }
+        }
+        self
+    }
 }
 
 impl PrincipalValue {
@@ -487,7 +512,90 @@ impl PrincipalType {
             PrincipalType::Domain => "domains",
             PrincipalType::ApiKey => "api-keys",
             PrincipalType::OauthClient => "oauth-clients",
-            _ => unimplemented!("resource_name for {:?}", self),
+            PrincipalType::Resource => "resources",
+            PrincipalType::Location => "locations",
+            PrincipalType::Other => "other",
+        }
+    }
+
+    pub fn is_bookable(&self) -> bool {
+        matches!(
+            self,
+            PrincipalType::Resource | PrincipalType::Location | PrincipalType::Other
+        )
+    }
+}
+
+impl PrincipalUpdate {
+    pub fn set(field: PrincipalField, value: PrincipalValue) -> Self {
+        PrincipalUpdate {
+            action: PrincipalAction::Set,
+            field,
+            value,
+        }
+    }
+
+    pub fn add_item(field: PrincipalField, value: impl Into<String>) -> Self {
+        PrincipalUpdate {
+            action: PrincipalAction::AddItem,
+            field,
+            value: PrincipalValue::String(value.into()),
+        }
+    }
+
+    pub fn remove_item(field: PrincipalField, value: impl Into<String>) -> Self {
+        PrincipalUpdate {
+            action: PrincipalAction::RemoveItem,
+            field,
+            value: PrincipalValue::String(value.into()),
+        }
+    }
+}
+
+impl PrincipalField {
+    pub const fn id(&self) -> &'static str {
+        match self {
+            PrincipalField::Name => "name",
+            PrincipalField::Type => "type",
+            PrincipalField::Quota => "quota",
+            PrincipalField::UsedQuota => "usedQuota",
+            PrincipalField::Description => "description",
+            PrincipalField::Secrets => "secrets",
+            PrincipalField::Emails => "emails",
+            PrincipalField::MemberOf => "memberOf",
+            PrincipalField::Members => "members",
+            PrincipalField::Tenant => "tenant",
+            PrincipalField::Roles => "roles",
+            PrincipalField::Lists => "lists",
+            PrincipalField::EnabledPermissions => "enabledPermissions",
+            PrincipalField::DisabledPermissions => "disabledPermissions",
+            PrincipalField::Picture => "picture",
+            PrincipalField::Urls => "urls",
+            PrincipalField::ExternalMembers => "externalMembers",
+            PrincipalField::Locale => "locale",
+        }
+    }
+
+    pub const fn name(&self) -> &'static str {
+        match self {
+            PrincipalField::Name => "Name",
+            PrincipalField::Type => "Type",
+            PrincipalField::Quota => "Quota",
+            PrincipalField::UsedQuota => "Used Quota",
+            PrincipalField::Description => "Description",
+            PrincipalField::Secrets => "Secrets",
+            PrincipalField::Emails => "E-mail Addresses",
+            PrincipalField::MemberOf => "Member Of",
+            PrincipalField::Members => "Members",
+            PrincipalField::Tenant => "Tenant",
+            PrincipalField::Roles => "Roles",
+            PrincipalField::Lists => "Mailing Lists",
+            PrincipalField::EnabledPermissions => "Enabled Permissions",
+            PrincipalField::DisabledPermissions => "Disabled Permissions",
+            PrincipalField::Picture => "Picture",
+            PrincipalField::Urls => "URLs",
+            PrincipalField::ExternalMembers => "External Members",
+            PrincipalField::Locale => "Locale",
         }
     }
 }
@@ -513,6 +621,34 @@ impl FromStr for PrincipalType {
     }
 }
 
+// Runs `task` for every item using at most `concurrency` simultaneous workers.
+// A worker stops as soon as `task` returns false, e.g. when the page was closed.
+pub fn spawn_workers<T, F, R>(items: impl IntoIterator<Item = T>, concurrency: usize, task: F)
+where
+    T: 'static,
+    F: Fn(T) -> R + Clone + 'static,
+    R: Future<Output = bool> + 'static,
+{
+    let queue = Rc::new(RefCell::new(items.into_iter().collect::<VecDeque<_>>()));
+    let workers = concurrency.max(1).min(queue.borrow().len());
+
+    for _ in 0..workers {
+        let queue = queue.clone();
+        let task = task.clone();
+
+        leptos::spawn_local(async move {
+            loop {
+                let Some(item) = queue.borrow_mut().pop_front() else {
+                    break;
+                };
+                if !task(item).await {
+                    break;
+                }
+            }
+        });
+    }
+}
+
 pub fn parse_app_password(secret: &str) -> Option<(String, &str)> {
     secret
         .strip_prefix("$app$")
@@ -0,0 +1,536 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::sync::Arc;
+
+use leptos::*;
+use leptos_router::{use_navigate, use_params_map};
+use serde_json::json;
+
+use crate::{
+    components::{
+        form::{
+            button::Button, input::InputText, select::Select, stacked_input::StackedInput, Form,
+            FormButtonBar, FormElement, FormItem, FormSection,
+        },
+        messages::alert::{use_alerts, Alert},
+        skeleton::Skeleton,
+        Color,
+    },
+    core::{
+        form::FormData,
+        http::{self, HttpRequest},
+        oauth::use_authorization,
+        schema::{ArrayType, Builder, Schemas, SelectType, Source, Transformer, Type, Validator},
+        AccessToken,
+    },
+    pages::directory::{
+        api_key::{copy_to_clipboard, generate_api_secret, RevealOnce},
+        Principal, PrincipalField, PrincipalType, PrincipalUpdate, PrincipalValue,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ClientType {
+    Confidential,
+    Public,
+}
+
+#[component]
+pub fn OAuthClientEdit() -> impl IntoView {
+    let auth = use_authorization();
+    let alert = use_alerts();
+    let params = use_params_map();
+    let data = expect_context::<Arc<Schemas>>()
+        .build_form("oauth-client")
+        .into_signal();
+    let current_client = create_rw_signal(Principal::default());
+    let issued_secret: RwSignal<Option<String>> = create_rw_signal(None);
+    let (pending, set_pending) = create_signal(false);
+
+    let fetch_client = create_resource(
+        move || params.get().get("id").cloned().unwrap_or_default(),
+        move |name| {
+            let auth = auth.get_untracked();
+
+            async move {
+                if !name.is_empty() {
+                    HttpRequest::get(("/api/principal", &name))
+                        .with_authorization(&auth)
+                        .send::<Principal>()
+                        .await
+                } else {
+                    Ok(Principal::default())
+                }
+            }
+        },
+    );
+
+    let save_changes = create_action(move |changes: &Principal| {
+        let current = current_client.get();
+        let mut changes = changes.clone();
+        let auth = auth.get();
+
+        async move {
+            set_pending.set(true);
+            let is_public = changes.secrets.as_string_list().is_empty();
+            let mut new_secret = None;
+            let result = if !current.is_blank() {
+                let name = current.name().unwrap_or_default().to_string();
+                let has_secret = !current.secrets.as_string_list().is_empty();
+
+                // Secrets are never edited through the form, only issued or dropped
+                changes.secrets = current.secrets.clone();
+                let mut updates = current.into_updates(changes);
+                if is_public && has_secret {
+                    updates.push(PrincipalUpdate::set(
+                        PrincipalField::Secrets,
+                        PrincipalValue::StringList(vec![]),
+                    ));
+                } else if !is_public && !has_secret {
+                    let secret = generate_api_secret();
+                    updates.push(PrincipalUpdate::set(
+                        PrincipalField::Secrets,
+                        PrincipalValue::StringList(vec![secret.clone()]),
+                    ));
+                    new_secret = Some(secret);
+                }
+
+                if !updates.is_empty() {
+                    HttpRequest::patch(("/api/principal", &name))
+                        .with_authorization(&auth)
+                        .with_body(updates)
+                        .unwrap()
+                        .send::<serde_json::Value>()
+                        .await
+                        .map(|_| ())
+                } else {
+                    Ok(())
+                }
+            } else {
+                new_secret = changes.secrets.as_string_list().first().cloned();
+                HttpRequest::post("/api/principal")
+                    .with_authorization(&auth)
+                    .with_body(&changes)
+                    .unwrap()
+                    .send::<u32>()
+                    .await
+                    .map(|_| ())
+            };
+            set_pending.set(false);
+
+            match result {
+                Ok(_) => {
+                    if let Some(secret) = new_secret {
+                        // Stay on the page until the secret has been copied
+                        issued_secret.set(Some(secret));
+                        if !current_client.with_untracked(|c| c.is_blank()) {
+                            fetch_client.refetch();
+                        }
+                    } else {
+                        use_navigate()("/manage/directory/oauth-clients", Default::default());
+                    }
+                }
+                Err(http::Error::Unauthorized) => {
+                    use_navigate()("/login", Default::default());
+                }
+                Err(err) => {
+                    alert.set(Alert::from(err));
+                }
+            }
+        }
+    });
+
+    let rotate_secret = create_action(move |name: &String| {
+        let auth = auth.get();
+        let name = name.clone();
+
+        async move {
+            set_pending.set(true);
+            let secret = generate_api_secret();
+            let result = HttpRequest::patch(("/api/principal", &name))
+                .with_authorization(&auth)
+                .with_body(vec![PrincipalUpdate::set(
+                    PrincipalField::Secrets,
+                    PrincipalValue::StringList(vec![secret.clone()]),
+                )])
+                .unwrap()
+                .send::<serde_json::Value>()
+                .await;
+            set_pending.set(false);
+
+            match result {
+                Ok(_) => {
+                    issued_secret.set(Some(secret));
+                }
+                Err(http::Error::Unauthorized) => {
+                    use_navigate()("/login", Default::default());
+                }
+                Err(err) => {
+                    alert.set(Alert::from(err));
+                }
+            }
+        }
+    });
+
+    let is_update =
+        create_memo(move |_| params.with(|p| p.get("id").is_some_and(|id| !id.is_empty())));
+    let client_type = create_memo(move |_| {
+        data.with(|data| ClientType::from_id(data.value::<String>("client-type").as_deref()))
+    });
+    let client_config = create_memo(move |_| {
+        data.with(|data| client_configuration(&auth.get(), data, client_type.get()))
+    });
+    let title = create_memo(move |_| {
+        if let Some(name) = params.get().get("id").filter(|id| !id.is_empty()) {
+            format!("Update '{name}' OAuth Client")
+        } else {
+            "Register OAuth Client".to_string()
+        }
+    });
+
+    view! {
+        <Form title=title subtitle="Redirect URIs, client type and secrets".to_string()>
+
+            <Transition fallback=Skeleton set_pending>
+
+                {move || match fetch_client.get() {
+                    None => None,
+                    Some(Err(http::Error::Unauthorized)) => {
+                        use_navigate()("/login", Default::default());
+                        Some(view! { <div></div> }.into_view())
+                    }
+                    Some(Err(http::Error::NotFound)) => {
+                        use_navigate()("/manage/directory/oauth-clients", Default::default());
+                        Some(view! { <div></div> }.into_view())
+                    }
+                    Some(Err(err)) => {
+                        alert.set(Alert::from(err));
+                        Some(view! { <div></div> }.into_view())
+                    }
+                    Some(Ok(client)) => {
+                        data.update(|data| {
+                            data.load_client(&client);
+                        });
+                        current_client.set(client);
+
+                        Some(
+                            view! {
+                                <FormSection title="Client".to_string()>
+                                    <FormItem label="Client Id">
+                                        <InputText
+                                            placeholder="my-app"
+                                            element=FormElement::new("name", data)
+                                            disabled=is_update.get()
+                                        />
+                                    </FormItem>
+                                    <FormItem label="Name" is_optional=true>
+                                        <InputText
+                                            placeholder="My Application"
+                                            element=FormElement::new("description", data)
+                                        />
+                                    </FormItem>
+                                    <FormItem label="Contact e-mail" is_optional=true>
+                                        <InputText
+                                            placeholder="developer@example.org"
+                                            element=FormElement::new("email", data)
+                                        />
+                                    </FormItem>
+                                    <FormItem label="Logo URL" is_optional=true>
+                                        <InputText element=FormElement::new("picture", data)/>
+                                    </FormItem>
+                                    <FormItem
+                                        label="Client type"
+                                        tooltip="Public clients such as mobile or single-page apps cannot keep a secret and must use PKCE"
+                                    >
+                                        <Select element=FormElement::new("client-type", data)/>
+                                    </FormItem>
+                                    <FormItem
+                                        label="Redirect URIs"
+                                        tooltip="Only https URIs are accepted, except for loopback addresses used by native apps"
+                                    >
+                                        <StackedInput
+                                            element=FormElement::new("urls", data)
+                                            placeholder="https://app.example.org/callback"
+                                            add_button_text="Add URI".to_string()
+                                        />
+                                    </FormItem>
+                                </FormSection>
+
+                                <FormSection
+                                    title="Client Secret".to_string()
+                                    hide=Signal::derive(move || {
+                                        !is_update.get() || client_type.get() == ClientType::Public
+                                    })
+                                >
+                                    <FormItem label="Secret">
+                                        <div class="flex items-center gap-x-4">
+                                            <span class="text-sm text-gray-800 dark:text-neutral-200">
+                                                {move || {
+                                                    if current_client
+                                                        .with(|c| c.secrets.as_string_list().is_empty())
+                                                    {
+                                                        "Not issued, a secret will be generated on save"
+                                                    } else {
+                                                        "Issued"
+                                                    }
+                                                }}
+
+                                            </span>
+                                            <Button
+                                                text="Rotate secret"
+                                                color=Color::Gray
+                                                on_click=move |_| {
+                                                    rotate_secret
+                                                        .dispatch(
+                                                            current_client.with(|c| c.name_or_empty()),
+                                                        );
+                                                }
+
+                                                disabled=Signal::derive(move || {
+                                                    pending.get()
+                                                        || current_client
+                                                            .with(|c| c.secrets.as_string_list().is_empty())
+                                                })
+                                            />
+                                        </div>
+                                    </FormItem>
+                                </FormSection>
+                            }
+                                .into_view(),
+                        )
+                    }
+                }}
+
+            </Transition>
+
+            {move || {
+                issued_secret
+                    .get()
+                    .map(|secret| {
+                        view! {
+                            <RevealOnce
+                                title="Client secret"
+                                secret=secret
+                                on_done=move |_| {
+                                    issued_secret.set(None);
+                                    if !is_update.get_untracked() {
+                                        use_navigate()(
+                                            "/manage/directory/oauth-clients",
+                                            Default::default(),
+                                        );
+                                    }
+                                }
+                            />
+                        }
+                    })
+            }}
+
+            <FormSection title="Client Configuration".to_string()>
+                <div class="flex flex-col gap-y-3">
+                    <textarea
+                        class="py-3 px-4 block w-full border-gray-200 rounded-lg text-sm font-mono text-nowrap focus:border-blue-500 focus:ring-blue-500 disabled:opacity-50 disabled:pointer-events-none dark:bg-slate-900 dark:border-gray-700 dark:text-gray-400 dark:focus:ring-gray-600"
+                        readonly=true
+                        rows=14
+                        prop:value=move || client_config.get()
+                    ></textarea>
+                    <div class="flex justify-end">
+                        <Button
+                            text="Copy"
+                            color=Color::Gray
+                            on_click=move |_| {
+                                if copy_to_clipboard(&client_config.get()) {
+                                    alert.set(Alert::success("Copied to clipboard."));
+                                } else {
+                                    alert.set(Alert::error("Could not access the clipboard."));
+                                }
+                            }
+                        />
+
+                    </div>
+                </div>
+            </FormSection>
+
+            <FormButtonBar>
+                <Button
+                    text="Cancel"
+                    color=Color::Gray
+                    on_click=move |_| {
+                        use_navigate()("/manage/directory/oauth-clients", Default::default());
+                    }
+                />
+
+                <Button
+                    text="Save changes"
+                    color=Color::Blue
+                    on_click=Callback::new(move |_| {
+                        data.update(|data| {
+                            if data.validate_form() {
+                                save_changes.dispatch(data.to_client());
+                            }
+                        });
+                    })
+
+                    disabled=pending
+                />
+            </FormButtonBar>
+        </Form>
+    }
+}
+
+// Endpoints are fixed paths below the server's base URL, the same ones
+// advertised in its OpenID discovery document.
+fn client_configuration(auth: &AccessToken, data: &FormData, client_type: ClientType) -> String {
+    let base_url = if !auth.base_url.is_empty() {
+        auth.base_url.trim_end_matches('/').to_string()
+    } else {
+        window().location().origin().unwrap_or_default()
+    };
+    let client_id = data.value::<String>("name").unwrap_or_default();
+    let redirect_uris = data.array_value("urls").collect::<Vec<_>>();
+
+    let mut config = json!({
+        "issuer": base_url,
+        "discovery_url": format!("{base_url}/.well-known/openid-configuration"),
+        "authorization_endpoint": format!("{base_url}/authorize/code"),
+        "token_endpoint": format!("{base_url}/auth/token"),
+        "device_authorization_endpoint": format!("{base_url}/auth/device"),
+        "userinfo_endpoint": format!("{base_url}/auth/userinfo"),
+        "jwks_uri": format!("{base_url}/auth/jwks.json"),
+        "client_id": client_id,
+        "redirect_uris": redirect_uris,
+        "scope": "openid email profile offline_access",
+    });
+    match client_type {
+        ClientType::Confidential => {
+            config["client_secret"] = "<client secret>".into();
+            config["token_endpoint_auth_method"] = "client_secret_basic".into();
+        }
+        ClientType::Public => {
+            config["token_endpoint_auth_method"] = "none".into();
+            config["code_challenge_method"] = "S256".into();
+        }
+    }
+
+    serde_json::to_string_pretty(&config).unwrap_or_default()
+}
+
+impl FormData {
+    fn load_client(&mut self, client: &Principal) {
+        for (key, field) in [
+            ("name", client.name.as_str()),
+            ("description", client.description.as_str()),
+            ("picture", client.picture.as_str()),
+            ("email", client.email()),
+        ] {
+            if let Some(value) = field {
+                self.set(key, value.to_string());
+            }
+        }
+        self.array_set("urls", client.urls.as_string_list());
+
+        // Clients without a secret can only be public
+        let client_type = if !client.is_blank() && client.secrets.as_string_list().is_empty() {
+            ClientType::Public
+        } else {
+            ClientType::Confidential
+        };
+        self.set("client-type", client_type.id());
+    }
+
+    fn to_client(&self) -> Principal {
+        let client_type = ClientType::from_id(self.value::<String>("client-type").as_deref());
+        let email = self.value::<String>("email").unwrap_or_default();
+
+        Principal {
+            typ: Some(PrincipalType::OauthClient),
+            name: PrincipalValue::String(self.value::<String>("name").unwrap_or_default()),
+            description: PrincipalValue::String(
+                self.value::<String>("description").unwrap_or_default(),
+            ),
+            picture: PrincipalValue::String(self.value::<String>("picture").unwrap_or_default()),
+            emails: PrincipalValue::StringList(if !email.is_empty() {
+                vec![email]
+            } else {
+                vec![]
+            }),
+            urls: PrincipalValue::StringList(
+                self.array_value("urls")
+                    .map(|url| url.to_string())
+                    .collect(),
+            ),
+            secrets: PrincipalValue::StringList(match client_type {
+                ClientType::Confidential => vec![generate_api_secret()],
+                ClientType::Public => vec![],
+            }),
+            ..Default::default()
+        }
+    }
+}
+
+impl ClientType {
+    const ALL: &'static [(&'static str, &'static str)] = &[
+        ("confidential", "Confidential (server-side application)"),
+        ("public", "Public (native or single-page application)"),
+    ];
+
+    fn id(&self) -> &'static str {
+        match self {
+            ClientType::Confidential => "confidential",
+            ClientType::Public => "public",
+        }
+    }
+
+    fn from_id(id: Option<&str>) -> Self {
+        match id {
+            Some("public") => ClientType::Public,
+            _ => ClientType::Confidential,
+        }
+    }
+}
+
+impl Builder<Schemas, ()> {
+    pub fn build_oauth_client(self) -> Self {
+        self.new_schema("oauth-client")
+            .new_field("name")
+            .typ(Type::Input)
+            .input_check(
+                [Transformer::RemoveSpaces, Transformer::Lowercase],
+                [Validator::Required, Validator::IsId],
+            )
+            .build()
+            .new_field("description")
+            .typ(Type::Input)
+            .input_check([Transformer::Trim], [])
+            .build()
+            .new_field("email")
+            .typ(Type::Input)
+            .input_check(
+                [Transformer::Trim, Transformer::Lowercase],
+                [Validator::IsEmail],
+            )
+            .build()
+            .new_field("picture")
+            .typ(Type::Input)
+            .input_check([Transformer::Trim], [Validator::IsUrl])
+            .build()
+            .new_field("client-type")
+            .typ(Type::Select {
+                typ: SelectType::Single,
+                source: Source::Static(ClientType::ALL),
+            })
+            .default("confidential")
+            .build()
+            .new_field("urls")
+            .typ(Type::Array(ArrayType::Text))
+            .input_check(
+                [Transformer::Trim],
+                [Validator::IsRedirectUri, Validator::MinItems(1)],
+            )
+            .build()
+            .build()
+    }
+}
@@ -0,0 +1,797 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{net::IpAddr, sync::Arc};
+
+use gloo_storage::{LocalStorage, Storage};
+use leptos::*;
+use leptos_router::use_navigate;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::{
+        form::{
+            button::Button,
+            input::{InputPassword, InputText},
+            stacked_input::StackedInput,
+            steps::StepProgress,
+            Form, FormButtonBar, FormElement, FormItem, FormSection,
+        },
+        layout::tenant::use_tenant_scope,
+        list::table::{Table, TableRow},
+        messages::alert::{use_alerts, Alert},
+        skeleton::Skeleton,
+        Color,
+    },
+    core::{
+        http::{self, HttpRequest, ManagementApiError},
+        oauth::use_authorization,
+        password::hash_secret,
+        schema::{ArrayType, Builder, Schemas, Transformer, Type, Validator},
+        Permission,
+    },
+    pages::{
+        directory::{Principal, PrincipalType, PrincipalValue},
+        manage::troubleshoot::{DmarcTroubleshootRequest, DmarcTroubleshootResponse},
+    },
+    ONBOARDING_STORAGE_KEY,
+};
+
+use super::{
+    dns::{format_zonefile, DnsRecord},
+    edit::{Algorithm, DkimSignature},
+};
+
+const DEFAULT_ALIASES: &[&str] = &["abuse", "hostmaster"];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+enum OnboardingStep {
+    #[default]
+    Domain,
+    Dkim,
+    Dns,
+    Postmaster,
+    SelfTest,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct OnboardingState {
+    step: OnboardingStep,
+    domain: String,
+    signatures: Vec<Algorithm>,
+    postmaster: Option<String>,
+}
+
+#[component]
+pub fn DomainOnboarding() -> impl IntoView {
+    let auth = use_authorization();
+    let alert = use_alerts();
+    let tenant_scope = use_tenant_scope();
+    let state = create_rw_signal(
+        LocalStorage::get::<OnboardingState>(ONBOARDING_STORAGE_KEY).unwrap_or_default(),
+    );
+    let data = expect_context::<Arc<Schemas>>()
+        .build_form("domain-onboarding")
+        .into_signal();
+    let self_test: RwSignal<Option<DmarcTroubleshootResponse>> = create_rw_signal(None);
+    let (pending, set_pending) = create_signal(false);
+
+    // Keep the wizard progress across page reloads
+    create_effect(move |_| {
+        let state = state.get();
+        data.update(|data| {
+            data.set("step", state.step.id());
+        });
+        if let Err(err) = LocalStorage::set(ONBOARDING_STORAGE_KEY, &state) {
+            log::error!(
+                "Failed to save onboarding progress to local storage: {}",
+                err
+            );
+        }
+    });
+    data.update(|data| {
+        let domain = state.get_untracked().domain;
+        if !domain.is_empty() {
+            data.set("domain", domain.clone());
+            data.set("name", format!("postmaster@{domain}"));
+            data.array_set(
+                "aliases",
+                DEFAULT_ALIASES
+                    .iter()
+                    .map(|alias| format!("{alias}@{domain}")),
+            );
+        }
+    });
+
+    let create_domain = create_action(move |principal: &Principal| {
+        let principal = principal.clone().with_tenant(tenant_scope.get_untracked());
+        let auth = auth.get();
+
+        async move {
+            set_pending.set(true);
+            let domain = principal.name().unwrap_or_default().to_string();
+            let result = HttpRequest::post("/api/principal")
+                .with_authorization(&auth)
+                .with_body(&principal)
+                .unwrap()
+                .send::<u32>()
+                .await;
+            set_pending.set(false);
+
+            match result {
+                Ok(_) => {}
+                Err(http::Error::Server(ManagementApiError::FieldAlreadyExists { .. })) => {
+                    alert.set(Alert::warning(format!(
+                        "Domain {domain} already exists, continuing with its setup."
+                    )));
+                }
+                Err(err) => {
+                    alert.set(Alert::from(err));
+                    return;
+                }
+            }
+
+            data.update(|data| {
+                data.set("name", format!("postmaster@{domain}"));
+                data.array_set(
+                    "aliases",
+                    DEFAULT_ALIASES
+                        .iter()
+                        .map(|alias| format!("{alias}@{domain}")),
+                );
+            });
+            state.update(|state| {
+                *state = OnboardingState {
+                    step: OnboardingStep::Dkim,
+                    domain,
+                    ..Default::default()
+                };
+            });
+        }
+    });
+
+    let create_signatures = create_action(move |_: &()| {
+        let auth = auth.get();
+        let current = state.get();
+
+        async move {
+            set_pending.set(true);
+            let mut signatures = current.signatures.clone();
+            for algorithm in [Algorithm::Rsa, Algorithm::Ed25519] {
+                if signatures.contains(&algorithm) {
+                    continue;
+                }
+                match HttpRequest::post("/api/dkim")
+                    .with_authorization(&auth)
+                    .with_body(DkimSignature {
+                        algorithm,
+                        domain: current.domain.clone(),
+                        ..Default::default()
+                    })
+                    .unwrap()
+                    .send::<serde_json::Value>()
+                    .await
+                {
+                    Ok(_) => {
+                        signatures.push(algorithm);
+                    }
+                    Err(err) => {
+                        alert.set(Alert::from(err));
+                    }
+                }
+            }
+            set_pending.set(false);
+
+            let completed = signatures.len() == 2;
+            state.update(|state| {
+                state.signatures = signatures;
+                if completed {
+                    state.step = OnboardingStep::Dns;
+                }
+            });
+        }
+    });
+
+    let create_postmaster = create_action(move |principal: &Principal| {
+        let principal = principal.clone().with_tenant(tenant_scope.get_untracked());
+        let auth = auth.get();
+
+        async move {
+            set_pending.set(true);
+            let result = HttpRequest::post("/api/principal")
+                .with_authorization(&auth)
+                .with_body(&principal)
+                .unwrap()
+                .send::<u32>()
+                .await;
+            set_pending.set(false);
+
+            match result {
+                Ok(_) => {
+                    state.update(|state| {
+                        state.postmaster = principal.name().map(|name| name.to_string());
+                        state.step = OnboardingStep::SelfTest;
+                    });
+                }
+                Err(err) => {
+                    alert.set(Alert::from(err));
+                }
+            }
+        }
+    });
+
+    let run_self_test = create_action(move |request: &DmarcTroubleshootRequest| {
+        let request = request.clone();
+        let auth = auth.get();
+
+        async move {
+            set_pending.set(true);
+            match HttpRequest::post("/api/troubleshoot/dmarc")
+                .with_authorization(&auth)
+                .with_body(request)
+                .unwrap()
+                .send::<DmarcTroubleshootResponse>()
+                .await
+            {
+                Ok(response) => {
+                    self_test.set(Some(response));
+                }
+                Err(err) => {
+                    alert.set(Alert::from(err));
+                }
+            }
+            set_pending.set(false);
+        }
+    });
+
+    let dns_records = create_resource(
+        move || {
+            let state = state.get();
+            (state.step == OnboardingStep::Dns).then_some(state.domain)
+        },
+        move |domain| {
+            let auth = auth.get_untracked();
+
+            async move {
+                match domain {
+                    Some(domain) => {
+                        HttpRequest::get(("/api/dns/records", &domain))
+                            .with_authorization(&auth)
+                            .send::<Vec<DnsRecord>>()
+                            .await
+                    }
+                    None => Ok(vec![]),
+                }
+            }
+        },
+    );
+
+    let finish = move |target: String| {
+        LocalStorage::delete(ONBOARDING_STORAGE_KEY);
+        use_navigate()(&target, Default::default());
+    };
+    let step = create_memo(move |_| state.get().step);
+    let domain = create_memo(move |_| state.get().domain);
+    let can_sign = auth
+        .get_untracked()
+        .permissions()
+        .has_access(Permission::DkimSignatureCreate);
+    let can_troubleshoot = auth
+        .get_untracked()
+        .permissions()
+        .has_access(Permission::Troubleshoot);
+    let can_create = auth
+        .get_untracked()
+        .permissions()
+        .has_access(Permission::IndividualCreate);
+
+    view! {
+        <Form
+            title=Signal::derive(move || {
+                if domain.get().is_empty() {
+                    "Add Domain".to_string()
+                } else {
+                    format!("Add Domain '{}'", domain.get())
+                }
+            })
+
+            subtitle=Signal::derive(move || step.get().subtitle().to_string())
+        >
+
+            <StepProgress
+                steps=OnboardingStep::TITLES
+                current=Signal::derive(move || step.get() as usize)
+            />
+
+            // Domain principal
+            <FormSection hide=Signal::derive(move || step.get() != OnboardingStep::Domain)>
+                <FormItem label="Domain name">
+                    <InputText
+                        placeholder="example.org"
+                        element=FormElement::new("domain", data)
+                    />
+                </FormItem>
+                <FormItem label="Description" is_optional=true>
+                    <InputText element=FormElement::new("description", data)/>
+                </FormItem>
+            </FormSection>
+
+            // DKIM signatures
+            <FormSection hide=Signal::derive(move || step.get() != OnboardingStep::Dkim)>
+                <div class="sm:col-span-12 text-sm text-gray-600 dark:text-gray-400">
+                    {move || {
+                        if can_sign {
+                            let signatures = state.get().signatures;
+                            [Algorithm::Rsa, Algorithm::Ed25519]
+                                .into_iter()
+                                .map(|algorithm| {
+                                    let status = if signatures.contains(&algorithm) {
+                                        "created"
+                                    } else {
+                                        "pending"
+                                    };
+                                    view! {
+                                        <p>
+                                            {format!("{} signature: {status}", algorithm.name())}
+                                        </p>
+                                    }
+                                })
+                                .collect_view()
+                        } else {
+                            view! {
+                                <p>
+                                    "You do not have permission to create DKIM signatures. Ask an administrator to create them or skip this step."
+                                </p>
+                            }
+                                .into_view()
+                        }
+                    }}
+
+                </div>
+            </FormSection>
+
+            // DNS records
+            <FormSection hide=Signal::derive(move || step.get() != OnboardingStep::Dns)>
+                <div class="sm:col-span-12">
+                    <Transition fallback=Skeleton>
+                        {move || match dns_records.get() {
+                            None => None,
+                            Some(Err(http::Error::Unauthorized)) => {
+                                use_navigate()("/login", Default::default());
+                                Some(view! { <div></div> }.into_view())
+                            }
+                            Some(Err(err)) => {
+                                alert.set(Alert::from(err));
+                                Some(view! { <div></div> }.into_view())
+                            }
+                            Some(Ok(records)) => {
+                                let zonefile = format_zonefile(
+                                    &records,
+                                    &format!("{}.", domain.get()),
+                                );
+                                Some(
+                                    view! {
+                                        <p class="pb-4 text-sm text-gray-600 dark:text-gray-400">
+                                            "Publish the following records in the DNS zone of the domain before running the self-test."
+                                        </p>
+                                        <Table headers=vec![
+                                            "Type".to_string(),
+                                            "Name".to_string(),
+                                            "Contents".to_string(),
+                                        ]>
+                                            {records
+                                                .into_iter()
+                                                .map(|record| {
+                                                    view! {
+                                                        <TableRow>
+                                                            <span>{record.typ}</span>
+                                                            <span>{record.name}</span>
+                                                            <span>{record.content}</span>
+
+                                                        </TableRow>
+                                                    }
+                                                })
+                                                .collect_view()}
+
+                                        </Table>
+                                        <textarea
+                                            class="mt-6 py-3 px-4 block w-full border-gray-200 rounded-lg text-sm font-mono text-nowrap focus:border-blue-500 focus:ring-blue-500 disabled:opacity-50 disabled:pointer-events-none dark:bg-slate-900 dark:border-gray-700 dark:text-gray-400 dark:focus:ring-gray-600"
+                                            readonly=true
+                                            rows=15
+                                            prop:value=zonefile
+                                        ></textarea>
+                                    }
+                                        .into_view(),
+                                )
+                            }
+                        }}
+
+                    </Transition>
+                </div>
+            </FormSection>
+
+            // Postmaster account
+            <FormSection hide=Signal::derive(move || step.get() != OnboardingStep::Postmaster)>
+                <Show when=move || !can_create>
+                    <div class="sm:col-span-12 text-sm text-gray-600 dark:text-gray-400">
+                        <p>
+                            "You do not have permission to create accounts. Ask an administrator to create the postmaster account or skip this step."
+                        </p>
+                    </div>
+                </Show>
+                <FormItem label="Login name">
+                    <InputText element=FormElement::new("name", data)/>
+                </FormItem>
+                <FormItem label="Password">
+                    <InputPassword element=FormElement::new("password", data) strength_meter=true/>
+                </FormItem>
+                <FormItem label="Aliases" is_optional=true>
+                    <StackedInput
+                        add_button_text="Add alias".to_string()
+                        element=FormElement::new("aliases", data)
+                        placeholder="alias@example.org"
+                    />
+                </FormItem>
+            </FormSection>
+
+            // DMARC and SPF self-test
+            <FormSection hide=Signal::derive(move || step.get() != OnboardingStep::SelfTest)>
+                <FormItem label="Server IP address">
+                    <InputText placeholder="192.0.2.1" element=FormElement::new("remote_ip", data)/>
+                </FormItem>
+                <FormItem label="EHLO hostname">
+                    <InputText
+                        placeholder="mail.example.org"
+                        element=FormElement::new("ehlo_domain", data)
+                    />
+                </FormItem>
+                <div class="sm:col-span-12">
+                    {move || {
+                        self_test
+                            .get()
+                            .map(|response| {
+                                view! {
+                                    <Table headers=vec![
+                                        "Check".to_string(),
+                                        "Domain".to_string(),
+                                        "Result".to_string(),
+                                    ]>
+                                        <TableRow>
+                                            <span>"SPF (EHLO)"</span>
+                                            <span>{response.spf_ehlo_domain}</span>
+                                            <span>{response.spf_ehlo_result}</span>
+                                        </TableRow>
+                                        <TableRow>
+                                            <span>"SPF (MAIL FROM)"</span>
+                                            <span>{response.spf_mail_from_domain}</span>
+                                            <span>{response.spf_mail_from_result}</span>
+                                        </TableRow>
+                                        <TableRow>
+                                            <span>"DMARC"</span>
+                                            <span>{domain.get()}</span>
+                                            <span>{response.dmarc_result}</span>
+                                        </TableRow>
+                                    </Table>
+                                    <p class="mt-3 text-sm text-gray-600 dark:text-gray-400">
+                                        "DKIM is not tested as the self-test message is not signed, DMARC relies on SPF alignment here. Send a message from the postmaster account to verify DKIM."
+                                    </p>
+                                }
+                            })
+                    }}
+
+                </div>
+            </FormSection>
+
+            <FormButtonBar>
+                <Button
+                    text="Cancel"
+                    color=Color::Gray
+                    on_click=move |_| {
+                        finish("/manage/directory/domains".to_string());
+                    }
+                />
+
+                {move || match step.get() {
+                    OnboardingStep::Domain => {
+                        view! {
+                            <Button
+                                text="Create domain"
+                                color=Color::Blue
+                                on_click=Callback::new(move |_| {
+                                    data.update(|data| {
+                                        if data.validate_form() {
+                                            create_domain
+                                                .dispatch(Principal {
+                                                    typ: Some(PrincipalType::Domain),
+                                                    name: PrincipalValue::String(
+                                                        data.value::<String>("domain").unwrap_or_default(),
+                                                    ),
+                                                    description: PrincipalValue::String(
+                                                        data.value::<String>("description").unwrap_or_default(),
+                                                    ),
+                                                    ..Default::default()
+                                                });
+                                        }
+                                    });
+                                })
+
+                                disabled=pending
+                            />
+                        }
+                            .into_view()
+                    }
+                    OnboardingStep::Dkim => {
+                        view! {
+                            <Button
+                                text="Skip"
+                                color=Color::Gray
+                                on_click=move |_| {
+                                    state.update(|state| state.step = OnboardingStep::Dns);
+                                }
+                            />
+
+                            <Button
+                                text="Create signatures"
+                                color=Color::Blue
+                                on_click=move |_| {
+                                    create_signatures.dispatch(());
+                                }
+
+                                disabled=Signal::derive(move || pending.get() || !can_sign)
+                            />
+                        }
+                            .into_view()
+                    }
+                    OnboardingStep::Dns => {
+                        view! {
+                            <Button
+                                text="Continue"
+                                color=Color::Blue
+                                on_click=move |_| {
+                                    state.update(|state| state.step = OnboardingStep::Postmaster);
+                                }
+                            />
+                        }
+                            .into_view()
+                    }
+                    OnboardingStep::Postmaster => {
+                        view! {
+                            <Button
+                                text="Skip"
+                                color=Color::Gray
+                                on_click=move |_| {
+                                    state.update(|state| state.step = OnboardingStep::SelfTest);
+                                }
+                            />
+
+                            <Button
+                                text="Create account"
+                                color=Color::Blue
+                                on_click=Callback::new(move |_| {
+                                    data.update(|data| {
+                                        if data.validate_form() {
+                                            let secret = match data
+                                                .value::<String>("password")
+                                                .map(hash_secret)
+                                                .transpose()
+                                            {
+                                                Ok(secret) => secret,
+                                                Err(err) => {
+                                                    data.new_error("password", err);
+                                                    return;
+                                                }
+                                            };
+                                            let domain = domain.get_untracked();
+                                            create_postmaster
+                                                .dispatch(Principal {
+                                                    typ: Some(PrincipalType::Individual),
+                                                    name: PrincipalValue::String(
+                                                        data.value::<String>("name").unwrap_or_default(),
+                                                    ),
+                                                    description: PrincipalValue::String(
+                                                        "Postmaster".to_string(),
+                                                    ),
+                                                    secrets: PrincipalValue::StringList(
+                                                        secret.into_iter().collect(),
+                                                    ),
+                                                    emails: PrincipalValue::StringList(
+                                                        [format!("postmaster@{domain}")]
+                                                            .into_iter()
+                                                            .chain(
+                                                                data.array_value("aliases").map(|alias| alias.to_string()),
+                                                            )
+                                                            .collect(),
+                                                    ),
+                                                    ..Default::default()
+                                                });
+                                        }
+                                    });
+                                })
+
+                                disabled=Signal::derive(move || pending.get() || !can_create)
+                            />
+                        }
+                            .into_view()
+                    }
+                    OnboardingStep::SelfTest => {
+                        view! {
+                            <Button
+                                text="Run self-test"
+                                color=Color::Gray
+                                on_click=Callback::new(move |_| {
+                                    data.update(|data| {
+                                        if !data.validate_form() {
+                                            return;
+                                        }
+                                        if let Some(remote_ip) = data.value::<IpAddr>("remote_ip") {
+                                            run_self_test
+                                                .dispatch(DmarcTroubleshootRequest {
+                                                    remote_ip,
+                                                    ehlo_domain: data
+                                                        .value::<String>("ehlo_domain")
+                                                        .unwrap_or_default(),
+                                                    mail_from: format!(
+                                                        "postmaster@{}",
+                                                        domain.get_untracked(),
+                                                    ),
+                                                    body: Some(
+                                                        self_test_message(&domain.get_untracked()),
+                                                    ),
+                                                });
+                                        } else {
+                                            alert.set(Alert::error("Enter a single IP address."));
+                                        }
+                                    });
+                                })
+
+                                disabled=Signal::derive(move || pending.get() || !can_troubleshoot)
+                            />
+
+                            <Button
+                                text="Finish"
+                                color=Color::Blue
+                                on_click=move |_| {
+                                    finish(format!("/manage/dns/{}/view", domain.get_untracked()));
+                                }
+                            />
+                        }
+                            .into_view()
+                    }
+                }}
+
+            </FormButtonBar>
+        </Form>
+    }
+}
+
+/// Minimal message used by the self-test so that DMARC has a From header to
+/// align against. It is not signed, so DKIM cannot pass.
+fn self_test_message(domain: &str) -> String {
+    format!(
+        concat!(
+            "From: <postmaster@{domain}>\r\n",
+            "To: <postmaster@{domain}>\r\n",
+            "Subject: Domain self-test\r\n",
+            "Message-ID: <self-test@{domain}>\r\n",
+            "\r\n",
+            "Self-test message for {domain}.\r\n",
+        ),
+        domain = domain
+    )
+}
+
+impl OnboardingStep {
+    const TITLES: &'static [&'static str] = &["Domain", "DKIM", "DNS", "Postmaster", "Self-test"];
+
+    fn id(&self) -> &'static str {
+        match self {
+            OnboardingStep::Domain => "domain",
+            OnboardingStep::Dkim => "dkim",
+            OnboardingStep::Dns => "dns",
+            OnboardingStep::Postmaster => "postmaster",
+            OnboardingStep::SelfTest => "self-test",
+        }
+    }
+
+    fn subtitle(&self) -> &'static str {
+        match self {
+            OnboardingStep::Domain => "Enter the name of the domain to add",
+            OnboardingStep::Dkim => "Generate RSA and Ed25519 DKIM signatures for the domain",
+            OnboardingStep::Dns => "Publish the DNS records required by the domain",
+            OnboardingStep::Postmaster => "Create the postmaster account and its aliases",
+            OnboardingStep::SelfTest => "Verify the SPF and DMARC setup of the domain",
+        }
+    }
+}
+
+impl Algorithm {
+    fn name(&self) -> &'static str {
+        match self {
+            Algorithm::Rsa => "RSA",
+            Algorithm::Ed25519 => "Ed25519",
+        }
+    }
+}
+
+impl Builder<Schemas, ()> {
+    pub fn build_onboarding(self) -> Self {
+        self.new_schema("domain-onboarding")
+            .new_field("step")
+            .typ(Type::Input)
+            .build()
+            .new_field("domain")
+            .typ(Type::Input)
+            .display_if_eq("step", ["domain"])
+            .input_check(
+                [Transformer::Trim, Transformer::Lowercase],
+                [Validator::Required, Validator::IsDomain],
+            )
+            .build()
+            .new_field("description")
+            .typ(Type::Input)
+            .display_if_eq("step", ["domain"])
+            .input_check([Transformer::Trim], [])
+            .build()
+            .new_field("name")
+            .typ(Type::Input)
+            .display_if_eq("step", ["postmaster"])
+            .input_check(
+                [Transformer::RemoveSpaces, Transformer::Lowercase],
+                [Validator::Required],
+            )
+            .build()
+            .new_field("password")
+            .typ(Type::Secret)
+            .display_if_eq("step", ["postmaster"])
+            .input_check(
+                [],
+                [
+                    Validator::Required,
+                    Validator::IsStrongPassword {
+                        personal: &["name"],
+                    },
+                ],
+            )
+            .build()
+            .new_field("aliases")
+            .typ(Type::Array(ArrayType::Text))
+            .display_if_eq("step", ["postmaster"])
+            .input_check(
+                [Transformer::Trim, Transformer::Lowercase],
+                [Validator::IsEmail],
+            )
+            .build()
+            .new_field("remote_ip")
+            .typ(Type::Input)
+            .display_if_eq("step", ["self-test"])
+            .input_check(
+                [Transformer::Trim],
+                [Validator::Required, Validator::IsIpOrMask],
+            )
+            .build()
+            .new_field("ehlo_domain")
+            .typ(Type::Input)
+            .display_if_eq("step", ["self-test"])
+            .input_check(
+                [Transformer::Trim, Transformer::Lowercase],
+                [Validator::Required],
+            )
+            .build()
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn self_test_message_is_aligned() {
+        let message = self_test_message("example.org");
+        assert!(message.starts_with("From: <postmaster@example.org>\r\n"));
+        assert!(message.contains("\r\n\r\n"));
+    }
+}
@@ -0,0 +1,912 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::sync::Arc;
+
+use ahash::AHashMap;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::Utc;
+use humansize::{format_size, DECIMAL};
+use leptos::*;
+use leptos_router::use_navigate;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::{
+        badge::Badge,
+        form::{
+            input::{InputSwitch, InputText},
+            select::CheckboxGroup,
+            tab::Tab,
+            Form, FormElement, FormItem, FormSection,
+        },
+        layout::tenant::use_tenant_scope,
+        list::table::{Table, TableRow},
+        messages::alert::{use_alerts, Alert},
+        skeleton::Skeleton,
+        Color,
+    },
+    core::{
+        http::{self, HttpRequest},
+        oauth::use_authorization,
+        schema::{Builder, Schemas, SelectType, Source, Transformer, Type, Validator},
+        AccessToken, Permission,
+    },
+    pages::{
+        directory::{
+            import::csv_escape, Principal, PrincipalField, PrincipalType, PrincipalUpdate,
+            PrincipalValue,
+        },
+        List,
+    },
+};
+
+const QUOTA_PAGE_SIZE: u32 = 100;
+const MB: u64 = 1024 * 1024;
+
+// Positions of the tenant limits within the quota list
+const TENANT_MAX_ACCOUNTS: usize = 1;
+const TENANT_MAX_GROUPS: usize = 2;
+const TENANT_MAX_DOMAINS: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct QuotaRow {
+    name: String,
+    description: String,
+    typ: PrincipalType,
+    tenant: Option<String>,
+    domain: Option<String>,
+    quota: u64,
+    used: u64,
+    limits: Vec<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum QuotaStatus {
+    Unlimited,
+    Ok,
+    Warning,
+    Critical,
+    Full,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Thresholds {
+    warning: u64,
+    critical: u64,
+    full: u64,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Thresholds {
+            warning: 80,
+            critical: 95,
+            full: 100,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Rollup {
+    name: String,
+    accounts: usize,
+    groups: usize,
+    domains: usize,
+    quota: u64,
+    used: u64,
+    limits: Vec<u64>,
+}
+
+#[component]
+pub fn QuotaReport() -> impl IntoView {
+    let auth = use_authorization();
+    let alert = use_alerts();
+    let show_tenants = auth.get_untracked().is_enterprise()
+        && auth
+            .get_untracked()
+            .permissions()
+            .has_access(Permission::TenantList);
+    let can_update = move |typ: PrincipalType| {
+        update_permission(typ)
+            .is_some_and(|permission| auth.get_untracked().permissions().has_access(permission))
+    };
+    let can_raise = [
+        PrincipalType::Individual,
+        PrincipalType::Group,
+        PrincipalType::Domain,
+        PrincipalType::Tenant,
+    ]
+    .into_iter()
+    .any(can_update);
+
+    let mut data = expect_context::<Arc<Schemas>>().build_form("quota-report");
+    data.apply_defaults(false);
+    let data = data.into_signal();
+
+    let rows: RwSignal<Vec<QuotaRow>> = create_rw_signal(Vec::new());
+    let tenant_scope = use_tenant_scope();
+    let fetch_rows = create_resource(
+        move || tenant_scope.get(),
+        move |tenant| {
+            let auth = auth.get_untracked();
+
+            async move { fetch_quotas(&auth, show_tenants, tenant).await }
+        },
+    );
+
+    let raise_quota = create_action(move |name: &String| {
+        let name = name.clone();
+        let auth = auth.get();
+        let (raise_by, row) = (
+            data.with_untracked(|data| data.value::<u64>("raise-by").unwrap_or(25)),
+            rows.with_untracked(|rows| rows.iter().find(|row| row.name == name).cloned()),
+        );
+
+        async move {
+            let Some(row) = row else {
+                return;
+            };
+            let quota = row.raised_quota(raise_by);
+            let value = row.quota_value(quota);
+
+            match HttpRequest::patch(("/api/principal", &name))
+                .with_authorization(&auth)
+                .with_body(vec![PrincipalUpdate::set(PrincipalField::Quota, value)])
+                .unwrap()
+                .send::<serde_json::Value>()
+                .await
+            {
+                Ok(_) => {
+                    rows.update(|rows| {
+                        if let Some(row) = rows.iter_mut().find(|row| row.name == name) {
+                            row.quota = quota;
+                            if let Some(limit) = row.limits.first_mut() {
+                                *limit = quota;
+                            }
+                        }
+                    });
+                    alert.set(Alert::success(format!(
+                        "Quota for {name} raised to {}.",
+                        format_size(quota, DECIMAL)
+                    )));
+                }
+                Err(http::Error::Unauthorized) => {
+                    use_navigate()("/login", Default::default());
+                }
+                Err(err) => {
+                    alert.set(Alert::from(err));
+                }
+            }
+        }
+    });
+
+    let entered_thresholds = create_memo(move |_| {
+        data.with(|data| {
+            let defaults = Thresholds::default();
+            Thresholds {
+                warning: data.value::<u64>("warning").unwrap_or(defaults.warning),
+                critical: data.value::<u64>("critical").unwrap_or(defaults.critical),
+                full: data.value::<u64>("full").unwrap_or(defaults.full),
+            }
+        })
+    });
+    let threshold_error = create_memo(move |_| entered_thresholds.get().validate().err());
+    // Out of order thresholds would flag rows inconsistently, use the defaults until they are fixed
+    let thresholds = create_memo(move |_| {
+        let thresholds = entered_thresholds.get();
+        if thresholds.validate().is_ok() {
+            thresholds
+        } else {
+            Thresholds::default()
+        }
+    });
+    let type_options = create_memo(move |_| {
+        data.with(|data| data.select_sources("types"))
+            .into_iter()
+            .filter(|(id, _)| show_tenants || id != "tenant")
+            .collect::<Vec<_>>()
+    });
+    let filtered = create_memo(move |_| {
+        let thresholds = thresholds.get();
+        let (types, flagged_only) = data.with(|data| {
+            (
+                data.array_value("types")
+                    .filter_map(|id| serde_json::from_value::<PrincipalType>(id.into()).ok())
+                    .collect::<Vec<_>>(),
+                data.value::<bool>("flagged-only").unwrap_or_default(),
+            )
+        });
+        let mut filtered = rows.with(|rows| {
+            rows.iter()
+                .filter(|row| {
+                    types.contains(&row.typ)
+                        && (!flagged_only
+                            || matches!(
+                                row.status(&thresholds),
+                                QuotaStatus::Warning | QuotaStatus::Critical | QuotaStatus::Full
+                            ))
+                })
+                .cloned()
+                .collect::<Vec<_>>()
+        });
+        filtered.sort_by(|a, b| {
+            b.percent()
+                .unwrap_or(-1.0)
+                .total_cmp(&a.percent().unwrap_or(-1.0))
+                .then_with(|| b.used.cmp(&a.used))
+        });
+        filtered
+    });
+    let domains = create_memo(move |_| rows.with(|rows| rollup_domains(rows)));
+    let tenants = create_memo(move |_| rows.with(|rows| rollup_tenants(rows)));
+    let export_url = create_memo(move |_| {
+        let thresholds = thresholds.get();
+        filtered.with(|rows| {
+            format!(
+                "data:text/csv;base64,{}",
+                STANDARD.encode(report_csv(rows, &thresholds))
+            )
+        })
+    });
+
+    view! {
+        <Form title="Quota Report" subtitle="Disk usage across accounts, groups, domains and tenants">
+
+            <FormSection>
+                <FormItem label="Principal types">
+                    <CheckboxGroup element=FormElement::new("types", data) options=type_options/>
+                </FormItem>
+                <FormItem
+                    label="Thresholds"
+                    tooltip="Usage percentages at which principals are flagged as warning, critical and full"
+                >
+                    <div class="grid grid-cols-3 gap-x-4">
+                        <InputText element=FormElement::new("warning", data) placeholder="80"/>
+                        <InputText element=FormElement::new("critical", data) placeholder="95"/>
+                        <InputText element=FormElement::new("full", data) placeholder="100"/>
+                    </div>
+                    {move || {
+                        threshold_error
+                            .get()
+                            .map(|error| {
+                                view! { <p class="text-xs text-red-600 mt-2">{error}</p> }
+                            })
+                    }}
+
+                </FormItem>
+                <FormItem
+                    label="Raise by (%)"
+                    tooltip="Percentage added to the current quota when using the raise action"
+                    hide=!can_raise
+                >
+                    <InputText element=FormElement::new("raise-by", data) placeholder="25"/>
+                </FormItem>
+                <FormItem label="">
+                    <InputSwitch
+                        label="Flagged only"
+                        tooltip="Only list principals above the warning threshold"
+                        element=FormElement::new("flagged-only", data)
+                    />
+                </FormItem>
+            </FormSection>
+
+            <Transition fallback=Skeleton>
+                {move || match fetch_rows.get() {
+                    None => None,
+                    Some(Err(http::Error::Unauthorized)) => {
+                        use_navigate()("/login", Default::default());
+                        Some(view! { <div></div> }.into_view())
+                    }
+                    Some(Err(err)) => {
+                        alert.set(Alert::from(err));
+                        Some(view! { <div></div> }.into_view())
+                    }
+                    Some(Ok(fetched)) => {
+                        rows.set(fetched);
+                        Some(
+                            view! {
+                                <div class="mt-5 flex justify-end">
+                                    <a
+                                        class="py-2 px-3 inline-flex items-center gap-x-2 text-sm font-semibold rounded-lg border border-gray-200 bg-white text-gray-800 shadow-sm hover:bg-gray-50 dark:bg-slate-900 dark:border-gray-700 dark:text-white dark:hover:bg-gray-800"
+                                        download=format!("quota-report-{}.csv", Utc::now().format("%Y%m%d"))
+                                        href=move || export_url.get()
+                                    >
+                                        "Export CSV"
+                                    </a>
+                                </div>
+                                <Tab tabs=vec![
+                                    Some("Principals".to_string()),
+                                    Some("Domains".to_string()),
+                                    show_tenants.then_some("Tenants".to_string()),
+                                ]>
+                                    <div class="mt-5">
+                                        <Table headers=vec![
+                                            "Name".to_string(),
+                                            "Type".to_string(),
+                                            "Used".to_string(),
+                                            "Quota".to_string(),
+                                            "Status".to_string(),
+                                            "".to_string(),
+                                        ]>
+                                            <For
+                                                each=move || filtered.get()
+                                                key=|row| (row.name.clone(), row.quota, row.used)
+                                                children=move |row| {
+                                                    let status = row.status(&thresholds.get_untracked());
+                                                    let (color, label) = status.badge();
+                                                    let name = row.name.clone();
+                                                    view! {
+                                                        <TableRow>
+                                                            <div class="flex flex-col">
+                                                                <span class="font-semibold">{row.name.clone()}</span>
+                                                                <span class="text-xs text-gray-500">
+                                                                    {row.description.clone()}
+                                                                </span>
+                                                            </div>
+                                                            <span>{row.typ.name()}</span>
+                                                            <span>
+                                                                {match row.percent() {
+                                                                    Some(percent) => {
+                                                                        format!(
+                                                                            "{} ({percent:.1}%)",
+                                                                            format_size(row.used, DECIMAL),
+                                                                        )
+                                                                    }
+                                                                    None => format_size(row.used, DECIMAL).to_string(),
+                                                                }}
+
+                                                            </span>
+                                                            <span>
+                                                                {if row.quota > 0 {
+                                                                    format_size(row.quota, DECIMAL).to_string()
+                                                                } else {
+                                                                    "Unlimited".to_string()
+                                                                }}
+
+                                                            </span>
+                                                            <Badge color=color>{label}</Badge>
+                                                            {(can_update(row.typ) && row.quota > 0)
+                                                                .then(|| {
+                                                                    view! {
+                                                                        <button
+                                                                            type="button"
+                                                                            class="text-sm font-semibold text-blue-600 hover:underline disabled:opacity-50 dark:text-blue-500"
+                                                                            disabled=move || raise_quota.pending().get()
+                                                                            on:click=move |_| {
+                                                                                raise_quota.dispatch(name.clone());
+                                                                            }
+                                                                        >
+                                                                            "Raise quota"
+                                                                        </button>
+                                                                    }
+                                                                })}
+
+                                                        </TableRow>
+                                                    }
+                                                }
+                                            />
+
+                                        </Table>
+                                    </div>
+                                    <div class="mt-5">
+                                        <Table headers=vec![
+                                            "Domain".to_string(),
+                                            "Accounts".to_string(),
+                                            "Used".to_string(),
+                                            "Allocated".to_string(),
+                                        ]>
+                                            {move || {
+                                                domains
+                                                    .get()
+                                                    .into_iter()
+                                                    .map(|domain| {
+                                                        view! {
+                                                            <TableRow>
+                                                                <span class="font-semibold">{domain.name}</span>
+                                                                <span>{domain.accounts}</span>
+                                                                <span>{format_size(domain.used, DECIMAL)}</span>
+                                                                <span>{format_size(domain.quota, DECIMAL)}</span>
+                                                            </TableRow>
+                                                        }
+                                                    })
+                                                    .collect_view()
+                                            }}
+
+                                        </Table>
+                                    </div>
+                                    <div class="mt-5">
+                                        <Table headers=vec![
+                                            "Tenant".to_string(),
+                                            "Used".to_string(),
+                                            "Accounts".to_string(),
+                                            "Groups".to_string(),
+                                            "Domains".to_string(),
+                                        ]>
+                                            {move || {
+                                                tenants
+                                                    .get()
+                                                    .into_iter()
+                                                    .map(|tenant| {
+                                                        view! {
+                                                            <TableRow>
+                                                                <span class="font-semibold">{tenant.name.clone()}</span>
+                                                                <span>{tenant.usage(tenant.used, tenant.quota, true)}</span>
+                                                                <span>
+                                                                    {tenant
+                                                                        .usage(
+                                                                            tenant.accounts as u64,
+                                                                            tenant.limit(TENANT_MAX_ACCOUNTS),
+                                                                            false,
+                                                                        )}
+
+                                                                </span>
+                                                                <span>
+                                                                    {tenant
+                                                                        .usage(
+                                                                            tenant.groups as u64,
+                                                                            tenant.limit(TENANT_MAX_GROUPS),
+                                                                            false,
+                                                                        )}
+
+                                                                </span>
+                                                                <span>
+                                                                    {tenant
+                                                                        .usage(
+                                                                            tenant.domains as u64,
+                                                                            tenant.limit(TENANT_MAX_DOMAINS),
+                                                                            false,
+                                                                        )}
+
+                                                                </span>
+                                                            </TableRow>
+                                                        }
+                                                    })
+                                                    .collect_view()
+                                            }}
+
+                                        </Table>
+                                    </div>
+                                </Tab>
+                            }
+                                .into_view(),
+                        )
+                    }
+                }}
+
+            </Transition>
+        </Form>
+    }
+}
+
+async fn fetch_quotas(
+    auth: &AccessToken,
+    include_tenants: bool,
+    tenant: Option<String>,
+) -> http::Result<Vec<QuotaRow>> {
+    let types = if include_tenants && tenant.is_none() {
+        "individual,group,domain,tenant"
+    } else {
+        "individual,group,domain"
+    };
+    let mut principals = Vec::new();
+    let mut page = 1;
+
+    loop {
+        let list = HttpRequest::get("/api/principal")
+            .with_authorization(auth)
+            .with_parameter("page", page.to_string())
+            .with_parameter("limit", QUOTA_PAGE_SIZE.to_string())
+            .with_parameter("types", types)
+            .with_parameter(
+                "fields",
+                "name,type,description,emails,tenant,quota,usedQuota",
+            )
+            .with_optional_parameter("tenant", tenant.clone())
+            .send::<List<Principal>>()
+            .await?;
+        let fetched = list.items.len();
+        principals.extend(list.items);
+
+        if fetched == 0 || principals.len() as u64 >= list.total {
+            break;
+        }
+        page += 1;
+    }
+
+    // Tenants do not belong to themselves, fetch the scoped one separately
+    if let Some(tenant) = tenant.filter(|_| include_tenants) {
+        principals.push(
+            HttpRequest::get(("/api/principal", &tenant))
+                .with_authorization(auth)
+                .send::<Principal>()
+                .await?,
+        );
+    }
+
+    Ok(principals
+        .into_iter()
+        .filter_map(|principal| {
+            let typ = principal.typ?;
+            let name = principal.name.as_str()?.to_string();
+            let domain = match typ {
+                PrincipalType::Domain => Some(name.clone()),
+                _ => principal
+                    .email()
+                    .or(Some(name.as_str()))
+                    .and_then(|address| address.rsplit_once('@'))
+                    .map(|(_, domain)| domain.to_lowercase()),
+            };
+            let limits = match &principal.quota {
+                PrincipalValue::IntegerList(limits) => limits.clone(),
+                _ => Vec::new(),
+            };
+
+            Some(QuotaRow {
+                description: principal
+                    .description
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                tenant: principal
+                    .tenant
+                    .as_str()
+                    .filter(|tenant| !tenant.is_empty())
+                    .map(|tenant| tenant.to_string()),
+                quota: principal.quota.as_int().unwrap_or_default(),
+                used: principal.used_quota.as_int().unwrap_or_default(),
+                name,
+                typ,
+                domain,
+                limits,
+            })
+        })
+        .collect())
+}
+
+fn rollup_domains(rows: &[QuotaRow]) -> Vec<Rollup> {
+    let mut domains: AHashMap<String, Rollup> = AHashMap::new();
+
+    for row in rows {
+        if let Some(domain) = &row.domain {
+            let rollup = domains.entry(domain.clone()).or_insert_with(|| Rollup {
+                name: domain.clone(),
+                ..Default::default()
+            });
+            if row.typ == PrincipalType::Individual {
+                rollup.accounts += 1;
+                rollup.used += row.used;
+                rollup.quota += row.quota;
+            }
+        }
+    }
+
+    let mut domains = domains.into_values().collect::<Vec<_>>();
+    domains.sort_by(|a, b| b.used.cmp(&a.used).then_with(|| a.name.cmp(&b.name)));
+    domains
+}
+
+fn rollup_tenants(rows: &[QuotaRow]) -> Vec<Rollup> {
+    let mut tenants = rows
+        .iter()
+        .filter(|row| row.typ == PrincipalType::Tenant)
+        .map(|row| {
+            (
+                row.name.clone(),
+                Rollup {
+                    name: row.name.clone(),
+                    quota: row.quota,
+                    limits: row.limits.clone(),
+                    ..Default::default()
+                },
+            )
+        })
+        .collect::<AHashMap<_, _>>();
+
+    for row in rows {
+        if let Some(rollup) = row
+            .tenant
+            .as_ref()
+            .and_then(|tenant| tenants.get_mut(tenant))
+        {
+            match row.typ {
+                PrincipalType::Individual => {
+                    rollup.accounts += 1;
+                    rollup.used += row.used;
+                }
+                PrincipalType::Group => {
+                    rollup.groups += 1;
+                    rollup.used += row.used;
+                }
+                PrincipalType::Domain => {
+                    rollup.domains += 1;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut tenants = tenants.into_values().collect::<Vec<_>>();
+    tenants.sort_by(|a, b| a.name.cmp(&b.name));
+    tenants
+}
+
+fn report_csv(rows: &[QuotaRow], thresholds: &Thresholds) -> String {
+    let mut csv = "name,type,tenant,domain,quota,used,percent,status\n".to_string();
+    for row in rows {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{}\n",
+            csv_escape(&row.name),
+            row.typ.id(),
+            csv_escape(row.tenant.as_deref().unwrap_or_default()),
+            csv_escape(row.domain.as_deref().unwrap_or_default()),
+            row.quota,
+            row.used,
+            row.percent()
+                .map(|percent| format!("{percent:.1}"))
+                .unwrap_or_default(),
+            row.status(thresholds).badge().1
+        ));
+    }
+    csv
+}
+
+impl Thresholds {
+    fn validate(&self) -> Result<(), String> {
+        if self.warning > self.critical {
+            Err("The warning threshold cannot be above the critical threshold".to_string())
+        } else if self.critical > self.full {
+            Err("The critical threshold cannot be above the full threshold".to_string())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl QuotaRow {
+    fn percent(&self) -> Option<f64> {
+        (self.quota > 0).then(|| self.used as f64 / self.quota as f64 * 100.0)
+    }
+
+    fn status(&self, thresholds: &Thresholds) -> QuotaStatus {
+        match self.percent() {
+            None => QuotaStatus::Unlimited,
+            Some(percent) if percent >= thresholds.full as f64 => QuotaStatus::Full,
+            Some(percent) if percent >= thresholds.critical as f64 => QuotaStatus::Critical,
+            Some(percent) if percent >= thresholds.warning as f64 => QuotaStatus::Warning,
+            Some(_) => QuotaStatus::Ok,
+        }
+    }
+
+    // New quotas are rounded up to the next megabyte
+    fn raised_quota(&self, percent: u64) -> u64 {
+        let quota = self.quota.max(self.used);
+        let raised = quota.saturating_add(quota / 100 * percent.max(1));
+        raised.div_ceil(MB) * MB
+    }
+
+    // Tenants store their limits as a list led by the disk quota, unless
+    // only the disk quota was ever set.
+    fn quota_value(&self, quota: u64) -> PrincipalValue {
+        if self.typ == PrincipalType::Tenant && !self.limits.is_empty() {
+            let mut limits = self.limits.clone();
+            limits[0] = quota;
+            PrincipalValue::IntegerList(limits)
+        } else {
+            PrincipalValue::Integer(quota)
+        }
+    }
+}
+
+fn update_permission(typ: PrincipalType) -> Option<Permission> {
+    match typ {
+        PrincipalType::Individual => Some(Permission::IndividualUpdate),
+        PrincipalType::Group => Some(Permission::GroupUpdate),
+        PrincipalType::Domain => Some(Permission::DomainUpdate),
+        PrincipalType::Tenant => Some(Permission::TenantUpdate),
+        _ => None,
+    }
+}
+
+impl QuotaStatus {
+    fn badge(&self) -> (Color, &'static str) {
+        match self {
+            QuotaStatus::Unlimited => (Color::Gray, "Unlimited"),
+            QuotaStatus::Ok => (Color::Green, "OK"),
+            QuotaStatus::Warning => (Color::Yellow, "Warning"),
+            QuotaStatus::Critical => (Color::Red, "Critical"),
+            QuotaStatus::Full => (Color::Red, "Full"),
+        }
+    }
+}
+
+impl Rollup {
+    fn limit(&self, idx: usize) -> u64 {
+        self.limits.get(idx).copied().unwrap_or_default()
+    }
+
+    fn usage(&self, used: u64, limit: u64, is_size: bool) -> String {
+        let format = |value: u64| {
+            if is_size {
+                format_size(value, DECIMAL).to_string()
+            } else {
+                value.to_string()
+            }
+        };
+
+        if limit > 0 {
+            format!(
+                "{} of {} ({:.0}%)",
+                format(used),
+                format(limit),
+                used as f64 / limit as f64 * 100.0
+            )
+        } else {
+            format(used)
+        }
+    }
+}
+
+impl Builder<Schemas, ()> {
+    pub fn build_quota_report(self) -> Self {
+        self.new_schema("quota-report")
+            .new_field("types")
+            .typ(Type::Select {
+                typ: SelectType::Many,
+                source: Source::Static(&[
+                    ("individual", "Accounts"),
+                    ("group", "Groups"),
+                    ("tenant", "Tenants"),
+                ]),
+            })
+            .default(["individual", "group", "tenant"].as_slice())
+            .build()
+            .new_field("warning")
+            .typ(Type::Input)
+            .input_check(
+                [Transformer::Trim],
+                [
+                    Validator::MinValue(1.into()),
+                    Validator::MaxValue(100.into()),
+                ],
+            )
+            .default("80")
+            .build()
+            .new_field("critical")
+            .typ(Type::Input)
+            .input_check(
+                [Transformer::Trim],
+                [
+                    Validator::MinValue(1.into()),
+                    Validator::MaxValue(100.into()),
+                ],
+            )
+            .default("95")
+            .build()
+            .new_field("full")
+            .typ(Type::Input)
+            .input_check(
+                [Transformer::Trim],
+                [
+                    Validator::MinValue(1.into()),
+                    Validator::MaxValue(1000.into()),
+                ],
+            )
+            .default("100")
+            .build()
+            .new_field("raise-by")
+            .typ(Type::Input)
+            .input_check(
+                [Transformer::Trim],
+                [
+                    Validator::MinValue(1.into()),
+                    Validator::MaxValue(1000.into()),
+                ],
+            )
+            .default("25")
+            .build()
+            .new_field("flagged-only")
+            .typ(Type::Boolean)
+            .default("false")
+            .build()
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(name: &str, domain: &str, quota: u64, used: u64) -> QuotaRow {
+        QuotaRow {
+            name: name.to_string(),
+            description: String::new(),
+            typ: PrincipalType::Individual,
+            tenant: None,
+            domain: Some(domain.to_string()),
+            quota,
+            used,
+            limits: vec![],
+        }
+    }
+
+    #[test]
+    fn validates_threshold_order() {
+        assert!(Thresholds::default().validate().is_ok());
+        let thresholds = |warning, critical, full| Thresholds {
+            warning,
+            critical,
+            full,
+        };
+        assert!(thresholds(80, 80, 80).validate().is_ok());
+        assert!(thresholds(90, 80, 100).validate().is_err());
+        assert!(thresholds(80, 110, 100).validate().is_err());
+    }
+
+    #[test]
+    fn flags_usage() {
+        let thresholds = Thresholds::default();
+        assert_eq!(
+            row("a", "x.org", 0, 10).status(&thresholds),
+            QuotaStatus::Unlimited
+        );
+        assert_eq!(
+            row("a", "x.org", 100, 50).status(&thresholds),
+            QuotaStatus::Ok
+        );
+        assert_eq!(
+            row("a", "x.org", 100, 85).status(&thresholds),
+            QuotaStatus::Warning
+        );
+        assert_eq!(
+            row("a", "x.org", 100, 97).status(&thresholds),
+            QuotaStatus::Critical
+        );
+        assert_eq!(
+            row("a", "x.org", 100, 120).status(&thresholds),
+            QuotaStatus::Full
+        );
+    }
+
+    #[test]
+    fn raises_to_whole_megabytes() {
+        assert_eq!(row("a", "x.org", 100 * MB, 0).raised_quota(25), 125 * MB);
+        assert_eq!(row("a", "x.org", 10, 200 * MB).raised_quota(10), 220 * MB);
+    }
+
+    #[test]
+    fn keeps_tenant_quota_shape() {
+        let mut tenant = row("t", "x.org", 100, 0);
+        tenant.typ = PrincipalType::Tenant;
+        assert_eq!(tenant.quota_value(200), PrincipalValue::Integer(200));
+
+        tenant.limits = vec![100, 5, 10];
+        assert_eq!(
+            tenant.quota_value(200),
+            PrincipalValue::IntegerList(vec![200, 5, 10])
+        );
+        assert_eq!(
+            row("a", "x.org", 100, 0).quota_value(200),
+            PrincipalValue::Integer(200)
+        );
+        assert_eq!(
+            update_permission(PrincipalType::Group),
+            Some(Permission::GroupUpdate)
+        );
+        assert_eq!(update_permission(PrincipalType::ApiKey), None);
+    }
+
+    #[test]
+    fn rolls_up_domains() {
+        let domains = rollup_domains(&[
+            row("a", "x.org", 10, 5),
+            row("b", "x.org", 10, 2),
+            row("c", "y.org", 10, 9),
+        ]);
+        assert_eq!(domains[0].name, "y.org");
+        assert_eq!((domains[1].accounts, domains[1].used), (2, 7));
+    }
+}
@@ -0,0 +1,657 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::sync::Arc;
+
+use ahash::AHashSet;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use leptos::*;
+use leptos_router::{use_navigate, use_params_map};
+
+use crate::{
+    components::{
+        badge::Badge,
+        form::{
+            button::Button,
+            input::{InputText, TextArea},
+            Form, FormButtonBar, FormElement, FormItem, FormSection,
+        },
+        list::{
+            pagination::Pagination,
+            table::{Table, TableRow},
+            toolbar::SearchBox,
+        },
+        messages::alert::{use_alerts, Alert},
+        skeleton::Skeleton,
+        Color,
+    },
+    core::{
+        http::{self, HttpRequest},
+        oauth::use_authorization,
+        schema::{Builder, InputCheck, Schemas, Transformer, Type, Validator},
+    },
+    pages::{
+        directory::{
+            import::{csv_escape, parse_csv},
+            Principal, PrincipalField, PrincipalUpdate,
+        },
+        maybe_plural,
+    },
+};
+
+const PAGE_SIZE: u32 = 25;
+const SUBSCRIBER_BATCH_SIZE: usize = 500;
+const MAX_REPORTED_ERRORS: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum SubscriberState {
+    Subscribed,
+    Added,
+    Removed,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct ImportReport {
+    valid: Vec<String>,
+    duplicates: usize,
+    invalid: Vec<(usize, String, String)>,
+}
+
+#[component]
+pub fn ListSubscribers() -> impl IntoView {
+    let auth = use_authorization();
+    let alert = use_alerts();
+    let params = use_params_map();
+    let name = create_memo(move |_| params.get().get("id").cloned().unwrap_or_default());
+
+    let data = expect_context::<Arc<Schemas>>()
+        .build_form("list-subscribers")
+        .into_signal();
+    let subscribers: RwSignal<Vec<String>> = create_rw_signal(Vec::new());
+    let added: RwSignal<Vec<String>> = create_rw_signal(Vec::new());
+    let removed: RwSignal<AHashSet<String>> = create_rw_signal(AHashSet::new());
+    let report: RwSignal<Option<ImportReport>> = create_rw_signal(None);
+    let progress: RwSignal<Option<(usize, usize)>> = create_rw_signal(None);
+    let filter: RwSignal<Option<String>> = create_rw_signal(None);
+    let page = create_rw_signal(1u32);
+
+    let fetch_list = create_resource(
+        move || name.get(),
+        move |name| {
+            let auth = auth.get_untracked();
+
+            async move {
+                HttpRequest::get(("/api/principal", &name))
+                    .with_authorization(&auth)
+                    .send::<Principal>()
+                    .await
+            }
+        },
+    );
+
+    let entries = create_memo(move |_| {
+        let filter = filter.get().map(|filter| filter.to_lowercase());
+        let matches = |email: &String| {
+            filter
+                .as_ref()
+                .is_none_or(|filter| email.contains(filter.as_str()))
+        };
+
+        added.with(|added| {
+            removed.with(|removed| {
+                subscribers.with(|subscribers| {
+                    added
+                        .iter()
+                        .filter(|email| matches(email))
+                        .map(|email| (email.clone(), SubscriberState::Added))
+                        .chain(
+                            subscribers
+                                .iter()
+                                .filter(|email| matches(email))
+                                .map(|email| {
+                                    (
+                                        email.clone(),
+                                        if removed.contains(email) {
+                                            SubscriberState::Removed
+                                        } else {
+                                            SubscriberState::Subscribed
+                                        },
+                                    )
+                                }),
+                        )
+                        .collect::<Vec<_>>()
+                })
+            })
+        })
+    });
+    let page_entries = create_memo(move |_| {
+        let offset = (page.get().saturating_sub(1) * PAGE_SIZE) as usize;
+        entries.with(|entries| {
+            entries
+                .iter()
+                .skip(offset)
+                .take(PAGE_SIZE as usize)
+                .cloned()
+                .collect::<Vec<_>>()
+        })
+    });
+    let total_changes = create_memo(move |_| added.with(|a| a.len()) + removed.with(|r| r.len()));
+    let is_saving = create_memo(move |_| progress.get().is_some_and(|(done, total)| done < total));
+    let export_url = create_memo(move |_| {
+        let mut csv = "email\n".to_string();
+        removed.with(|removed| {
+            subscribers.with(|subscribers| {
+                added.with(|added| {
+                    for email in added
+                        .iter()
+                        .chain(subscribers.iter().filter(|email| !removed.contains(*email)))
+                    {
+                        csv.push_str(&csv_escape(email));
+                        csv.push('\n');
+                    }
+                })
+            })
+        });
+        format!("data:text/csv;base64,{}", STANDARD.encode(csv))
+    });
+
+    // Queues an address for addition, undoing a pending removal if there is one
+    let add_subscriber = move |email: String| -> bool {
+        if removed.try_update(|removed| removed.remove(&email)) == Some(true) {
+            return true;
+        }
+        let is_new = subscribers.with_untracked(|s| !s.contains(&email))
+            && added.with_untracked(|a| !a.contains(&email));
+        if is_new {
+            added.update(|added| added.push(email));
+        }
+        is_new
+    };
+
+    let save_changes = create_action(move |_: &()| {
+        let auth = auth.get();
+        let name = name.get();
+        let mut changes = removed.with_untracked(|removed| {
+            removed
+                .iter()
+                .map(|email| (email.clone(), false))
+                .collect::<Vec<_>>()
+        });
+        changes.extend(added.with_untracked(|added| {
+            added
+                .iter()
+                .map(|email| (email.clone(), true))
+                .collect::<Vec<_>>()
+        }));
+
+        async move {
+            let total = changes.len();
+            let mut done = 0;
+            progress.set(Some((0, total)));
+
+            for batch in changes.chunks(SUBSCRIBER_BATCH_SIZE) {
+                let updates = batch
+                    .iter()
+                    .map(|(email, is_add)| {
+                        if *is_add {
+                            PrincipalUpdate::add_item(PrincipalField::ExternalMembers, email)
+                        } else {
+                            PrincipalUpdate::remove_item(PrincipalField::ExternalMembers, email)
+                        }
+                    })
+                    .collect::<Vec<_>>();
+
+                match HttpRequest::patch(("/api/principal", &name))
+                    .with_authorization(&auth)
+                    .with_body(updates)
+                    .unwrap()
+                    .send::<serde_json::Value>()
+                    .await
+                {
+                    Ok(_) => {
+                        // Applied batches are no longer pending, so a retry only
+                        // resends what is left
+                        for (email, is_add) in batch {
+                            if *is_add {
+                                added.update(|added| added.retain(|a| a != email));
+                                subscribers.update(|s| {
+                                    if let Err(idx) = s.binary_search(email) {
+                                        s.insert(idx, email.clone());
+                                    }
+                                });
+                            } else {
+                                removed.update(|removed| {
+                                    removed.remove(email);
+                                });
+                                subscribers.update(|s| s.retain(|s| s != email));
+                            }
+                        }
+                        done += batch.len();
+                        progress.set(Some((done, total)));
+                    }
+                    Err(http::Error::Unauthorized) => {
+                        progress.set(None);
+                        use_navigate()("/login", Default::default());
+                        return;
+                    }
+                    Err(err) => {
+                        progress.set(None);
+                        alert.set(Alert::from(err).with_details(format!(
+                            "{} applied before the error, {} still pending.",
+                            maybe_plural(done, "change was", "changes were"),
+                            total - done
+                        )));
+                        return;
+                    }
+                }
+            }
+
+            alert.set(Alert::success(format!(
+                "Saved {} to {name}.",
+                maybe_plural(total, "subscriber change", "subscriber changes")
+            )));
+        }
+    });
+
+    view! {
+        <Form
+            title="Subscribers"
+            subtitle=Signal::derive(move || {
+                format!("Manage external subscribers of {}", name.get())
+            })
+        >
+
+            <Transition fallback=Skeleton>
+                {move || match fetch_list.get() {
+                    None => None,
+                    Some(Err(http::Error::Unauthorized)) => {
+                        use_navigate()("/login", Default::default());
+                        Some(view! { <div></div> }.into_view())
+                    }
+                    Some(Err(http::Error::NotFound)) => {
+                        use_navigate()("/manage/directory/lists", Default::default());
+                        Some(view! { <div></div> }.into_view())
+                    }
+                    Some(Err(err)) => {
+                        alert.set(Alert::from(err));
+                        Some(view! { <div></div> }.into_view())
+                    }
+                    Some(Ok(principal)) => {
+                        let mut emails = principal.external_members.unwrap_string_list();
+                        emails.sort_unstable();
+                        emails.dedup();
+                        subscribers.set(emails);
+                        added.set(Vec::new());
+                        removed.set(AHashSet::new());
+                        Some(view! { <div></div> }.into_view())
+                    }
+                }}
+
+            </Transition>
+
+            <FormSection title="Add subscribers".to_string()>
+                <FormItem label="E-mail">
+                    <div class="flex gap-x-2">
+                        <InputText
+                            element=FormElement::new("email", data)
+                            placeholder="jane@example.org"
+                        />
+                        <Button
+                            text="Add"
+                            color=Color::Gray
+                            on_click=move |_| {
+                                if !data.try_update(|data| data.validate_form()).unwrap_or_default() {
+                                    return;
+                                }
+                                let email = data
+                                    .with_untracked(|data| data.value::<String>("email"))
+                                    .unwrap_or_default();
+                                if email.is_empty() {
+                                    return;
+                                }
+                                if add_subscriber(email.clone()) {
+                                    data.update(|data| data.remove("email"));
+                                } else {
+                                    alert.set(Alert::warning(format!("{email} is already subscribed.")));
+                                }
+                            }
+                        />
+
+                    </div>
+                </FormItem>
+                <FormItem
+                    label="Import"
+                    tooltip="One address per line, or a CSV file with an 'email' column"
+                >
+                    <TextArea
+                        element=FormElement::new("contents", data)
+                        placeholder="email\njane@example.org\njohn@example.org"
+                    />
+                </FormItem>
+                <FormItem label="">
+                    <div class="flex gap-x-2">
+                        <Button
+                            text="Check import"
+                            color=Color::Gray
+                            on_click=move |_| {
+                                let contents = data
+                                    .with_untracked(|data| data.value::<String>("contents"))
+                                    .unwrap_or_default();
+                                let existing = subscribers
+                                    .with_untracked(|s| {
+                                        added
+                                            .with_untracked(|a| {
+                                                s.iter().chain(a.iter()).cloned().collect::<AHashSet<_>>()
+                                            })
+                                    });
+                                let result = check_import(&contents, &existing);
+                                if result.valid.is_empty() && result.invalid.is_empty()
+                                    && result.duplicates == 0
+                                {
+                                    alert.set(Alert::error("No addresses were found."));
+                                } else {
+                                    report.set(Some(result));
+                                }
+                            }
+                        />
+
+                        <Button
+                            text=Signal::derive(move || {
+                                format!(
+                                    "Add {}",
+                                    maybe_plural(
+                                        report.get().map(|r| r.valid.len()).unwrap_or_default(),
+                                        "subscriber",
+                                        "subscribers",
+                                    ),
+                                )
+                            })
+
+                            color=Color::Blue
+                            on_click=move |_| {
+                                if let Some(result) = report.get_untracked() {
+                                    for email in result.valid {
+                                        add_subscriber(email);
+                                    }
+                                    report.set(None);
+                                    data.update(|data| data.remove("contents"));
+                                }
+                            }
+
+                            disabled=Signal::derive(move || {
+                                report.get().is_none_or(|r| r.valid.is_empty())
+                            })
+                        />
+
+                    </div>
+                </FormItem>
+                <Show when=move || report.get().is_some()>
+                    <div class="sm:col-span-12">
+                        {move || {
+                            report
+                                .get()
+                                .map(|result| {
+                                    let total_invalid = result.invalid.len();
+                                    view! {
+                                        <p class="mb-4 text-sm text-gray-600 dark:text-gray-400">
+                                            {format!(
+                                                "{} can be added, {} skipped and {} invalid.",
+                                                maybe_plural(result.valid.len(), "address", "addresses"),
+                                                maybe_plural(result.duplicates, "duplicate", "duplicates"),
+                                                total_invalid,
+                                            )}
+
+                                        </p>
+                                        {(total_invalid > 0)
+                                            .then(|| {
+                                                view! {
+                                                    <Table headers=vec![
+                                                        "Line".to_string(),
+                                                        "Value".to_string(),
+                                                        "Error".to_string(),
+                                                    ]>
+                                                        {result
+                                                            .invalid
+                                                            .into_iter()
+                                                            .take(MAX_REPORTED_ERRORS)
+                                                            .map(|(line, value, error)| {
+                                                                view! {
+                                                                    <TableRow>
+                                                                        <span>{line}</span>
+                                                                        <span>{value}</span>
+                                                                        <span class="text-red-600">{error}</span>
+                                                                    </TableRow>
+                                                                }
+                                                            })
+                                                            .collect_view()}
+                                                    </Table>
+                                                }
+                                            })}
+                                    }
+                                })
+                        }}
+
+                    </div>
+                </Show>
+            </FormSection>
+
+            <FormSection title="Subscribers".to_string()>
+                <div class="sm:col-span-12">
+                    <div class="mb-4 flex flex-wrap items-center justify-between gap-2">
+                        <SearchBox
+                            value=filter
+                            on_search=move |value: String| {
+                                let value = value.trim().to_string();
+                                filter.set((!value.is_empty()).then_some(value));
+                                page.set(1);
+                            }
+                        />
+
+                        <div class="flex items-center gap-x-4">
+                            <span class="text-sm text-gray-600 dark:text-gray-400">
+                                {move || {
+                                    maybe_plural(
+                                        subscribers.with(|s| s.len()) + added.with(|a| a.len())
+                                            - removed.with(|r| r.len()),
+                                        "subscriber",
+                                        "subscribers",
+                                    )
+                                }}
+
+                            </span>
+                            <a
+                                class="py-2 px-3 inline-flex items-center gap-x-2 text-sm font-semibold rounded-lg border border-gray-200 bg-white text-gray-800 shadow-sm hover:bg-gray-50 dark:bg-slate-900 dark:border-gray-700 dark:text-white dark:hover:bg-gray-800"
+                                download=move || format!("{}-subscribers.csv", name.get())
+                                href=move || export_url.get()
+                            >
+                                "Export CSV"
+                            </a>
+                        </div>
+                    </div>
+                    <Table headers=vec![
+                        "E-mail".to_string(),
+                        "Status".to_string(),
+                        "".to_string(),
+                    ]>
+                        <For
+                            each=move || page_entries.get()
+                            key=|entry| entry.clone()
+                            children=move |(email, state)| {
+                                let (color, label, action) = match state {
+                                    SubscriberState::Subscribed => {
+                                        (Color::Green, "Subscribed", "Remove")
+                                    }
+                                    SubscriberState::Added => (Color::Blue, "Pending add", "Undo"),
+                                    SubscriberState::Removed => {
+                                        (Color::Red, "Pending removal", "Undo")
+                                    }
+                                };
+                                let email_ = email.clone();
+                                view! {
+                                    <TableRow>
+                                        <span>{email}</span>
+                                        <Badge color=color>{label}</Badge>
+                                        <button
+                                            type="button"
+                                            class="text-sm font-semibold text-blue-600 hover:underline disabled:opacity-50 dark:text-blue-500"
+                                            disabled=move || is_saving.get()
+                                            on:click=move |_| {
+                                                let email = email_.clone();
+                                                match state {
+                                                    SubscriberState::Subscribed => {
+                                                        removed
+                                                            .update(|removed| {
+                                                                removed.insert(email);
+                                                            });
+                                                    }
+                                                    SubscriberState::Added => {
+                                                        added.update(|added| added.retain(|a| a != &email));
+                                                    }
+                                                    SubscriberState::Removed => {
+                                                        removed
+                                                            .update(|removed| {
+                                                                removed.remove(&email);
+                                                            });
+                                                    }
+                                                }
+                                            }
+                                        >
+
+                                            {action}
+                                        </button>
+                                    </TableRow>
+                                }
+                            }
+                        />
+
+                    </Table>
+                    <Pagination
+                        current_page=page
+                        total_results=Signal::derive(move || {
+                            Some(entries.with(|entries| entries.len()) as u32)
+                        })
+
+                        page_size=PAGE_SIZE
+                        on_page_change=move |new_page: u32| {
+                            page.set(new_page);
+                        }
+                    />
+
+                </div>
+            </FormSection>
+
+            <Show when=move || progress.get().is_some()>
+                <div class="mb-4 flex w-full h-2 bg-gray-200 rounded-full overflow-hidden dark:bg-gray-700">
+                    <div
+                        class="flex flex-col justify-center overflow-hidden bg-blue-600 transition duration-500"
+                        style=move || {
+                            let (done, total) = progress.get().unwrap_or_default();
+                            format!("width: {}%;", done * 100 / total.max(1))
+                        }
+                    >
+                    </div>
+                </div>
+            </Show>
+
+            <FormButtonBar>
+                <Button
+                    text="Close"
+                    color=Color::Gray
+                    on_click=move |_| {
+                        use_navigate()(
+                            &format!("/manage/directory/lists/{}/edit", name.get()),
+                            Default::default(),
+                        );
+                    }
+
+                    disabled=is_saving
+                />
+                <Button
+                    text=Signal::derive(move || {
+                        match total_changes.get() {
+                            0 => "Save changes".to_string(),
+                            changes => format!("Save changes ({changes})"),
+                        }
+                    })
+
+                    color=Color::Blue
+                    on_click=move |_| {
+                        if total_changes.get_untracked() > 0 {
+                            save_changes.dispatch(());
+                        }
+                    }
+
+                    disabled=Signal::derive(move || is_saving.get() || total_changes.get() == 0)
+                />
+            </FormButtonBar>
+        </Form>
+    }
+}
+
+fn check_import(contents: &str, existing: &AHashSet<String>) -> ImportReport {
+    let check = InputCheck {
+        transformers: vec![Transformer::Trim, Transformer::Lowercase],
+        validators: vec![Validator::IsEmail],
+    };
+    let mut rows = parse_csv(contents);
+    let mut report = ImportReport::default();
+    let mut seen = AHashSet::new();
+
+    // Use the e-mail column when a header is present, otherwise the first one
+    let header = rows.first().and_then(|(_, header)| {
+        header.iter().position(|column| {
+            matches!(
+                column.trim().to_lowercase().as_str(),
+                "email" | "e-mail" | "mail" | "address" | "email address"
+            )
+        })
+    });
+    let column = if let Some(column) = header {
+        rows.remove(0);
+        column
+    } else {
+        0
+    };
+
+    for (line, row) in rows {
+        let value = row.get(column).cloned().unwrap_or_default();
+        if value.trim().is_empty() {
+            continue;
+        }
+
+        match check.check_value(value.clone()) {
+            Ok(email) => {
+                if existing.contains(&email) || !seen.insert(email.clone()) {
+                    report.duplicates += 1;
+                } else {
+                    report.valid.push(email);
+                }
+            }
+            Err(err) => {
+                report.invalid.push((line, value, err.to_string()));
+            }
+        }
+    }
+
+    report
+}
+
+impl Builder<Schemas, ()> {
+    pub fn build_list_subscribers(self) -> Self {
+        self.new_schema("list-subscribers")
+            .new_field("email")
+            .typ(Type::Input)
+            .input_check(
+                [Transformer::Trim, Transformer::Lowercase],
+                [Validator::IsEmail],
+            )
+            .build()
+            .new_field("contents")
+            .typ(Type::Text)
+            .build()
+            .build()
+    }
+}
@@ -0,0 +1,542 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use serde_json::json;
+
+use super::dns::{format_zonefile, DnsRecord};
+
+const EXPORT_TTL: u32 = 3600;
+const TXT_CHUNK_SIZE: usize = 255;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum ZoneFormat {
+    Bind,
+    CloudflareImport,
+    TerraformCloudflare,
+    TerraformRoute53,
+    Route53ChangeBatch,
+    OctoDns,
+    DnsControl,
+}
+
+impl ZoneFormat {
+    pub const ALL: &'static [ZoneFormat] = &[
+        ZoneFormat::Bind,
+        ZoneFormat::CloudflareImport,
+        ZoneFormat::TerraformCloudflare,
+        ZoneFormat::TerraformRoute53,
+        ZoneFormat::Route53ChangeBatch,
+        ZoneFormat::OctoDns,
+        ZoneFormat::DnsControl,
+    ];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            ZoneFormat::Bind => "bind",
+            ZoneFormat::CloudflareImport => "cloudflare-import",
+            ZoneFormat::TerraformCloudflare => "terraform-cloudflare",
+            ZoneFormat::TerraformRoute53 => "terraform-route53",
+            ZoneFormat::Route53ChangeBatch => "route53",
+            ZoneFormat::OctoDns => "octodns",
+            ZoneFormat::DnsControl => "dnscontrol",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Self {
+        ZoneFormat::ALL
+            .iter()
+            .copied()
+            .find(|format| format.id() == id)
+            .unwrap_or(ZoneFormat::Bind)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ZoneFormat::Bind => "BIND zone file",
+            ZoneFormat::CloudflareImport => "Cloudflare bulk import",
+            ZoneFormat::TerraformCloudflare => "Terraform (cloudflare_record)",
+            ZoneFormat::TerraformRoute53 => "Terraform (aws_route53_record)",
+            ZoneFormat::Route53ChangeBatch => "Route53 change batch (JSON)",
+            ZoneFormat::OctoDns => "OctoDNS (YAML)",
+            ZoneFormat::DnsControl => "DNSControl (dnsconfig.js)",
+        }
+    }
+
+    pub fn file_name(&self, domain: &str) -> String {
+        match self {
+            ZoneFormat::Bind => format!("{domain}.zone"),
+            ZoneFormat::CloudflareImport => format!("{domain}.cloudflare.txt"),
+            ZoneFormat::TerraformCloudflare => format!("{domain}.cloudflare.tf"),
+            ZoneFormat::TerraformRoute53 => format!("{domain}.route53.tf"),
+            ZoneFormat::Route53ChangeBatch => format!("{domain}.route53.json"),
+            ZoneFormat::OctoDns => format!("{domain}.yaml"),
+            ZoneFormat::DnsControl => "dnsconfig.js".to_string(),
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ZoneFormat::Route53ChangeBatch => "application/json",
+            ZoneFormat::DnsControl => "text/javascript",
+            ZoneFormat::OctoDns => "application/yaml",
+            _ => "text/plain",
+        }
+    }
+
+    // The domain is expected without the trailing dot
+    pub fn format(&self, records: &[DnsRecord], domain: &str) -> String {
+        match self {
+            ZoneFormat::Bind => format_zonefile(records, &format!("{domain}.")),
+            ZoneFormat::CloudflareImport => format!(
+                "$ORIGIN {domain}.\n$TTL {EXPORT_TTL}\n{}",
+                format_zonefile(records, &format!("{domain}."))
+            ),
+            ZoneFormat::TerraformCloudflare => format_terraform_cloudflare(records, domain),
+            ZoneFormat::TerraformRoute53 => format_terraform_route53(records, domain),
+            ZoneFormat::Route53ChangeBatch => format_route53_change_batch(records, domain),
+            ZoneFormat::OctoDns => format_octodns(records, domain),
+            ZoneFormat::DnsControl => format_dnscontrol(records, domain),
+        }
+    }
+}
+
+fn format_terraform_cloudflare(records: &[DnsRecord], domain: &str) -> String {
+    let mut out = String::from("variable \"cloudflare_zone_id\" {\n  type = string\n}\n");
+
+    for (idx, record) in records.iter().enumerate() {
+        let Some(name) = relative_name(&record.name, domain) else {
+            continue;
+        };
+        let fields = record.content.split_whitespace().collect::<Vec<_>>();
+        out.push_str(&format!(
+            "\nresource \"cloudflare_record\" \"{}\" {{\n  zone_id = var.cloudflare_zone_id\n  name    = {}\n  type    = \"{}\"\n  ttl     = {EXPORT_TTL}\n",
+            resource_name(&record.typ, name, idx),
+            hcl_string(name),
+            record.typ
+        ));
+
+        match (record.typ.as_str(), fields.as_slice()) {
+            ("MX", [priority, exchange]) => {
+                out.push_str(&format!(
+                    "  priority = {priority}\n  content  = {}\n",
+                    hcl_string(exchange.trim_end_matches('.'))
+                ));
+            }
+            ("SRV", [priority, weight, port, target]) => {
+                out.push_str(&format!(
+                    "  data {{\n    priority = {priority}\n    weight   = {weight}\n    port     = {port}\n    target   = {}\n  }}\n",
+                    hcl_string(target.trim_end_matches('.'))
+                ));
+            }
+            ("TLSA", [usage, selector, matching_type, certificate]) => {
+                out.push_str(&format!(
+                    "  data {{\n    usage         = {usage}\n    selector      = {selector}\n    matching_type = {matching_type}\n    certificate   = {}\n  }}\n",
+                    hcl_string(certificate)
+                ));
+            }
+            ("CNAME", _) => {
+                out.push_str(&format!(
+                    "  content = {}\n",
+                    hcl_string(record.content.trim_end_matches('.'))
+                ));
+            }
+            // Cloudflare splits long TXT values into character strings on its own
+            _ => {
+                out.push_str(&format!("  content = {}\n", hcl_string(&record.content)));
+            }
+        }
+        out.push_str("}\n");
+    }
+
+    out
+}
+
+fn format_terraform_route53(records: &[DnsRecord], domain: &str) -> String {
+    let mut out = String::from("variable \"route53_zone_id\" {\n  type = string\n}\n");
+
+    // Route53 holds all values of a name and type in a single record set
+    for (idx, (name, typ, contents)) in group_records(records, domain).into_iter().enumerate() {
+        let values = contents
+            .iter()
+            .map(|content| {
+                if typ == "TXT" {
+                    // Terraform wraps each value in quotes, longer values are
+                    // split into character strings by closing and reopening them
+                    hcl_string(&chunk_txt(content).join("\"\""))
+                } else {
+                    hcl_string(content)
+                }
+            })
+            .collect::<Vec<_>>();
+        let relative = relative_name(name, domain).unwrap_or(name);
+
+        out.push_str(&format!(
+            "\nresource \"aws_route53_record\" \"{}\" {{\n  zone_id = var.route53_zone_id\n  name    = {}\n  type    = \"{typ}\"\n  ttl     = {EXPORT_TTL}\n  records = [\n",
+            resource_name(typ, relative, idx),
+            hcl_string(name.trim_end_matches('.')),
+        ));
+        for value in values {
+            out.push_str(&format!("    {value},\n"));
+        }
+        out.push_str("  ]\n}\n");
+    }
+
+    out
+}
+
+fn format_route53_change_batch(records: &[DnsRecord], domain: &str) -> String {
+    let changes = group_records(records, domain)
+        .into_iter()
+        .map(|(name, typ, contents)| {
+            let values = contents
+                .iter()
+                .map(|content| {
+                    let value = if typ == "TXT" {
+                        chunk_txt(content)
+                            .into_iter()
+                            .map(|chunk| {
+                                format!("\"{}\"", chunk.replace('\\', "\\\\").replace('"', "\\\""))
+                            })
+                            .collect::<Vec<_>>()
+                            .join(" ")
+                    } else {
+                        content.to_string()
+                    };
+                    json!({ "Value": value })
+                })
+                .collect::<Vec<_>>();
+
+            json!({
+                "Action": "UPSERT",
+                "ResourceRecordSet": {
+                    "Name": absolute_name(name),
+                    "Type": typ,
+                    "TTL": EXPORT_TTL,
+                    "ResourceRecords": values,
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+
+    serde_json::to_string_pretty(&json!({
+        "Comment": format!("Stalwart Mail Server records for {domain}"),
+        "Changes": changes,
+    }))
+    .unwrap_or_default()
+}
+
+fn format_octodns(records: &[DnsRecord], domain: &str) -> String {
+    let mut out = String::from("---\n");
+    let groups = group_records(records, domain);
+
+    // Each name is a YAML key, so all of its record types go under one entry
+    let mut names = Vec::new();
+    for (name, _, _) in &groups {
+        if let Some(relative) = relative_name(name, domain).filter(|name| !names.contains(name)) {
+            names.push(relative);
+        }
+    }
+
+    for relative in names {
+        let key = if relative == "@" { "" } else { relative };
+        out.push_str(&format!("{}:\n", yaml_string(key)));
+
+        for (_, typ, contents) in groups
+            .iter()
+            .filter(|(name, _, _)| relative_name(name, domain) == Some(relative))
+        {
+            format_octodns_values(&mut out, typ, contents);
+        }
+    }
+
+    out
+}
+
+fn format_octodns_values(out: &mut String, typ: &str, contents: &[&str]) {
+    out.push_str(&format!("  - type: {typ}\n    ttl: {EXPORT_TTL}\n"));
+
+    // Single value types take a plain "value" instead of a list
+    if typ == "CNAME" {
+        out.push_str(&format!(
+            "    value: {}\n",
+            yaml_string(&absolute_name(contents[0]))
+        ));
+        return;
+    }
+    out.push_str("    values:\n");
+
+    for content in contents {
+        let fields = content.split_whitespace().collect::<Vec<_>>();
+        match (typ, fields.as_slice()) {
+            ("MX", [preference, exchange]) => {
+                out.push_str(&format!(
+                    "      - exchange: {}\n        preference: {preference}\n",
+                    yaml_string(&absolute_name(exchange))
+                ));
+            }
+            ("SRV", [priority, weight, port, target]) => {
+                out.push_str(&format!(
+                        "      - port: {port}\n        priority: {priority}\n        target: {}\n        weight: {weight}\n",
+                        yaml_string(&absolute_name(target))
+                    ));
+            }
+            ("TLSA", [usage, selector, matching_type, certificate]) => {
+                out.push_str(&format!(
+                        "      - certificate_association_data: {}\n        certificate_usage: {usage}\n        matching_type: {matching_type}\n        selector: {selector}\n",
+                        yaml_string(certificate)
+                    ));
+            }
+            // OctoDNS chunks long TXT values itself but requires semicolons
+            // to be escaped
+            ("TXT", _) => {
+                out.push_str(&format!(
+                    "      - {}\n",
+                    yaml_string(&content.replace(';', "\\;"))
+                ));
+            }
+            _ => {
+                out.push_str(&format!("      - {}\n", yaml_string(content)));
+            }
+        }
+    }
+}
+
+fn format_dnscontrol(records: &[DnsRecord], domain: &str) -> String {
+    let mut out = format!(
+        "var REG_NONE = NewRegistrar(\"none\");\nvar DSP = NewDnsProvider(\"dns\");\n\nD({}, REG_NONE, DnsProvider(DSP),\n    DefaultTTL({EXPORT_TTL}),\n",
+        js_string(domain)
+    );
+
+    for record in records {
+        let Some(name) = relative_name(&record.name, domain) else {
+            continue;
+        };
+        let name = js_string(name);
+        let fields = record.content.split_whitespace().collect::<Vec<_>>();
+        let entry = match (record.typ.as_str(), fields.as_slice()) {
+            ("MX", [priority, exchange]) => {
+                format!(
+                    "MX({name}, {priority}, {})",
+                    js_string(&absolute_name(exchange))
+                )
+            }
+            ("SRV", [priority, weight, port, target]) => format!(
+                "SRV({name}, {priority}, {weight}, {port}, {})",
+                js_string(&absolute_name(target))
+            ),
+            ("TLSA", [usage, selector, matching_type, certificate]) => format!(
+                "TLSA({name}, {usage}, {selector}, {matching_type}, {})",
+                js_string(certificate)
+            ),
+            ("CNAME", _) => format!(
+                "CNAME({name}, {})",
+                js_string(&absolute_name(&record.content))
+            ),
+            // DNSControl splits TXT values longer than 255 octets automatically
+            (typ, _) => format!("{typ}({name}, {})", js_string(&record.content)),
+        };
+        out.push_str(&format!("    {entry},\n"));
+    }
+
+    out.push_str(");\n");
+    out
+}
+
+fn group_records<'x>(
+    records: &'x [DnsRecord],
+    domain: &str,
+) -> Vec<(&'x str, &'x str, Vec<&'x str>)> {
+    let mut groups: Vec<(&str, &str, Vec<&str>)> = Vec::new();
+
+    for record in records {
+        if relative_name(&record.name, domain).is_none() {
+            continue;
+        }
+        if let Some((_, _, contents)) = groups
+            .iter_mut()
+            .find(|(name, typ, _)| *name == record.name && *typ == record.typ)
+        {
+            contents.push(&record.content);
+        } else {
+            groups.push((&record.name, &record.typ, vec![&record.content]));
+        }
+    }
+
+    groups
+}
+
+fn relative_name<'x>(name: &'x str, domain: &str) -> Option<&'x str> {
+    let name = name.strip_suffix('.').unwrap_or(name);
+    let name = name.strip_suffix(domain)?;
+    if name.is_empty() {
+        Some("@")
+    } else {
+        name.strip_suffix('.')
+    }
+}
+
+fn absolute_name(name: &str) -> String {
+    if name.ends_with('.') {
+        name.to_string()
+    } else {
+        format!("{name}.")
+    }
+}
+
+// TXT character strings are limited to 255 octets, split on character
+// boundaries so that multi-byte values are never cut in half.
+fn chunk_txt(value: &str) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut value = value;
+
+    while value.len() > TXT_CHUNK_SIZE {
+        let mut end = TXT_CHUNK_SIZE;
+        while !value.is_char_boundary(end) {
+            end -= 1;
+        }
+        let (chunk, rest) = value.split_at(end);
+        chunks.push(chunk);
+        value = rest;
+    }
+    chunks.push(value);
+
+    chunks
+}
+
+fn resource_name(typ: &str, name: &str, idx: usize) -> String {
+    let name = if name == "@" { "apex" } else { name };
+    format!("{}_{}_{idx}", typ, name)
+        .chars()
+        .map(|ch| {
+            if ch.is_ascii_alphanumeric() {
+                ch.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn hcl_string(value: &str) -> String {
+    format!(
+        "\"{}\"",
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace("${", "$${")
+            .replace("%{", "%%{")
+    )
+}
+
+fn js_string(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+fn yaml_string(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(typ: &str, name: &str, content: &str) -> DnsRecord {
+        DnsRecord {
+            typ: typ.to_string(),
+            name: name.to_string(),
+            content: content.to_string(),
+        }
+    }
+
+    fn records() -> Vec<DnsRecord> {
+        vec![
+            record("MX", "example.org.", "10 mail.example.org."),
+            record("TXT", "example.org.", "v=spf1 mx -all"),
+            record("CNAME", "mail.example.org.", "mx.example.org."),
+            record("TXT", "example.org.", "google-site-verification=abc"),
+            record("A", "example.org", "192.0.2.1"),
+            record("TXT", "evilexample.org.", "v=spf1 -all"),
+        ]
+    }
+
+    #[test]
+    fn chunks_txt_on_char_boundaries() {
+        assert_eq!(chunk_txt("v=spf1 -all"), ["v=spf1 -all"]);
+        assert_eq!(chunk_txt(""), [""]);
+
+        let value = "a".repeat(TXT_CHUNK_SIZE * 2 + 10);
+        let chunks = chunk_txt(&value);
+        assert_eq!(
+            chunks.iter().map(|chunk| chunk.len()).collect::<Vec<_>>(),
+            [TXT_CHUNK_SIZE, TXT_CHUNK_SIZE, 10]
+        );
+
+        // "é" takes two octets and would straddle the 255 octet boundary
+        let value = format!("{}é{}", "a".repeat(TXT_CHUNK_SIZE - 1), "b".repeat(10));
+        let chunks = chunk_txt(&value);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].len(), TXT_CHUNK_SIZE - 1);
+        assert!(chunks[1].starts_with('é'));
+        assert_eq!(chunks.concat(), value);
+    }
+
+    #[test]
+    fn resolves_relative_names() {
+        assert_eq!(relative_name("example.org.", "example.org"), Some("@"));
+        assert_eq!(relative_name("example.org", "example.org"), Some("@"));
+        assert_eq!(
+            relative_name("_dmarc.example.org.", "example.org"),
+            Some("_dmarc")
+        );
+        assert_eq!(relative_name("a.b.example.org", "example.org"), Some("a.b"));
+        assert_eq!(relative_name("evilexample.org.", "example.org"), None);
+        assert_eq!(relative_name("example.com.", "example.org"), None);
+    }
+
+    #[test]
+    fn groups_octodns_records_by_name() {
+        let out = format_octodns(&records(), "example.org");
+        assert_eq!(out.matches("'':\n").count(), 1);
+        assert_eq!(out.matches("'mail':\n").count(), 1);
+        assert!(!out.contains("evil"));
+
+        let apex = out.find("'':\n").unwrap();
+        let mail = out.find("'mail':\n").unwrap();
+        assert!(apex < mail);
+        for typ in ["MX", "TXT", "A"] {
+            let pos = out.find(&format!("type: {typ}\n")).unwrap();
+            assert!(pos > apex && pos < mail, "{typ} outside of the apex entry");
+        }
+        assert_eq!(out.matches("type: TXT").count(), 1);
+        assert!(out.contains("- 'google-site-verification=abc'"));
+    }
+
+    #[test]
+    fn exports_every_format() {
+        let records = records();
+        for format in ZoneFormat::ALL {
+            let out = format.format(&records, "example.org");
+            assert_eq!(ZoneFormat::from_id(format.id()), *format);
+            assert!(
+                out.contains("mail.example.org") || out.contains("mx.example.org"),
+                "{} is missing records",
+                format.id()
+            );
+            assert!(
+                out.contains("v=spf1 mx -all"),
+                "{} is missing the SPF record",
+                format.id()
+            );
+            if *format != ZoneFormat::Bind && *format != ZoneFormat::CloudflareImport {
+                assert!(!out.contains("evil"), "{} leaks other zones", format.id());
+            }
+        }
+
+        let json = ZoneFormat::Route53ChangeBatch.format(&records, "example.org");
+        let batch = serde_json::from_str::<serde_json::Value>(&json).unwrap();
+        assert_eq!(batch["Changes"].as_array().unwrap().len(), 4);
+    }
+}
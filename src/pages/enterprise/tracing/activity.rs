@@ -0,0 +1,409 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: LicenseRef-SEL
+ *
+ * This file is subject to the Stalwart Enterprise License Agreement (SEL) and
+ * is not open source software. It must not be modified or distributed without
+ * explicit permission from Stalwart Labs LLC.
+ * Unauthorized use, modification, or distribution is strictly prohibited.
+ */
+
+use chrono::{DateTime, Utc};
+use humansize::{format_size, DECIMAL};
+use leptos::*;
+use leptos_router::use_navigate;
+
+use crate::{
+    components::{
+        badge::Badge,
+        form::button::Button,
+        messages::alert::{use_alerts, Alert},
+        Color,
+    },
+    core::{
+        http::{self, HttpRequest},
+        oauth::use_authorization,
+        AccessToken,
+    },
+    pages::{directory::spawn_workers, FormatDateTime, List},
+};
+
+use super::event::{Event, Key};
+
+const ACTIVITY_LIMIT: u32 = 10;
+const SESSION_WORKERS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ActivityKind {
+    Login,
+    FailedLogin,
+    Session,
+    Received,
+    Sent,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ActivitySource {
+    event_type: &'static str,
+    protocol: &'static str,
+    by_email: bool,
+}
+
+// Spans are indexed by the keywords they contain, so sessions are looked up
+// by account name and messages by address.
+static ACTIVITY_SOURCES: &[ActivitySource] = &[
+    ActivitySource {
+        event_type: "imap.connection-start",
+        protocol: "IMAP",
+        by_email: false,
+    },
+    ActivitySource {
+        event_type: "http.connection-start",
+        protocol: "JMAP/HTTP",
+        by_email: false,
+    },
+    ActivitySource {
+        event_type: "pop3.connection-start",
+        protocol: "POP3",
+        by_email: false,
+    },
+    ActivitySource {
+        event_type: "smtp.connection-start",
+        protocol: "SMTP",
+        by_email: true,
+    },
+    ActivitySource {
+        event_type: "delivery.attempt-start",
+        protocol: "SMTP",
+        by_email: true,
+    },
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Activity {
+    kind: ActivityKind,
+    protocol: &'static str,
+    created_at: DateTime<Utc>,
+    details: String,
+    span_id: u64,
+}
+
+#[component]
+pub fn AccountActivity(account: String, emails: Vec<String>) -> impl IntoView {
+    let auth = use_authorization();
+    let alert = use_alerts();
+    let activity: RwSignal<Option<Vec<Activity>>> = create_rw_signal(None);
+
+    let fetch_activity = create_action(move |_: &()| {
+        let auth = auth.get();
+        let account = account.clone();
+        let emails = emails.clone();
+
+        async move {
+            match fetch_account_activity(&auth, &account, &emails).await {
+                Ok(mut entries) => {
+                    entries.sort_by_key(|entry| std::cmp::Reverse(entry.created_at));
+                    let sessions = entries
+                        .iter()
+                        .filter(|entry| entry.kind == ActivityKind::Session)
+                        .map(|entry| entry.span_id)
+                        .collect::<Vec<_>>();
+                    activity.set(Some(entries));
+
+                    // Session outcomes are filled in as their events arrive.
+                    spawn_workers(sessions, SESSION_WORKERS, move |span_id| {
+                        let auth = auth.clone();
+                        async move {
+                            let kind = session_kind(&auth, span_id).await;
+                            if kind != ActivityKind::Session {
+                                activity.update(|entries| {
+                                    if let Some(entry) = entries.as_mut().and_then(|entries| {
+                                        entries.iter_mut().find(|entry| entry.span_id == span_id)
+                                    }) {
+                                        entry.kind = kind;
+                                    }
+                                });
+                            }
+                            true
+                        }
+                    });
+                }
+                Err(http::Error::Unauthorized) => {
+                    use_navigate()("/login", Default::default());
+                }
+                Err(err) => {
+                    alert.set(Alert::from(err));
+                }
+            }
+        }
+    });
+
+    view! {
+        <div class="flex items-center justify-between pb-6">
+            <p class="text-sm text-gray-600 dark:text-neutral-400">
+                "Recent logins, sessions and message deliveries recorded by the tracing store."
+            </p>
+            <Button
+                text=Signal::derive(move || {
+                    if activity.get().is_some() { "Refresh" } else { "Load activity" }.to_string()
+                })
+
+                color=Color::Gray
+                on_click=move |_| {
+                    fetch_activity.dispatch(());
+                }
+
+                disabled=fetch_activity.pending()
+            />
+        </div>
+
+        {move || match activity.get() {
+            None => None,
+            Some(entries) if entries.is_empty() => {
+                Some(
+                    view! {
+                        <p class="text-sm text-gray-500 dark:text-neutral-500">
+                            "No activity was found for this account."
+                        </p>
+                    }
+                        .into_view(),
+                )
+            }
+            Some(entries) => {
+                Some(
+                    entries
+                        .into_iter()
+                        .map(|entry| view! { <ActivityItem entry=entry/> })
+                        .collect_view(),
+                )
+            }
+        }}
+    }
+}
+
+#[component]
+fn ActivityItem(entry: Activity) -> impl IntoView {
+    let (color, label) = entry.kind.badge();
+
+    view! {
+        <div class="group relative flex gap-x-5">
+            <div class="relative group-last:after:hidden after:absolute after:top-8 after:bottom-2 after:start-3 after:w-px after:-translate-x-[0.5px] after:bg-gray-200 dark:after:bg-neutral-700">
+                <div class="relative z-10 size-6 flex justify-center items-center">
+                    <div class="size-2 rounded-full bg-gray-400 dark:bg-neutral-600"></div>
+                </div>
+            </div>
+
+            <div class="grow pb-8 group-last:pb-0">
+                <h3 class="mb-1 text-xs text-gray-600 dark:text-neutral-400">
+                    {entry.created_at.format_date_time()}
+                </h3>
+                <p class="flex items-center gap-x-2 font-semibold text-sm text-gray-800 dark:text-neutral-200">
+                    <Badge color=color>{label}</Badge>
+                    {entry.protocol}
+                </p>
+                <p class="mt-1 text-sm text-gray-600 dark:text-neutral-400">{entry.details}</p>
+                <a
+                    class="mt-1 inline-flex items-center gap-x-1 text-sm text-blue-600 decoration-2 hover:underline font-medium"
+                    href=format!("/manage/tracing/span/{}", entry.span_id)
+                >
+                    View span
+                </a>
+            </div>
+        </div>
+    }
+}
+
+async fn fetch_account_activity(
+    auth: &AccessToken,
+    account: &str,
+    emails: &[String],
+) -> http::Result<Vec<Activity>> {
+    let mut activity = Vec::new();
+
+    for source in ACTIVITY_SOURCES {
+        let filters = if source.by_email {
+            emails
+                .iter()
+                .map(|email| email.as_str())
+                .collect::<Vec<_>>()
+        } else {
+            vec![account]
+        };
+
+        for filter in filters {
+            let spans = HttpRequest::get("/api/telemetry/traces")
+                .with_authorization(auth)
+                .with_parameter("type", source.event_type)
+                .with_parameter("page", "1")
+                .with_parameter("limit", ACTIVITY_LIMIT.to_string())
+                .with_parameter("values", "1")
+                .with_parameter("filter", filter)
+                .send::<List<Event>>()
+                .await?;
+
+            for span in spans.items {
+                let Some(span_id) = span.get_as_int(Key::SpanId) else {
+                    continue;
+                };
+                if activity
+                    .iter()
+                    .any(|entry: &Activity| entry.span_id == span_id)
+                {
+                    continue;
+                }
+
+                let (kind, details) = if source.by_email {
+                    (message_kind(&span, emails), message_details(&span))
+                } else {
+                    (ActivityKind::Session, session_details(&span))
+                };
+
+                activity.push(Activity {
+                    kind,
+                    protocol: source.protocol,
+                    created_at: span.created_at,
+                    details,
+                    span_id,
+                });
+            }
+        }
+    }
+
+    Ok(activity)
+}
+
+fn message_details(span: &Event) -> String {
+    let from = span.get_as_str(Key::From).unwrap_or_default();
+    let to = span.get_as_str_list(Key::To).collect::<Vec<_>>();
+    let mut details = format!("From {} to {}", from, to.join(", "));
+    if let Some(size) = span.get_as_int(Key::Size) {
+        details.push_str(&format!(" ({})", format_size(size, DECIMAL)));
+    }
+    details
+}
+
+// Both inbound connections and queue deliveries may carry messages in either
+// direction, the sender address tells which way the message went.
+fn message_kind(span: &Event, emails: &[String]) -> ActivityKind {
+    if span
+        .get_as_str(Key::From)
+        .is_some_and(|from| emails.iter().any(|email| email.eq_ignore_ascii_case(from)))
+    {
+        ActivityKind::Sent
+    } else {
+        ActivityKind::Received
+    }
+}
+
+fn session_details(span: &Event) -> String {
+    match span.get_as_str(Key::RemoteIp) {
+        Some(ip) => format!("Connection from {ip}"),
+        None => span.text.clone().unwrap_or_default(),
+    }
+}
+
+// The session start event does not say how authentication went, so the
+// span events are fetched to tell logins apart from failed attempts.
+async fn session_kind(auth: &AccessToken, span_id: u64) -> ActivityKind {
+    let events = HttpRequest::get(("/api/telemetry/trace", &span_id.to_string()))
+        .with_authorization(auth)
+        .send::<Vec<Event>>()
+        .await
+        .unwrap_or_default();
+    session_outcome(&events)
+}
+
+fn session_outcome(events: &[Event]) -> ActivityKind {
+    if events.iter().any(|event| event.typ == "auth.success") {
+        ActivityKind::Login
+    } else if events
+        .iter()
+        .any(|event| event.typ.starts_with("auth.failed"))
+    {
+        ActivityKind::FailedLogin
+    } else {
+        ActivityKind::Session
+    }
+}
+
+impl ActivityKind {
+    fn badge(&self) -> (Color, &'static str) {
+        match self {
+            ActivityKind::Login => (Color::Green, "Login"),
+            ActivityKind::FailedLogin => (Color::Red, "Failed login"),
+            ActivityKind::Session => (Color::Gray, "Session"),
+            ActivityKind::Received => (Color::Blue, "Received"),
+            ActivityKind::Sent => (Color::Yellow, "Sent"),
+        }
+    }
+}
+
+pub async fn fetch_last_used(
+    auth: &AccessToken,
+    account: &str,
+) -> http::Result<Option<DateTime<Utc>>> {
+    HttpRequest::get("/api/telemetry/traces")
+        .with_authorization(auth)
+        .with_parameter("type", "http.connection-start")
+        .with_parameter("page", "1")
+        .with_parameter("limit", "1")
+        .with_parameter("values", "1")
+        .with_parameter("filter", account)
+        .send::<List<Event>>()
+        .await
+        .map(|spans| spans.items.into_iter().map(|span| span.created_at).max())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pages::enterprise::tracing::event::Value;
+
+    fn event(typ: &str, data: Vec<(Key, Value)>) -> Event {
+        Event {
+            typ: typ.to_string(),
+            text: None,
+            details: None,
+            created_at: Utc::now(),
+            data,
+        }
+    }
+
+    #[test]
+    fn derives_message_direction_from_sender() {
+        let emails = vec!["john@example.org".to_string()];
+        let from = |address: &str| {
+            event(
+                "delivery.attempt-start",
+                vec![(Key::From, Value::String(address.to_string()))],
+            )
+        };
+
+        assert_eq!(
+            message_kind(&from("John@Example.org"), &emails),
+            ActivityKind::Sent
+        );
+        assert_eq!(
+            message_kind(&from("jane@example.org"), &emails),
+            ActivityKind::Received
+        );
+        assert_eq!(
+            message_kind(&event("smtp.connection-start", vec![]), &emails),
+            ActivityKind::Received
+        );
+    }
+
+    #[test]
+    fn detects_session_outcome() {
+        assert_eq!(
+            session_outcome(&[event("auth.failed", vec![]), event("auth.success", vec![])]),
+            ActivityKind::Login
+        );
+        assert_eq!(
+            session_outcome(&[event("auth.failed", vec![])]),
+            ActivityKind::FailedLogin
+        );
+        assert_eq!(session_outcome(&[]), ActivityKind::Session);
+    }
+}
@@ -103,10 +103,19 @@ fn Span(events: Vec<Event>) -> impl IntoView {
         .to_text_en(Accuracy::Precise, Tense::Present);
     let start_date = span_start.format_date();
     let start_time = span_start.format_time();
-    let (span_type, back_url) = if events.first().unwrap().typ.starts_with("smtp.") {
-        ("Received Message", "/manage/tracing/received")
+    let span_type = events.first().unwrap().typ.as_str();
+    // Session spans are only reachable from an account's activity, so closing
+    // them returns to wherever they were opened from
+    let (span_type, back_url) = if span_type.starts_with("smtp.") {
+        ("Received Message", Some("/manage/tracing/received"))
+    } else if span_type.starts_with("imap.") {
+        ("IMAP Session", None)
+    } else if span_type.starts_with("http.") {
+        ("HTTP Session", None)
+    } else if span_type.starts_with("pop3.") {
+        ("POP3 Session", None)
     } else {
-        ("Delivery Attempt", "/manage/tracing/delivery")
+        ("Delivery Attempt", Some("/manage/tracing/delivery"))
     };
     let num_events = (events.len() - 2).to_string();
 
@@ -160,8 +169,13 @@ fn Span(events: Vec<Event>) -> impl IntoView {
                 <Button
                     text="Close"
                     color=Color::Blue
-                    on_click=move |_| {
-                        use_navigate()(back_url, Default::default());
+                    on_click=move |_| match back_url {
+                        Some(back_url) => {
+                            use_navigate()(back_url, Default::default());
+                        }
+                        None => {
+                            let _ = window().history().and_then(|history| history.back());
+                        }
                     }
                 />
 
@@ -9,6 +9,7 @@
  * Unauthorized use, modification, or distribution is strictly prohibited.
  */
 
+pub mod activity;
 pub mod display;
 pub mod event;
 pub mod list;
@@ -742,43 +742,43 @@ struct Details {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-struct DmarcTroubleshootRequest {
+pub(crate) struct DmarcTroubleshootRequest {
     #[serde(rename = "remoteIp")]
-    remote_ip: IpAddr,
+    pub remote_ip: IpAddr,
     #[serde(rename = "ehloDomain")]
-    ehlo_domain: String,
+    pub ehlo_domain: String,
     #[serde(rename = "mailFrom")]
-    mail_from: String,
-    body: Option<String>,
+    pub mail_from: String,
+    pub body: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-struct DmarcTroubleshootResponse {
+pub(crate) struct DmarcTroubleshootResponse {
     #[serde(rename = "spfEhloDomain")]
-    spf_ehlo_domain: String,
+    pub spf_ehlo_domain: String,
     #[serde(rename = "spfEhloResult")]
-    spf_ehlo_result: AuthResult,
+    pub spf_ehlo_result: AuthResult,
     #[serde(rename = "spfMailFromDomain")]
-    spf_mail_from_domain: String,
+    pub spf_mail_from_domain: String,
     #[serde(rename = "spfMailFromResult")]
-    spf_mail_from_result: AuthResult,
+    pub spf_mail_from_result: AuthResult,
     #[serde(rename = "ipRevResult")]
-    ip_rev_result: AuthResult,
+    pub ip_rev_result: AuthResult,
     #[serde(rename = "ipRevPtr")]
-    ip_rev_ptr: Vec<String>,
+    pub ip_rev_ptr: Vec<String>,
     #[serde(rename = "dkimResults")]
-    dkim_results: Vec<AuthResult>,
+    pub dkim_results: Vec<AuthResult>,
     #[serde(rename = "dkimPass")]
-    dkim_pass: bool,
+    pub dkim_pass: bool,
     #[serde(rename = "arcResult")]
-    arc_result: AuthResult,
+    pub arc_result: AuthResult,
     #[serde(rename = "dmarcResult")]
-    dmarc_result: AuthResult,
+    pub dmarc_result: AuthResult,
     #[serde(rename = "dmarcPass")]
-    dmarc_pass: bool,
+    pub dmarc_pass: bool,
     #[serde(rename = "dmarcPolicy")]
-    dmarc_policy: DmarcPolicy,
-    elapsed: u64,
+    pub dmarc_policy: DmarcPolicy,
+    pub elapsed: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
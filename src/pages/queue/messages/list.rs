@@ -16,6 +16,7 @@ use crate::{
             IconAlertTriangle, IconCancel, IconCheckCircle, IconClock, IconLaunch, IconPauseCircle,
             IconPlayCircle, IconRefresh,
         },
+        layout::tenant::use_tenant_scope,
         list::{
             header::ColumnList,
             pagination::Pagination,
@@ -75,14 +76,15 @@ pub fn QueueList() -> impl IntoView {
     });
 
     let auth = use_authorization();
+    let tenant_scope = use_tenant_scope();
     let alert = use_alerts();
     let modal = use_modals();
     let selected = create_rw_signal::<ItemSelection>(ItemSelection::None);
     provide_context(selected);
 
     let messages = create_resource(
-        move || (page.get(), filter.get()),
-        move |(page, filter)| {
+        move || (page.get(), filter.get(), tenant_scope.get()),
+        move |(page, filter, tenant)| {
             let auth = auth.get_untracked();
 
             async move {
@@ -93,6 +95,7 @@ pub fn QueueList() -> impl IntoView {
                     .with_parameter("values", "1")
                     .with_parameter("max-total", "100")
                     .with_optional_parameter("text", filter)
+                    .with_optional_parameter("tenant", tenant)
                     .send::<List<Message>>()
                     .await
             }
@@ -100,6 +103,22 @@ pub fn QueueList() -> impl IntoView {
     );
 
     let total_results = create_rw_signal(None::<u32>);
+    let listed = create_rw_signal(Vec::<String>::new());
+    let total_selected = move || {
+        selected
+            .get()
+            .scoped(tenant_scope.get().is_some(), &listed.get())
+            .total_selected(total_results.get())
+    };
+    let take_selection = move || {
+        selected
+            .try_update(std::mem::take)
+            .unwrap_or_default()
+            .scoped(
+                tenant_scope.get_untracked().is_some(),
+                &listed.get_untracked(),
+            )
+    };
     let is_active = create_rw_signal(true);
 
     let cancel_action = create_action(move |items: &Arc<ItemSelection>| {
@@ -305,20 +324,16 @@ pub fn QueueList() -> impl IntoView {
 
                     <ToolbarButton
                         text=Signal::derive(move || {
-                            let ns = selected.get().total_selected(total_results.get());
+                            let ns = total_selected();
                             if ns > 0 { format!("Retry ({ns})") } else { "Retry".to_string() }
                         })
 
                         color=Color::Gray
                         on_click=Callback::new(move |_| {
-                            let to_delete = selected.get().total_selected(total_results.get());
+                            let to_delete = total_selected();
                             if to_delete > 0 {
                                 retry_action
-                                    .dispatch(
-                                        Arc::new(
-                                            selected.try_update(std::mem::take).unwrap_or_default(),
-                                        ),
-                                    );
+                                    .dispatch(Arc::new(take_selection()));
                             }
                         })
                     >
@@ -328,13 +343,13 @@ pub fn QueueList() -> impl IntoView {
 
                     <ToolbarButton
                         text=Signal::derive(move || {
-                            let ns = selected.get().total_selected(total_results.get());
+                            let ns = total_selected();
                             if ns > 0 { format!("Cancel ({ns})") } else { "Cancel".to_string() }
                         })
 
                         color=Color::Red
                         on_click=Callback::new(move |_| {
-                            let to_delete = selected.get().total_selected(total_results.get());
+                            let to_delete = total_selected();
                             if to_delete > 0 {
                                 let text = maybe_plural(to_delete, "message", "messages");
                                 modal
@@ -348,11 +363,7 @@ pub fn QueueList() -> impl IntoView {
                                             .with_button(format!("Delete {text}"))
                                             .with_dangerous_callback(move || {
                                                 cancel_action
-                                                    .dispatch(
-                                                        Arc::new(
-                                                            selected.try_update(std::mem::take).unwrap_or_default(),
-                                                        ),
-                                                    );
+                                                    .dispatch(Arc::new(take_selection()));
                                             }),
                                     )
                             }
@@ -378,6 +389,10 @@ pub fn QueueList() -> impl IntoView {
                         }
                         Some(Ok(messages)) if !messages.items.is_empty() => {
                             total_results.set(Some(messages.total as u32));
+                            listed
+                                .set(
+                                    messages.items.iter().map(|message| message.id.to_string()).collect(),
+                                );
                             is_active.set(messages.status);
                             Some(
                                 view! {
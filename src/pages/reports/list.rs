@@ -12,6 +12,7 @@ use super::ReportType;
 use crate::{
     components::{
         icon::{IconCancel, IconRefresh},
+        layout::tenant::use_tenant_scope,
         list::{
             header::ColumnList,
             pagination::Pagination,
@@ -80,14 +81,15 @@ pub fn IncomingReportList() -> impl IntoView {
     });
 
     let auth = use_authorization();
+    let tenant_scope = use_tenant_scope();
     let alert = use_alerts();
     let modal = use_modals();
     let selected = create_rw_signal::<ItemSelection>(ItemSelection::None);
     provide_context(selected);
 
     let reports = create_resource(
-        move || (page.get(), filter.get()),
-        move |(page, filter)| {
+        move || (page.get(), filter.get(), tenant_scope.get()),
+        move |(page, filter, tenant)| {
             let auth = auth.get_untracked();
             let report_type = report_type.get();
 
@@ -98,6 +100,7 @@ pub fn IncomingReportList() -> impl IntoView {
                     .with_parameter("limit", PAGE_SIZE.to_string())
                     .with_parameter("max-total", "100")
                     .with_optional_parameter("filter", filter)
+                    .with_optional_parameter("tenant", tenant)
                     .send::<List<String>>()
                     .await?;
                 let mut result = List {
@@ -134,6 +137,22 @@ pub fn IncomingReportList() -> impl IntoView {
     );
 
     let total_results = create_rw_signal(None::<u32>);
+    let listed = create_rw_signal(Vec::<String>::new());
+    let total_selected = move || {
+        selected
+            .get()
+            .scoped(tenant_scope.get().is_some(), &listed.get())
+            .total_selected(total_results.get())
+    };
+    let take_selection = move || {
+        selected
+            .try_update(std::mem::take)
+            .unwrap_or_default()
+            .scoped(
+                tenant_scope.get_untracked().is_some(),
+                &listed.get_untracked(),
+            )
+    };
 
     let delete_action = create_action(move |items: &Arc<ItemSelection>| {
         let items = items.clone();
@@ -241,13 +260,13 @@ pub fn IncomingReportList() -> impl IntoView {
 
                     <ToolbarButton
                         text=Signal::derive(move || {
-                            let ns = selected.get().total_selected(total_results.get());
+                            let ns = total_selected();
                             if ns > 0 { format!("Delete ({ns})") } else { "Delete".to_string() }
                         })
 
                         color=Color::Red
                         on_click=Callback::new(move |_| {
-                            let to_delete = selected.get().total_selected(total_results.get());
+                            let to_delete = total_selected();
                             if to_delete > 0 {
                                 let text = maybe_plural(to_delete, "report", "reports");
                                 modal
@@ -261,11 +280,7 @@ pub fn IncomingReportList() -> impl IntoView {
                                             .with_button(format!("Delete {text}"))
                                             .with_dangerous_callback(move || {
                                                 delete_action
-                                                    .dispatch(
-                                                        Arc::new(
-                                                            selected.try_update(std::mem::take).unwrap_or_default(),
-                                                        ),
-                                                    );
+                                                    .dispatch(Arc::new(take_selection()));
                                             }),
                                     )
                             }
@@ -291,6 +306,8 @@ pub fn IncomingReportList() -> impl IntoView {
                         }
                         Some(Ok(reports)) if !reports.items.is_empty() => {
                             total_results.set(Some(reports.total as u32));
+                            listed
+                                .set(reports.items.iter().map(|report| report.id().to_string()).collect());
                             let headers = match report_type.get() {
                                 ReportType::Dmarc => {
                                     vec![